@@ -37,6 +37,12 @@ class MainActivity : AppCompatActivity() {
             statusText.text = "${selectedPaths.size} file(s) selected"
         }
 
+    private val pickPdf =
+        registerForActivityResult(ActivityResultContracts.GetContent()) { uri ->
+            val path = uri?.let { uriToPath(it) } ?: return@registerForActivityResult
+            onPdfPicked(path)
+        }
+
     // ── Storage permission request ─────────────────────────────────────────
     private val requestPermission =
         registerForActivityResult(ActivityResultContracts.RequestPermission()) { granted ->
@@ -73,12 +79,13 @@ class MainActivity : AppCompatActivity() {
         findViewById<Button>(R.id.convertButton).setOnClickListener { onConvertClicked() }
         findViewById<Button>(R.id.toPdfButton).setOnClickListener { onToPdfClicked() }
 
-        // PDF extraction requires pdfium (libpdfium.so), which is not bundled
-        // in the Android APK. Hide the button when the feature is unavailable
-        // so users are not presented with an option that will always fail.
+        // Without libpdfium.so extraction draws each page's embedded images,
+        // which is enough for scanned documents. Hide the button if a build
+        // ever reports the feature as unavailable.
         extractPdfButton = findViewById(R.id.extractPdfButton)
         extractPdfButton.visibility =
             if (TransmuteLib.pdfExtractSupported()) View.VISIBLE else View.GONE
+        extractPdfButton.setOnClickListener { pickPdf.launch("application/pdf") }
     }
 
     // ── Button handlers ────────────────────────────────────────────────────
@@ -151,6 +158,22 @@ class MainActivity : AppCompatActivity() {
         }
     }
 
+    private fun onPdfPicked(pdfPath: String) {
+        val format = formatSpinner.selectedItem as String
+        statusText.text = "Extracting pages…"
+
+        lifecycleScope.launch {
+            try {
+                val pages = withContext(Dispatchers.IO) {
+                    TransmuteLib.extractPdfPages(pdfPath, format, 150f)
+                }
+                statusText.text = "Done! ${pages.size} page(s) extracted.\n${pages.firstOrNull()}"
+            } catch (e: RuntimeException) {
+                statusText.text = "Error: ${e.message}"
+            }
+        }
+    }
+
     // ── Helpers ────────────────────────────────────────────────────────────
 
     /** Resolve a content:// URI to a real filesystem path. */
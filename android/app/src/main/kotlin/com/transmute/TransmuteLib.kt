@@ -70,17 +70,50 @@ object TransmuteLib {
      */
     external fun imagesToPdf(inputPaths: Array<String>, outputPath: String): String
 
+    /**
+     * Combine multiple images into a single PDF with a chosen page layout.
+     *
+     * @param inputPaths  Array of absolute paths to source images (page order).
+     * @param outputPath  Absolute path for the output PDF file.
+     * @param pageSize    `"a3"`, `"a4"`, `"a5"`, `"letter"`, `"legal"` or `"WIDTHxHEIGHT"` in mm.
+     * @param orientation `"auto"` (follow each image), `"portrait"` or `"landscape"`.
+     * @param marginMm    Blank space on each side of the image, in millimetres.
+     * @param fit         `"fit"`, `"fill"` (crop to the page), `"original"` (size at 300 DPI)
+     *                    or `"image"` (page sized to the image).
+     * @return [outputPath] on success.
+     * @throws RuntimeException if a setting is invalid or the operation fails.
+     */
+    external fun imagesToPdfWithLayout(
+        inputPaths: Array<String>,
+        outputPath: String,
+        pageSize: String,
+        orientation: String,
+        marginMm: Float,
+        fit: String,
+    ): String
+
     /**
      * Returns `true` if PDF-to-image extraction is available in this build.
      *
-     * Extraction requires `pdfium-render` (wrapping Google's `libpdfium.so`),
-     * which is not bundled in the Android APK. This function will return
-     * `false` in all current Android builds.
+     * Always `true`: without `libpdfium.so` [extractPdfPages] falls back to a
+     * pure-Rust renderer that draws the images embedded in each page. That covers
+     * scanned documents; text and vector pages are reported as errors.
      *
-     * Use this to conditionally show or hide any "Extract PDF pages" UI:
      * ```kotlin
      * extractPdfButton.isVisible = TransmuteLib.pdfExtractSupported()
      * ```
      */
     external fun pdfExtractSupported(): Boolean
+
+    /**
+     * Render every page of a PDF to an image in the output directory.
+     *
+     * @param pdfPath Absolute path to the source PDF.
+     * @param format  Target extension: `"png"`, `"jpg"`, `"webp"`, etc.
+     * @param dpi     Rendering resolution, e.g. `150f`.
+     * @return Output paths in page order (`<name>_page_001.<format>`, ...).
+     * @throws RuntimeException if the PDF cannot be read or a page has nothing
+     *   the built-in renderer can draw (text or vector graphics only).
+     */
+    external fun extractPdfPages(pdfPath: String, format: String, dpi: Float): Array<String>
 }
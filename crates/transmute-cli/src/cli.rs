@@ -1,5 +1,5 @@
 // crates/transmute-cli/src/cli.rs
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 
 /// Transmute - Privacy-focused media converter with GPU acceleration
@@ -31,11 +31,13 @@ pub struct Cli {
     pub command: Commands,
 }
 
+// Parsed once per run, so the size of the Convert variant does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Convert image format (supports multiple inputs for PDF)
+    /// Convert image format (supports multiple inputs for PDF and animations, and PDF input)
     Convert {
-        /// Input file path(s) - multiple files allowed for PDF output
+        /// Input file path(s) - multiple files allowed for PDF, GIF, WebP and APNG output
         #[arg(required = true)]
         input: Vec<PathBuf>,
 
@@ -46,6 +48,21 @@ pub enum Commands {
         /// Output path (optional)
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        #[command(flatten)]
+        transform: TransformArgs,
+
+        #[command(flatten)]
+        webp: WebpArgs,
+
+        #[command(flatten)]
+        avif: AvifArgs,
+
+        #[command(flatten)]
+        animation: AnimationArgs,
+
+        #[command(flatten)]
+        pdf: PdfArgs,
     },
 
     /// Compress/optimize image
@@ -64,6 +81,40 @@ pub enum Commands {
         /// Output path (optional)
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// Colour transparent pixels are flattened onto for JPEG (#rrggbb, white or black)
+        #[arg(long, default_value = "white")]
+        background: String,
+
+        /// Downsample images in a PDF drawn sharper than this (default by quality: 300/200/150/100)
+        #[arg(long, value_name = "DPI")]
+        max_dpi: Option<f32>,
+
+        /// Pick the highest quality that fits this file size, e.g. 200KB, 1.5MB or 512KiB
+        #[arg(long, value_name = "SIZE", conflicts_with = "quality")]
+        max_size: Option<String>,
+
+        /// Also scale the image down if --max-size cannot be met otherwise
+        #[arg(long, requires = "max_size")]
+        downscale: bool,
+
+        /// Pick the smallest output whose SSIM against the input is at least this (0-1, e.g. 0.95)
+        #[arg(long, value_name = "SSIM", conflicts_with_all = ["quality", "max_size"])]
+        min_ssim: Option<f64>,
+
+        /// Losslessly repack a JPEG as JPEG XL, or rebuild the original JPEG from such
+        /// a JPEG XL (bit-exact both ways). Needs the jxl feature
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "format", "quality", "background", "max_dpi", "max_size", "min_ssim",
+                "rotate", "flip", "crop", "resize", "max_edge",
+            ]
+        )]
+        lossless_jpeg: bool,
+
+        #[command(flatten)]
+        transform: TransformArgs,
     },
 
     /// Enhance/upscale image (requires models)
@@ -92,6 +143,81 @@ pub enum Commands {
         /// Output directory
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// Compress each file to the smallest output whose SSIM against it is at least this (0-1)
+        #[arg(long, value_name = "SSIM")]
+        min_ssim: Option<f64>,
+    },
+
+    /// Compare two images by PSNR, SSIM and MSE, failing (exit status 2) below thresholds
+    Compare {
+        /// Reference image, e.g. the original or a known-good encode
+        reference: PathBuf,
+
+        /// Image checked against the reference
+        candidate: PathBuf,
+
+        /// Write an image showing where they differ (e.g. diff.png)
+        #[arg(long, value_name = "FILE")]
+        heatmap: Option<PathBuf>,
+
+        /// Fail when SSIM is below this (0-1)
+        #[arg(long, value_name = "SSIM")]
+        min_ssim: Option<f64>,
+
+        /// Fail when MS-SSIM is below this (0-1)
+        #[arg(long, value_name = "SSIM")]
+        min_ms_ssim: Option<f64>,
+
+        /// Fail when the perceptual score is below this (0-100)
+        #[arg(long, value_name = "SCORE")]
+        min_perceptual: Option<f64>,
+
+        /// Fail when PSNR is below this many dB
+        #[arg(long, value_name = "DB")]
+        min_psnr: Option<f64>,
+
+        /// Fail when the mean squared error is above this
+        #[arg(long, value_name = "MSE")]
+        max_mse: Option<f64>,
+    },
+
+    /// Extract the images embedded in a PDF as stored (no re-rendering)
+    ExtractImages {
+        /// Input PDF
+        input: PathBuf,
+
+        /// Output directory
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Lay images out in a grid with file name captions, as a PDF or image pages
+    ContactSheet {
+        /// Input images
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Output file; its extension picks the format (pdf, png, jpg, ...)
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Resolution in dots per inch (default 150)
+        #[arg(long)]
+        dpi: Option<f32>,
+
+        /// Leave out the file name captions
+        #[arg(long)]
+        no_captions: bool,
+
+        #[command(flatten)]
+        layout: LayoutArgs,
+    },
+
+    /// Merge, split, reorder, rotate or delete pages of existing PDFs, or edit their properties
+    Pdf {
+        #[command(subcommand)]
+        action: PdfCommands,
     },
 
     /// Execute natural language command
@@ -108,6 +234,308 @@ pub enum Commands {
     },
 }
 
+/// WebP encoder settings (only used for WebP output)
+#[derive(Args, Debug, Clone, Default)]
+pub struct WebpArgs {
+    /// Lossy WebP quality (0-100, default 90)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub webp_quality: Option<u8>,
+
+    /// Encode WebP losslessly
+    #[arg(long, conflicts_with_all = ["webp_quality", "near_lossless"])]
+    pub lossless: bool,
+
+    /// Near-lossless WebP preprocessing (0 = strongest, 100 = off)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100), conflicts_with = "webp_quality")]
+    pub near_lossless: Option<u8>,
+}
+
+/// AVIF encoder settings (only used for AVIF output)
+#[derive(Args, Debug, Clone, Default)]
+pub struct AvifArgs {
+    /// AVIF quality (1-100, default 80)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub avif_quality: Option<u8>,
+
+    /// AVIF encoder speed (1 = slowest/smallest, 10 = fastest, default 4)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=10))]
+    pub avif_speed: Option<u8>,
+}
+
+/// Animation settings (GIF, WebP and APNG)
+#[derive(Args, Debug, Clone, Default)]
+pub struct AnimationArgs {
+    /// Write every frame of an animated input as a separate image (-o names the directory)
+    #[arg(long, conflicts_with_all = ["delay", "loop_count"])]
+    pub extract_frames: bool,
+
+    /// Frame delay in milliseconds when assembling several inputs (default 100)
+    #[arg(long)]
+    pub delay: Option<u32>,
+
+    /// Number of plays when assembling several inputs (0 = loop forever)
+    #[arg(long = "loop", default_value = "0")]
+    pub loop_count: u32,
+}
+
+/// PDF input settings (one image is written per page, -o names the directory)
+#[derive(Args, Debug, Clone, Default)]
+pub struct PdfArgs {
+    /// Pages to render, e.g. 1-3,7,last (default: all)
+    #[arg(long)]
+    pub pages: Option<String>,
+
+    /// Resolution in dots per inch of rendered pages, or of images placed with -f pdf (default 300)
+    #[arg(long)]
+    pub dpi: Option<f32>,
+
+    #[command(flatten)]
+    pub layout: LayoutArgs,
+
+    /// Print each image's file name below it (-f pdf)
+    #[arg(long)]
+    pub captions: bool,
+
+    #[command(flatten)]
+    pub info: DocumentInfoArgs,
+
+    /// Leave out the outline entry added for each image (-f pdf)
+    #[arg(long)]
+    pub no_bookmarks: bool,
+
+    /// Page label style: decimal, roman, upper-roman, letters, upper-letters or none (-f pdf)
+    #[arg(long)]
+    pub page_labels: Option<String>,
+
+    /// Text before each page label, e.g. "A-" (-f pdf)
+    #[arg(long)]
+    pub page_label_prefix: Option<String>,
+
+    /// Write PDF/A-2b for long-term archiving (-f pdf; not with --captions)
+    #[arg(long)]
+    pub pdfa: bool,
+
+    /// Number of the first page label (default 1, -f pdf)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub page_label_start: Option<u32>,
+
+    #[command(flatten)]
+    pub encryption: EncryptionArgs,
+}
+
+/// Password protection of written PDFs (passwords are prompted for, never given as arguments)
+#[derive(Args, Debug, Clone, Default)]
+pub struct EncryptionArgs {
+    /// Protect the output PDF with AES-256, prompting for the password that opens it
+    #[arg(long)]
+    pub encrypt: bool,
+
+    /// Also prompt for an owner password that lifts --deny (default: a random one)
+    #[arg(long, requires = "encrypt")]
+    pub owner_password: bool,
+
+    /// Withhold permissions from readers, e.g. print,copy
+    #[arg(
+        long,
+        requires = "encrypt",
+        value_delimiter = ',',
+        value_parser = ["print", "copy", "modify", "annotate", "forms", "assemble"]
+    )]
+    pub deny: Vec<String>,
+}
+
+/// PDF document properties (when editing, an empty value removes a property)
+#[derive(Args, Debug, Clone, Default)]
+pub struct DocumentInfoArgs {
+    /// Document title
+    #[arg(long)]
+    pub title: Option<String>,
+
+    /// Document author
+    #[arg(long)]
+    pub author: Option<String>,
+
+    /// Document subject
+    #[arg(long)]
+    pub subject: Option<String>,
+
+    /// Comma-separated keywords
+    #[arg(long)]
+    pub keywords: Option<String>,
+
+    /// Application the content was made with
+    #[arg(long)]
+    pub creator: Option<String>,
+
+    /// Application that wrote the PDF (default Transmute for new PDFs)
+    #[arg(long)]
+    pub producer: Option<String>,
+
+    /// Creation date, e.g. 2024-01-31, "2024-01-31 09:30" or RFC 3339 (default: now for new PDFs)
+    #[arg(long)]
+    pub created: Option<String>,
+
+    /// Modification date, written like --created (default: now)
+    #[arg(long)]
+    pub modified: Option<String>,
+}
+
+/// Page layout of images on PDF or contact sheet pages
+#[derive(Args, Debug, Clone, Default)]
+pub struct LayoutArgs {
+    /// Page size: a3, a4, a5, letter, legal or WIDTHxHEIGHT in mm (default a4)
+    #[arg(long)]
+    pub page_size: Option<String>,
+
+    /// Page orientation: auto, portrait or landscape (default auto)
+    #[arg(long)]
+    pub orientation: Option<String>,
+
+    /// Blank space around the page edge in mm (default 10)
+    #[arg(long)]
+    pub margin: Option<f32>,
+
+    /// Image placement: fit, fill (crop), original (size at --dpi) or image (page sized to the image)
+    #[arg(long)]
+    pub fit: Option<String>,
+
+    /// Images per page as ROWSxCOLUMNS or N-up, e.g. 2x2, 4-up (default 1x1; contact-sheet 5x4)
+    #[arg(long)]
+    pub grid: Option<String>,
+
+    /// Gap between images in mm (default 5)
+    #[arg(long)]
+    pub spacing: Option<f32>,
+}
+
+/// Image transformations, applied in order: rotate, flip, crop, resize, max-edge
+#[derive(Args, Debug, Clone, Default)]
+pub struct TransformArgs {
+    /// Rotate clockwise by 90, 180 or 270 degrees
+    #[arg(long, allow_hyphen_values = true)]
+    pub rotate: Option<i32>,
+
+    /// Flip: horizontal or vertical
+    #[arg(long)]
+    pub flip: Option<String>,
+
+    /// Center-crop to aspect ratio (e.g. 16:9, 1:1)
+    #[arg(long)]
+    pub crop: Option<String>,
+
+    /// Resize to WIDTHxHEIGHT (e.g. 800x600, 800x for width only, x600 for height only)
+    #[arg(long)]
+    pub resize: Option<String>,
+
+    /// Downscale so the longest edge is at most this many pixels
+    #[arg(long)]
+    pub max_edge: Option<u32>,
+}
+
+/// Page operations on existing PDFs (pages are copied, never rasterized)
+#[derive(Subcommand)]
+pub enum PdfCommands {
+    /// Merge PDFs and images, in order, into one PDF
+    Merge {
+        /// Input PDFs and/or images
+        #[arg(required = true, num_args = 2..)]
+        inputs: Vec<PathBuf>,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        #[command(flatten)]
+        encryption: EncryptionArgs,
+    },
+
+    /// Split into one PDF per --range, or one per page without any
+    Split {
+        /// Input PDF
+        input: PathBuf,
+
+        /// Pages for one output file, e.g. 1-3 (repeatable)
+        #[arg(long = "range")]
+        ranges: Vec<String>,
+
+        /// Output directory
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        encryption: EncryptionArgs,
+    },
+
+    /// Move pages to the front in the given order, e.g. 3,1,2
+    Reorder {
+        /// Input PDF
+        input: PathBuf,
+
+        /// New page order; unlisted pages follow in their original order
+        order: String,
+
+        /// Output path (optional)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        encryption: EncryptionArgs,
+    },
+
+    /// Rotate pages clockwise by 90, 180 or 270 degrees
+    Rotate {
+        /// Input PDF
+        input: PathBuf,
+
+        /// Degrees clockwise (negative for counter-clockwise)
+        #[arg(allow_hyphen_values = true)]
+        degrees: i32,
+
+        /// Pages to rotate, e.g. 1-3,last (default: all)
+        #[arg(long)]
+        pages: Option<String>,
+
+        /// Output path (optional)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        encryption: EncryptionArgs,
+    },
+
+    /// Show document properties, or set them with --title, --author, ...
+    Info {
+        /// Input PDF
+        input: PathBuf,
+
+        #[command(flatten)]
+        info: DocumentInfoArgs,
+
+        /// Output path when setting properties (optional)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        encryption: EncryptionArgs,
+    },
+
+    /// Delete pages, e.g. 2,5-7
+    Delete {
+        /// Input PDF
+        input: PathBuf,
+
+        /// Pages to remove
+        pages: String,
+
+        /// Output path (optional)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        encryption: EncryptionArgs,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum ConfigCommands {
     /// Show current configuration
@@ -2,6 +2,7 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use transmute_common::MetadataPolicy;
 
 /// CLI configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +30,14 @@ pub struct Config {
     /// Colored output
     #[serde(default = "default_color")]
     pub colored_output: bool,
+
+    /// Metadata policy: keep-all, strip-all, strip-private, keep-icc
+    #[serde(default = "default_metadata")]
+    pub metadata: String,
+
+    /// Rotate pixels according to the EXIF Orientation tag
+    #[serde(default = "default_auto_orient")]
+    pub auto_orient: bool,
 }
 
 fn default_output_dir() -> PathBuf {
@@ -57,6 +66,14 @@ fn default_color() -> bool {
     true
 }
 
+fn default_auto_orient() -> bool {
+    true
+}
+
+fn default_metadata() -> String {
+    MetadataPolicy::default().name().to_string()
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -66,6 +83,8 @@ impl Default for Config {
             parallel_jobs: default_jobs(),
             show_progress: default_progress(),
             colored_output: default_color(),
+            metadata: default_metadata(),
+            auto_orient: default_auto_orient(),
         }
     }
 }
@@ -113,6 +132,12 @@ impl Config {
         Ok(())
     }
 
+    /// Parsed metadata policy
+    pub fn metadata_policy(&self) -> Result<MetadataPolicy> {
+        MetadataPolicy::from_name(&self.metadata)
+            .with_context(|| format!("Invalid metadata policy: {}", self.metadata))
+    }
+
     /// Reset to defaults
     pub fn reset() -> Result<()> {
         let config = Self::default();
@@ -130,6 +155,17 @@ mod tests {
         assert!(config.use_gpu);
         assert!(config.show_progress);
         assert_eq!(config.default_quality, "high");
+        assert_eq!(
+            config.metadata_policy().unwrap(),
+            MetadataPolicy::StripPrivate
+        );
+    }
+
+    #[test]
+    fn test_missing_metadata_key_uses_default() {
+        // Configs written before the key existed must still load
+        let parsed: Config = toml::from_str("default_quality = \"low\"").unwrap();
+        assert_eq!(parsed.metadata, "strip-private");
     }
 
     #[test]
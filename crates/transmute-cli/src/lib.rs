@@ -3,7 +3,10 @@ pub mod config;
 pub mod output;
 pub mod progress;
 
-pub use cli::{Cli, Commands, ConfigCommands};
+pub use cli::{
+    AnimationArgs, AvifArgs, Cli, Commands, ConfigCommands, DocumentInfoArgs, EncryptionArgs,
+    LayoutArgs, PdfArgs, PdfCommands, TransformArgs, WebpArgs,
+};
 pub use config::Config;
 pub use output::OutputFormatter;
 pub use progress::ProgressReporter;
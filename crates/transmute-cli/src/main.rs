@@ -1,10 +1,27 @@
 use anyhow::{Context, Result};
 use clap::Parser;
 use std::path::PathBuf;
-use transmute_cli::{Cli, Commands, ConfigCommands, Config, OutputFormatter, ProgressReporter};
-use transmute_common::MediaFormat;
-use transmute_compress::QualitySettings;
-use transmute_core::Converter;
+use transmute_cli::{
+    AnimationArgs, AvifArgs, Cli, Commands, Config, ConfigCommands, DocumentInfoArgs,
+    EncryptionArgs, LayoutArgs, OutputFormatter, PdfArgs, PdfCommands, ProgressReporter,
+    TransformArgs, WebpArgs,
+};
+use transmute_common::{FileSize, MediaFormat, PageSelection};
+use transmute_compress::{QualitySettings, QualityThresholds, SizeTarget};
+use transmute_core::{
+    AvifOptions, Converter, FlipAxis, Grid, ImageOp, LoopCount, PageEdit, PageLabels,
+    PdfEncryption, PdfInfo, PdfOptions, Rotation, TransformPipeline, WebpOptions,
+    DEFAULT_FRAME_DELAY_MS,
+};
+
+/// Contact sheets are for looking at, so they render below print resolution
+const DEFAULT_CONTACT_SHEET_DPI: f32 = 150.0;
+
+/// Exit status of `compare` when a threshold is violated; errors exit with 1
+const COMPARE_FAILED_EXIT_CODE: i32 = 2;
+
+/// How many skipped batch files are named before the rest are only counted
+const SKIPPED_FILES_LISTED: usize = 5;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -50,8 +67,16 @@ async fn main() -> Result<()> {
             input,
             format,
             output,
+            transform,
+            webp,
+            avif,
+            animation,
+            pdf,
         } => {
-            handle_convert(input, format, output, &config, &formatter, &progress)?;
+            handle_convert(
+                input, format, output, transform, webp, avif, animation, pdf, &config, &formatter,
+                &progress,
+            )?;
         }
 
         Commands::Compress {
@@ -59,10 +84,30 @@ async fn main() -> Result<()> {
             format,
             quality,
             output,
+            background,
+            max_dpi,
+            max_size,
+            downscale,
+            min_ssim,
+            lossless_jpeg,
+            transform,
         } => {
-            handle_compress(
-                input, format, quality, output, &config, &formatter, &progress,
-            )?;
+            if lossless_jpeg {
+                handle_jxl_transcode(input, output, &config, &formatter, &progress)?;
+            } else {
+                let size_target = max_size
+                    .map(|size| -> Result<SizeTarget> {
+                        Ok(SizeTarget {
+                            max_size: FileSize::parse(&size)?,
+                            allow_downscale: downscale,
+                        })
+                    })
+                    .transpose()?;
+                handle_compress(
+                    input, format, quality, output, &background, max_dpi, size_target, min_ssim,
+                    transform, &config, &formatter, &progress,
+                )?;
+            }
         }
 
         Commands::Enhance {
@@ -77,8 +122,62 @@ async fn main() -> Result<()> {
             pattern,
             format,
             output,
+            min_ssim,
+        } => {
+            handle_batch(pattern, format, output, min_ssim, &config, &formatter, &progress)
+                .await?;
+        }
+
+        Commands::Compare {
+            reference,
+            candidate,
+            heatmap,
+            min_ssim,
+            min_ms_ssim,
+            min_perceptual,
+            min_psnr,
+            max_mse,
         } => {
-            handle_batch(pattern, format, output, &config, &formatter, &progress).await?;
+            let thresholds = QualityThresholds {
+                min_ssim,
+                min_ms_ssim,
+                min_perceptual,
+                min_psnr,
+                max_mse,
+            };
+            let passed = handle_compare(
+                reference, candidate, heatmap, thresholds, &config, &formatter, &progress,
+            )?;
+            if !passed {
+                std::process::exit(COMPARE_FAILED_EXIT_CODE);
+            }
+        }
+
+        Commands::ExtractImages { input, output } => {
+            handle_extract_images(input, output, &formatter, &progress)?;
+        }
+
+        Commands::ContactSheet {
+            inputs,
+            output,
+            dpi,
+            no_captions,
+            layout,
+        } => {
+            handle_contact_sheet(
+                inputs,
+                output,
+                dpi,
+                !no_captions,
+                layout,
+                &config,
+                &formatter,
+                &progress,
+            )?;
+        }
+
+        Commands::Pdf { action } => {
+            handle_pdf(action, &formatter, &progress)?;
         }
 
         Commands::Natural { command } => {
@@ -93,33 +192,115 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_convert(
     inputs: Vec<PathBuf>,
     format_str: String,
     output: Option<PathBuf>,
+    transform: TransformArgs,
+    webp: WebpArgs,
+    avif: AvifArgs,
+    animation: AnimationArgs,
+    pdf: PdfArgs,
     config: &Config,
     formatter: &OutputFormatter,
     progress: &ProgressReporter,
 ) -> Result<()> {
     let format = MediaFormat::from_extension(&format_str)
         .context(format!("Unsupported format: {}", format_str))?;
-
-    let spinner = progress.create_spinner("Converting...");
+    let pipeline = parse_transform(&transform)?;
 
     let mut converter = Converter::new()?;
     converter.set_gpu_enabled(config.use_gpu);
+    converter.set_metadata_policy(config.metadata_policy()?);
+    converter.set_auto_orient(config.auto_orient);
+    converter.set_transform(pipeline);
+    converter.set_webp_options(webp_options(&webp));
+    converter.set_avif_options(avif_options(&avif));
+
+    let layout = &pdf.layout;
+    let layout_set = layout.page_size.is_some()
+        || layout.orientation.is_some()
+        || layout.margin.is_some()
+        || layout.fit.is_some()
+        || layout.grid.is_some()
+        || layout.spacing.is_some()
+        || pdf.captions;
+    let info = document_info(&pdf.info)?;
+    let page_labels = page_labels(&pdf)?;
+    let mut options = None;
+    if format == MediaFormat::Pdf {
+        let mut settings = pdf_options(layout, pdf.dpi)?;
+        settings.captions = pdf.captions;
+        settings.info = info;
+        settings.bookmarks = !pdf.no_bookmarks;
+        settings.page_labels = page_labels;
+        settings.pdfa = pdf.pdfa;
+        options = Some(settings);
+    } else if layout_set {
+        anyhow::bail!(
+            "--page-size, --orientation, --margin, --fit, --grid, --spacing and --captions only apply to -f pdf"
+        );
+    } else if !info.is_empty()
+        || pdf.no_bookmarks
+        || page_labels.is_some()
+        || pdf.pdfa
+        || pdf.encryption.encrypt
+    {
+        anyhow::bail!(
+            "Document properties, --no-bookmarks, page labels, --pdfa and --encrypt only apply to -f pdf"
+        );
+    }
 
-    // Special handling for multi-image PDF conversion
+    // Inputs are unlocked before the output's password is asked for
+    let unlocked = unlock_pdfs(&mut converter, &inputs, formatter)?;
+    if let Some(mut options) = options {
+        options.encryption = pdf_encryption(&pdf.encryption)?;
+        converter.set_pdf_options(options);
+    }
+    let spinner = progress.create_spinner("Converting...");
+
+    // Special handling for multi-image PDF conversion (input PDFs are merged page by page)
     if format == MediaFormat::Pdf && inputs.len() > 1 {
         let output_path = output.ok_or_else(|| {
             anyhow::anyhow!("Output path required for multi-image PDF conversion")
         })?;
 
-        let result = converter.images_to_pdf(inputs.clone(), output_path, None)?;
+        let result = converter.merge_to_pdf(inputs.clone(), output_path, None)?;
+
+        ProgressReporter::finish_bar(&spinner, "Done");
+        formatter.success(&format!(
+            "Created PDF from {} files: {}",
+            inputs.len(),
+            formatter.format_path(&result)
+        ));
+        if unlocked && !pdf.encryption.encrypt {
+            warn_unprotected(formatter);
+        }
+
+        return Ok(());
+    }
+
+    // Multiple stills become the frames of one animation
+    let animated_output = matches!(
+        format,
+        MediaFormat::Gif | MediaFormat::Webp | MediaFormat::Png
+    );
+    if animated_output && inputs.len() > 1 {
+        let output_path = output
+            .ok_or_else(|| anyhow::anyhow!("Output path required when assembling an animation"))?;
+
+        let result = converter.assemble_animation(
+            inputs.clone(),
+            output_path,
+            format,
+            animation.delay.unwrap_or(DEFAULT_FRAME_DELAY_MS),
+            LoopCount::from_plays(animation.loop_count),
+        )?;
 
         ProgressReporter::finish_bar(&spinner, "Done");
         formatter.success(&format!(
-            "Created {}-page PDF: {}",
+            "Created {}-frame animation: {}",
             inputs.len(),
             formatter.format_path(&result)
         ));
@@ -130,13 +311,54 @@ fn handle_convert(
     // Single image conversion
     if inputs.len() != 1 {
         anyhow::bail!(
-            "Multiple inputs only supported for PDF output. Got {} inputs for {}",
+            "Multiple inputs only supported for PDF, GIF, WebP and PNG output. Got {} inputs for {}",
             inputs.len(),
             format_str
         );
     }
 
     let input = &inputs[0];
+
+    // A PDF becomes one image per selected page
+    if MediaFormat::detect(input) == Some(MediaFormat::Pdf) && format.is_image() {
+        let pages = match &pdf.pages {
+            Some(spec) => PageSelection::parse(spec)?,
+            None => PageSelection::all(),
+        };
+        let outputs = converter.pdf_to_images_with(input, format, output, pdf.dpi, &pages)?;
+
+        ProgressReporter::finish_bar(&spinner, "Done");
+        formatter.success(&format!(
+            "Rendered {} pages from {}",
+            outputs.len(),
+            formatter.format_path(input)
+        ));
+        for output in &outputs {
+            formatter.info(&formatter.format_path(output));
+        }
+
+        return Ok(());
+    }
+    if pdf.pages.is_some() {
+        anyhow::bail!("--pages only applies to PDF input");
+    }
+    if pdf.dpi.is_some() && format != MediaFormat::Pdf {
+        anyhow::bail!("--dpi only applies to PDF input or -f pdf");
+    }
+
+    if animation.extract_frames {
+        let outputs = converter.extract_frames(input, format, output)?;
+
+        ProgressReporter::finish_bar(&spinner, "Done");
+        formatter.success(&format!(
+            "Extracted {} frames from {}",
+            outputs.len(),
+            formatter.format_path(input)
+        ));
+
+        return Ok(());
+    }
+
     let output_path = converter.convert_image(input, output, format)?;
 
     ProgressReporter::finish_bar(&spinner, "Done");
@@ -145,31 +367,141 @@ fn handle_convert(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_compress(
     input: PathBuf,
     format_str: Option<String>,
     quality_str: String,
     output: Option<PathBuf>,
+    background: &str,
+    max_dpi: Option<f32>,
+    size_target: Option<SizeTarget>,
+    min_ssim: Option<f64>,
+    transform: TransformArgs,
     config: &Config,
     formatter: &OutputFormatter,
     progress: &ProgressReporter,
 ) -> Result<()> {
     // Parse quality
     let quality = parse_quality(&quality_str)?;
+    let pipeline = parse_transform(&transform)?;
+    let background = parse_color(background)?;
 
     // Determine format
     let format = if let Some(fmt) = format_str {
         MediaFormat::from_extension(&fmt).context(format!("Unsupported format: {}", fmt))?
     } else {
-        MediaFormat::from_path(&input).unwrap_or(MediaFormat::Jpeg)
+        MediaFormat::detect(&input).unwrap_or(MediaFormat::Jpeg)
     };
 
+    if MediaFormat::detect(&input) == Some(MediaFormat::Pdf) {
+        if format != MediaFormat::Pdf {
+            anyhow::bail!(
+                "A PDF compresses to PDF; use `convert` to turn its pages into {}",
+                format
+            );
+        }
+        if !pipeline.is_empty() {
+            anyhow::bail!("Transforms do not apply to PDF input");
+        }
+        if size_target.is_some() {
+            anyhow::bail!("--max-size does not apply to PDF input; lower --quality or --max-dpi");
+        }
+        if min_ssim.is_some() {
+            anyhow::bail!("--min-ssim does not apply to PDF input; lower --quality or --max-dpi");
+        }
+        return handle_compress_pdf(input, quality, max_dpi, output, config, formatter, progress);
+    }
+    if max_dpi.is_some() {
+        anyhow::bail!("--max-dpi only applies to PDF input");
+    }
+
     let spinner = progress.create_spinner("Compressing...");
 
     let mut converter = Converter::new()?;
     converter.set_gpu_enabled(config.use_gpu);
+    converter.set_metadata_policy(config.metadata_policy()?);
+    converter.set_auto_orient(config.auto_orient);
+    converter.set_background(background);
+    converter.set_transform(pipeline);
+
+    let (output_path, result) = match (size_target, min_ssim) {
+        (Some(target), _) => converter.compress_image_to_size(&input, format, target, output)?,
+        (None, Some(min_ssim)) => {
+            converter.compress_image_to_ssim(&input, format, min_ssim, output)?
+        }
+        (None, None) => converter.compress_image(&input, format, quality, output)?,
+    };
 
-    let (output_path, result) = converter.compress_image(&input, format, quality, output)?;
+    ProgressReporter::finish_bar(&spinner, "Done");
+    formatter.print_compression(
+        &input,
+        &output_path,
+        result.original_size,
+        result.compressed_size,
+        result.ratio,
+    );
+
+    Ok(())
+}
+
+fn handle_compress_pdf(
+    input: PathBuf,
+    quality: QualitySettings,
+    max_dpi: Option<f32>,
+    output: Option<PathBuf>,
+    config: &Config,
+    formatter: &OutputFormatter,
+    progress: &ProgressReporter,
+) -> Result<()> {
+    let mut converter = Converter::new()?;
+    converter.set_gpu_enabled(config.use_gpu);
+    let unlocked = unlock_pdfs(&mut converter, std::slice::from_ref(&input), formatter)?;
+
+    let spinner = progress.create_spinner("Compressing PDF images...");
+    let (output_path, result, stats) = converter.compress_pdf(&input, quality, max_dpi, output)?;
+
+    ProgressReporter::finish_bar(&spinner, "Done");
+    formatter.print_compression(
+        &input,
+        &output_path,
+        result.original_size,
+        result.compressed_size,
+        result.ratio,
+    );
+    formatter.info(&format!(
+        "Re-encoded {} of {} images ({} downsampled)",
+        stats.recompressed, stats.images, stats.downsampled
+    ));
+    if unlocked {
+        formatter.warn("The input was password protected, the compressed copy is not");
+    }
+
+    Ok(())
+}
+
+fn handle_jxl_transcode(
+    input: PathBuf,
+    output: Option<PathBuf>,
+    config: &Config,
+    formatter: &OutputFormatter,
+    progress: &ProgressReporter,
+) -> Result<()> {
+    let mut converter = Converter::new()?;
+    converter.set_metadata_policy(config.metadata_policy()?);
+
+    // A repacked JPEG XL goes the other way, back to its original JPEG
+    let reconstruct = MediaFormat::detect(&input) == Some(MediaFormat::Jxl);
+    let spinner = progress.create_spinner(if reconstruct {
+        "Reconstructing JPEG..."
+    } else {
+        "Transcoding to JPEG XL..."
+    });
+    let (output_path, result) = if reconstruct {
+        converter.reconstruct_jpeg_from_jxl(&input, output)?
+    } else {
+        converter.transcode_jpeg_to_jxl(&input, output)?
+    };
 
     ProgressReporter::finish_bar(&spinner, "Done");
     formatter.print_compression(
@@ -205,6 +537,7 @@ async fn handle_batch(
     pattern: String,
     format_str: String,
     output: Option<PathBuf>,
+    min_ssim: Option<f64>,
     config: &Config,
     formatter: &OutputFormatter,
     progress: &ProgressReporter,
@@ -216,8 +549,26 @@ async fn handle_batch(
 
     // Resolve pattern
     let resolver = PathResolver::new()?;
-    let files = resolver.resolve_pattern(&pattern)?;
-
+    let matches = resolver.match_pattern(&pattern)?;
+    let files = matches.files;
+
+    if !matches.skipped.is_empty() {
+        let skipped = matches.skipped.len();
+        let mut names: Vec<String> = matches
+            .skipped
+            .iter()
+            .take(SKIPPED_FILES_LISTED)
+            .map(|path| formatter.format_path(path))
+            .collect();
+        if skipped > SKIPPED_FILES_LISTED {
+            names.push(format!("and {} more", skipped - SKIPPED_FILES_LISTED));
+        }
+        formatter.warn(&format!(
+            "Skipping {} files that are not readable media: {}",
+            skipped,
+            names.join(", ")
+        ));
+    }
     if files.is_empty() {
         anyhow::bail!("No files matched pattern: {}", pattern);
     }
@@ -228,8 +579,17 @@ async fn handle_batch(
 
     let mut converter = Converter::new()?;
     converter.set_gpu_enabled(config.use_gpu);
-
-    let results = converter.convert_batch(files, format, output);
+    converter.set_metadata_policy(config.metadata_policy()?);
+    converter.set_auto_orient(config.auto_orient);
+
+    let results: Vec<_> = match min_ssim {
+        Some(min_ssim) => converter
+            .compress_batch_to_ssim(files, format, min_ssim, output)
+            .into_iter()
+            .map(|result| result.map(|(path, _)| path))
+            .collect(),
+        None => converter.convert_batch(files, format, output),
+    };
 
     let mut success = 0;
     let mut failed = 0;
@@ -254,6 +614,404 @@ async fn handle_batch(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
+fn handle_contact_sheet(
+    inputs: Vec<PathBuf>,
+    output: PathBuf,
+    dpi: Option<f32>,
+    captions: bool,
+    layout: LayoutArgs,
+    config: &Config,
+    formatter: &OutputFormatter,
+    progress: &ProgressReporter,
+) -> Result<()> {
+    let format = MediaFormat::detect(&output)
+        .context(format!("Unsupported output format: {}", output.display()))?;
+
+    let mut options = pdf_options(&layout, Some(dpi.unwrap_or(DEFAULT_CONTACT_SHEET_DPI)))?;
+    if layout.grid.is_none() {
+        options.grid = Grid::new(5, 4)?;
+    }
+    options.captions = captions;
+
+    let spinner = progress.create_spinner("Laying out contact sheet...");
+
+    let mut converter = Converter::new()?;
+    converter.set_metadata_policy(config.metadata_policy()?);
+    converter.set_auto_orient(config.auto_orient);
+    let outputs = converter.contact_sheet(inputs.clone(), output, format, Some(options))?;
+
+    ProgressReporter::finish_bar(&spinner, "Done");
+    formatter.success(&format!(
+        "Laid out {} images on {}",
+        inputs.len(),
+        if outputs.len() == 1 {
+            formatter.format_path(&outputs[0])
+        } else {
+            format!("{} pages", outputs.len())
+        }
+    ));
+    if outputs.len() > 1 {
+        for output in &outputs {
+            formatter.info(&formatter.format_path(output));
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the metrics of `candidate` against `reference`; false when a threshold is missed
+#[allow(clippy::too_many_arguments)]
+fn handle_compare(
+    reference: PathBuf,
+    candidate: PathBuf,
+    heatmap: Option<PathBuf>,
+    thresholds: QualityThresholds,
+    config: &Config,
+    formatter: &OutputFormatter,
+    progress: &ProgressReporter,
+) -> Result<bool> {
+    let mut converter = Converter::new()?;
+    converter.set_auto_orient(config.auto_orient);
+
+    let spinner = progress.create_spinner("Comparing...");
+    let metric = converter.compare(&reference, &candidate, heatmap.as_deref())?;
+    ProgressReporter::finish_bar(&spinner, "Done");
+
+    formatter.info(&format!(
+        "{} → {}",
+        formatter.format_path(&reference),
+        formatter.format_path(&candidate)
+    ));
+    println!("{:<12}{:.2} dB", "PSNR:", metric.psnr);
+    println!("{:<12}{:.4}", "SSIM:", metric.ssim);
+    println!("{:<12}{:.4}", "MS-SSIM:", metric.ms_ssim);
+    println!("{:<12}{:.1}", "Perceptual:", metric.perceptual);
+    println!("{:<12}{:.4}", "MSE:", metric.mse);
+    if let Some(heatmap) = &heatmap {
+        formatter.success(&format!("Wrote heatmap to {}", formatter.format_path(heatmap)));
+    }
+
+    let violations = thresholds.violations(&metric);
+    for violation in &violations {
+        formatter.error(violation);
+    }
+    if !thresholds.is_empty() && violations.is_empty() {
+        formatter.success("All thresholds met");
+    }
+    Ok(violations.is_empty())
+}
+
+fn handle_extract_images(
+    input: PathBuf,
+    output: Option<PathBuf>,
+    formatter: &OutputFormatter,
+    progress: &ProgressReporter,
+) -> Result<()> {
+    let mut converter = Converter::new()?;
+    unlock_pdfs(&mut converter, std::slice::from_ref(&input), formatter)?;
+
+    let spinner = progress.create_spinner("Extracting images...");
+    let images = converter.extract_pdf_images(&input, output)?;
+
+    ProgressReporter::finish_bar(&spinner, "Done");
+
+    if images.is_empty() {
+        formatter.warn(&format!("No embedded images found in {}", formatter.format_path(&input)));
+        return Ok(());
+    }
+
+    for image in &images {
+        formatter.success(&format!(
+            "Page {}: {} ({}x{}{})",
+            image.page,
+            formatter.format_path(&image.path),
+            image.width,
+            image.height,
+            if image.passthrough { ", original bytes" } else { "" }
+        ));
+    }
+    formatter.info(&format!("Extracted {} images", images.len()));
+
+    Ok(())
+}
+
+fn handle_pdf(
+    action: PdfCommands,
+    formatter: &OutputFormatter,
+    progress: &ProgressReporter,
+) -> Result<()> {
+    let mut converter = Converter::new()?;
+    let (inputs, encryption) = match &action {
+        PdfCommands::Merge {
+            inputs, encryption, ..
+        } => (inputs.clone(), encryption),
+        PdfCommands::Split {
+            input, encryption, ..
+        }
+        | PdfCommands::Reorder {
+            input, encryption, ..
+        }
+        | PdfCommands::Rotate {
+            input, encryption, ..
+        }
+        | PdfCommands::Info {
+            input, encryption, ..
+        }
+        | PdfCommands::Delete {
+            input, encryption, ..
+        } => (vec![input.clone()], encryption),
+    };
+    if let PdfCommands::Info { info, .. } = &action {
+        if encryption.encrypt && document_info(info)?.is_empty() {
+            anyhow::bail!("--encrypt only applies when setting properties, e.g. --title");
+        }
+    }
+    // Inputs are unlocked before the output's password is asked for
+    let unlocked = unlock_pdfs(&mut converter, &inputs, formatter)?;
+    let encryption = pdf_encryption(encryption)?;
+    let unprotected = unlocked && encryption.is_none();
+    // Page edits write with the converter's encryption
+    converter.set_pdf_options(PdfOptions {
+        encryption,
+        ..Default::default()
+    });
+
+    let spinner = progress.create_spinner("Processing PDF...");
+    let (input, edit, output) = match action {
+        PdfCommands::Merge { inputs, output, .. } => {
+            let result = converter.merge_to_pdf(inputs.clone(), output, None)?;
+
+            ProgressReporter::finish_bar(&spinner, "Done");
+            formatter.success(&format!(
+                "Merged {} files into {}",
+                inputs.len(),
+                formatter.format_path(&result)
+            ));
+            if unprotected {
+                warn_unprotected(formatter);
+            }
+            return Ok(());
+        }
+
+        PdfCommands::Split {
+            input,
+            ranges,
+            output,
+            ..
+        } => {
+            let ranges = ranges
+                .iter()
+                .map(|range| PageSelection::parse(range))
+                .collect::<transmute_common::Result<Vec<_>>>()?;
+            let outputs = converter.split_pdf(&input, &ranges, output)?;
+
+            ProgressReporter::finish_bar(&spinner, "Done");
+            formatter.success(&format!(
+                "Split {} into {} files",
+                formatter.format_path(&input),
+                outputs.len()
+            ));
+            for output in &outputs {
+                formatter.info(&formatter.format_path(output));
+            }
+            if unprotected {
+                warn_unprotected(formatter);
+            }
+            return Ok(());
+        }
+
+        PdfCommands::Reorder {
+            input,
+            order,
+            output,
+            ..
+        } => (input, PageEdit::Reorder(PageSelection::parse(&order)?), output),
+
+        PdfCommands::Rotate {
+            input,
+            degrees,
+            pages,
+            output,
+            ..
+        } => {
+            let pages = match pages {
+                Some(spec) => PageSelection::parse(&spec)?,
+                None => PageSelection::all(),
+            };
+            (input, PageEdit::Rotate { pages, degrees }, output)
+        }
+
+        PdfCommands::Delete {
+            input,
+            pages,
+            output,
+            ..
+        } => (input, PageEdit::Delete(PageSelection::parse(&pages)?), output),
+
+        PdfCommands::Info {
+            input,
+            info,
+            output,
+            ..
+        } => {
+            let info = document_info(&info)?;
+            if info.is_empty() {
+                if output.is_some() {
+                    anyhow::bail!("-o only applies when setting properties, e.g. --title");
+                }
+                let info = converter.pdf_info(&input)?;
+
+                ProgressReporter::finish_bar(&spinner, "Done");
+                print_pdf_info(&info, formatter);
+                return Ok(());
+            }
+
+            let result = converter.edit_pdf_info(&input, &info, output)?;
+
+            ProgressReporter::finish_bar(&spinner, "Done");
+            formatter.success(&format!(
+                "Updated properties of {} → {}",
+                formatter.format_path(&input),
+                formatter.format_path(&result)
+            ));
+            if unprotected {
+                warn_unprotected(formatter);
+            }
+            return Ok(());
+        }
+    };
+
+    let result = converter.edit_pdf(&input, &[edit], output)?;
+
+    ProgressReporter::finish_bar(&spinner, "Done");
+    formatter.success(&format!(
+        "Edited {} → {}",
+        formatter.format_path(&input),
+        formatter.format_path(&result)
+    ));
+    if unprotected {
+        warn_unprotected(formatter);
+    }
+
+    Ok(())
+}
+
+/// Read a password from the terminal without echoing it, or a line of stdin when
+/// there is no terminal
+fn prompt_password(prompt: &str) -> Result<String> {
+    let term = console::Term::stderr();
+    if term.is_term() {
+        term.write_str(prompt)?;
+        return Ok(term.read_secure_line()?);
+    }
+
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line)? == 0 {
+        anyhow::bail!("No password on standard input for \"{}\"", prompt.trim_end_matches([':', ' ']));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Prompt for a new password, twice when it is typed at a terminal
+fn new_password(name: &str) -> Result<String> {
+    let password = prompt_password(&format!("{}: ", name))?;
+    if console::Term::stderr().is_term()
+        && prompt_password(&format!("Repeat {}: ", name.to_lowercase()))? != password
+    {
+        anyhow::bail!("Passwords do not match");
+    }
+    Ok(password)
+}
+
+/// Prompt for the passwords of --encrypt
+fn pdf_encryption(args: &EncryptionArgs) -> Result<Option<PdfEncryption>> {
+    if !args.encrypt {
+        return Ok(None);
+    }
+
+    let mut encryption = PdfEncryption {
+        user_password: new_password("Password to open the PDF")?,
+        ..Default::default()
+    };
+    if args.owner_password {
+        encryption.owner_password = Some(new_password("Owner password")?);
+    }
+    for permission in &args.deny {
+        encryption.permissions.deny(permission)?;
+    }
+    if encryption.user_password.is_empty() && args.deny.is_empty() {
+        anyhow::bail!("An empty password lets anyone open the PDF; type one, or restrict it with --deny");
+    }
+    Ok(Some(encryption))
+}
+
+/// Prompt for the passwords of protected input PDFs, three tries each.
+///
+/// Returns whether any input needed one.
+fn unlock_pdfs(
+    converter: &mut Converter,
+    inputs: &[PathBuf],
+    formatter: &OutputFormatter,
+) -> Result<bool> {
+    let mut unlocked = false;
+    for input in inputs
+        .iter()
+        .filter(|input| MediaFormat::detect(input) == Some(MediaFormat::Pdf))
+    {
+        let mut tries = 0;
+        while converter.pdf_needs_password(input)? {
+            if tries == 3 {
+                anyhow::bail!("Incorrect password for {}", formatter.format_path(input));
+            }
+            if tries > 0 {
+                formatter.warn("Incorrect password, try again");
+            }
+            let password = prompt_password(&format!("Password for {}: ", input.display()))?;
+            converter.set_pdf_password(input.clone(), password);
+            tries += 1;
+            unlocked = true;
+        }
+    }
+    Ok(unlocked)
+}
+
+fn warn_unprotected(formatter: &OutputFormatter) {
+    formatter.warn("The input was password protected, the output is not (add --encrypt to protect it)");
+}
+
+fn print_pdf_info(info: &PdfInfo, formatter: &OutputFormatter) {
+    const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %:z";
+    let properties = [
+        ("Title", info.title.clone()),
+        ("Author", info.author.clone()),
+        ("Subject", info.subject.clone()),
+        ("Keywords", info.keywords.clone()),
+        ("Creator", info.creator.clone()),
+        ("Producer", info.producer.clone()),
+        (
+            "Created",
+            info.creation_date
+                .map(|date| date.format(DATE_FORMAT).to_string()),
+        ),
+        (
+            "Modified",
+            info.modification_date
+                .map(|date| date.format(DATE_FORMAT).to_string()),
+        ),
+    ];
+
+    if properties.iter().all(|(_, value)| value.is_none()) {
+        formatter.info("No document properties set");
+        return;
+    }
+    for (name, value) in properties {
+        if let Some(value) = value {
+            println!("{:<10}{}", format!("{}:", name), value);
+        }
+    }
+}
+
 fn handle_natural(
     command_parts: Vec<String>,
     config: &Config,
@@ -268,6 +1026,8 @@ fn handle_natural(
 
     let mut converter = Converter::new()?;
     converter.set_gpu_enabled(config.use_gpu);
+    converter.set_metadata_policy(config.metadata_policy()?);
+    converter.set_auto_orient(config.auto_orient);
 
     let outputs = converter.execute_command(&command)?;
 
@@ -297,6 +1057,11 @@ fn handle_config(action: ConfigCommands, formatter: &OutputFormatter) -> Result<
                 "parallel_jobs" => config.parallel_jobs = value.parse()?,
                 "show_progress" => config.show_progress = value.parse()?,
                 "colored_output" => config.colored_output = value.parse()?,
+                "auto_orient" => config.auto_orient = value.parse()?,
+                "metadata" => {
+                    config.metadata = value.clone();
+                    config.metadata_policy()?;
+                }
                 _ => anyhow::bail!("Unknown config key: {}", key),
             }
 
@@ -336,3 +1101,162 @@ fn parse_quality(quality_str: &str) -> Result<QualitySettings> {
         ),
     }
 }
+
+fn parse_color(color: &str) -> Result<[u8; 3]> {
+    match color.to_lowercase().as_str() {
+        "white" => return Ok([255, 255, 255]),
+        "black" => return Ok([0, 0, 0]),
+        _ => {}
+    }
+
+    let hex = color.trim_start_matches('#');
+    let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => anyhow::bail!(
+            "Invalid background colour: {}. Use #rrggbb, white or black",
+            color
+        ),
+    }
+}
+
+fn parse_transform(args: &TransformArgs) -> Result<TransformPipeline> {
+    let mut pipeline = TransformPipeline::new();
+
+    if let Some(degrees) = args.rotate {
+        let rotation = Rotation::from_degrees(degrees)
+            .context(format!("Invalid rotation: {}. Use 90, 180 or 270", degrees))?;
+        pipeline.push(ImageOp::Rotate(rotation));
+    }
+
+    if let Some(flip) = &args.flip {
+        let axis = match flip.to_lowercase().as_str() {
+            "h" | "horizontal" => FlipAxis::Horizontal,
+            "v" | "vertical" => FlipAxis::Vertical,
+            _ => anyhow::bail!("Invalid flip: {}. Use horizontal or vertical", flip),
+        };
+        pipeline.push(ImageOp::Flip(axis));
+    }
+
+    if let Some(crop) = &args.crop {
+        let (w, h) = crop
+            .split_once(':')
+            .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+            .context(format!("Invalid crop ratio: {}. Use W:H (e.g. 16:9)", crop))?;
+        pipeline.push(ImageOp::CropAspect {
+            width: w,
+            height: h,
+        });
+    }
+
+    if let Some(resize) = &args.resize {
+        let (w, h) = resize
+            .to_lowercase()
+            .split_once('x')
+            .map(|(w, h)| (w.trim().to_string(), h.trim().to_string()))
+            .context(format!(
+                "Invalid size: {}. Use WIDTHxHEIGHT (e.g. 800x600)",
+                resize
+            ))?;
+        let parse_side = |side: &str| -> Result<Option<u32>> {
+            if side.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(
+                    side.parse().context(format!("Invalid size: {}", resize))?,
+                ))
+            }
+        };
+        pipeline.push(ImageOp::Resize {
+            width: parse_side(&w)?,
+            height: parse_side(&h)?,
+        });
+    }
+
+    if let Some(max) = args.max_edge {
+        pipeline.push(ImageOp::MaxEdge(max));
+    }
+
+    Ok(pipeline)
+}
+
+fn webp_options(args: &WebpArgs) -> WebpOptions {
+    if args.lossless {
+        WebpOptions::lossless()
+    } else if let Some(level) = args.near_lossless {
+        WebpOptions::near_lossless(level)
+    } else if let Some(quality) = args.webp_quality {
+        WebpOptions::lossy(quality as f32)
+    } else {
+        WebpOptions::default()
+    }
+}
+
+fn pdf_options(layout: &LayoutArgs, dpi: Option<f32>) -> Result<PdfOptions> {
+    let mut options = PdfOptions::default();
+    if let Some(size) = &layout.page_size {
+        options.page_size = size.parse()?;
+    }
+    if let Some(orientation) = &layout.orientation {
+        options.orientation = orientation.parse()?;
+    }
+    if let Some(margin) = layout.margin {
+        options.margin_mm = margin;
+    }
+    if let Some(fit) = &layout.fit {
+        options.fit = fit.parse()?;
+    }
+    if let Some(grid) = &layout.grid {
+        options.grid = grid.parse()?;
+    }
+    if let Some(spacing) = layout.spacing {
+        options.spacing_mm = spacing;
+    }
+    if let Some(dpi) = dpi {
+        options.dpi = dpi;
+    }
+    Ok(options)
+}
+
+fn document_info(args: &DocumentInfoArgs) -> Result<PdfInfo> {
+    let date = |text: &Option<String>| text.as_deref().map(PdfInfo::parse_date).transpose();
+    Ok(PdfInfo {
+        title: args.title.clone(),
+        author: args.author.clone(),
+        subject: args.subject.clone(),
+        keywords: args.keywords.clone(),
+        creator: args.creator.clone(),
+        producer: args.producer.clone(),
+        creation_date: date(&args.created)?,
+        modification_date: date(&args.modified)?,
+    })
+}
+
+fn page_labels(args: &PdfArgs) -> Result<Option<PageLabels>> {
+    if args.page_labels.is_none()
+        && args.page_label_prefix.is_none()
+        && args.page_label_start.is_none()
+    {
+        return Ok(None);
+    }
+
+    let mut labels = PageLabels::default();
+    if let Some(style) = &args.page_labels {
+        labels.style = style.parse()?;
+    }
+    if let Some(prefix) = &args.page_label_prefix {
+        labels.prefix = prefix.clone();
+    }
+    if let Some(start) = args.page_label_start {
+        labels.start = start;
+    }
+    Ok(Some(labels))
+}
+
+fn avif_options(args: &AvifArgs) -> AvifOptions {
+    let defaults = AvifOptions::default();
+    AvifOptions::new(
+        args.avif_quality.unwrap_or(defaults.quality),
+        args.avif_speed.unwrap_or(defaults.speed),
+    )
+}
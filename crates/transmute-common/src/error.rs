@@ -12,6 +12,12 @@ pub enum Error {
     #[error("File not found: {0}")]
     FileNotFound(PathBuf),
 
+    #[error("{0} is password protected")]
+    PasswordRequired(PathBuf),
+
+    #[error("Incorrect password for {0}")]
+    IncorrectPassword(PathBuf),
+
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
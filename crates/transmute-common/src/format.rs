@@ -1,5 +1,19 @@
+use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 
+/// Number of leading bytes needed by [`MediaFormat::sniff`], enough for an
+/// ISO-BMFF `ftyp` box with a few compatible brands
+const SNIFF_LEN: usize = 64;
+
+/// `ftyp` brands of AVIF images and sequences
+const AVIF_BRANDS: [&[u8; 4]; 2] = [b"avif", b"avis"];
+
+/// `ftyp` brands of HEVC-coded HEIF (iPhone photos) and generic HEIF
+const HEIF_BRANDS: [&[u8; 4]; 8] = [
+    b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1", b"msf1",
+];
+
 /// Supported media formats
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MediaFormat {
@@ -9,6 +23,9 @@ pub enum MediaFormat {
     Tiff,
     Bmp,
     Gif,
+    Avif,
+    Heif,
+    Jxl,
     Pdf,
 }
 
@@ -20,6 +37,90 @@ impl MediaFormat {
             .and_then(Self::from_extension)
     }
 
+    /// Detect format from magic bytes at the start of a file
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some(Self::Png),
+            [0xFF, 0xD8, 0xFF, ..] => Some(Self::Jpeg),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(Self::Webp),
+            [b'I', b'I', 0x2A, 0x00, ..] | [b'M', b'M', 0x00, 0x2A, ..] => Some(Self::Tiff),
+            [b'B', b'M', ..] => Some(Self::Bmp),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(Self::Gif),
+            [b'%', b'P', b'D', b'F', b'-', ..] => Some(Self::Pdf),
+            // Bare JPEG XL codestream or ISO-BMFF container signature
+            [0xFF, 0x0A, ..]
+            | [0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A, ..] => {
+                Some(Self::Jxl)
+            }
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] => Self::sniff_ftyp(bytes),
+            _ => None,
+        }
+    }
+
+    /// AVIF or HEIF from an ISO-BMFF `ftyp` box. AVIF files often carry the
+    /// generic HEIF `mif1`/`msf1` major brand, so an AVIF brand anywhere in
+    /// the box wins
+    fn sniff_ftyp(bytes: &[u8]) -> Option<Self> {
+        let size = u32::from_be_bytes(bytes.get(..4)?.try_into().ok()?) as usize;
+        let major = bytes.get(8..12)?;
+        // Compatible brands follow the minor version, up to the end of the box
+        let compatible = bytes.get(16..size.min(bytes.len())).unwrap_or_default();
+        let brands: Vec<&[u8]> = std::iter::once(major)
+            .chain(compatible.chunks_exact(4))
+            .collect();
+
+        let has_brand = |set: &[&[u8; 4]]| {
+            brands
+                .iter()
+                .any(|brand| set.iter().any(|known| *brand == known.as_slice()))
+        };
+
+        if has_brand(&AVIF_BRANDS) {
+            Some(Self::Avif)
+        } else if has_brand(&HEIF_BRANDS) {
+            Some(Self::Heif)
+        } else {
+            None
+        }
+    }
+
+    /// Detect format from file contents only
+    pub fn from_content(path: &Path) -> std::io::Result<Option<Self>> {
+        let mut header = Vec::with_capacity(SNIFF_LEN);
+        File::open(path)?
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut header)?;
+        Ok(Self::sniff(&header))
+    }
+
+    /// Detect format from contents, falling back to the extension.
+    ///
+    /// A mismatch between the two is logged as a warning; the content wins.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let by_ext = Self::from_path(path);
+        let by_content = match Self::from_content(path) {
+            Ok(format) => format,
+            Err(e) => {
+                tracing::debug!("Could not sniff {:?}: {}", path, e);
+                None
+            }
+        };
+
+        match (by_content, by_ext) {
+            (Some(content), Some(ext)) if content != ext => {
+                tracing::warn!(
+                    "{:?} has a .{} extension but contains {} data",
+                    path,
+                    ext.extension(),
+                    content
+                );
+                Some(content)
+            }
+            (Some(content), _) => Some(content),
+            (None, ext) => ext,
+        }
+    }
+
     /// Parse from extension string
     pub fn from_extension(ext: &str) -> Option<Self> {
         match ext.to_lowercase().as_str() {
@@ -29,6 +130,10 @@ impl MediaFormat {
             "tif" | "tiff" => Some(Self::Tiff),
             "bmp" => Some(Self::Bmp),
             "gif" => Some(Self::Gif),
+            "avif" => Some(Self::Avif),
+            "heic" | "heif" => Some(Self::Heif),
+            "jxl" => Some(Self::Jxl),
+            "apng" => Some(Self::Png),
             "pdf" => Some(Self::Pdf),
             _ => None,
         }
@@ -43,6 +148,9 @@ impl MediaFormat {
             Self::Tiff => "tiff",
             Self::Bmp => "bmp",
             Self::Gif => "gif",
+            Self::Avif => "avif",
+            Self::Heif => "heic",
+            Self::Jxl => "jxl",
             Self::Pdf => "pdf",
         }
     }
@@ -61,7 +169,9 @@ impl MediaFormat {
             Self::Tiff => Some(image::ImageFormat::Tiff),
             Self::Bmp => Some(image::ImageFormat::Bmp),
             Self::Gif => Some(image::ImageFormat::Gif),
-            Self::Pdf => None,
+            Self::Avif => Some(image::ImageFormat::Avif),
+            // Handled by libheif/libjxl, not the image crate
+            Self::Heif | Self::Jxl | Self::Pdf => None,
         }
     }
 
@@ -79,6 +189,9 @@ impl MediaFormat {
             Self::Tiff => "image/tiff",
             Self::Bmp => "image/bmp",
             Self::Gif => "image/gif",
+            Self::Avif => "image/avif",
+            Self::Heif => "image/heic",
+            Self::Jxl => "image/jxl",
             Self::Pdf => "application/pdf",
         }
     }
@@ -98,6 +211,79 @@ mod tests {
     fn test_format_detection() {
         assert_eq!(MediaFormat::from_extension("png"), Some(MediaFormat::Png));
         assert_eq!(MediaFormat::from_extension("JPG"), Some(MediaFormat::Jpeg));
+        assert_eq!(MediaFormat::from_extension("avif"), Some(MediaFormat::Avif));
         assert_eq!(MediaFormat::from_extension("unknown"), None);
     }
+
+    #[test]
+    fn test_sniff_magic_bytes() {
+        assert_eq!(
+            MediaFormat::sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some(MediaFormat::Png)
+        );
+        assert_eq!(
+            MediaFormat::sniff(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some(MediaFormat::Jpeg)
+        );
+        assert_eq!(
+            MediaFormat::sniff(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some(MediaFormat::Webp)
+        );
+        assert_eq!(MediaFormat::sniff(b"GIF89a"), Some(MediaFormat::Gif));
+        assert_eq!(MediaFormat::sniff(b"%PDF-1.7"), Some(MediaFormat::Pdf));
+        assert_eq!(
+            MediaFormat::sniff(b"\0\0\0\x1cftypavif"),
+            Some(MediaFormat::Avif)
+        );
+        assert_eq!(
+            MediaFormat::sniff(b"\0\0\0\x18ftypheic"),
+            Some(MediaFormat::Heif)
+        );
+        assert_eq!(
+            MediaFormat::sniff(b"\0\0\0\x18ftypmif1\0\0\0\0mif1heic"),
+            Some(MediaFormat::Heif)
+        );
+        // Generic HEIF major brand, AVIF in the compatible brands
+        assert_eq!(
+            MediaFormat::sniff(b"\0\0\0\x1cftypmif1\0\0\0\0mif1miafavif"),
+            Some(MediaFormat::Avif)
+        );
+        assert_eq!(
+            MediaFormat::sniff(b"\0\0\0\x18ftypmsf1\0\0\0\0msf1avis"),
+            Some(MediaFormat::Avif)
+        );
+        // Other ISO-BMFF files, such as MP4 video
+        assert_eq!(
+            MediaFormat::sniff(b"\0\0\0\x18ftypisom\0\0\0\0isomiso2"),
+            None
+        );
+        assert_eq!(
+            MediaFormat::sniff(&[0xFF, 0x0A, 0xFA]),
+            Some(MediaFormat::Jxl)
+        );
+        assert_eq!(MediaFormat::sniff(b"hello"), None);
+        assert_eq!(MediaFormat::sniff(&[]), None);
+    }
+
+    #[test]
+    fn test_detect_prefers_content() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // PNG data behind a .jpg extension
+        let mislabeled = dir.path().join("photo.jpg");
+        image::DynamicImage::new_rgb8(2, 2)
+            .save_with_format(&mislabeled, image::ImageFormat::Png)
+            .unwrap();
+        assert_eq!(MediaFormat::detect(&mislabeled), Some(MediaFormat::Png));
+
+        // No extension at all
+        let bare = dir.path().join("scan");
+        std::fs::copy(&mislabeled, &bare).unwrap();
+        assert_eq!(MediaFormat::detect(&bare), Some(MediaFormat::Png));
+
+        // Unknown content falls back to the extension
+        let text = dir.path().join("notes.gif");
+        std::fs::write(&text, b"not an image").unwrap();
+        assert_eq!(MediaFormat::detect(&text), Some(MediaFormat::Gif));
+    }
 }
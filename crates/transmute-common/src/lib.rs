@@ -1,13 +1,19 @@
 pub mod error;
 pub mod format;
+pub mod metadata;
+pub mod pages;
 pub mod path;
+pub mod size;
 
 #[cfg(feature = "gpu")]
 pub mod gpu;
 
 pub use error::{Error, Result};
 pub use format::MediaFormat;
+pub use metadata::{EmbeddedMetadata, MetadataPolicy};
+pub use pages::PageSelection;
 pub use path::PathManager;
+pub use size::FileSize;
 
 #[cfg(feature = "gpu")]
 pub use gpu::GpuContext;
@@ -0,0 +1,887 @@
+use crate::{Error, MediaFormat, Result};
+use std::io::Write;
+
+/// What happens to EXIF, XMP and ICC data when an image is re-encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MetadataPolicy {
+    /// Carry EXIF, XMP and ICC over unchanged
+    KeepAll,
+
+    /// Drop all metadata
+    StripAll,
+
+    /// Keep metadata but remove GPS location and personal/device identifiers.
+    /// XMP is dropped entirely because it can duplicate location data.
+    #[default]
+    StripPrivate,
+
+    /// Keep only the ICC color profile
+    KeepIccOnly,
+}
+
+impl MetadataPolicy {
+    pub const ALL: [Self; 4] = [
+        Self::KeepAll,
+        Self::StripAll,
+        Self::StripPrivate,
+        Self::KeepIccOnly,
+    ];
+
+    /// Parse from a config/CLI name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "keep" | "keep-all" | "all" => Some(Self::KeepAll),
+            "strip" | "strip-all" | "none" => Some(Self::StripAll),
+            "strip-private" | "private" | "strip-gps" => Some(Self::StripPrivate),
+            "icc" | "keep-icc" | "icc-only" | "keep-icc-only" => Some(Self::KeepIccOnly),
+            _ => None,
+        }
+    }
+
+    /// Canonical config/CLI name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::KeepAll => "keep-all",
+            Self::StripAll => "strip-all",
+            Self::StripPrivate => "strip-private",
+            Self::KeepIccOnly => "keep-icc",
+        }
+    }
+
+    /// Apply the policy to a JPEG's own metadata segments, leaving its image
+    /// data untouched (for repacking that never decodes the pixels)
+    pub fn apply_to_jpeg(&self, jpeg: &[u8]) -> Result<Vec<u8>> {
+        container::filter_jpeg(jpeg, *self)
+    }
+
+    /// Human readable label for UIs
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::KeepAll => "Keep all",
+            Self::StripAll => "Strip all",
+            Self::StripPrivate => "Strip GPS & private",
+            Self::KeepIccOnly => "Keep color profile only",
+        }
+    }
+}
+
+impl std::fmt::Display for MetadataPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Metadata blocks carried from decode to encode
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmbeddedMetadata {
+    /// Raw EXIF (TIFF structure, without the `Exif\0\0` prefix)
+    pub exif: Option<Vec<u8>>,
+
+    /// XMP packet (UTF-8 XML)
+    pub xmp: Option<Vec<u8>>,
+
+    /// ICC color profile
+    pub icc: Option<Vec<u8>>,
+
+    /// Rendering intent of a PNG `sRGB` chunk (0-3), which marks the pixels
+    /// as sRGB without a profile. Kept wherever the ICC profile is
+    pub srgb: Option<u8>,
+}
+
+impl EmbeddedMetadata {
+    pub fn is_empty(&self) -> bool {
+        !self.has_blocks() && self.srgb.is_none()
+    }
+
+    /// Whether there is EXIF, XMP or ICC data; other formats take sRGB as given
+    fn has_blocks(&self) -> bool {
+        self.exif.is_some() || self.xmp.is_some() || self.icc.is_some()
+    }
+
+    /// Store EXIF, normalizing away a leading `Exif\0\0` header
+    pub fn set_exif(&mut self, exif: Vec<u8>) {
+        self.exif = Some(match exif.strip_prefix(EXIF_HEADER) {
+            Some(tiff) => tiff.to_vec(),
+            None => exif,
+        });
+    }
+
+    /// Apply a policy, returning the metadata that may be written out
+    pub fn filtered(&self, policy: MetadataPolicy) -> Self {
+        match policy {
+            MetadataPolicy::KeepAll => self.clone(),
+            MetadataPolicy::StripAll => Self::default(),
+            MetadataPolicy::KeepIccOnly => Self {
+                icc: self.icc.clone(),
+                srgb: self.srgb,
+                ..Default::default()
+            },
+            MetadataPolicy::StripPrivate => Self {
+                exif: self.exif.as_deref().and_then(exif::strip_private),
+                xmp: None,
+                icc: self.icc.clone(),
+                srgb: self.srgb,
+            },
+        }
+    }
+
+    /// Write metadata into an encoded image, replacing whatever the codec emitted.
+    ///
+    /// Formats without metadata support are returned unchanged.
+    pub fn embed(&self, data: Vec<u8>, format: MediaFormat) -> Result<Vec<u8>> {
+        match format {
+            MediaFormat::Jpeg => container::embed_jpeg(&data, self),
+            MediaFormat::Png => container::embed_png(&data, self),
+            MediaFormat::Webp => container::embed_webp(&data, self),
+            _ => {
+                if self.has_blocks() {
+                    tracing::warn!("Metadata is not written for {} output", format);
+                }
+                Ok(data)
+            }
+        }
+    }
+}
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+
+/// EXIF (TIFF) scrubbing
+mod exif {
+    const TAG_GPS_IFD: u16 = 0x8825;
+    const TAG_EXIF_IFD: u16 = 0x8769;
+
+    /// Tags holding personal or device-identifying data
+    const PRIVATE_TAGS: &[u16] = &[
+        0x013B, // Artist
+        0x013C, // HostComputer
+        0x927C, // MakerNote
+        0x9286, // UserComment
+        0xA420, // ImageUniqueID
+        0xA430, // CameraOwnerName
+        0xA431, // BodySerialNumber
+        0xA435, // LensSerialNumber
+    ];
+
+    struct Tiff {
+        data: Vec<u8>,
+        big_endian: bool,
+    }
+
+    impl Tiff {
+        fn u16_at(&self, offset: usize) -> Option<u16> {
+            let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+            Some(if self.big_endian {
+                u16::from_be_bytes(bytes)
+            } else {
+                u16::from_le_bytes(bytes)
+            })
+        }
+
+        fn u32_at(&self, offset: usize) -> Option<u32> {
+            let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+            Some(if self.big_endian {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            })
+        }
+
+        fn put_u16(&mut self, offset: usize, value: u16) {
+            let bytes = if self.big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            };
+            self.data[offset..offset + 2].copy_from_slice(&bytes);
+        }
+
+        fn zero(&mut self, start: usize, len: usize) -> Option<()> {
+            self.data.get_mut(start..start.checked_add(len)?)?.fill(0);
+            Some(())
+        }
+
+        /// Byte range of an IFD (count + entries + next pointer)
+        fn ifd_len(&self, ifd: usize) -> Option<usize> {
+            let count = self.u16_at(ifd)? as usize;
+            let len = 2 + count * 12 + 4;
+            self.data.get(ifd..ifd + len)?;
+            Some(len)
+        }
+
+        /// Zero the value of an entry if it is stored outside the entry itself
+        fn zero_external_value(&mut self, entry: usize) -> Option<()> {
+            let kind = self.u16_at(entry + 2)?;
+            let count = self.u32_at(entry + 4)? as usize;
+            let size = type_size(kind).checked_mul(count)?;
+            if size > 4 {
+                let offset = self.u32_at(entry + 8)? as usize;
+                self.zero(offset, size)?;
+            }
+            Some(())
+        }
+
+        /// Remove an entry from an IFD, wiping its value
+        fn remove_entry(&mut self, ifd: usize, index: usize) -> Option<()> {
+            let len = self.ifd_len(ifd)?;
+            let count = self.u16_at(ifd)? as usize;
+            let entry = ifd + 2 + index * 12;
+            self.zero_external_value(entry)?;
+
+            // Shift later entries and the next-IFD pointer up by one slot
+            self.data.copy_within(entry + 12..ifd + len, entry);
+            self.zero(ifd + len - 12, 12)?;
+            self.put_u16(ifd, (count - 1) as u16);
+            Some(())
+        }
+
+        /// Wipe an entire IFD and everything it points to
+        fn wipe_ifd(&mut self, ifd: usize) -> Option<()> {
+            let len = self.ifd_len(ifd)?;
+            let count = self.u16_at(ifd)? as usize;
+            for i in 0..count {
+                self.zero_external_value(ifd + 2 + i * 12)?;
+            }
+            self.zero(ifd, len)
+        }
+
+        fn scrub_ifd(&mut self, ifd: usize, depth: u8) -> Option<()> {
+            if depth > 4 {
+                return None;
+            }
+
+            let mut index = 0;
+            while index < self.u16_at(ifd)? as usize {
+                let entry = ifd + 2 + index * 12;
+                let tag = self.u16_at(entry)?;
+
+                if tag == TAG_GPS_IFD {
+                    let gps = self.u32_at(entry + 8)? as usize;
+                    self.wipe_ifd(gps)?;
+                    self.remove_entry(ifd, index)?;
+                } else if PRIVATE_TAGS.contains(&tag) {
+                    self.remove_entry(ifd, index)?;
+                } else {
+                    if tag == TAG_EXIF_IFD {
+                        let sub = self.u32_at(entry + 8)? as usize;
+                        self.scrub_ifd(sub, depth + 1)?;
+                    }
+                    index += 1;
+                }
+            }
+            Some(())
+        }
+    }
+
+    fn type_size(kind: u16) -> usize {
+        match kind {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 | 11 | 13 => 4,
+            5 | 10 | 12 => 8,
+            _ => 1,
+        }
+    }
+
+    /// Remove GPS and private tags. Returns `None` if the EXIF cannot be parsed,
+    /// in which case the caller drops it rather than risk leaking location data.
+    pub(super) fn strip_private(exif: &[u8]) -> Option<Vec<u8>> {
+        let big_endian = match exif.get(0..2)? {
+            b"II" => false,
+            b"MM" => true,
+            _ => {
+                tracing::warn!("Dropping unparseable EXIF block");
+                return None;
+            }
+        };
+
+        let mut tiff = Tiff {
+            data: exif.to_vec(),
+            big_endian,
+        };
+
+        let scrubbed = (|| {
+            if tiff.u16_at(2)? != 42 {
+                return None;
+            }
+
+            // Walk IFD0 and the thumbnail IFD1
+            let mut ifd = tiff.u32_at(4)? as usize;
+            for _ in 0..2 {
+                if ifd == 0 {
+                    break;
+                }
+                tiff.scrub_ifd(ifd, 0)?;
+                let len = tiff.ifd_len(ifd)?;
+                ifd = tiff.u32_at(ifd + len - 4)? as usize;
+            }
+            Some(())
+        })();
+
+        match scrubbed {
+            Some(()) => Some(tiff.data),
+            None => {
+                tracing::warn!("Dropping unparseable EXIF block");
+                None
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(super) fn tags(exif: &[u8]) -> Vec<u16> {
+        let tiff = Tiff {
+            data: exif.to_vec(),
+            big_endian: &exif[0..2] == b"MM",
+        };
+        let mut tags = Vec::new();
+        let mut stack = vec![tiff.u32_at(4).unwrap() as usize];
+        while let Some(ifd) = stack.pop() {
+            for i in 0..tiff.u16_at(ifd).unwrap() as usize {
+                let entry = ifd + 2 + i * 12;
+                let tag = tiff.u16_at(entry).unwrap();
+                tags.push(tag);
+                if tag == TAG_EXIF_IFD || tag == TAG_GPS_IFD {
+                    stack.push(tiff.u32_at(entry + 8).unwrap() as usize);
+                }
+            }
+        }
+        tags
+    }
+}
+
+/// Writing metadata into JPEG/PNG/WebP containers
+mod container {
+    use super::*;
+
+    const MAX_SEGMENT: usize = u16::MAX as usize - 2;
+
+    pub(super) fn embed_jpeg(data: &[u8], meta: &EmbeddedMetadata) -> Result<Vec<u8>> {
+        if !data.starts_with(&[0xFF, 0xD8]) {
+            return Err(Error::ConversionError("Invalid JPEG stream".into()));
+        }
+
+        let mut out = Vec::with_capacity(data.len() + 1024);
+        out.extend_from_slice(&data[..2]);
+
+        // Copy header segments, dropping metadata the codec may have written
+        let mut pos = 2;
+        let mut inserted = false;
+        while pos + 4 <= data.len() && data[pos] == 0xFF {
+            let marker = data[pos + 1];
+            if marker == 0xDA {
+                break; // start of scan, entropy-coded data follows
+            }
+
+            let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+            let end = (pos + 2 + len).min(data.len());
+            let payload = &data[(pos + 4).min(end)..end];
+
+            if !inserted && marker != 0xE0 {
+                write_jpeg_segments(&mut out, meta);
+                inserted = true;
+            }
+
+            let is_metadata = (marker == 0xE1
+                && (payload.starts_with(EXIF_HEADER) || payload.starts_with(XMP_HEADER)))
+                || (marker == 0xE2 && payload.starts_with(ICC_HEADER));
+            if !is_metadata {
+                out.extend_from_slice(&data[pos..end]);
+            }
+            pos = end;
+        }
+
+        if !inserted {
+            write_jpeg_segments(&mut out, meta);
+        }
+        out.extend_from_slice(&data[pos..]);
+        Ok(out)
+    }
+
+    pub(super) fn filter_jpeg(data: &[u8], policy: MetadataPolicy) -> Result<Vec<u8>> {
+        if !data.starts_with(&[0xFF, 0xD8]) {
+            return Err(Error::ConversionError("Invalid JPEG stream".into()));
+        }
+
+        let mut out = Vec::with_capacity(data.len());
+        out.extend_from_slice(&data[..2]);
+
+        let mut pos = 2;
+        while pos + 4 <= data.len() && data[pos] == 0xFF {
+            let marker = data[pos + 1];
+            if marker == 0xDA {
+                break; // start of scan, entropy-coded data follows
+            }
+
+            let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+            let end = (pos + 2 + len).min(data.len());
+            let payload = &data[(pos + 4).min(end)..end];
+
+            let keep = match marker {
+                // JFIF and Adobe segments describe how to read the image data
+                0xE0 | 0xEE => true,
+                0xE1 if payload.starts_with(EXIF_HEADER) => match policy {
+                    MetadataPolicy::KeepAll => true,
+                    MetadataPolicy::StripPrivate => {
+                        // Scrubbing keeps every offset, so the block stays the same size
+                        if let Some(exif) = exif::strip_private(&payload[EXIF_HEADER.len()..]) {
+                            write_jpeg_segment(&mut out, 0xE1, &[EXIF_HEADER, &exif]);
+                        }
+                        false
+                    }
+                    MetadataPolicy::StripAll | MetadataPolicy::KeepIccOnly => false,
+                },
+                0xE2 if payload.starts_with(ICC_HEADER) => policy != MetadataPolicy::StripAll,
+                // XMP, IPTC, comments and other application data
+                0xE1..=0xEF | 0xFE => policy == MetadataPolicy::KeepAll,
+                _ => true,
+            };
+            if keep {
+                out.extend_from_slice(&data[pos..end]);
+            }
+            pos = end;
+        }
+
+        out.extend_from_slice(&data[pos..]);
+        Ok(out)
+    }
+
+    fn write_jpeg_segment(out: &mut Vec<u8>, marker: u8, parts: &[&[u8]]) {
+        let len: usize = parts.iter().map(|p| p.len()).sum::<usize>() + 2;
+        out.extend_from_slice(&[0xFF, marker]);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+        for part in parts {
+            out.extend_from_slice(part);
+        }
+    }
+
+    fn write_jpeg_segments(out: &mut Vec<u8>, meta: &EmbeddedMetadata) {
+        if let Some(exif) = &meta.exif {
+            if exif.len() + EXIF_HEADER.len() <= MAX_SEGMENT {
+                write_jpeg_segment(out, 0xE1, &[EXIF_HEADER, exif]);
+            } else {
+                tracing::warn!("EXIF block too large for JPEG, skipping");
+            }
+        }
+
+        if let Some(xmp) = &meta.xmp {
+            if xmp.len() + XMP_HEADER.len() <= MAX_SEGMENT {
+                write_jpeg_segment(out, 0xE1, &[XMP_HEADER, xmp]);
+            } else {
+                tracing::warn!("XMP packet too large for JPEG, skipping");
+            }
+        }
+
+        if let Some(icc) = &meta.icc {
+            // ICC profiles are split across numbered APP2 segments
+            let chunk_size = MAX_SEGMENT - ICC_HEADER.len() - 2;
+            let chunks: Vec<&[u8]> = icc.chunks(chunk_size).collect();
+            if chunks.len() > u8::MAX as usize {
+                tracing::warn!("ICC profile too large for JPEG, skipping");
+                return;
+            }
+            for (i, chunk) in chunks.iter().enumerate() {
+                let seq = [(i + 1) as u8, chunks.len() as u8];
+                write_jpeg_segment(out, 0xE2, &[ICC_HEADER, &seq, chunk]);
+            }
+        }
+    }
+
+    const PNG_SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    const XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+
+    pub(super) fn embed_png(data: &[u8], meta: &EmbeddedMetadata) -> Result<Vec<u8>> {
+        if !data.starts_with(PNG_SIGNATURE) {
+            return Err(Error::ConversionError("Invalid PNG stream".into()));
+        }
+
+        let mut out = Vec::with_capacity(data.len() + 1024);
+        out.extend_from_slice(PNG_SIGNATURE);
+
+        let mut pos = PNG_SIGNATURE.len();
+        while pos + 12 <= data.len() {
+            let len = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            let end = (pos + 12 + len).min(data.len());
+            let kind = &data[pos + 4..pos + 8];
+            let body = &data[(pos + 8).min(end)..(pos + 8 + len).min(end)];
+
+            let is_metadata = matches!(kind, b"eXIf" | b"iCCP" | b"sRGB")
+                || (kind == b"iTXt" && body.starts_with(XMP_KEYWORD));
+            if !is_metadata {
+                out.extend_from_slice(&data[pos..end]);
+            }
+
+            // Metadata chunks must precede PLTE/IDAT, so place them right after IHDR
+            if kind == b"IHDR" {
+                write_png_chunks(&mut out, meta)?;
+            }
+            pos = end;
+        }
+
+        Ok(out)
+    }
+
+    fn write_png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) {
+        out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(kind);
+        crc.update(body);
+        out.extend_from_slice(&crc.finalize().to_be_bytes());
+    }
+
+    fn write_png_chunks(out: &mut Vec<u8>, meta: &EmbeddedMetadata) -> Result<()> {
+        if let Some(icc) = &meta.icc {
+            let mut body = b"ICC Profile\0\0".to_vec();
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(&mut body, flate2::Compression::default());
+            encoder.write_all(icc)?;
+            encoder.finish()?;
+            write_png_chunk(out, b"iCCP", &body);
+        } else if let Some(intent) = meta.srgb {
+            // A PNG carries either a profile or the sRGB chunk, never both
+            write_png_chunk(out, b"sRGB", &[intent]);
+        }
+
+        if let Some(exif) = &meta.exif {
+            write_png_chunk(out, b"eXIf", exif);
+        }
+
+        if let Some(xmp) = &meta.xmp {
+            // keyword, NUL, uncompressed, method 0, empty language and translated keyword
+            let mut body = XMP_KEYWORD.to_vec();
+            body.extend_from_slice(&[0, 0, 0, 0, 0]);
+            body.extend_from_slice(xmp);
+            write_png_chunk(out, b"iTXt", &body);
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn embed_webp(data: &[u8], meta: &EmbeddedMetadata) -> Result<Vec<u8>> {
+        if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+            return Err(Error::ConversionError("Invalid WebP stream".into()));
+        }
+
+        let mut chunks: Vec<([u8; 4], &[u8])> = Vec::new();
+        let mut pos = 12;
+        while pos + 8 <= data.len() {
+            let kind: [u8; 4] = data[pos..pos + 4].try_into().unwrap();
+            let len = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize;
+            let body = &data[pos + 8..(pos + 8 + len).min(data.len())];
+            chunks.push((kind, body));
+            pos += 8 + len + (len & 1);
+        }
+
+        chunks.retain(|(kind, _)| !matches!(kind, b"ICCP" | b"EXIF" | b"XMP "));
+
+        if !meta.has_blocks() && !chunks.iter().any(|(kind, _)| kind == b"VP8X") {
+            return Ok(data.to_vec());
+        }
+
+        // Metadata requires the extended (VP8X) header
+        let mut vp8x = match chunks.iter().position(|(kind, _)| kind == b"VP8X") {
+            Some(i) => chunks.remove(i).1.to_vec(),
+            None => new_vp8x(&chunks)?,
+        };
+        vp8x[0] &= !(0x20 | 0x08 | 0x04);
+        if meta.icc.is_some() {
+            vp8x[0] |= 0x20;
+        }
+        if meta.exif.is_some() {
+            vp8x[0] |= 0x08;
+        }
+        if meta.xmp.is_some() {
+            vp8x[0] |= 0x04;
+        }
+
+        let mut body = b"WEBP".to_vec();
+        write_riff_chunk(&mut body, b"VP8X", &vp8x);
+        if let Some(icc) = &meta.icc {
+            write_riff_chunk(&mut body, b"ICCP", icc);
+        }
+        for (kind, chunk) in &chunks {
+            write_riff_chunk(&mut body, kind, chunk);
+        }
+        if let Some(exif) = &meta.exif {
+            write_riff_chunk(&mut body, b"EXIF", exif);
+        }
+        if let Some(xmp) = &meta.xmp {
+            write_riff_chunk(&mut body, b"XMP ", xmp);
+        }
+
+        let mut out = b"RIFF".to_vec();
+        out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    fn write_riff_chunk(out: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) {
+        out.extend_from_slice(kind);
+        out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        out.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            out.push(0);
+        }
+    }
+
+    /// Build a VP8X header from a simple-format (VP8/VP8L) bitstream
+    fn new_vp8x(chunks: &[([u8; 4], &[u8])]) -> Result<Vec<u8>> {
+        let invalid = || Error::ConversionError("Unrecognized WebP bitstream".into());
+        let has_alph = chunks.iter().any(|(kind, _)| kind == b"ALPH");
+
+        let (width, height, alpha) = chunks
+            .iter()
+            .find_map(|(kind, body)| match kind {
+                b"VP8 " if body.len() >= 10 => {
+                    let w = u16::from_le_bytes([body[6], body[7]]) & 0x3FFF;
+                    let h = u16::from_le_bytes([body[8], body[9]]) & 0x3FFF;
+                    Some((w as u32, h as u32, has_alph))
+                }
+                b"VP8L" if body.len() >= 5 && body[0] == 0x2F => {
+                    let bits = u32::from_le_bytes([body[1], body[2], body[3], body[4]]);
+                    let w = (bits & 0x3FFF) + 1;
+                    let h = ((bits >> 14) & 0x3FFF) + 1;
+                    Some((w, h, (bits >> 28) & 1 == 1))
+                }
+                _ => None,
+            })
+            .ok_or_else(invalid)?;
+
+        let mut vp8x = vec![0u8; 10];
+        if alpha {
+            vp8x[0] |= 0x10;
+        }
+        vp8x[4..7].copy_from_slice(&(width - 1).to_le_bytes()[..3]);
+        vp8x[7..10].copy_from_slice(&(height - 1).to_le_bytes()[..3]);
+        Ok(vp8x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::ImageDecoder as _;
+
+    /// Little-endian EXIF with Make, Artist, an Exif IFD (serial number) and a GPS IFD
+    fn sample_exif() -> Vec<u8> {
+        let mut t = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+
+        // IFD0 at 8: Make, Artist, ExifIFD, GPS
+        t.extend_from_slice(&4u16.to_le_bytes());
+        let entry = |t: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32| {
+            t.extend_from_slice(&tag.to_le_bytes());
+            t.extend_from_slice(&kind.to_le_bytes());
+            t.extend_from_slice(&count.to_le_bytes());
+            t.extend_from_slice(&value.to_le_bytes());
+        };
+        // layout: IFD0 = 8..62, strings at 62.., exif IFD at 100, gps IFD at 140
+        entry(&mut t, 0x010F, 2, 6, 62); // Make "Canon\0"
+        entry(&mut t, 0x013B, 2, 8, 68); // Artist "J. Doe\0\0"
+        entry(&mut t, 0x8769, 4, 1, 100);
+        entry(&mut t, 0x8825, 4, 1, 140);
+        t.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(t.len(), 62);
+        t.extend_from_slice(b"Canon\0");
+        t.extend_from_slice(b"J. Doe\0\0");
+        t.resize(100, 0);
+
+        // Exif IFD: ExposureTime-ish SHORT, BodySerialNumber
+        t.extend_from_slice(&2u16.to_le_bytes());
+        entry(&mut t, 0x8827, 3, 1, 100);
+        entry(&mut t, 0xA431, 2, 8, 130);
+        t.extend_from_slice(&0u32.to_le_bytes());
+        t.resize(130, 0);
+        t.extend_from_slice(b"SN12345\0");
+        t.resize(140, 0);
+
+        // GPS IFD: GPSLatitudeRef, GPSLatitude (3 rationals at 172)
+        t.extend_from_slice(&2u16.to_le_bytes());
+        entry(&mut t, 0x0001, 2, 2, u32::from_le_bytes(*b"N\0\0\0"));
+        entry(&mut t, 0x0002, 5, 3, 172);
+        t.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(t.len(), 170);
+        t.resize(172, 0);
+        for v in [48u32, 1, 51, 1, 30, 1] {
+            t.extend_from_slice(&v.to_le_bytes());
+        }
+        t
+    }
+
+    #[test]
+    fn test_policy_names() {
+        for policy in MetadataPolicy::ALL {
+            assert_eq!(MetadataPolicy::from_name(policy.name()), Some(policy));
+        }
+        assert_eq!(
+            MetadataPolicy::from_name("strip_gps"),
+            Some(MetadataPolicy::StripPrivate)
+        );
+        assert_eq!(MetadataPolicy::from_name("bogus"), None);
+    }
+
+    #[test]
+    fn test_strip_private_removes_gps() {
+        let exif = sample_exif();
+        assert!(exif::tags(&exif).contains(&0x0002));
+
+        let meta = EmbeddedMetadata {
+            exif: Some(exif),
+            xmp: Some(b"<x:xmpmeta/>".to_vec()),
+            icc: Some(vec![1, 2, 3]),
+            srgb: None,
+        };
+        let filtered = meta.filtered(MetadataPolicy::StripPrivate);
+        let scrubbed = filtered.exif.unwrap();
+
+        assert_eq!(exif::tags(&scrubbed), vec![0x010F, 0x8769, 0x8827]);
+        assert!(filtered.xmp.is_none());
+        assert_eq!(filtered.icc, Some(vec![1, 2, 3]));
+
+        // No trace of the GPS values, serial number or artist
+        let contains = |needle: &[u8]| scrubbed.windows(needle.len()).any(|w| w == needle);
+        assert!(!contains(&48u32.to_le_bytes()));
+        assert!(!contains(b"SN12345"));
+        assert!(!contains(b"J. Doe"));
+        assert!(contains(b"Canon"));
+    }
+
+    #[test]
+    fn test_strip_private_drops_garbage() {
+        let meta = EmbeddedMetadata {
+            exif: Some(b"not a tiff".to_vec()),
+            ..Default::default()
+        };
+        assert!(meta.filtered(MetadataPolicy::StripPrivate).exif.is_none());
+    }
+
+    #[test]
+    fn test_apply_to_jpeg_keeps_image_data() {
+        let mut jpeg = Vec::new();
+        image::DynamicImage::new_rgb8(8, 8)
+            .write_to(
+                &mut std::io::Cursor::new(&mut jpeg),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        let meta = EmbeddedMetadata {
+            exif: Some(sample_exif()),
+            xmp: Some(b"<x:xmpmeta/>".to_vec()),
+            icc: Some(vec![7; 300]),
+            srgb: None,
+        };
+        let mut jpeg = meta.embed(jpeg, MediaFormat::Jpeg).unwrap();
+        // A comment segment right after SOI
+        jpeg.splice(2..2, [0xFF, 0xFE, 0x00, 0x07, b'h', b'e', b'l', b'l', b'o']);
+
+        let contains = |data: &[u8], needle: &[u8]| data.windows(needle.len()).any(|w| w == needle);
+        let scan =
+            |data: &[u8]| data[data.windows(2).position(|w| w == [0xFF, 0xDA]).unwrap()..].to_vec();
+
+        assert_eq!(MetadataPolicy::KeepAll.apply_to_jpeg(&jpeg).unwrap(), jpeg);
+
+        let private = MetadataPolicy::StripPrivate.apply_to_jpeg(&jpeg).unwrap();
+        assert!(contains(&private, b"Canon"));
+        assert!(!contains(&private, b"SN12345"));
+        assert!(!contains(&private, b"xmpmeta"));
+        assert!(!contains(&private, b"hello"));
+        assert!(contains(&private, ICC_HEADER));
+        assert_eq!(scan(&private), scan(&jpeg));
+
+        let stripped = MetadataPolicy::StripAll.apply_to_jpeg(&jpeg).unwrap();
+        assert!(!contains(&stripped, EXIF_HEADER));
+        assert!(!contains(&stripped, ICC_HEADER));
+        assert_eq!(scan(&stripped), scan(&jpeg));
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+
+    #[test]
+    fn test_embed_roundtrip() {
+        let img = image::DynamicImage::new_rgba8(4, 3);
+        let meta = EmbeddedMetadata {
+            exif: Some(sample_exif()),
+            xmp: Some(b"<x:xmpmeta/>".to_vec()),
+            icc: Some(vec![7; 300]),
+            srgb: None,
+        };
+
+        for (format, image_format) in [
+            (MediaFormat::Jpeg, image::ImageFormat::Jpeg),
+            (MediaFormat::Png, image::ImageFormat::Png),
+            (MediaFormat::Webp, image::ImageFormat::WebP),
+        ] {
+            let img = if format == MediaFormat::Jpeg {
+                image::DynamicImage::ImageRgb8(img.to_rgb8())
+            } else {
+                img.clone()
+            };
+            let mut encoded = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut encoded), image_format)
+                .unwrap();
+
+            let embedded = meta.embed(encoded, format).unwrap();
+            let mut decoder =
+                image::ImageReader::with_format(std::io::Cursor::new(&embedded), image_format)
+                    .into_decoder()
+                    .unwrap();
+
+            assert_eq!(decoder.icc_profile().unwrap(), meta.icc, "{format}");
+            assert_eq!(
+                decoder
+                    .exif_metadata()
+                    .unwrap()
+                    .map(|e| { e.strip_prefix(EXIF_HEADER).map(<[u8]>::to_vec).unwrap_or(e) }),
+                meta.exif,
+                "{format}"
+            );
+            assert_eq!(decoder.xmp_metadata().unwrap(), meta.xmp, "{format}");
+            assert_eq!(decoder.dimensions(), (4, 3));
+
+            // Stripping removes it all again
+            let stripped = EmbeddedMetadata::default()
+                .embed(embedded.clone(), format)
+                .unwrap();
+            let mut decoder =
+                image::ImageReader::with_format(std::io::Cursor::new(&stripped), image_format)
+                    .into_decoder()
+                    .unwrap();
+            assert!(decoder.exif_metadata().unwrap().is_none(), "{format}");
+            assert!(decoder.icc_profile().unwrap().is_none(), "{format}");
+        }
+    }
+
+    #[test]
+    fn test_png_srgb_chunk() {
+        let mut encoded = Vec::new();
+        image::DynamicImage::new_rgb8(2, 2)
+            .write_to(
+                &mut std::io::Cursor::new(&mut encoded),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        let has_srgb = |png: &[u8]| png.windows(5).any(|w| w == b"sRGB\x01");
+
+        let srgb = EmbeddedMetadata {
+            srgb: Some(1),
+            ..Default::default()
+        };
+        let embedded = srgb.embed(encoded.clone(), MediaFormat::Png).unwrap();
+        assert!(has_srgb(&embedded));
+        assert!(image::load_from_memory(&embedded).is_ok());
+
+        // Kept wherever the profile is, and yields to one
+        for policy in [MetadataPolicy::KeepIccOnly, MetadataPolicy::StripPrivate] {
+            assert_eq!(srgb.filtered(policy).srgb, Some(1), "{policy}");
+        }
+        assert!(srgb.filtered(MetadataPolicy::StripAll).is_empty());
+        let with_icc = EmbeddedMetadata {
+            icc: Some(vec![7; 300]),
+            ..srgb
+        };
+        assert!(!has_srgb(
+            &with_icc.embed(encoded, MediaFormat::Png).unwrap()
+        ));
+    }
+}
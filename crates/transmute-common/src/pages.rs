@@ -0,0 +1,197 @@
+use crate::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// A page number in a selection, possibly relative to the end of the document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PageRef {
+    Number(u32),
+    Last,
+}
+
+impl PageRef {
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("last") {
+            return Some(Self::Last);
+        }
+        text.parse().ok().filter(|&n| n > 0).map(Self::Number)
+    }
+
+    fn resolve(self, page_count: u32) -> Result<u32> {
+        match self {
+            Self::Last if page_count > 0 => Ok(page_count),
+            Self::Number(n) if n <= page_count => Ok(n),
+            Self::Last => Err(Error::ConversionError("Document has no pages".into())),
+            Self::Number(n) => Err(Error::ConversionError(format!(
+                "Page {} is out of range (document has {} pages)",
+                n, page_count
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for PageRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Last => write!(f, "last"),
+        }
+    }
+}
+
+/// Pages to work on, written like `1-3,7,last` (1-based, inclusive ranges)
+///
+/// `5-` runs to the end of the document and `3-1` counts down.
+/// The empty selection (also spelled `all`) means every page.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PageSelection {
+    ranges: Vec<(PageRef, PageRef)>,
+}
+
+impl PageSelection {
+    /// Every page, in document order
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Just page `page` (1-based)
+    pub fn single(page: u32) -> Self {
+        let page = PageRef::Number(page);
+        Self {
+            ranges: vec![(page, page)],
+        }
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Parse a selection such as `1-3,7,last`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if spec.is_empty() || spec.eq_ignore_ascii_case("all") {
+            return Ok(Self::all());
+        }
+
+        let invalid = |part: &str| {
+            Error::ConversionError(format!(
+                "Invalid page range '{}' (expected e.g. 1-3,7,last)",
+                part.trim()
+            ))
+        };
+
+        let mut ranges = Vec::new();
+        for part in spec.split(',') {
+            let range = match part.split_once('-') {
+                Some((start, end)) => {
+                    let start = PageRef::parse(start).ok_or_else(|| invalid(part))?;
+                    let end = if end.trim().is_empty() {
+                        PageRef::Last
+                    } else {
+                        PageRef::parse(end).ok_or_else(|| invalid(part))?
+                    };
+                    (start, end)
+                }
+                None => {
+                    let page = PageRef::parse(part).ok_or_else(|| invalid(part))?;
+                    (page, page)
+                }
+            };
+            ranges.push(range);
+        }
+
+        Ok(Self { ranges })
+    }
+
+    /// 1-based page numbers in the order written, each page once
+    pub fn resolve(&self, page_count: u32) -> Result<Vec<u32>> {
+        if self.is_all() {
+            return Ok((1..=page_count).collect());
+        }
+
+        let mut pages = Vec::new();
+        for &(start, end) in &self.ranges {
+            let start = start.resolve(page_count)?;
+            let end = end.resolve(page_count)?;
+            let range: Box<dyn Iterator<Item = u32>> = if start <= end {
+                Box::new(start..=end)
+            } else {
+                Box::new((end..=start).rev())
+            };
+
+            for page in range {
+                if !pages.contains(&page) {
+                    pages.push(page);
+                }
+            }
+        }
+
+        Ok(pages)
+    }
+}
+
+impl FromStr for PageSelection {
+    type Err = Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        Self::parse(spec)
+    }
+}
+
+impl fmt::Display for PageSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_all() {
+            return write!(f, "all");
+        }
+
+        for (i, (start, end)) in self.ranges.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if start == end {
+                write!(f, "{}", start)?;
+            } else {
+                write!(f, "{}-{}", start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_resolve() {
+        let pages = PageSelection::parse("1-3, 7,last").unwrap();
+        assert_eq!(pages.resolve(10).unwrap(), vec![1, 2, 3, 7, 10]);
+        assert_eq!(pages.to_string(), "1-3,7,last");
+
+        assert_eq!(
+            PageSelection::parse("8-").unwrap().resolve(10).unwrap(),
+            vec![8, 9, 10]
+        );
+        assert_eq!(
+            PageSelection::parse("3-1,2").unwrap().resolve(5).unwrap(),
+            vec![3, 2, 1]
+        );
+        assert_eq!(
+            PageSelection::parse("ALL").unwrap().resolve(3).unwrap(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_invalid_selections() {
+        for spec in ["0", "1-x", "-3", "1,,2", "first"] {
+            assert!(
+                PageSelection::parse(spec).is_err(),
+                "{} should not parse",
+                spec
+            );
+        }
+        assert!(PageSelection::parse("4-6").unwrap().resolve(5).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Unit suffixes, largest first; `KB`/`MB`/`GB` are decimal and `KiB`/`MiB`/`GiB` binary
+const UNITS: [(&str, u64); 6] = [
+    ("GiB", 1 << 30),
+    ("GB", 1_000_000_000),
+    ("MiB", 1 << 20),
+    ("MB", 1_000_000),
+    ("KiB", 1 << 10),
+    ("KB", 1_000),
+];
+
+/// A file size limit, written like `200KB`, `1.5 MB` or `512KiB`
+///
+/// Units are case-insensitive, `K`/`M`/`G` are short for `KB`/`MB`/`GB`,
+/// and a bare number counts bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FileSize(u64);
+
+impl FileSize {
+    pub fn from_bytes(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    pub fn bytes(self) -> u64 {
+        self.0
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let invalid = || {
+            Error::ConversionError(format!(
+                "Invalid size '{}' (expected e.g. 500KB, 1.5MB or 200KiB)",
+                text
+            ))
+        };
+
+        let split = text
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(text.len());
+        let (number, unit) = text.split_at(split);
+        let number: f64 = number.parse().map_err(|_| invalid())?;
+
+        let unit = unit.trim().to_ascii_lowercase();
+        let factor = match unit.as_str() {
+            "" | "b" | "bytes" => 1,
+            "k" => 1_000,
+            "m" => 1_000_000,
+            "g" => 1_000_000_000,
+            _ => UNITS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&unit))
+                .map(|&(_, factor)| factor)
+                .ok_or_else(invalid)?,
+        };
+
+        let bytes = (number * factor as f64).round();
+        if bytes < 1.0 || bytes > u64::MAX as f64 {
+            return Err(invalid());
+        }
+        Ok(Self(bytes as u64))
+    }
+}
+
+impl FromStr for FileSize {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text)
+    }
+}
+
+impl fmt::Display for FileSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The largest unit that shows the size exactly, so it parses back the same
+        match UNITS
+            .iter()
+            .find(|&&(_, factor)| self.0.is_multiple_of(factor))
+        {
+            Some((name, factor)) => write!(f, "{} {}", self.0 / factor, name),
+            None => write!(f, "{} B", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_units() {
+        let bytes = |text: &str| FileSize::parse(text).unwrap().bytes();
+        assert_eq!(bytes("200KB"), 200_000);
+        assert_eq!(bytes("200 kb"), 200_000);
+        assert_eq!(bytes("500k"), 500_000);
+        assert_eq!(bytes("1.5MB"), 1_500_000);
+        assert_eq!(bytes("512KiB"), 524_288);
+        assert_eq!(bytes("2 MiB"), 2_097_152);
+        assert_eq!(bytes("4096"), 4096);
+
+        for invalid in ["", "KB", "-5KB", "10 parsecs", "0"] {
+            assert!(FileSize::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for text in ["200 KB", "512 KiB", "3 MB", "1234 B"] {
+            assert_eq!(FileSize::parse(text).unwrap().to_string(), text);
+        }
+    }
+}
@@ -1,8 +1,14 @@
-use crate::quality::{QualityMetric, QualitySettings};
-use image::DynamicImage;
+use crate::quality::{QualityMetric, QualitySettings, SizeTarget};
+use crate::quantize;
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgb, RgbImage, RgbaImage};
 use std::io::Cursor;
 use std::path::Path;
-use transmute_common::{Error, MediaFormat, Result};
+use transmute_common::{EmbeddedMetadata, Error, MediaFormat, Result};
+use transmute_formats::{
+    AvifEncoder, AvifOptions, JxlEncoder, JxlOptions, PdfImageStats, PdfPages, WebpEncoder,
+    WebpOptions,
+};
 
 #[cfg(feature = "gpu")]
 use crate::gpu_convert::GpuColorConverter;
@@ -13,7 +19,7 @@ pub struct CompressionResult {
     /// Compressed image data
     pub data: Vec<u8>,
 
-    /// Original file size (bytes)
+    /// Decoded pixel buffer size (bytes, using the image's real channel layout)
     pub original_size: usize,
 
     /// Compressed file size (bytes)
@@ -30,6 +36,13 @@ impl CompressionResult {
     pub fn size_reduction_percent(&self) -> f32 {
         (1.0 - (self.compressed_size as f32 / self.original_size as f32)) * 100.0
     }
+
+    /// Measure against another original size, such as the source's decoded
+    /// size before transforms (`ImageMetadata::decoded_size`)
+    pub fn set_original_size(&mut self, original_size: usize) {
+        self.original_size = original_size;
+        self.ratio = original_size as f32 / self.compressed_size as f32;
+    }
 }
 
 /// GPU-accelerated image compressor
@@ -37,8 +50,23 @@ pub struct ImageCompressor {
     #[cfg(feature = "gpu")]
     gpu_converter: Option<GpuColorConverter>,
     use_gpu: bool,
+    background: Rgb<u8>,
+    avif_speed: u8,
 }
 
+/// Default JPEG matte colour for transparent pixels
+const DEFAULT_BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// Lowest encoder quality tried when fitting a size budget or SSIM floor
+const MIN_SEARCH_QUALITY: u8 = 5;
+
+/// Highest quality that still palette-reduces PNG; above it PNG is lossless
+/// and quality only sets the optimization effort
+const PNG_PALETTE_MAX_QUALITY: u8 = 89;
+
+/// Images are not downscaled below this many pixels on their short side
+const MIN_DOWNSCALE_SIDE: u32 = 16;
+
 impl ImageCompressor {
     /// Create compressor with optional GPU acceleration
     pub fn new(use_gpu: bool) -> Result<Self> {
@@ -74,9 +102,21 @@ impl ImageCompressor {
             #[cfg(feature = "gpu")]
             gpu_converter,
             use_gpu: has_gpu,
+            background: DEFAULT_BACKGROUND,
+            avif_speed: AvifOptions::default().speed,
         })
     }
 
+    /// Set the colour transparent pixels are flattened onto for JPEG output
+    pub fn set_background(&mut self, background: Rgb<u8>) {
+        self.background = background;
+    }
+
+    /// Set the AVIF encoder speed (1 = slowest/smallest, 10 = fastest)
+    pub fn set_avif_speed(&mut self, speed: u8) {
+        self.avif_speed = speed.clamp(1, 10);
+    }
+
     /// Compress image to target format with quality settings
     pub fn compress(
         &self,
@@ -84,6 +124,24 @@ impl ImageCompressor {
         format: MediaFormat,
         quality: QualitySettings,
         calculate_metrics: bool,
+    ) -> Result<CompressionResult> {
+        self.compress_with_metadata(
+            img,
+            format,
+            quality,
+            calculate_metrics,
+            &EmbeddedMetadata::default(),
+        )
+    }
+
+    /// Compress and embed EXIF/XMP/ICC metadata (already filtered by policy)
+    pub fn compress_with_metadata(
+        &self,
+        img: &DynamicImage,
+        format: MediaFormat,
+        quality: QualitySettings,
+        calculate_metrics: bool,
+        metadata: &EmbeddedMetadata,
     ) -> Result<CompressionResult> {
         tracing::info!(
             "Compressing {}x{} to {} (quality: {:?}, GPU: {})",
@@ -94,13 +152,16 @@ impl ImageCompressor {
             self.use_gpu
         );
 
-        // Calculate original size (uncompressed RGB)
-        let original_size = (img.width() * img.height() * 3) as usize;
+        // Uncompressed size in the decoded colour type (RGBA, 16-bit, gray, ...)
+        let original_size =
+            img.width() as usize * img.height() as usize * img.color().bytes_per_pixel() as usize;
 
         let compressed_data = match format {
             MediaFormat::Jpeg => self.compress_jpeg(img, quality)?,
             MediaFormat::Png => self.compress_png(img, quality)?,
             MediaFormat::Webp => self.compress_webp(img, quality)?,
+            MediaFormat::Avif => self.compress_avif(img, quality)?,
+            MediaFormat::Jxl => self.compress_jxl(img, quality)?,
             _ => {
                 return Err(Error::UnsupportedFormat(format!(
                     "{} compression not implemented",
@@ -109,6 +170,9 @@ impl ImageCompressor {
             }
         };
 
+        // Codecs differ in what they emit, so always rewrite the metadata blocks
+        let compressed_data = metadata.embed(compressed_data, format)?;
+
         let compressed_size = compressed_data.len();
         let ratio = original_size as f32 / compressed_size as f32;
 
@@ -129,10 +193,247 @@ impl ImageCompressor {
         })
     }
 
+    /// Compress at the highest quality whose output fits `target.max_size`,
+    /// scaling the image down as well when that is allowed and needed
+    pub fn compress_to_size(
+        &self,
+        img: &DynamicImage,
+        format: MediaFormat,
+        target: SizeTarget,
+        metadata: &EmbeddedMetadata,
+    ) -> Result<CompressionResult> {
+        let max_bytes = usize::try_from(target.max_size.bytes()).unwrap_or(usize::MAX);
+        let original_size =
+            img.width() as usize * img.height() as usize * img.color().bytes_per_pixel() as usize;
+
+        // Flatten once up front rather than on every JPEG attempt
+        let mut current = if format == MediaFormat::Jpeg && img.color().has_alpha() {
+            DynamicImage::ImageRgb8(flatten_alpha(img, self.background))
+        } else {
+            img.clone()
+        };
+
+        loop {
+            let (fitting, smallest) = self.search_quality(&current, format, max_bytes, metadata)?;
+            if let Some(mut result) = fitting {
+                result.set_original_size(original_size);
+                return Ok(result);
+            }
+
+            // File size follows the pixel count, which falls with the square of the scale
+            let scale = ((max_bytes as f32 / smallest as f32).sqrt() * 0.9).min(0.9);
+            let width = (current.width() as f32 * scale) as u32;
+            let height = (current.height() as f32 * scale) as u32;
+            if !target.allow_downscale || width.min(height) < MIN_DOWNSCALE_SIDE {
+                return Err(Error::ConversionError(format!(
+                    "Cannot fit a {}x{} {} under {}: the lowest quality still takes {} bytes{}",
+                    current.width(),
+                    current.height(),
+                    format,
+                    target.max_size,
+                    smallest,
+                    if target.allow_downscale {
+                        ""
+                    } else {
+                        " (allow downscaling to shrink it further)"
+                    }
+                )));
+            }
+
+            tracing::info!(
+                "Downscaling to {}x{} to fit under {}",
+                width,
+                height,
+                target.max_size
+            );
+            current = current.resize_exact(width, height, FilterType::Lanczos3);
+        }
+    }
+
+    /// Binary-search the encoder quality for the best encoding that fits in
+    /// `max_bytes`, also returning the size at the lowest quality
+    fn search_quality(
+        &self,
+        img: &DynamicImage,
+        format: MediaFormat,
+        max_bytes: usize,
+        metadata: &EmbeddedMetadata,
+    ) -> Result<(Option<CompressionResult>, usize)> {
+        let encode = |quality: u8| {
+            self.compress_with_metadata(
+                img,
+                format,
+                QualitySettings::Custom(quality),
+                false,
+                metadata,
+            )
+        };
+
+        // Lossless PNG at full effort when it fits, otherwise search the palette sizes
+        let highest = if format == MediaFormat::Png {
+            let lossless = encode(100)?;
+            if lossless.compressed_size <= max_bytes {
+                let size = lossless.compressed_size;
+                return Ok((Some(lossless), size));
+            }
+            PNG_PALETTE_MAX_QUALITY
+        } else {
+            100
+        };
+
+        let lowest = encode(MIN_SEARCH_QUALITY)?;
+        let smallest = lowest.compressed_size;
+        if smallest > max_bytes {
+            return Ok((None, smallest));
+        }
+
+        let (mut best, mut best_quality) = (lowest, MIN_SEARCH_QUALITY);
+        let (mut low, mut high) = (MIN_SEARCH_QUALITY + 1, highest);
+        while low <= high {
+            let quality = low + (high - low) / 2;
+            let result = encode(quality)?;
+            if result.compressed_size <= max_bytes {
+                (best, best_quality) = (result, quality);
+                low = quality + 1;
+            } else {
+                high = quality - 1;
+            }
+        }
+
+        tracing::info!(
+            "Quality {} fits {}x{} {} in {} of {} bytes",
+            best_quality,
+            img.width(),
+            img.height(),
+            format,
+            best.compressed_size,
+            max_bytes
+        );
+        Ok((Some(best), smallest))
+    }
+
+    /// Compress to the smallest output whose SSIM against `img` is at least
+    /// `min_ssim`, so each image gets the lowest quality it can take
+    ///
+    /// SSIM is measured on luma only, as most tools report it, so colour
+    /// bleeding from chroma subsampling does not count against the floor.
+    /// PNG palettes are additionally gated per colour channel.
+    pub fn compress_to_ssim(
+        &self,
+        img: &DynamicImage,
+        format: MediaFormat,
+        min_ssim: f64,
+        metadata: &EmbeddedMetadata,
+    ) -> Result<CompressionResult> {
+        if !(min_ssim > 0.0 && min_ssim <= 1.0) {
+            return Err(Error::ConversionError(format!(
+                "Minimum SSIM must be above 0 and at most 1, got {}",
+                min_ssim
+            )));
+        }
+
+        // Only SSIM is measured while searching; the full metrics once at the end
+        let encode = |img: &DynamicImage, quality: u8| -> Result<(CompressionResult, f64)> {
+            let result = self.compress_with_metadata(
+                img,
+                format,
+                QualitySettings::Custom(quality),
+                false,
+                metadata,
+            )?;
+            let ssim = QualityMetric::ssim(img, &image::load_from_memory(&result.data)?)?;
+            Ok((result, ssim))
+        };
+
+        // Below 100 the search only needs the qualities that change the output:
+        // PNG is lossless at full effort at 100 and palette-reduced up to its cap
+        let highest = match format {
+            MediaFormat::Png => PNG_PALETTE_MAX_QUALITY,
+            MediaFormat::Jpeg | MediaFormat::Webp => 99,
+            _ => {
+                return Err(Error::UnsupportedFormat(format!(
+                    "{} output cannot be decoded to measure its SSIM",
+                    format
+                )))
+            }
+        };
+
+        let original_size =
+            img.width() as usize * img.height() as usize * img.color().bytes_per_pixel() as usize;
+
+        // JPEG drops alpha either way, so measure against the flattened image
+        let flattened;
+        let img = if format == MediaFormat::Jpeg && img.color().has_alpha() {
+            flattened = DynamicImage::ImageRgb8(flatten_alpha(img, self.background));
+            &flattened
+        } else {
+            img
+        };
+
+        let (best, best_ssim) = encode(img, 100)?;
+        if best_ssim < min_ssim {
+            return Err(Error::ConversionError(format!(
+                "{} at the highest quality only reaches SSIM {:.4}, below {}",
+                format, best_ssim, min_ssim
+            )));
+        }
+
+        // Lowest quality that still meets the floor; SSIM rises with quality
+        let (mut best, mut best_ssim, mut best_quality) = (best, best_ssim, 100);
+        let (mut low, mut high) = (MIN_SEARCH_QUALITY, highest);
+        while low <= high {
+            let quality = low + (high - low) / 2;
+            let (result, ssim) = encode(img, quality)?;
+            if ssim >= min_ssim {
+                (best, best_ssim, best_quality) = (result, ssim, quality);
+                high = quality - 1;
+            } else {
+                low = quality + 1;
+            }
+        }
+
+        tracing::info!(
+            "Quality {} reaches SSIM {:.4} (at least {}) for {}x{} {} in {} bytes",
+            best_quality,
+            best_ssim,
+            min_ssim,
+            img.width(),
+            img.height(),
+            format,
+            best.compressed_size
+        );
+        best.set_original_size(original_size);
+        best.quality = Some(QualityMetric::calculate(
+            img,
+            &image::load_from_memory(&best.data)?,
+        )?);
+        Ok(best)
+    }
+
     /// GPU-accelerated JPEG compression
     fn compress_jpeg(&self, img: &DynamicImage, quality: QualitySettings) -> Result<Vec<u8>> {
         let quality_value = quality.jpeg_quality();
 
+        // JPEG has no alpha channel: composite onto the background instead of dropping it
+        let flattened;
+        let img = if img.color().has_alpha() {
+            tracing::warn!(
+                "JPEG has no alpha channel, flattening transparency onto #{:02x}{:02x}{:02x}",
+                self.background[0],
+                self.background[1],
+                self.background[2]
+            );
+            flattened = DynamicImage::ImageRgb8(flatten_alpha(img, self.background));
+            &flattened
+        } else {
+            img
+        };
+
+        // Keep grayscale single-channel; the GPU path only converts colour
+        if !img.color().has_color() {
+            return self.compress_jpeg_gray(img, quality_value);
+        }
+
         #[cfg(feature = "gpu")]
         {
             // Use GPU for color space conversion if available (>2MP)
@@ -167,8 +468,9 @@ impl ImageCompressor {
 
         let mut comp = Compress::new(ColorSpace::JCS_YCbCr);
         comp.set_size(width, height);
-        comp.set_quality(quality as f32);
+        // Resets the encoder defaults, so it has to come before the quality
         comp.set_scan_optimization_mode(ScanMode::AllComponentsTogether);
+        comp.set_quality(quality as f32);
         comp.set_optimize_coding(true);
 
         let mut comp = comp
@@ -195,8 +497,9 @@ impl ImageCompressor {
 
         let mut comp = Compress::new(ColorSpace::JCS_RGB);
         comp.set_size(width, height);
-        comp.set_quality(quality as f32);
+        // Resets the encoder defaults, so it has to come before the quality
         comp.set_scan_optimization_mode(ScanMode::AllComponentsTogether);
+        comp.set_quality(quality as f32);
         comp.set_optimize_coding(true);
 
         let mut comp = comp
@@ -213,11 +516,44 @@ impl ImageCompressor {
         Ok(jpeg_data)
     }
 
+    /// Grayscale path: single-component mozjpeg
+    fn compress_jpeg_gray(&self, img: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+        use mozjpeg::{ColorSpace, Compress, ScanMode};
+
+        let gray_img = img.to_luma8();
+
+        let mut comp = Compress::new(ColorSpace::JCS_GRAYSCALE);
+        comp.set_size(gray_img.width() as usize, gray_img.height() as usize);
+        // Resets the encoder defaults, so it has to come before the quality
+        comp.set_scan_optimization_mode(ScanMode::AllComponentsTogether);
+        comp.set_quality(quality as f32);
+        comp.set_optimize_coding(true);
+
+        let mut comp = comp
+            .start_compress(Vec::new())
+            .map_err(|e| Error::ConversionError(format!("JPEG compression failed: {}", e)))?;
+
+        comp.write_scanlines(gray_img.as_raw())
+            .map_err(|e| Error::ConversionError(format!("JPEG write failed: {}", e)))?;
+
+        comp.finish()
+            .map_err(|e| Error::ConversionError(format!("JPEG finish failed: {}", e)))
+    }
+
     /// PNG compression with oxipng optimization
     fn compress_png(&self, img: &DynamicImage, quality: QualitySettings) -> Result<Vec<u8>> {
         let level = quality.png_level();
         tracing::debug!("PNG compression level: {}", level);
 
+        // Lossy qualities try a dithered palette first; oxipng then stores it indexed
+        let quantized = match quality.png_colors() {
+            Some(colors) if img.color().has_color() => {
+                quantize_png(img, colors, quality.target_ssim())?
+            }
+            _ => None,
+        };
+        let img = quantized.as_ref().unwrap_or(img);
+
         // First encode with image crate
         let mut buffer = Vec::new();
         let mut cursor = Cursor::new(&mut buffer);
@@ -236,11 +572,65 @@ impl ImageCompressor {
         let quality_value = quality.webp_quality();
         tracing::debug!("WebP compression quality: {}", quality_value);
 
-        let rgb_img = img.to_rgb8();
-        let encoder = webp::Encoder::from_rgb(rgb_img.as_raw(), rgb_img.width(), rgb_img.height());
+        WebpEncoder::encode(img, &WebpOptions::lossy(quality_value))
+    }
+
+    /// AVIF compression (rav1e)
+    fn compress_avif(&self, img: &DynamicImage, quality: QualitySettings) -> Result<Vec<u8>> {
+        let options = AvifOptions::new(quality.avif_quality(), self.avif_speed);
+        tracing::debug!(
+            "AVIF compression quality: {}, speed: {}",
+            options.quality,
+            options.speed
+        );
+
+        AvifEncoder::encode(img, &options)
+    }
 
-        let webp_data = encoder.encode(quality_value);
-        Ok(webp_data.to_vec())
+    /// JPEG XL compression (lossy VarDCT, lossless at quality 100)
+    fn compress_jxl(&self, img: &DynamicImage, quality: QualitySettings) -> Result<Vec<u8>> {
+        let options = JxlOptions::new(quality.jxl_quality(), quality.jxl_effort());
+        tracing::debug!(
+            "JXL compression quality: {}, effort: {}",
+            options.quality,
+            options.effort
+        );
+
+        JxlEncoder::encode(img, &options)
+    }
+
+    /// Losslessly repack a JPEG file as JPEG XL without decoding its pixels.
+    ///
+    /// `original_size` is the JPEG file size here, since nothing is decoded.
+    pub fn transcode_jpeg_to_jxl(&self, jpeg: &[u8]) -> Result<CompressionResult> {
+        let data = JxlEncoder::transcode_jpeg(jpeg)?;
+
+        Ok(CompressionResult {
+            original_size: jpeg.len(),
+            compressed_size: data.len(),
+            ratio: jpeg.len() as f32 / data.len() as f32,
+            quality: None,
+            data,
+        })
+    }
+
+    /// Shrink the images of an existing PDF: downsample those drawn sharper than
+    /// `max_dpi` (default: the quality's [`QualitySettings::pdf_dpi`]) and
+    /// re-encode them as JPEG
+    pub fn compress_pdf(
+        &self,
+        pdf: &mut PdfPages,
+        quality: QualitySettings,
+        max_dpi: Option<f32>,
+    ) -> Result<PdfImageStats> {
+        let max_dpi = max_dpi.unwrap_or_else(|| quality.pdf_dpi());
+        tracing::info!(
+            "Compressing PDF images (quality: {:?}, max {} DPI)",
+            quality,
+            max_dpi
+        );
+
+        pdf.compress_images(max_dpi, |img| self.compress_jpeg(img, quality))
     }
 
     /// Compress and save to file
@@ -257,10 +647,63 @@ impl ImageCompressor {
     }
 }
 
+/// Palette-reduce `img` for PNG, or `None` when that would not keep the SSIM
+/// of every colour channel at `min_ssim` or above
+fn quantize_png(img: &DynamicImage, colors: usize, min_ssim: f64) -> Result<Option<DynamicImage>> {
+    let rgba = img.to_rgba8();
+    let Some(quantized) = quantize::quantize(&rgba, colors) else {
+        return Ok(None);
+    };
+
+    // Compare what is visible: colours weighted by their opacity. Per channel,
+    // since a palette can swap hues of equal luma
+    let ssim = QualityMetric::color_ssim(&premultiplied(&rgba), &premultiplied(&quantized))?;
+    if ssim < min_ssim {
+        tracing::info!(
+            "Keeping PNG lossless: {} colours only reach colour SSIM {:.4}, below {}",
+            colors,
+            ssim,
+            min_ssim
+        );
+        return Ok(None);
+    }
+
+    tracing::debug!(
+        "Quantized PNG to {} colours (colour SSIM {:.4})",
+        colors,
+        ssim
+    );
+    Ok(Some(DynamicImage::ImageRgba8(quantized)))
+}
+
+/// RGB of `img` composited onto black
+fn premultiplied(img: &RgbaImage) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(img.width(), img.height(), |x, y| {
+        let p = img.get_pixel(x, y);
+        Rgb([0, 1, 2].map(|c| (p[c] as u16 * p[3] as u16 / 255) as u8))
+    }))
+}
+
+/// Alpha-composite an image onto a solid background colour
+fn flatten_alpha(img: &DynamicImage, background: Rgb<u8>) -> RgbImage {
+    let rgba = img.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let a = a as u32;
+        let blend = |c: u8, bg: u8| ((c as u32 * a + bg as u32 * (255 - a) + 127) / 255) as u8;
+        Rgb([
+            blend(r, background[0]),
+            blend(g, background[1]),
+            blend(b, background[2]),
+        ])
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use image::DynamicImage;
+    use transmute_common::FileSize;
 
     #[test]
     fn test_jpeg_compression_quality() {
@@ -312,4 +755,350 @@ mod tests {
             result.size_reduction_percent()
         );
     }
+
+    fn half_transparent_red() -> DynamicImage {
+        DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            64,
+            64,
+            image::Rgba([255, 0, 0, 128]),
+        ))
+    }
+
+    #[test]
+    fn test_jpeg_flattens_alpha_onto_background() {
+        let mut compressor = ImageCompressor::new(false).unwrap();
+        compressor.set_background(Rgb([0, 0, 255]));
+
+        let result = compressor
+            .compress(
+                &half_transparent_red(),
+                MediaFormat::Jpeg,
+                QualitySettings::Maximum,
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(result.original_size, 64 * 64 * 4);
+
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgb8();
+        let Rgb([r, g, b]) = *decoded.get_pixel(32, 32);
+        assert!(
+            r.abs_diff(128) < 8 && g < 8 && b.abs_diff(127) < 8,
+            "{:?}",
+            (r, g, b)
+        );
+    }
+
+    #[test]
+    fn test_png_and_webp_keep_alpha() {
+        let compressor = ImageCompressor::new(false).unwrap();
+        let img = half_transparent_red();
+
+        for format in [MediaFormat::Png, MediaFormat::Webp] {
+            let result = compressor
+                .compress(&img, format, QualitySettings::High, false)
+                .unwrap();
+            let decoded = image::load_from_memory(&result.data).unwrap();
+
+            assert!(decoded.color().has_alpha(), "{} lost alpha", format);
+            assert_eq!(decoded.to_rgba8().get_pixel(10, 10)[3], 128);
+        }
+    }
+
+    #[test]
+    fn test_grayscale_jpeg_stays_single_channel() {
+        let img = DynamicImage::ImageLuma8(image::GrayImage::from_fn(64, 64, |x, y| {
+            image::Luma([((x + y) * 2) as u8])
+        }));
+        let compressor = ImageCompressor::new(false).unwrap();
+
+        let result = compressor
+            .compress(&img, MediaFormat::Jpeg, QualitySettings::High, false)
+            .unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap();
+
+        assert_eq!(decoded.color(), image::ColorType::L8);
+    }
+
+    /// Colour type byte of a PNG's IHDR chunk (3 = indexed)
+    fn png_color_type(data: &[u8]) -> u8 {
+        data[25]
+    }
+
+    #[test]
+    fn test_lossy_png_uses_palette() {
+        // Busy detail hides dithering well, like photos and screenshots
+        let img = noisy_rgb(128, 128);
+        let compressor = ImageCompressor::new(false).unwrap();
+
+        let lossless = compressor
+            .compress(&img, MediaFormat::Png, QualitySettings::Maximum, false)
+            .unwrap();
+        let lossy = compressor
+            .compress(&img, MediaFormat::Png, QualitySettings::Low, true)
+            .unwrap();
+
+        assert_ne!(png_color_type(&lossless.data), 3);
+        assert_eq!(png_color_type(&lossy.data), 3);
+        assert!(lossy.compressed_size < lossless.compressed_size);
+        assert!(lossy.quality.unwrap().ssim >= QualitySettings::Low.target_ssim());
+    }
+
+    #[test]
+    fn test_lossy_png_keeps_alpha() {
+        // Busy detail, as smooth gradients fall back to lossless
+        let mut rgba = noisy_rgb(128, 128).to_rgba8();
+        for (x, _, p) in rgba.enumerate_pixels_mut() {
+            p[3] = if x < 64 { 0 } else { 255 };
+        }
+        let img = DynamicImage::ImageRgba8(rgba);
+        let compressor = ImageCompressor::new(false).unwrap();
+
+        let result = compressor
+            .compress(&img, MediaFormat::Png, QualitySettings::Balanced, false)
+            .unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap().to_rgba8();
+
+        assert_eq!(png_color_type(&result.data), 3);
+        assert_eq!(decoded.get_pixel(10, 10)[3], 0);
+        assert_eq!(decoded.get_pixel(100, 10)[3], 255);
+    }
+
+    #[test]
+    fn test_lossy_png_falls_back_to_lossless() {
+        // A smooth two-way colour gradient bands or speckles with any small palette
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(256, 256, |x, y| {
+            Rgb([x as u8, y as u8, ((x + y) / 2) as u8])
+        }));
+        let compressor = ImageCompressor::new(false).unwrap();
+
+        let result = compressor
+            .compress(&img, MediaFormat::Png, QualitySettings::Low, false)
+            .unwrap();
+        let decoded = image::load_from_memory(&result.data).unwrap();
+
+        assert_ne!(png_color_type(&result.data), 3);
+        assert_eq!(decoded.to_rgb8(), img.to_rgb8());
+    }
+
+    #[test]
+    fn test_png_gate_sees_hue_shifts() {
+        // Red traded for green at constant luma, as a palette can do
+        let hues = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| {
+            let d = if (x / 4 + y / 4) % 2 == 0 {
+                40.0
+            } else {
+                -40.0
+            };
+            Rgb([(128.0 + d) as u8, (128.0 - d * 0.299 / 0.587) as u8, 128])
+        }));
+        let gray = DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 64, Rgb([128, 128, 128])));
+
+        assert!(QualityMetric::ssim(&hues, &gray).unwrap() > 0.95);
+        assert!(QualityMetric::color_ssim(&hues, &gray).unwrap() < 0.1);
+    }
+
+    fn noisy_rgb(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let noise = (x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)) >> 7;
+            Rgb([(x + noise) as u8, (y ^ noise) as u8, (noise >> 3) as u8])
+        }))
+    }
+
+    #[test]
+    fn test_compress_to_size_fits_budget() {
+        let img = noisy_rgb(256, 256);
+        let compressor = ImageCompressor::new(false).unwrap();
+        let size_at = |quality| {
+            compressor
+                .compress(
+                    &img,
+                    MediaFormat::Jpeg,
+                    QualitySettings::Custom(quality),
+                    false,
+                )
+                .unwrap()
+                .compressed_size
+        };
+        let (lowest, highest) = (size_at(MIN_SEARCH_QUALITY), size_at(100));
+        let budget = (lowest + highest) / 2;
+
+        let target = SizeTarget::new(FileSize::from_bytes(budget as u64));
+        let result = compressor
+            .compress_to_size(
+                &img,
+                MediaFormat::Jpeg,
+                target,
+                &EmbeddedMetadata::default(),
+            )
+            .unwrap();
+
+        // The search settles on a quality close to the budget, not the lowest one
+        assert!(result.compressed_size <= budget);
+        assert!(result.compressed_size > lowest);
+        assert_eq!(result.original_size, 256 * 256 * 3);
+    }
+
+    #[test]
+    fn test_compress_to_size_uses_png_palette() {
+        let img = noisy_rgb(128, 128);
+        let compressor = ImageCompressor::new(false).unwrap();
+        let lossless = compressor
+            .compress(&img, MediaFormat::Png, QualitySettings::Custom(100), false)
+            .unwrap();
+
+        let budget = lossless.compressed_size - 1;
+        let target = SizeTarget::new(FileSize::from_bytes(budget as u64));
+        let result = compressor
+            .compress_to_size(&img, MediaFormat::Png, target, &EmbeddedMetadata::default())
+            .unwrap();
+
+        assert!(result.compressed_size <= budget);
+        assert_eq!(png_color_type(&result.data), 3);
+        assert_eq!(image::load_from_memory(&result.data).unwrap().width(), 128);
+    }
+
+    #[test]
+    fn test_compress_to_size_downscales_only_when_allowed() {
+        let img = noisy_rgb(256, 256);
+        let compressor = ImageCompressor::new(false).unwrap();
+        let mut target = SizeTarget::new(FileSize::from_bytes(2_000));
+
+        assert!(compressor
+            .compress_to_size(&img, MediaFormat::Png, target, &EmbeddedMetadata::default())
+            .is_err());
+
+        target.allow_downscale = true;
+        let result = compressor
+            .compress_to_size(&img, MediaFormat::Png, target, &EmbeddedMetadata::default())
+            .unwrap();
+        assert!(result.compressed_size <= 2_000);
+
+        let decoded = image::load_from_memory(&result.data).unwrap();
+        assert!(decoded.width() < 256 && decoded.width() >= MIN_DOWNSCALE_SIDE);
+    }
+
+    #[test]
+    fn test_compress_to_ssim_picks_smallest_passing_quality() {
+        // Smooth like a photo; lossy codecs cannot keep pure noise at any quality
+        let img = DynamicImage::ImageRgb8(RgbImage::from_fn(128, 128, |x, y| {
+            Rgb([(x * 2) as u8, (y * 2) as u8, (x + y) as u8])
+        }));
+        let compressor = ImageCompressor::new(false).unwrap();
+        let metadata = EmbeddedMetadata::default();
+
+        let loose = compressor
+            .compress_to_ssim(&img, MediaFormat::Jpeg, 0.5, &metadata)
+            .unwrap();
+        let strict = compressor
+            .compress_to_ssim(&img, MediaFormat::Webp, 0.99, &metadata)
+            .unwrap();
+        let best = compressor
+            .compress(&img, MediaFormat::Webp, QualitySettings::Custom(100), true)
+            .unwrap();
+
+        assert!(loose.quality.unwrap().ssim >= 0.5);
+        assert!(strict.quality.unwrap().ssim >= 0.99);
+        assert!(strict.compressed_size <= best.compressed_size);
+        assert_eq!(loose.original_size, 128 * 128 * 3);
+    }
+
+    #[test]
+    fn test_compress_to_ssim_rejects_unreachable_floor() {
+        let img = noisy_rgb(64, 64);
+        let compressor = ImageCompressor::new(false).unwrap();
+        let metadata = EmbeddedMetadata::default();
+
+        // Chroma subsampling alone keeps JPEG below a perfect score
+        assert!(compressor
+            .compress_to_ssim(&img, MediaFormat::Jpeg, 1.0, &metadata)
+            .is_err());
+        assert!(compressor
+            .compress_to_ssim(&img, MediaFormat::Jpeg, 1.5, &metadata)
+            .is_err());
+        assert!(compressor
+            .compress_to_ssim(&img, MediaFormat::Avif, 0.9, &metadata)
+            .is_err());
+
+        let png = compressor
+            .compress_to_ssim(&img, MediaFormat::Png, 1.0, &metadata)
+            .unwrap();
+        assert_eq!(png.quality.unwrap().ssim, 1.0);
+        assert_ne!(png_color_type(&png.data), 3);
+    }
+
+    #[test]
+    fn test_compress_to_ssim_uses_png_palette() {
+        let img = noisy_rgb(128, 128);
+        let compressor = ImageCompressor::new(false).unwrap();
+        let lossless = compressor
+            .compress(&img, MediaFormat::Png, QualitySettings::Custom(100), false)
+            .unwrap();
+
+        let result = compressor
+            .compress_to_ssim(&img, MediaFormat::Png, 0.8, &EmbeddedMetadata::default())
+            .unwrap();
+
+        assert_eq!(png_color_type(&result.data), 3);
+        assert!(result.compressed_size < lossless.compressed_size);
+        assert!(result.quality.unwrap().ssim >= 0.8);
+    }
+
+    #[test]
+    fn test_perceptual_score_at_jpeg_qualities() {
+        // Smooth shading, hard edges and mild grain, like a photo
+        let photo = DynamicImage::ImageRgb8(RgbImage::from_fn(256, 256, |x, y| {
+            let (fx, fy) = (x as f32, y as f32);
+            let shade = 0.5 + 0.5 * ((fx / 37.0).sin() * (fy / 23.0).cos());
+            let grain = (((x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)) >> 9) % 6) as f32;
+            let edge = if (x / 64 + y / 48) % 2 == 0 {
+                40.0
+            } else {
+                0.0
+            };
+            Rgb([
+                (shade * 160.0 + edge + grain) as u8,
+                (fy * 0.6 + shade * 60.0 + grain / 2.0) as u8,
+                (200.0 - fx * 0.5 + edge / 2.0) as u8,
+            ])
+        }));
+        let compressor = ImageCompressor::new(false).unwrap();
+        let score = |quality| {
+            compressor
+                .compress(
+                    &photo,
+                    MediaFormat::Jpeg,
+                    QualitySettings::Custom(quality),
+                    true,
+                )
+                .unwrap()
+                .quality
+                .unwrap()
+                .perceptual
+        };
+
+        // The figures the perceptual scale's documentation quotes
+        let (high, medium) = (score(90), score(50));
+        assert!((high - 82.0).abs() < 3.0, "{}", high);
+        assert!((medium - 75.0).abs() < 3.0, "{}", medium);
+    }
+
+    #[test]
+    fn test_avif_quality_presets() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, y| {
+            Rgb([(x * 4) as u8, (y * 4) as u8, ((x ^ y) * 4) as u8])
+        }));
+        let mut compressor = ImageCompressor::new(false).unwrap();
+        compressor.set_avif_speed(10);
+
+        let high = compressor
+            .compress(&img, MediaFormat::Avif, QualitySettings::Maximum, false)
+            .unwrap();
+        let low = compressor
+            .compress(&img, MediaFormat::Avif, QualitySettings::Low, false)
+            .unwrap();
+
+        assert_eq!(MediaFormat::sniff(&high.data), Some(MediaFormat::Avif));
+        assert!(low.compressed_size < high.compressed_size);
+    }
 }
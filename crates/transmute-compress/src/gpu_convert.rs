@@ -281,8 +281,8 @@ impl GpuColorConverter {
             compute_pass.set_bind_group(0, &bind_group, &[]);
 
             // Calculate workgroups (16x16 workgroup size from shader)
-            let workgroups_x = (width + 15) / 16;
-            let workgroups_y = (height + 15) / 16;
+            let workgroups_x = width.div_ceil(16);
+            let workgroups_y = height.div_ceil(16);
             compute_pass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
         }
 
@@ -315,11 +315,9 @@ impl GpuColorConverter {
         let ycbcr_u32_slice: &[u32] = bytemuck::cast_slice(&data);
 
         // Pre-allocate with exact size
-        let mut ycbcr_flat = Vec::with_capacity(pixel_count * 3);
+        let mut ycbcr_flat = vec![0u8; pixel_count * 3];
         unsafe {
-            // SAFETY: We're manually controlling the vec length and capacity
-            ycbcr_flat.set_len(pixel_count * 3);
-
+            // SAFETY: ycbcr_flat holds pixel_count * 3 bytes and the source holds pixel_count * 4 u32s
             // Batch copy: process 4 u32 at a time (1 pixel) = extract 3 bytes
             for i in 0..pixel_count {
                 let base_idx = i * 4;  // Source: vec4<u32>
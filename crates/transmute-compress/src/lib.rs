@@ -1,11 +1,13 @@
 pub mod compressor;
+mod metrics;
 pub mod quality;
+mod quantize;
 
 #[cfg(feature = "gpu")]
 pub mod gpu_convert;
 
 pub use compressor::{CompressionResult, ImageCompressor};
-pub use quality::{QualityMetric, QualitySettings};
+pub use quality::{QualityMetric, QualitySettings, QualityThresholds, SizeTarget};
 
 #[cfg(feature = "gpu")]
 pub use gpu_convert::GpuColorConverter;
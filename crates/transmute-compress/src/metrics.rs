@@ -0,0 +1,678 @@
+//! Full-reference image quality measures: windowed SSIM and MS-SSIM on luma
+//! (Wang et al. 2004, 2003) and an SSIMULACRA2-style perceptual score
+
+use image::{DynamicImage, RgbImage};
+use rayon::prelude::*;
+use std::borrow::Cow;
+
+/// Side of the Gaussian SSIM window
+const WINDOW: usize = 11;
+
+/// Standard deviation of the Gaussian SSIM window
+const SIGMA: f64 = 1.5;
+
+/// Output rows per parallel task; each task also blurs the window's extra rows
+const BAND_ROWS: usize = 32;
+
+/// SSIM stabilizers for 8-bit samples: (0.01 × 255)² and (0.03 × 255)²
+const C1: f64 = 6.5025;
+const C2: f64 = 58.5225;
+
+/// MS-SSIM exponent of each scale, finest first
+const MS_SSIM_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+
+/// Resolutions the perceptual score compares, halving each time
+const PERCEPTUAL_SCALES: usize = 6;
+
+/// Structure stabilizer for the perceptual score's XYB planes (values near 0-1)
+const PERCEPTUAL_C2: f64 = 0.0009;
+
+/// Weight of each XYB channel in the perceptual distance
+const CHANNEL_WEIGHTS: [f64; 3] = [0.5, 1.0, 0.25];
+
+/// Weight of the structure, artifact and detail-loss terms, as (mean, 4-norm)
+const FEATURE_WEIGHTS: [(f64, f64); 3] = [(1.0, 0.5), (0.5, 0.25), (0.5, 0.25)];
+
+/// Maps the perceptual distance onto 0-100: `100 - scale × distance^exponent`.
+/// A smooth photo-like image scores about 82 at JPEG quality 90 and 75 at
+/// quality 50; noisy content scores far lower at the same quality
+const PERCEPTUAL_SCALE: f64 = 150.0;
+const PERCEPTUAL_EXPONENT: f64 = 0.47;
+
+/// One channel of samples, row by row
+#[derive(Debug, Clone)]
+pub(crate) struct Plane {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+}
+
+impl Plane {
+    /// BT.601 luma of the image's 8-bit RGB, alpha ignored (as MATLAB's `rgb2gray`)
+    pub(crate) fn luma(img: &DynamicImage) -> Self {
+        let rgb = img.to_rgb8();
+        let data = rgb
+            .as_raw()
+            .par_chunks_exact(3)
+            .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+            .collect();
+        Self {
+            width: rgb.width() as usize,
+            height: rgb.height() as usize,
+            data,
+        }
+    }
+
+    /// The image's 8-bit R, G and B channels, alpha ignored
+    pub(crate) fn channels(img: &DynamicImage) -> [Self; 3] {
+        let rgb = img.to_rgb8();
+        [0, 1, 2].map(|channel| Self {
+            width: rgb.width() as usize,
+            height: rgb.height() as usize,
+            data: rgb
+                .as_raw()
+                .par_chunks_exact(3)
+                .map(|p| p[channel] as f32)
+                .collect(),
+        })
+    }
+
+    fn sample(&self, x: isize, y: isize) -> f64 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.data[y * self.width + x] as f64
+    }
+
+    /// 2×2 box filter and decimation
+    fn downsample(&self) -> Self {
+        let (width, height) = (self.width / 2, self.height / 2);
+        let data = (0..width * height)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = (i % width * 2, i / width * 2);
+                let at = |x: usize, y: usize| self.data[y * self.width + x];
+                (at(x, y) + at(x + 1, y) + at(x, y + 1) + at(x + 1, y + 1)) / 4.0
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+/// Gaussian-weighted statistics of the window around one pixel of both images
+#[derive(Debug, Clone, Copy)]
+struct Moments {
+    mu1: f64,
+    mu2: f64,
+    var1: f64,
+    var2: f64,
+    covar: f64,
+
+    /// The centre samples themselves
+    x1: f64,
+    x2: f64,
+}
+
+impl Moments {
+    fn ssim_terms(&self) -> (f64, f64) {
+        let luminance =
+            (2.0 * self.mu1 * self.mu2 + C1) / (self.mu1.powi(2) + self.mu2.powi(2) + C1);
+        let contrast_structure = (2.0 * self.covar + C2) / (self.var1 + self.var2 + C2);
+        (luminance, contrast_structure)
+    }
+}
+
+/// Where windows may sit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edges {
+    /// Only fully inside the image, like MATLAB's `filter2(..., 'valid')`
+    Valid,
+
+    /// Centred on every pixel, repeating the edge samples
+    Clamp,
+}
+
+fn gaussian_kernel() -> [f64; WINDOW] {
+    let radius = (WINDOW / 2) as f64;
+    let mut kernel = [0.0; WINDOW];
+    for (i, weight) in kernel.iter_mut().enumerate() {
+        let d = i as f64 - radius;
+        *weight = (-d * d / (2.0 * SIGMA * SIGMA)).exp();
+    }
+    let sum: f64 = kernel.iter().sum();
+    kernel.map(|weight| weight / sum)
+}
+
+/// Runs `row` on the window moments of each output row, rows in order
+///
+/// The Gaussian is separable: each band of rows is blurred horizontally once,
+/// then every output pixel sums a column of the band.
+fn window_rows<T: Send>(
+    a: &Plane,
+    b: &Plane,
+    edges: Edges,
+    row: impl Fn(&[Moments]) -> T + Sync,
+) -> Vec<T> {
+    let kernel = gaussian_kernel();
+    let (width, height, offset) = match edges {
+        Edges::Valid => (a.width + 1 - WINDOW, a.height + 1 - WINDOW, 0),
+        Edges::Clamp => (a.width, a.height, -((WINDOW / 2) as isize)),
+    };
+    let centre = (WINDOW / 2) as isize + offset;
+
+    (0..height.div_ceil(BAND_ROWS))
+        .into_par_iter()
+        .flat_map_iter(|band| {
+            let first = band * BAND_ROWS;
+            let last = (first + BAND_ROWS).min(height);
+
+            // a, b, a², b² and ab of every input row the band reads, blurred horizontally
+            let rows = last - first + WINDOW - 1;
+            let mut blurred = vec![[0.0; 5]; rows * width];
+            for r in 0..rows {
+                let y = (first + r) as isize + offset;
+                for x in 0..width {
+                    let mut sums = [0.0; 5];
+                    for (k, weight) in kernel.iter().enumerate() {
+                        let sx = (x + k) as isize + offset;
+                        let (p, q) = (a.sample(sx, y), b.sample(sx, y));
+                        sums[0] += weight * p;
+                        sums[1] += weight * q;
+                        sums[2] += weight * p * p;
+                        sums[3] += weight * q * q;
+                        sums[4] += weight * p * q;
+                    }
+                    blurred[r * width + x] = sums;
+                }
+            }
+
+            let mut moments = Vec::with_capacity(width);
+            (first..last)
+                .map(|y| {
+                    moments.clear();
+                    for x in 0..width {
+                        let mut sums = [0.0; 5];
+                        for (k, weight) in kernel.iter().enumerate() {
+                            let values = &blurred[(y - first + k) * width + x];
+                            for (sum, value) in sums.iter_mut().zip(values) {
+                                *sum += weight * value;
+                            }
+                        }
+                        let (cx, cy) = (x as isize + centre, y as isize + centre);
+                        moments.push(Moments {
+                            mu1: sums[0],
+                            mu2: sums[1],
+                            var1: sums[2] - sums[0] * sums[0],
+                            var2: sums[3] - sums[1] * sums[1],
+                            covar: sums[4] - sums[0] * sums[1],
+                            x1: a.sample(cx, cy),
+                            x2: b.sample(cx, cy),
+                        });
+                    }
+                    row(&moments)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Mean SSIM and mean contrast-structure term over all windows
+///
+/// Planes narrower or shorter than a window fall back to edge-clamped windows.
+fn ssim_and_cs(a: &Plane, b: &Plane) -> (f64, f64) {
+    let edges = if a.width >= WINDOW && a.height >= WINDOW {
+        Edges::Valid
+    } else {
+        Edges::Clamp
+    };
+    let rows = window_rows(a, b, edges, |row| {
+        row.iter().fold((0.0, 0.0, 0usize), |(ssim, cs, n), m| {
+            let (luminance, contrast_structure) = m.ssim_terms();
+            (
+                ssim + luminance * contrast_structure,
+                cs + contrast_structure,
+                n + 1,
+            )
+        })
+    });
+    let (ssim, cs, n) = rows
+        .into_iter()
+        .fold((0.0, 0.0, 0), |(s, c, n), (rs, rc, rn)| {
+            (s + rs, c + rc, n + rn)
+        });
+    (ssim / n as f64, cs / n as f64)
+}
+
+/// Mean SSIM over 11×11 Gaussian windows (σ = 1.5)
+pub(crate) fn ssim(a: &Plane, b: &Plane) -> f64 {
+    ssim_and_cs(a, b).0
+}
+
+/// MS-SSIM over five scales, or as many as still fit a window
+///
+/// Fewer scales share the weights of those that are left, so a single scale is
+/// plain SSIM. Negative contrast-structure terms count as zero.
+pub(crate) fn ms_ssim(a: &Plane, b: &Plane) -> f64 {
+    let mut scales = 1;
+    let mut side = a.width.min(a.height);
+    while scales < MS_SSIM_WEIGHTS.len() && side / 2 >= WINDOW {
+        side /= 2;
+        scales += 1;
+    }
+    let weights = &MS_SSIM_WEIGHTS[..scales];
+    let share = MS_SSIM_WEIGHTS.iter().sum::<f64>() / weights.iter().sum::<f64>();
+
+    let (mut a, mut b) = (Cow::Borrowed(a), Cow::Borrowed(b));
+    let mut score = 1.0;
+    for (scale, weight) in weights.iter().enumerate() {
+        let (ssim, cs) = ssim_and_cs(&a, &b);
+        // Only the coarsest scale adds the luminance comparison
+        let value = if scale + 1 == scales { ssim } else { cs };
+        score *= value.max(0.0).powf(weight * share);
+
+        if scale + 1 < scales {
+            a = Cow::Owned(a.downsample());
+            b = Cow::Owned(b.downsample());
+        }
+    }
+    score
+}
+
+/// Local SSIM at every pixel, from the window centred on it
+fn ssim_map(a: &Plane, b: &Plane) -> Vec<f32> {
+    window_rows(a, b, Edges::Clamp, |row| {
+        row.iter()
+            .map(|m| {
+                let (luminance, contrast_structure) = m.ssim_terms();
+                (luminance * contrast_structure) as f32
+            })
+            .collect::<Vec<_>>()
+    })
+    .concat()
+}
+
+/// `a` dimmed to gray, with the local dissimilarity to `b` (1 - SSIM) painted
+/// over it from blue through red to yellow
+pub(crate) fn heatmap(a: &Plane, b: &Plane) -> RgbImage {
+    const RAMP: [[f32; 3]; 3] = [
+        [40.0, 60.0, 255.0],
+        [255.0, 40.0, 40.0],
+        [255.0, 255.0, 80.0],
+    ];
+
+    let map = ssim_map(a, b);
+    let pixels = a
+        .data
+        .par_iter()
+        .zip(map.par_iter())
+        .flat_map_iter(|(&luma, &ssim)| {
+            // The square root spreads out the small differences that matter most
+            let t = (1.0 - ssim).clamp(0.0, 1.0).sqrt();
+            let (low, high, f) = if t < 0.5 {
+                (RAMP[0], RAMP[1], t * 2.0)
+            } else {
+                (RAMP[1], RAMP[2], t * 2.0 - 1.0)
+            };
+            let base = luma * 0.4;
+            let weight = (t * 3.0).min(1.0);
+            [0, 1, 2].map(|c| {
+                let colour = low[c] + (high[c] - low[c]) * f;
+                (base + (colour - base) * weight).round() as u8
+            })
+        })
+        .collect();
+
+    RgbImage::from_raw(a.width as u32, a.height as u32, pixels).expect("one RGB pixel per sample")
+}
+
+/// Linear-light R, G and B planes (0.0-1.0) of the image
+pub(crate) fn linear_rgb(img: &DynamicImage) -> [Plane; 3] {
+    let rgb = img.to_rgb8();
+    let to_linear: Vec<f32> = (0..=255)
+        .map(|v| {
+            let v = v as f32 / 255.0;
+            if v <= 0.04045 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            }
+        })
+        .collect();
+
+    [0, 1, 2].map(|channel| Plane {
+        width: rgb.width() as usize,
+        height: rgb.height() as usize,
+        data: rgb
+            .as_raw()
+            .par_chunks_exact(3)
+            .map(|p| to_linear[p[channel] as usize])
+            .collect(),
+    })
+}
+
+/// Positive XYB planes of linear RGB, as JPEG XL and SSIMULACRA2 use
+fn xyb(rgb: &[Plane; 3]) -> [Plane; 3] {
+    // Opsin absorbance matrix and bias of JPEG XL
+    const OPSIN: [[f32; 3]; 3] = [
+        [0.30, 0.622, 0.078],
+        [0.23, 0.692, 0.078],
+        [0.243_422_69, 0.204_767_44, 0.551_809_87],
+    ];
+    const BIAS: f32 = 0.003_793_073_3;
+
+    let [r, g, b] = rgb;
+    let pixels: Vec<[f32; 3]> = (0..r.data.len())
+        .into_par_iter()
+        .map(|i| {
+            let (r, g, b) = (r.data[i], g.data[i], b.data[i]);
+            let [l, m, s] = OPSIN.map(|row| {
+                let mixed = (row[0] * r + row[1] * g + row[2] * b).max(0.0) + BIAS;
+                mixed.cbrt() - BIAS.cbrt()
+            });
+            let (x, y) = ((l - m) / 2.0, (l + m) / 2.0);
+            // Shifted and scaled like SSIMULACRA2 so every channel is positive
+            [x * 14.0 + 0.42, y + 0.01, s - y + 0.55]
+        })
+        .collect();
+
+    [0, 1, 2].map(|channel| Plane {
+        width: r.width,
+        height: r.height,
+        data: pixels.iter().map(|p| p[channel]).collect(),
+    })
+}
+
+/// Perceptual similarity in the style of SSIMULACRA2 (0-100, 100 = identical)
+///
+/// Compares XYB colour at up to six scales by local structure, added edges
+/// (ringing, blocking) and lost edges (blur). The weights are this crate's own
+/// rather than SSIMULACRA2's fitted ones, so scores only compare with each other.
+pub(crate) fn perceptual_score(a: &[Plane; 3], b: &[Plane; 3]) -> f64 {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    let mut distance = 0.0;
+    let mut scales = 0;
+
+    while scales < PERCEPTUAL_SCALES && a[0].width >= 8 && a[0].height >= 8 {
+        for ((pa, pb), channel_weight) in xyb(&a).iter().zip(&xyb(&b)).zip(CHANNEL_WEIGHTS) {
+            let [structure, artifact, detail] = error_norms(pa, pb);
+            for ((mean, norm4), (mean_weight, norm4_weight)) in [structure, artifact, detail]
+                .into_iter()
+                .zip(FEATURE_WEIGHTS)
+            {
+                distance += channel_weight * (mean_weight * mean + norm4_weight * norm4);
+            }
+        }
+
+        scales += 1;
+        a = a.map(|plane| plane.downsample());
+        b = b.map(|plane| plane.downsample());
+    }
+    if scales == 0 {
+        return if a[0].data == b[0].data && a[1].data == b[1].data && a[2].data == b[2].data {
+            100.0
+        } else {
+            0.0
+        };
+    }
+
+    let distance = distance / scales as f64;
+    (100.0 - PERCEPTUAL_SCALE * distance.powf(PERCEPTUAL_EXPONENT)).clamp(0.0, 100.0)
+}
+
+/// Mean and 4-norm of the structure, artifact and detail-loss error maps
+fn error_norms(a: &Plane, b: &Plane) -> [(f64, f64); 3] {
+    let rows = window_rows(a, b, Edges::Clamp, |row| {
+        let mut sums = [[0.0; 2]; 3];
+        for m in row {
+            let structure = (2.0 * m.covar + PERCEPTUAL_C2) / (m.var1 + m.var2 + PERCEPTUAL_C2);
+            let similarity = (1.0 - (m.mu1 - m.mu2).powi(2)) * structure;
+            // Edges are where a sample stands out from its blurred surroundings
+            let edge_ratio = (1.0 + (m.x2 - m.mu2).abs()) / (1.0 + (m.x1 - m.mu1).abs()) - 1.0;
+
+            let errors = [
+                (1.0 - similarity).max(0.0),
+                edge_ratio.max(0.0),
+                (-edge_ratio).max(0.0),
+            ];
+            for (sum, error) in sums.iter_mut().zip(errors) {
+                sum[0] += error;
+                sum[1] += error.powi(4);
+            }
+        }
+        sums
+    });
+
+    let n = (a.width * a.height) as f64;
+    let mut totals = [[0.0; 2]; 3];
+    for sums in rows {
+        for (total, sum) in totals.iter_mut().zip(sums) {
+            total[0] += sum[0];
+            total[1] += sum[1];
+        }
+    }
+    totals.map(|[sum, sum4]| (sum / n, (sum4 / n).powf(0.25)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    fn plane(width: usize, height: usize, f: impl Fn(usize, usize) -> f32) -> Plane {
+        let data = (0..width * height)
+            .map(|i| f(i % width, i / width))
+            .collect();
+        Plane {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Deterministic texture: an LCG seeded per pixel, so no RNG crate is needed
+    fn texture(x: usize, y: usize) -> f32 {
+        let seed = (x as u32).wrapping_mul(73_856_093) ^ (y as u32).wrapping_mul(19_349_663);
+        let noise = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345) >> 24;
+        ((x * 9 + y * 5) % 160) as f32 + (noise % 64) as f32
+    }
+
+    #[test]
+    fn test_gaussian_kernel_sums_to_one() {
+        let kernel = gaussian_kernel();
+        assert!((kernel.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!((kernel[5] - 0.266_011_2).abs() < 1e-6);
+        assert_eq!(kernel[0], kernel[10]);
+    }
+
+    #[test]
+    fn test_identical_planes_score_one() {
+        let a = plane(40, 30, texture);
+        assert!((ssim(&a, &a) - 1.0).abs() < 1e-12);
+        assert!((ms_ssim(&a, &a) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_flat_planes_compare_luminance_only() {
+        // Flat windows have no contrast, so SSIM = (2ab + C1) / (a² + b² + C1)
+        let a = plane(16, 16, |_, _| 100.0);
+        let b = plane(16, 16, |_, _| 110.0);
+        let expected = (2.0 * 100.0 * 110.0 + C1) / (100.0_f64.powi(2) + 110.0_f64.powi(2) + C1);
+        assert!((ssim(&a, &b) - expected).abs() < 1e-9);
+        assert!((expected - 0.995_476_5).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_inverted_texture_is_negative() {
+        let a = plane(32, 32, texture);
+        let b = plane(32, 32, |x, y| 255.0 - texture(x, y));
+        assert!(ssim(&a, &b) < 0.0);
+        assert_eq!(ms_ssim(&a, &b), 0.0);
+    }
+
+    const SSIM_OFFSET: f64 = 0.993_561_924_678_270_8;
+    const SSIM_BLURRED: f64 = 0.798_086_184_280_414_9;
+
+    // Regression values from a direct float64 implementation of Wang et al.:
+    // 11×11 Gaussian (σ = 1.5) 'valid' windows without separable filtering.
+    // The same windows are pinned to the MATLAB reference through the
+    // MS-SSIM fixtures below, whose coarsest scale is a plain mean SSIM.
+
+    #[test]
+    fn test_ssim_reference_vectors() {
+        let a = plane(24, 20, texture);
+        let offset = plane(24, 20, |x, y| texture(x, y) + 12.0);
+        let blurred = plane(24, 20, |x, y| (texture(x, y) + texture(x + 1, y)) / 2.0);
+
+        assert!(
+            (ssim(&a, &offset) - SSIM_OFFSET).abs() < 1e-6,
+            "{}",
+            ssim(&a, &offset)
+        );
+        assert!(
+            (ssim(&a, &blurred) - SSIM_BLURRED).abs() < 1e-6,
+            "{}",
+            ssim(&a, &blurred)
+        );
+    }
+
+    /// `(reference, distorted, MS-SSIM)` over the 192×192 fixtures in
+    /// `testdata/ms_ssim`, which come from the `iqa` crate 1.2.1 (MIT OR
+    /// Apache-2.0). The scores are its `tests/ms_ssim_reference.rs` goldens:
+    /// Wang, Simoncelli and Bovik's own `msssim.m` run under Octave.
+    const MS_SSIM_GOLDENS: [(&[u8], &[u8], f64); 4] = [
+        (
+            GRADIENT_REF,
+            include_bytes!("../testdata/ms_ssim/gradient_lo.pgm"),
+            0.985_502,
+        ),
+        (
+            GRADIENT_REF,
+            include_bytes!("../testdata/ms_ssim/gradient_hi.pgm"),
+            0.859_923,
+        ),
+        (
+            include_bytes!("../testdata/ms_ssim/solid_ref.pgm"),
+            include_bytes!("../testdata/ms_ssim/solid_dist.pgm"),
+            0.981_648,
+        ),
+        (
+            include_bytes!("../testdata/ms_ssim/texture_ref.pgm"),
+            include_bytes!("../testdata/ms_ssim/texture_dist.pgm"),
+            0.992_655,
+        ),
+    ];
+
+    const GRADIENT_REF: &[u8] = include_bytes!("../testdata/ms_ssim/gradient_ref.pgm");
+
+    /// Binary 8-bit PGM (P5) with a bare `P5\n<w> <h>\n255\n` header
+    fn pgm(bytes: &[u8]) -> Plane {
+        let header: Vec<&[u8]> = bytes.splitn(4, |&b| b == b'\n').collect();
+        assert_eq!(header[0], b"P5");
+        assert_eq!(header[2], b"255");
+        let size = std::str::from_utf8(header[1]).unwrap();
+        let (width, height) = size.split_once(' ').unwrap();
+        let (width, height) = (width.parse().unwrap(), height.parse().unwrap());
+        plane(width, height, |x, y| header[3][y * width + x] as f32)
+    }
+
+    #[test]
+    fn test_ms_ssim_matches_matlab_reference() {
+        for (reference, distorted, expected) in MS_SSIM_GOLDENS {
+            let score = ms_ssim(&pgm(reference), &pgm(distorted));
+            // The goldens are printed to six decimals
+            assert!((score - expected).abs() < 1e-6, "{score} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn test_heatmap_marks_changed_region() {
+        let a = plane(48, 32, texture);
+        let b = plane(48, 32, |x, y| {
+            if x >= 32 {
+                255.0 - texture(x, y)
+            } else {
+                texture(x, y)
+            }
+        });
+
+        let same = heatmap(&a, &a);
+        assert!(same.pixels().all(|p| p[0] == p[1] && p[1] == p[2]));
+
+        let diff = heatmap(&a, &b);
+        let (left, right) = (diff.get_pixel(4, 16), diff.get_pixel(44, 16));
+        assert_eq!(left[0], left[2], "unchanged pixels stay gray");
+        assert!(right[0] > 200 && right[2] < 120, "{:?}", right);
+    }
+
+    #[test]
+    fn test_luma_uses_bt601_weights() {
+        let img =
+            DynamicImage::ImageRgb8(image::RgbImage::from_pixel(1, 1, image::Rgb([255, 0, 0])));
+        assert!((Plane::luma(&img).data[0] - 76.245).abs() < 1e-4);
+
+        let gray = DynamicImage::ImageLuma8(GrayImage::from_pixel(2, 2, Luma([80])));
+        assert!(Plane::luma(&gray)
+            .data
+            .iter()
+            .all(|&v| (v - 80.0).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_perceptual_score_falls_with_distortion() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, y| {
+            let v = texture(x as usize, y as usize) as u8;
+            image::Rgb([v, v / 2, 255 - v])
+        }));
+        let noisy = |amount: u8| {
+            let mut noisy = img.to_rgb8();
+            for (i, p) in noisy.pixels_mut().enumerate() {
+                let delta = if i % 2 == 0 { amount } else { 0 };
+                p.0 = p.0.map(|v| v.saturating_add(delta));
+            }
+            DynamicImage::ImageRgb8(noisy)
+        };
+
+        let reference = linear_rgb(&img);
+        let score = |other: &DynamicImage| perceptual_score(&reference, &linear_rgb(other));
+        let (same, light, heavy) = (score(&img), score(&noisy(4)), score(&noisy(40)));
+
+        assert_eq!(same, 100.0);
+        assert!(light < 100.0 && light > heavy, "{} {}", light, heavy);
+    }
+
+    // Regression values of this implementation: the weights are its own, so
+    // there is no external reference to compare against
+    const PERCEPTUAL_OFFSET: f64 = 86.361_063_299_207_25;
+    const PERCEPTUAL_BLURRED: f64 = 26.742_931_324_104_49;
+
+    #[test]
+    fn test_perceptual_score_reference_vectors() {
+        let planes = |f: &dyn Fn(usize, usize) -> [u8; 3]| {
+            linear_rgb(&DynamicImage::ImageRgb8(image::RgbImage::from_fn(
+                96,
+                80,
+                |x, y| image::Rgb(f(x as usize, y as usize)),
+            )))
+        };
+        let colour = |x, y| {
+            let v = texture(x, y);
+            [v as u8, (v / 2.0 + 40.0) as u8, (255.0 - v) as u8]
+        };
+        let a = planes(&colour);
+        let offset = planes(&|x, y| colour(x, y).map(|c| c.saturating_add(12)));
+        let blurred = planes(&|x, y| {
+            let (p, q) = (colour(x, y), colour(x + 1, y));
+            [0, 1, 2].map(|c| ((p[c] as u16 + q[c] as u16) / 2) as u8)
+        });
+
+        let (offset, blurred) = (
+            perceptual_score(&a, &offset),
+            perceptual_score(&a, &blurred),
+        );
+        assert!((offset - PERCEPTUAL_OFFSET).abs() < 1e-6, "{}", offset);
+        assert!((blurred - PERCEPTUAL_BLURRED).abs() < 1e-6, "{}", blurred);
+    }
+}
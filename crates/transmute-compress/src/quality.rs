@@ -1,12 +1,22 @@
-use image::{DynamicImage, GenericImageView};
-use transmute_common::{Error, Result};
+use crate::metrics::{self, Plane};
+use image::{DynamicImage, GenericImageView, RgbImage};
+use rayon::prelude::*;
+use transmute_common::{Error, FileSize, Result};
 
-/// Quality assessment metrics
+/// Quality assessment metrics; more may be added, so only
+/// [`QualityMetric::calculate`] builds one
 #[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
 pub struct QualityMetric {
-    /// Structural Similarity Index (0.0-1.0, higher is better)
+    /// Mean SSIM over 11×11 Gaussian windows of the luma (-1.0-1.0, higher is better)
     pub ssim: f64,
 
+    /// Multi-scale SSIM of the luma (0.0-1.0, higher is better)
+    pub ms_ssim: f64,
+
+    /// SSIMULACRA2-style perceptual score (0-100, 100 = identical)
+    pub perceptual: f64,
+
     /// Peak Signal-to-Noise Ratio (dB, higher is better)
     pub psnr: f64,
 
@@ -17,33 +27,81 @@ pub struct QualityMetric {
 impl QualityMetric {
     /// Calculate quality metrics between original and compressed
     pub fn calculate(original: &DynamicImage, compressed: &DynamicImage) -> Result<Self> {
-        if original.dimensions() != compressed.dimensions() {
-            return Err(Error::ConversionError(
-                "Images must have same dimensions for quality comparison".into(),
-            ));
-        }
+        Self::check_dimensions(original, compressed)?;
 
         let orig_rgb = original.to_rgb8();
         let comp_rgb = compressed.to_rgb8();
 
         let mse = Self::calculate_mse(&orig_rgb, &comp_rgb);
         let psnr = Self::calculate_psnr(mse);
-        let ssim = Self::calculate_ssim(&orig_rgb, &comp_rgb);
 
-        Ok(Self { ssim, psnr, mse })
+        let (orig_luma, comp_luma) = (Plane::luma(original), Plane::luma(compressed));
+        let ssim = metrics::ssim(&orig_luma, &comp_luma);
+        let ms_ssim = metrics::ms_ssim(&orig_luma, &comp_luma);
+        let perceptual = metrics::perceptual_score(
+            &metrics::linear_rgb(original),
+            &metrics::linear_rgb(compressed),
+        );
+
+        Ok(Self {
+            ssim,
+            ms_ssim,
+            perceptual,
+            psnr,
+            mse,
+        })
+    }
+
+    /// SSIM alone, for when the other metrics are not needed
+    pub fn ssim(original: &DynamicImage, compressed: &DynamicImage) -> Result<f64> {
+        Self::check_dimensions(original, compressed)?;
+        Ok(metrics::ssim(
+            &Plane::luma(original),
+            &Plane::luma(compressed),
+        ))
+    }
+
+    /// Lowest SSIM of the R, G and B channels, which catches the hue shifts
+    /// luma SSIM cannot see (such as a palette swapping red for green)
+    pub fn color_ssim(original: &DynamicImage, compressed: &DynamicImage) -> Result<f64> {
+        Self::check_dimensions(original, compressed)?;
+        let (orig, comp) = (Plane::channels(original), Plane::channels(compressed));
+        Ok(orig
+            .iter()
+            .zip(&comp)
+            .map(|(a, b)| metrics::ssim(a, b))
+            .fold(1.0, f64::min))
+    }
+
+    /// Heatmap of where `compressed` differs from `original`: the original in
+    /// dimmed gray, with local dissimilarity from blue through red to yellow
+    pub fn heatmap(original: &DynamicImage, compressed: &DynamicImage) -> Result<RgbImage> {
+        Self::check_dimensions(original, compressed)?;
+        Ok(metrics::heatmap(
+            &Plane::luma(original),
+            &Plane::luma(compressed),
+        ))
+    }
+
+    fn check_dimensions(original: &DynamicImage, compressed: &DynamicImage) -> Result<()> {
+        if original.dimensions() != compressed.dimensions() {
+            return Err(Error::ConversionError(
+                "Images must have same dimensions for quality comparison".into(),
+            ));
+        }
+        Ok(())
     }
 
     /// Mean Squared Error between two images
     fn calculate_mse(img1: &image::RgbImage, img2: &image::RgbImage) -> f64 {
-        let mut sum = 0.0;
         let pixel_count = (img1.width() * img1.height() * 3) as f64; // 3 = R/G/B
 
-        for (p1, p2) in img1.pixels().zip(img2.pixels()) {
-            for i in 0..3 {
-                let diff = p1[i] as f64 - p2[i] as f64;
-                sum += diff * diff;
-            }
-        }
+        let sum: f64 = img1
+            .as_raw()
+            .par_iter()
+            .zip(img2.as_raw().par_iter())
+            .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+            .sum();
 
         sum / pixel_count
     }
@@ -57,76 +115,61 @@ impl QualityMetric {
         20.0 * (max_pixel / mse.sqrt()).log10()
     }
 
-    /// Simplified SSIM calculation (full SSIM requires windowing)
-    fn calculate_ssim(img1: &image::RgbImage, img2: &image::RgbImage) -> f64 {
-        // Constants for stability
-        let c1 = (0.01_f64 * 255.0).powi(2);
-        let c2 = (0.03_f64 * 255.0).powi(2);
-
-        // Calculate means
-        let mean1 = Self::mean_intensity(img1);
-        let mean2 = Self::mean_intensity(img2);
-
-        // Calculate variances and covariance
-        let (var1, var2, covar) = Self::calculate_variances(img1, img2, mean1, mean2);
+    /// Check if quality meets minimum threshold
+    pub fn meets_threshold(&self, min_ssim: f64) -> bool {
+        self.ssim >= min_ssim
+    }
+}
 
-        // SSIM formula
-        let numerator = (2.0 * mean1 * mean2 + c1) * (2.0 * covar + c2);
-        let denominator = (mean1.powi(2) + mean2.powi(2) + c1) * (var1 + var2 + c2);
+/// Limits a [`QualityMetric`] is checked against; unset ones always pass
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QualityThresholds {
+    pub min_ssim: Option<f64>,
+    pub min_ms_ssim: Option<f64>,
+    pub min_perceptual: Option<f64>,
+    pub min_psnr: Option<f64>,
+    pub max_mse: Option<f64>,
+}
 
-        numerator / denominator
+impl QualityThresholds {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
     }
 
-    fn mean_intensity(img: &image::RgbImage) -> f64 {
-        let mut sum = 0.0;
-        let pixel_count = (img.width() * img.height() * 3) as f64;
-
-        for pixel in img.pixels() {
-            for &channel in pixel.0.iter() {
-                sum += channel as f64;
+    /// Describe each threshold `metric` misses
+    pub fn violations(&self, metric: &QualityMetric) -> Vec<String> {
+        let minimums = [
+            ("SSIM", self.min_ssim, metric.ssim),
+            ("MS-SSIM", self.min_ms_ssim, metric.ms_ssim),
+            ("Perceptual score", self.min_perceptual, metric.perceptual),
+            ("PSNR", self.min_psnr, metric.psnr),
+        ];
+
+        let mut violations = Vec::new();
+        for (name, min, value) in minimums {
+            if let Some(min) = min {
+                if value < min {
+                    violations.push(format!("{} {:.4} is below {}", name, value, min));
+                }
             }
         }
-
-        sum / pixel_count
-    }
-
-    fn calculate_variances(
-        img1: &image::RgbImage,
-        img2: &image::RgbImage,
-        mean1: f64,
-        mean2: f64,
-    ) -> (f64, f64, f64) {
-        let mut var1 = 0.0;
-        let mut var2 = 0.0;
-        let mut covar = 0.0;
-        let pixel_count = (img1.width() * img1.height() * 3) as f64;
-
-        for (p1, p2) in img1.pixels().zip(img2.pixels()) {
-            for i in 0..3 {
-                let v1 = p1[i] as f64 - mean1;
-                let v2 = p2[i] as f64 - mean2;
-                var1 += v1 * v1;
-                var2 += v2 * v2;
-                covar += v1 * v2;
+        if let Some(max) = self.max_mse {
+            if metric.mse > max {
+                violations.push(format!("MSE {:.4} is above {}", metric.mse, max));
             }
         }
-
-        (var1 / pixel_count, var2 / pixel_count, covar / pixel_count)
-    }
-
-    /// Check if quality meets minimum threshold
-    pub fn meets_threshold(&self, min_ssim: f64) -> bool {
-        self.ssim >= min_ssim
+        violations
     }
 }
 
 /// Compression quality presets
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum QualitySettings {
     /// Maximum quality (SSIM > 0.98, JPEG ~98)
     Maximum,
 
     /// High quality (SSIM > 0.95, JPEG ~95)
+    #[default]
     High,
 
     /// Balanced quality/size (SSIM > 0.90, JPEG ~85)
@@ -162,6 +205,17 @@ impl QualitySettings {
         }
     }
 
+    /// Get the palette size for lossy PNG (64-256 colours, `None` = lossless)
+    pub fn png_colors(&self) -> Option<usize> {
+        match self {
+            Self::Maximum | Self::High => None,
+            Self::Balanced => Some(256),
+            Self::Low => Some(128),
+            Self::Custom(q) if *q >= 90 => None,
+            Self::Custom(q) => Some((*q as usize * 256 / 80).clamp(64, 256)),
+        }
+    }
+
     /// Get WebP quality (0-100)
     pub fn webp_quality(&self) -> f32 {
         match self {
@@ -173,6 +227,49 @@ impl QualitySettings {
         }
     }
 
+    /// Get AVIF quality (1-100); AVIF holds up at lower values than JPEG
+    pub fn avif_quality(&self) -> u8 {
+        match self {
+            Self::Maximum => 90,
+            Self::High => 80,
+            Self::Balanced => 65,
+            Self::Low => 50,
+            Self::Custom(q) => (*q).clamp(1, 100),
+        }
+    }
+
+    /// Get JPEG XL quality (0-100, 100 = lossless)
+    pub fn jxl_quality(&self) -> u8 {
+        match self {
+            Self::Maximum => 95,
+            Self::High => 90,
+            Self::Balanced => 80,
+            Self::Low => 70,
+            Self::Custom(q) => (*q).min(100),
+        }
+    }
+
+    /// Get JPEG XL encoder effort (1-9, higher = smaller but slower)
+    pub fn jxl_effort(&self) -> u8 {
+        match self {
+            Self::Maximum => 9,
+            Self::High | Self::Custom(_) => 7,
+            Self::Balanced => 6,
+            Self::Low => 5,
+        }
+    }
+
+    /// Get the resolution images in PDFs are downsampled to (DPI)
+    pub fn pdf_dpi(&self) -> f32 {
+        match self {
+            Self::Maximum => 300.0,
+            Self::High => 200.0,
+            Self::Balanced => 150.0,
+            Self::Low => 100.0,
+            Self::Custom(q) => 72.0 + 2.28 * (*q).min(100) as f32,
+        }
+    }
+
     /// Get target SSIM threshold
     pub fn target_ssim(&self) -> f64 {
         match self {
@@ -185,8 +282,21 @@ impl QualitySettings {
     }
 }
 
-impl Default for QualitySettings {
-    fn default() -> Self {
-        Self::High
+/// File size budget for [`crate::ImageCompressor::compress_to_size`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeTarget {
+    /// Largest acceptable output file
+    pub max_size: FileSize,
+
+    /// Scale the image down when even the lowest quality is too large
+    pub allow_downscale: bool,
+}
+
+impl SizeTarget {
+    pub fn new(max_size: FileSize) -> Self {
+        Self {
+            max_size,
+            allow_downscale: false,
+        }
     }
 }
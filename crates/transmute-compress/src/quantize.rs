@@ -0,0 +1,193 @@
+//! Lossy PNG: palette reduction with Floyd–Steinberg dithering, as pngquant
+//! does, leaving oxipng to store the result as an indexed PNG
+
+use color_quant::NeuQuant;
+use image::{Rgba, RgbaImage};
+use std::collections::{HashMap, HashSet};
+
+/// NeuQuant does not train well on smaller palettes
+const MIN_COLORS: usize = 64;
+
+/// Images up to this many pixels train on all of them, larger ones on a sample
+const FULL_SAMPLE_PIXELS: usize = 256 * 256;
+
+/// Palette alpha at or above this counts as opaque; NeuQuant rarely lands on 255
+const OPAQUE_SNAP: u8 = 250;
+
+/// Share of the quantization error passed on; a little under 1 keeps flat
+/// areas from speckling
+const DITHER_STRENGTH: f32 = 0.85;
+
+/// Reduce `img` to at most `colors` RGBA colours (64-256), dithering the error
+///
+/// Fully transparent pixels keep one exact transparent entry. Returns `None`
+/// when the image already has few enough colours for a lossless palette.
+pub(crate) fn quantize(img: &RgbaImage, colors: usize) -> Option<RgbaImage> {
+    let colors = colors.clamp(MIN_COLORS, 256);
+    if count_colors(img, colors + 1) <= colors {
+        return None;
+    }
+
+    // Invisible pixels neither train the palette nor take entries of their own
+    let has_transparent = img.pixels().any(|p| p[3] == 0);
+    let visible: Vec<u8> = img
+        .pixels()
+        .filter(|p| p[3] > 0)
+        .flat_map(|p| p.0)
+        .collect();
+    let samplefac = if visible.len() / 4 <= FULL_SAMPLE_PIXELS {
+        1
+    } else {
+        10
+    };
+    let trained = if has_transparent { colors - 1 } else { colors };
+    let quant = NeuQuant::new(samplefac, trained, &visible);
+
+    let opaque = img.pixels().all(|p| p[3] == 255);
+    let palette: Vec<[u8; 4]> = quant
+        .color_map_rgba()
+        .chunks_exact(4)
+        .map(|c| {
+            let alpha = if opaque || c[3] >= OPAQUE_SNAP {
+                255
+            } else {
+                c[3]
+            };
+            [c[0], c[1], c[2], alpha]
+        })
+        .collect();
+
+    Some(dither(img, &palette))
+}
+
+/// Distinct colours in `img`, counting no further than `limit`
+fn count_colors(img: &RgbaImage, limit: usize) -> usize {
+    let mut seen = HashSet::new();
+    for pixel in img.pixels() {
+        // Every fully transparent pixel looks the same
+        let color = if pixel[3] == 0 { [0; 4] } else { pixel.0 };
+        if seen.insert(color) && seen.len() >= limit {
+            break;
+        }
+    }
+    seen.len()
+}
+
+/// Map every pixel to its nearest palette colour, passing the colour error on
+/// to the pixels right and below (Floyd–Steinberg)
+///
+/// Alpha is matched but not dithered, and opaque pixels only take opaque
+/// entries, so no translucent speckles appear inside solid areas.
+fn dither(img: &RgbaImage, palette: &[[u8; 4]]) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let mut out = RgbaImage::new(width, height);
+
+    let opaque: Vec<[u8; 4]> = palette.iter().copied().filter(|c| c[3] == 255).collect();
+    let opaque = if opaque.is_empty() { palette } else { &opaque };
+
+    // Error owed to the current and the next row, with a pixel of padding each side
+    let mut current = vec![[0.0f32; 3]; width as usize + 2];
+    let mut next = current.clone();
+    let mut nearest_cache: HashMap<[u8; 4], [u8; 4]> = HashMap::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel(x, y);
+            if pixel[3] == 0 {
+                continue;
+            }
+
+            let i = x as usize + 1;
+            let mut target = pixel.0;
+            for c in 0..3 {
+                target[c] = (pixel[c] as f32 + current[i][c]).round().clamp(0.0, 255.0) as u8;
+            }
+            let candidates = if pixel[3] == 255 { opaque } else { palette };
+            let chosen = *nearest_cache
+                .entry(target)
+                .or_insert_with(|| nearest(candidates, target));
+            out.put_pixel(x, y, Rgba(chosen));
+
+            for c in 0..3 {
+                let error = (target[c] as f32 - chosen[c] as f32) * DITHER_STRENGTH;
+                current[i + 1][c] += error * 7.0 / 16.0;
+                next[i - 1][c] += error * 3.0 / 16.0;
+                next[i][c] += error * 5.0 / 16.0;
+                next[i + 1][c] += error / 16.0;
+            }
+        }
+        std::mem::swap(&mut current, &mut next);
+        next.fill([0.0; 3]);
+    }
+    out
+}
+
+fn nearest(palette: &[[u8; 4]], color: [u8; 4]) -> [u8; 4] {
+    let distance = |entry: &[u8; 4]| -> i32 {
+        entry
+            .iter()
+            .zip(color)
+            .map(|(&a, b)| (a as i32 - b as i32).pow(2))
+            .sum()
+    };
+    *palette
+        .iter()
+        .min_by_key(|entry| distance(entry))
+        .expect("palette is never empty")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Smooth two-way gradient with far more than 256 colours
+    fn gradient(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x * 255 / width) as u8, (y * 255 / height) as u8, 96, 255])
+        })
+    }
+
+    #[test]
+    fn test_few_colors_stay_lossless() {
+        let img = RgbaImage::from_fn(32, 32, |x, _| Rgba([(x % 4) as u8 * 60, 0, 0, 255]));
+        assert!(quantize(&img, 64).is_none());
+    }
+
+    #[test]
+    fn test_quantize_limits_palette_and_keeps_opaque() {
+        let img = gradient(128, 128);
+        let quantized = quantize(&img, 64).unwrap();
+
+        assert!(count_colors(&quantized, 1000) <= 64);
+        assert!(quantized.pixels().all(|p| p[3] == 255));
+
+        // Dithering keeps the average colour of an area close to the original
+        let mean = |img: &RgbaImage, c: usize| {
+            img.pixels().map(|p| p[c] as f64).sum::<f64>() / (128.0 * 128.0)
+        };
+        for c in 0..3 {
+            assert!((mean(&img, c) - mean(&quantized, c)).abs() < 2.0);
+        }
+    }
+
+    #[test]
+    fn test_quantize_keeps_transparency() {
+        let mut img = gradient(96, 96);
+        for (x, _, pixel) in img.enumerate_pixels_mut() {
+            if x < 32 {
+                pixel.0 = [200, 10, 10, 0];
+            } else if x < 48 {
+                pixel[3] = 128;
+            }
+        }
+        let quantized = quantize(&img, 128).unwrap();
+
+        for (x, y, pixel) in quantized.enumerate_pixels() {
+            match x {
+                0..=31 => assert_eq!(pixel.0, [0, 0, 0, 0]),
+                32..=47 => assert!(pixel[3].abs_diff(128) < 24, "{:?} at {},{}", pixel, x, y),
+                _ => assert_eq!(pixel[3], 255),
+            }
+        }
+    }
+}
@@ -27,8 +27,8 @@ fn benchmark_conversions(c: &mut Criterion) {
                 b.iter(|| {
                     converter.convert_image(
                         black_box(path),
-                        black_box(MediaFormat::Jpeg),
                         Some(temp_dir.path().to_path_buf()),
+                        black_box(MediaFormat::Jpeg),
                     )
                 });
             },
@@ -1,14 +1,36 @@
+use image::metadata::Orientation;
 use rayon::prelude::*;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use transmute_common::{Error, MediaFormat, PathManager, Result};
-use transmute_compress::{CompressionResult, ImageCompressor, QualitySettings};
-use transmute_formats::{ImageDecoder, ImageEncoder};
-use transmute_nlp::{CommandParser, Intent};
+use transmute_common::{
+    EmbeddedMetadata, Error, MediaFormat, MetadataPolicy, PageSelection, PathManager, Result,
+};
+use transmute_compress::{
+    CompressionResult, ImageCompressor, QualityMetric, QualitySettings, SizeTarget,
+};
+use transmute_formats::animation::DEFAULT_FRAME_DELAY_MS;
+use transmute_formats::{
+    pdf_security, AnimationFrame, AvifOptions, ContactSheet, DecodeOptions, EmbeddedImage,
+    FrameSequence, ImageDecoder, ImageEncoder, JxlDecoder, JxlOptions, LoopCount, PageEdit,
+    PdfExtractor, PdfImageStats, PdfInfo, PdfOptions, PdfPages, WebpOptions,
+};
+use transmute_nlp::{CommandParser, Intent, QualitySpec, TransformSpec};
+
+use crate::transform::{FlipAxis, ImageOp, Rotation, TransformPipeline};
 
 /// Main conversion engine
 pub struct Converter {
     path_manager: PathManager,
     use_gpu: bool,
+    transform: TransformPipeline,
+    metadata_policy: MetadataPolicy,
+    decode_options: DecodeOptions,
+    webp_options: WebpOptions,
+    avif_options: AvifOptions,
+    jxl_options: JxlOptions,
+    pdf_options: PdfOptions,
+    pdf_passwords: HashMap<PathBuf, String>,
+    background: [u8; 3],
 }
 
 impl Converter {
@@ -16,6 +38,15 @@ impl Converter {
         Ok(Self {
             path_manager: PathManager::new()?,
             use_gpu: false, // GPU conversion in Phase 3+
+            transform: TransformPipeline::new(),
+            metadata_policy: MetadataPolicy::default(),
+            decode_options: DecodeOptions::default(),
+            webp_options: WebpOptions::default(),
+            avif_options: AvifOptions::default(),
+            jxl_options: JxlOptions::default(),
+            pdf_options: PdfOptions::default(),
+            pdf_passwords: HashMap::new(),
+            background: [255, 255, 255],
         })
     }
 
@@ -25,6 +56,17 @@ impl Converter {
         input: &Path,
         output: Option<PathBuf>,
         target_format: MediaFormat,
+    ) -> Result<PathBuf> {
+        self.convert_image_with(input, output, target_format, &self.transform)
+    }
+
+    /// Convert single image, applying `pipeline` between decode and encode
+    pub fn convert_image_with(
+        &self,
+        input: &Path,
+        output: Option<PathBuf>,
+        target_format: MediaFormat,
+        pipeline: &TransformPipeline,
     ) -> Result<PathBuf> {
         // Validate input
         self.path_manager.validate_input(input)?;
@@ -43,11 +85,20 @@ impl Converter {
                 parent.join(format!("{}.pdf", stem))
             };
 
-            return self.images_to_pdf(vec![input.to_path_buf()], output_path, None);
+            return self.images_to_pdf_with(vec![input.to_path_buf()], output_path, None, pipeline);
+        }
+
+        // Keep every frame when both sides can hold an animation
+        if FrameSequence::supports(target_format)
+            && MediaFormat::detect(input).is_some_and(FrameSequence::supports)
+            && FrameSequence::is_animated(input)?
+        {
+            return self.convert_animation(input, output, target_format, pipeline);
         }
 
         // Decode
-        let (img, metadata) = ImageDecoder::decode(input)?;
+        let (img, metadata) = ImageDecoder::decode_with(input, &self.decode_options)?;
+        let img = pipeline.apply(img)?;
 
         tracing::info!(
             "Converting {}x{} {} → {}",
@@ -63,11 +114,186 @@ impl Converter {
                 .generate_unique_path(input, target_format.extension(), output)?;
 
         // Encode
-        ImageEncoder::encode(&img, &output_path, target_format)?;
+        let embedded = metadata.embedded.filtered(self.metadata_policy);
+        self.encode(&img, &output_path, target_format, &embedded)?;
+
+        Ok(output_path)
+    }
+
+    /// Encode with the converter's WebP/AVIF/JXL settings
+    fn encode(
+        &self,
+        img: &image::DynamicImage,
+        output_path: &Path,
+        format: MediaFormat,
+        embedded: &EmbeddedMetadata,
+    ) -> Result<()> {
+        match format {
+            MediaFormat::Webp => {
+                ImageEncoder::encode_webp(img, output_path, &self.webp_options, embedded)
+            }
+            MediaFormat::Avif => {
+                ImageEncoder::encode_avif(img, output_path, &self.avif_options, embedded)
+            }
+            MediaFormat::Jxl => {
+                ImageEncoder::encode_jxl(img, output_path, &self.jxl_options, embedded)
+            }
+            _ => ImageEncoder::encode_with_metadata(img, output_path, format, embedded),
+        }
+    }
+
+    /// Re-encode an animated GIF/WebP/PNG frame by frame, keeping delays and loop count
+    fn convert_animation(
+        &self,
+        input: &Path,
+        output: Option<PathBuf>,
+        target_format: MediaFormat,
+        pipeline: &TransformPipeline,
+    ) -> Result<PathBuf> {
+        let sequence = FrameSequence::decode(input)?.map_frames(|img| pipeline.apply(img))?;
+        let (width, height) = sequence.dimensions();
+
+        tracing::info!(
+            "Converting {}-frame {}x{} animation → {}",
+            sequence.frames.len(),
+            width,
+            height,
+            target_format
+        );
+
+        let output_path =
+            self.path_manager
+                .generate_unique_path(input, target_format.extension(), output)?;
+        std::fs::write(
+            &output_path,
+            sequence.encode(target_format, &self.webp_options)?,
+        )?;
 
         Ok(output_path)
     }
 
+    /// Write every frame of an animation as a separate still image
+    pub fn extract_frames(
+        &self,
+        input: &Path,
+        output_format: MediaFormat,
+        output_dir: Option<PathBuf>,
+    ) -> Result<Vec<PathBuf>> {
+        self.path_manager.validate_input(input)?;
+
+        if !output_format.is_image() {
+            return Err(Error::UnsupportedFormat(format!(
+                "Cannot extract frames as {}",
+                output_format
+            )));
+        }
+
+        let sequence = FrameSequence::decode(input)?;
+        tracing::info!(
+            "Extracting {} frames from {:?}",
+            sequence.frames.len(),
+            input
+        );
+
+        let base_name = input
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("frame");
+        let output_dir =
+            output_dir.unwrap_or_else(|| self.path_manager.default_output_dir().to_path_buf());
+        std::fs::create_dir_all(&output_dir)?;
+
+        let mut output_paths = Vec::new();
+        for (index, frame) in sequence.frames.into_iter().enumerate() {
+            let img = self
+                .transform
+                .apply(image::DynamicImage::ImageRgba8(frame.image))?;
+            // JPEG has no alpha channel
+            let img = if output_format == MediaFormat::Jpeg {
+                image::DynamicImage::ImageRgb8(img.to_rgb8())
+            } else {
+                img
+            };
+
+            let output_path = output_dir.join(format!(
+                "{}_frame_{:03}.{}",
+                base_name,
+                index + 1,
+                output_format.extension()
+            ));
+
+            self.encode(&img, &output_path, output_format, &Default::default())?;
+            output_paths.push(output_path);
+        }
+
+        tracing::info!("Saved {} frames to {:?}", output_paths.len(), output_dir);
+        Ok(output_paths)
+    }
+
+    /// Combine still images into an animated GIF, WebP or PNG
+    pub fn assemble_animation(
+        &self,
+        inputs: Vec<PathBuf>,
+        output: PathBuf,
+        format: MediaFormat,
+        delay_ms: u32,
+        loop_count: LoopCount,
+    ) -> Result<PathBuf> {
+        if !FrameSequence::supports(format) {
+            return Err(Error::UnsupportedFormat(format!(
+                "{} (animations can be written as GIF, WebP or PNG)",
+                format
+            )));
+        }
+
+        tracing::info!("Assembling {} images into {}", inputs.len(), format);
+
+        for input in &inputs {
+            self.path_manager.validate_input(input)?;
+        }
+
+        // Decode in parallel, preserving order
+        let decoded: Vec<Result<image::RgbaImage>> = inputs
+            .par_iter()
+            .map(|input| {
+                let (img, _metadata) = ImageDecoder::decode_with(input, &self.decode_options)?;
+                Ok(self.transform.apply(img)?.to_rgba8())
+            })
+            .collect();
+
+        let mut frames = Vec::with_capacity(decoded.len());
+        let mut canvas = None;
+        for image in decoded {
+            let mut image = image?;
+            let (width, height) = *canvas.get_or_insert(image.dimensions());
+
+            // Every frame shares the first frame's canvas
+            if image.dimensions() != (width, height) {
+                tracing::warn!(
+                    "Resizing {}x{} frame to {}x{}",
+                    image.width(),
+                    image.height(),
+                    width,
+                    height
+                );
+                image = image::imageops::resize(
+                    &image,
+                    width,
+                    height,
+                    image::imageops::FilterType::Lanczos3,
+                );
+            }
+
+            frames.push(AnimationFrame::new(image, delay_ms));
+        }
+
+        let sequence = FrameSequence::new(frames, loop_count);
+        std::fs::write(&output, sequence.encode(format, &self.webp_options)?)?;
+
+        tracing::info!("Animation created at {:?}", output);
+        Ok(output)
+    }
+
     /// Convert batch of images in parallel
     pub fn convert_batch(
         &self,
@@ -81,16 +307,117 @@ impl Converter {
             .collect()
     }
 
+    /// Compress a batch of images in parallel, each to the smallest output
+    /// whose SSIM is at least `min_ssim`
+    pub fn compress_batch_to_ssim(
+        &self,
+        inputs: Vec<PathBuf>,
+        target_format: MediaFormat,
+        min_ssim: f64,
+        output_dir: Option<PathBuf>,
+    ) -> Vec<Result<(PathBuf, CompressionResult)>> {
+        inputs
+            .par_iter()
+            .map(|input| {
+                self.compress_image_to_ssim(input, target_format, min_ssim, output_dir.clone())
+            })
+            .collect()
+    }
+
     /// Enable/disable GPU acceleration
     pub fn set_gpu_enabled(&mut self, enabled: bool) {
         self.use_gpu = enabled;
     }
 
+    /// Set the transformation pipeline applied to every decoded image
+    pub fn set_transform(&mut self, pipeline: TransformPipeline) {
+        self.transform = pipeline;
+    }
+
+    /// Get the default transformation pipeline
+    pub fn transform(&self) -> &TransformPipeline {
+        &self.transform
+    }
+
+    /// Set how EXIF/XMP/ICC metadata is carried into outputs
+    pub fn set_metadata_policy(&mut self, policy: MetadataPolicy) {
+        self.metadata_policy = policy;
+    }
+
+    pub fn metadata_policy(&self) -> MetadataPolicy {
+        self.metadata_policy
+    }
+
+    /// Set lossy/lossless settings used when converting to WebP
+    pub fn set_webp_options(&mut self, options: WebpOptions) {
+        self.webp_options = options;
+    }
+
+    /// Set quality/speed used when converting to AVIF (compression keeps only the speed)
+    pub fn set_avif_options(&mut self, options: AvifOptions) {
+        self.avif_options = options;
+    }
+
+    /// Set quality/effort used when converting to JPEG XL
+    pub fn set_jxl_options(&mut self, options: JxlOptions) {
+        self.jxl_options = options;
+    }
+
+    /// Set the page layout used when images become PDF pages and no options are passed.
+    ///
+    /// Its encryption also protects the output of page edits.
+    pub fn set_pdf_options(&mut self, options: PdfOptions) {
+        self.pdf_options = options;
+    }
+
+    /// Set the password that opens the protected input PDF `pdf`
+    pub fn set_pdf_password(&mut self, pdf: impl Into<PathBuf>, password: impl Into<String>) {
+        self.pdf_passwords.insert(pdf.into(), password.into());
+    }
+
+    /// Whether `pdf` is protected and no password set for it opens it
+    pub fn pdf_needs_password(&self, pdf: &Path) -> Result<bool> {
+        self.path_manager.validate_input(pdf)?;
+        let password = self.pdf_password(pdf).unwrap_or_default();
+        Ok(!pdf_security::check_password(pdf, password)?)
+    }
+
+    fn pdf_password(&self, pdf: &Path) -> Option<&str> {
+        self.pdf_passwords.get(pdf).map(String::as_str)
+    }
+
+    /// Open an input PDF for page edits, decrypting it if it is protected
+    fn open_pdf(&self, pdf: &Path) -> Result<PdfPages> {
+        let mut pages = PdfPages::open(pdf, self.pdf_password(pdf))?;
+        pages.set_encryption(self.pdf_options.encryption.clone());
+        Ok(pages)
+    }
+
+    /// Set the RGB colour transparent pixels are flattened onto when compressing to JPEG
+    pub fn set_background(&mut self, rgb: [u8; 3]) {
+        self.background = rgb;
+    }
+
+    /// Enable/disable rotating pixels according to the EXIF Orientation tag
+    pub fn set_auto_orient(&mut self, enabled: bool) {
+        self.decode_options.auto_orient = enabled;
+    }
+
     pub fn images_to_pdf(
         &self,
         input_images: Vec<PathBuf>,
         output: PathBuf,
-        pdf_options: Option<transmute_formats::PdfOptions>,
+        pdf_options: Option<PdfOptions>,
+    ) -> Result<PathBuf> {
+        self.images_to_pdf_with(input_images, output, pdf_options, &self.transform)
+    }
+
+    fn images_to_pdf_with(
+        &self,
+        input_images: Vec<PathBuf>,
+        output: PathBuf,
+        pdf_options: Option<PdfOptions>,
+        pipeline: &TransformPipeline,
     ) -> Result<PathBuf> {
         use transmute_formats::{ImageDecoder, PdfGenerator};
 
@@ -106,8 +433,8 @@ impl Converter {
         let decode_results: Vec<Result<(image::DynamicImage, PathBuf)>> = input_images
             .par_iter()
             .map(|input_path| {
-                let (img, _metadata) = ImageDecoder::decode(input_path)?;
-                Ok((img, input_path.clone()))
+                let (img, _metadata) = ImageDecoder::decode_with(input_path, &self.decode_options)?;
+                Ok((pipeline.apply(img)?, input_path.clone()))
             })
             .collect();
 
@@ -118,7 +445,9 @@ impl Converter {
         }
 
         // Generate PDF (must be sequential - PDF spec requires ordered assembly)
-        let options = pdf_options.unwrap_or_default();
+        let mut options = pdf_options.unwrap_or_else(|| self.pdf_options.clone());
+        // The source JPEG bytes no longer match transformed pixels
+        options.jpeg_passthrough &= pipeline.is_empty();
         let generator = PdfGenerator::new(options);
         generator.generate_from_images(images_with_paths, &output)?;
 
@@ -126,6 +455,227 @@ impl Converter {
         Ok(output)
     }
 
+    /// Merge PDFs and images, in order, into one PDF.
+    ///
+    /// Pages of input PDFs are copied as-is; each run of consecutive images becomes
+    /// pages laid out with `pdf_options`.
+    pub fn merge_to_pdf(
+        &self,
+        inputs: Vec<PathBuf>,
+        output: PathBuf,
+        pdf_options: Option<PdfOptions>,
+    ) -> Result<PathBuf> {
+        use transmute_formats::PdfGenerator;
+
+        let is_pdf = |path: &PathBuf| MediaFormat::detect(path) == Some(MediaFormat::Pdf);
+        if !inputs.iter().any(is_pdf) {
+            return self.images_to_pdf(inputs, output, pdf_options);
+        }
+
+        tracing::info!("Merging {} files into PDF: {:?}", inputs.len(), output);
+        for input in &inputs {
+            self.path_manager.validate_input(input)?;
+        }
+
+        let options = pdf_options.unwrap_or_else(|| self.pdf_options.clone());
+        if options.pdfa {
+            return Err(Error::ConversionError(
+                "PDF/A output can only be made from images, not by merging existing PDFs".into(),
+            ));
+        }
+        let info = options.resolved_info().for_new_document();
+        // Pages are encrypted once, when the merged document is saved
+        let encryption = options.encryption.clone();
+        let generator = PdfGenerator::new(PdfOptions {
+            encryption: None,
+            jpeg_passthrough: options.jpeg_passthrough && self.transform.is_empty(),
+            ..options
+        });
+        let mut parts = Vec::new();
+        for run in inputs.chunk_by(|a, b| is_pdf(a) == is_pdf(b)) {
+            if is_pdf(&run[0]) {
+                for pdf in run {
+                    parts.push(PdfPages::open(pdf, self.pdf_password(pdf))?);
+                }
+                continue;
+            }
+
+            let images = run
+                .par_iter()
+                .map(|input| {
+                    let (img, _metadata) = ImageDecoder::decode_with(input, &self.decode_options)?;
+                    Ok((self.transform.apply(img)?, input.clone()))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            parts.push(PdfPages::load_mem(&generator.generate_bytes(images)?)?);
+        }
+
+        // The merged document keeps the first input's properties unless overridden
+        let mut merged = PdfPages::merge(parts)?;
+        merged.set_info(&info)?;
+        merged.set_encryption(encryption);
+        merged.save(&output)?;
+
+        tracing::info!("PDF created at {:?}", output);
+        Ok(output)
+    }
+
+    /// Lay images out on grid pages (`pdf_options.grid`, with optional captions).
+    ///
+    /// PDF output is one document; image formats get one file per page, numbered
+    /// `{stem}_001.{ext}` when there is more than one.
+    pub fn contact_sheet(
+        &self,
+        inputs: Vec<PathBuf>,
+        output: PathBuf,
+        format: MediaFormat,
+        pdf_options: Option<PdfOptions>,
+    ) -> Result<Vec<PathBuf>> {
+        if format == MediaFormat::Pdf {
+            return Ok(vec![self.images_to_pdf(inputs, output, pdf_options)?]);
+        }
+        tracing::info!(
+            "Laying out {} images as a {} contact sheet",
+            inputs.len(),
+            format
+        );
+        for input in &inputs {
+            self.path_manager.validate_input(input)?;
+        }
+
+        let images = inputs
+            .par_iter()
+            .map(|input| {
+                let (img, _metadata) = ImageDecoder::decode_with(input, &self.decode_options)?;
+                Ok((self.transform.apply(img)?, input.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let options = pdf_options.unwrap_or_else(|| self.pdf_options.clone());
+        let pages = ContactSheet::new(options).render(&images)?;
+
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("contact_sheet")
+            .to_string();
+        let mut outputs = Vec::with_capacity(pages.len());
+        for (index, page) in pages.iter().enumerate() {
+            let path = if pages.len() == 1 {
+                output.clone()
+            } else {
+                output.with_file_name(format!("{}_{:03}.{}", stem, index + 1, format.extension()))
+            };
+            self.encode(page, &path, format, &EmbeddedMetadata::default())?;
+            outputs.push(path);
+        }
+
+        tracing::info!("Wrote {} contact sheet pages", outputs.len());
+        Ok(outputs)
+    }
+
+    /// Split a PDF into one file per page selection (one per page if `ranges` is empty)
+    pub fn split_pdf(
+        &self,
+        input: &Path,
+        ranges: &[PageSelection],
+        output_dir: Option<PathBuf>,
+    ) -> Result<Vec<PathBuf>> {
+        self.path_manager.validate_input(input)?;
+
+        let pdf = self.open_pdf(input)?;
+        let base_name = input
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("document");
+        let output_dir =
+            output_dir.unwrap_or_else(|| self.path_manager.default_output_dir().to_path_buf());
+        std::fs::create_dir_all(&output_dir)?;
+
+        let parts: Vec<(String, PageSelection)> = if ranges.is_empty() {
+            (1..=pdf.page_count())
+                .map(|page| {
+                    (
+                        format!("{}_page_{:03}.pdf", base_name, page),
+                        PageSelection::single(page),
+                    )
+                })
+                .collect()
+        } else {
+            ranges
+                .iter()
+                .enumerate()
+                .map(|(index, range)| {
+                    (
+                        format!("{}_part_{:02}.pdf", base_name, index + 1),
+                        range.clone(),
+                    )
+                })
+                .collect()
+        };
+
+        let mut output_paths = Vec::new();
+        for (name, pages) in parts {
+            let output_path = output_dir.join(name);
+            pdf.extract(&pages)?.save(&output_path)?;
+            output_paths.push(output_path);
+        }
+
+        tracing::info!("Split {:?} into {} files", input, output_paths.len());
+        Ok(output_paths)
+    }
+
+    /// Reorder, rotate, delete or select pages of a PDF without rasterizing them
+    pub fn edit_pdf(
+        &self,
+        input: &Path,
+        edits: &[PageEdit],
+        output: Option<PathBuf>,
+    ) -> Result<PathBuf> {
+        self.path_manager.validate_input(input)?;
+
+        let mut pdf = self.open_pdf(input)?;
+        for edit in edits {
+            pdf.apply(edit)?;
+        }
+
+        let output_path = self
+            .path_manager
+            .generate_unique_path(input, "pdf", output)?;
+        pdf.save(&output_path)?;
+
+        tracing::info!("Edited PDF saved at {:?}", output_path);
+        Ok(output_path)
+    }
+
+    /// Document properties from a PDF's Info dictionary
+    pub fn pdf_info(&self, input: &Path) -> Result<PdfInfo> {
+        self.path_manager.validate_input(input)?;
+        Ok(self.open_pdf(input)?.info())
+    }
+
+    /// Set or remove Info dictionary properties of a PDF (empty strings remove).
+    ///
+    /// The modification date is set to now unless `info` gives one.
+    pub fn edit_pdf_info(
+        &self,
+        input: &Path,
+        info: &PdfInfo,
+        output: Option<PathBuf>,
+    ) -> Result<PathBuf> {
+        self.path_manager.validate_input(input)?;
+
+        let mut pdf = self.open_pdf(input)?;
+        pdf.set_info(&info.for_edited_document())?;
+
+        let output_path = self
+            .path_manager
+            .generate_unique_path(input, "pdf", output)?;
+        pdf.save(&output_path)?;
+
+        tracing::info!("Updated PDF properties saved at {:?}", output_path);
+        Ok(output_path)
+    }
+
     /// Extract PDF pages to individual images
     pub fn pdf_to_images(
         &self,
@@ -134,7 +684,28 @@ impl Converter {
         output_dir: Option<PathBuf>,
         dpi: Option<f32>,
     ) -> Result<Vec<PathBuf>> {
-        use transmute_formats::{ImageEncoder, PdfExtractor};
+        self.pdf_to_images_with(
+            pdf_path,
+            output_format,
+            output_dir,
+            dpi,
+            &PageSelection::all(),
+        )
+    }
+
+    /// Extract the selected PDF pages to individual images.
+    ///
+    /// Pages are rendered and encoded one at a time, so memory use does not grow
+    /// with the page count.
+    pub fn pdf_to_images_with(
+        &self,
+        pdf_path: &Path,
+        output_format: MediaFormat,
+        output_dir: Option<PathBuf>,
+        dpi: Option<f32>,
+        pages: &PageSelection,
+    ) -> Result<Vec<PathBuf>> {
+        self.path_manager.validate_input(pdf_path)?;
 
         if !output_format.is_image() {
             return Err(Error::UnsupportedFormat(format!(
@@ -143,48 +714,67 @@ impl Converter {
             )));
         }
 
-        tracing::info!("Extracting PDF pages from {:?}", pdf_path);
+        tracing::info!("Extracting PDF pages {} from {:?}", pages, pdf_path);
 
-        // Extract pages as images
-        let extractor = PdfExtractor::new(dpi.unwrap_or(300.0));
-        let images = extractor.extract_pages(pdf_path)?;
+        let base_name = pdf_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("page");
+        let output_dir =
+            output_dir.unwrap_or_else(|| self.path_manager.default_output_dir().to_path_buf());
+        std::fs::create_dir_all(&output_dir)?;
 
-        tracing::info!(
-            "Extracted {} pages, encoding to {}",
-            images.len(),
-            output_format
-        );
-
-        // Save each page as separate image
+        // Encode each page as soon as it is rendered
+        let mut extractor = PdfExtractor::new(dpi.unwrap_or(300.0));
+        extractor.set_password(self.pdf_password(pdf_path).map(str::to_string));
         let mut output_paths = Vec::new();
-        for (page_num, img) in images.into_iter().enumerate() {
-            // Generate unique path with page number
-            let base_name = pdf_path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("page");
-
-            let output_dir = output_dir
-                .clone()
-                .unwrap_or_else(|| self.path_manager.default_output_dir().to_path_buf());
-
-            std::fs::create_dir_all(&output_dir)?;
+        extractor.for_each_page(pdf_path, pages, |page, img| {
+            let img = self.transform.apply(img)?;
+            // JPEG has no alpha channel
+            let img = if output_format == MediaFormat::Jpeg {
+                image::DynamicImage::ImageRgb8(img.to_rgb8())
+            } else {
+                img
+            };
 
             let output_path = output_dir.join(format!(
                 "{}_page_{:03}.{}",
                 base_name,
-                page_num + 1,
+                page,
                 output_format.extension()
             ));
 
-            ImageEncoder::encode(&img, &output_path, output_format)?;
+            self.encode(&img, &output_path, output_format, &Default::default())?;
             output_paths.push(output_path);
-        }
+            Ok(())
+        })?;
 
         tracing::info!("Saved {} images to {:?}", output_paths.len(), output_dir);
         Ok(output_paths)
     }
 
+    /// Save the images embedded in a PDF without re-rendering them.
+    ///
+    /// JPEG, JPEG 2000 and JBIG2 streams are copied byte-for-byte; other bitmaps become PNG.
+    pub fn extract_pdf_images(
+        &self,
+        pdf_path: &Path,
+        output_dir: Option<PathBuf>,
+    ) -> Result<Vec<EmbeddedImage>> {
+        self.path_manager.validate_input(pdf_path)?;
+
+        if MediaFormat::detect(pdf_path) != Some(MediaFormat::Pdf) {
+            return Err(Error::UnsupportedFormat(format!(
+                "{:?} is not a PDF",
+                pdf_path
+            )));
+        }
+
+        let output_dir =
+            output_dir.unwrap_or_else(|| self.path_manager.default_output_dir().to_path_buf());
+        PdfExtractor::extract_embedded_images(pdf_path, self.pdf_password(pdf_path), &output_dir)
+    }
+
     /// Compress image with quality control
     pub fn compress_image(
         &self,
@@ -192,24 +782,111 @@ impl Converter {
         target_format: MediaFormat,
         quality: QualitySettings,
         output: Option<PathBuf>,
+    ) -> Result<(PathBuf, CompressionResult)> {
+        self.compress_image_with(input, target_format, quality, output, &self.transform)
+    }
+
+    /// Compress image, applying `pipeline` before encoding
+    pub fn compress_image_with(
+        &self,
+        input: &Path,
+        target_format: MediaFormat,
+        quality: QualitySettings,
+        output: Option<PathBuf>,
+        pipeline: &TransformPipeline,
+    ) -> Result<(PathBuf, CompressionResult)> {
+        tracing::info!("Compressing to {} (quality: {:?})", target_format, quality);
+
+        self.compress_decoded(
+            input,
+            target_format,
+            output,
+            pipeline,
+            |compressor, img, embedded| {
+                compressor.compress_with_metadata(img, target_format, quality, false, embedded)
+            },
+        )
+    }
+
+    /// Compress image at the highest quality that fits `target`
+    pub fn compress_image_to_size(
+        &self,
+        input: &Path,
+        target_format: MediaFormat,
+        target: SizeTarget,
+        output: Option<PathBuf>,
+    ) -> Result<(PathBuf, CompressionResult)> {
+        tracing::info!("Compressing to {} under {}", target_format, target.max_size);
+
+        self.compress_decoded(
+            input,
+            target_format,
+            output,
+            &self.transform,
+            |compressor, img, embedded| {
+                compressor.compress_to_size(img, target_format, target, embedded)
+            },
+        )
+    }
+
+    /// Compress image to the smallest output whose SSIM is at least `min_ssim`
+    /// (luma SSIM, see [`ImageCompressor::compress_to_ssim`])
+    pub fn compress_image_to_ssim(
+        &self,
+        input: &Path,
+        target_format: MediaFormat,
+        min_ssim: f64,
+        output: Option<PathBuf>,
+    ) -> Result<(PathBuf, CompressionResult)> {
+        tracing::info!(
+            "Compressing to {} at SSIM {} or better",
+            target_format,
+            min_ssim
+        );
+
+        self.compress_decoded(
+            input,
+            target_format,
+            output,
+            &self.transform,
+            |compressor, img, embedded| {
+                compressor.compress_to_ssim(img, target_format, min_ssim, embedded)
+            },
+        )
+    }
+
+    /// Decode, transform and encode an image with `compress`, writing the result
+    fn compress_decoded(
+        &self,
+        input: &Path,
+        target_format: MediaFormat,
+        output: Option<PathBuf>,
+        pipeline: &TransformPipeline,
+        compress: impl FnOnce(
+            &ImageCompressor,
+            &image::DynamicImage,
+            &EmbeddedMetadata,
+        ) -> Result<CompressionResult>,
     ) -> Result<(PathBuf, CompressionResult)> {
         use transmute_formats::ImageDecoder;
 
         // Validate and decode
         self.path_manager.validate_input(input)?;
-        let (img, metadata) = ImageDecoder::decode(input)?;
+        let (img, metadata) = ImageDecoder::decode_with(input, &self.decode_options)?;
+        let img = pipeline.apply(img)?;
 
         tracing::info!(
-            "Compressing {}x{} {} → {} (quality: {:?})",
+            "Compressing {}x{} {} → {}",
             metadata.width,
             metadata.height,
             metadata.format,
-            target_format,
-            quality
+            target_format
         );
 
         // Create compressor with GPU if enabled
-        let compressor = ImageCompressor::new(self.use_gpu)?;
+        let mut compressor = ImageCompressor::new(self.use_gpu)?;
+        compressor.set_background(image::Rgb(self.background));
+        compressor.set_avif_speed(self.avif_options.speed);
 
         // Generate output path
         let output_path =
@@ -217,7 +894,11 @@ impl Converter {
                 .generate_unique_path(input, target_format.extension(), output)?;
 
         // Compress to file
-        let result = compressor.compress_to_file(&img, &output_path, target_format, quality)?;
+        let embedded = metadata.embedded.filtered(self.metadata_policy);
+        let mut result = compress(&compressor, &img, &embedded)?;
+        // Measured against the source as decoded, not as resized or flattened
+        result.set_original_size(metadata.decoded_size());
+        std::fs::write(&output_path, &result.data)?;
 
         tracing::info!(
             "Compression complete: {:.1}% size reduction (ratio: {:.2}x)",
@@ -228,6 +909,189 @@ impl Converter {
         Ok((output_path, result))
     }
 
+    /// Losslessly repack a JPEG as JPEG XL (bit-exact reversible, pixels are never decoded)
+    pub fn transcode_jpeg_to_jxl(
+        &self,
+        input: &Path,
+        output: Option<PathBuf>,
+    ) -> Result<(PathBuf, CompressionResult)> {
+        self.path_manager.validate_input(input)?;
+        let jpeg = std::fs::read(input)?;
+
+        // libjxl carries every APPn segment over, so apply the policy to the JPEG first
+        let policy = self.metadata_policy;
+        if matches!(policy, MetadataPolicy::StripAll | MetadataPolicy::KeepIccOnly)
+            && Self::jpeg_orientation(&jpeg) != Orientation::NoTransforms
+        {
+            return Err(Error::ConversionError(format!(
+                "{:?} is stored sideways and relies on its EXIF orientation, which the {} \
+                 metadata policy would drop; the pixels cannot be rotated losslessly",
+                input, policy
+            )));
+        }
+        let jpeg = policy.apply_to_jpeg(&jpeg)?;
+
+        let output_path =
+            self.path_manager
+                .generate_unique_path(input, MediaFormat::Jxl.extension(), output)?;
+
+        let result = ImageCompressor::new(false)?.transcode_jpeg_to_jxl(&jpeg)?;
+        std::fs::write(&output_path, &result.data)?;
+
+        tracing::info!(
+            "JPEG → JXL transcode: {:.1}% smaller ({} → {} bytes)",
+            result.size_reduction_percent(),
+            result.original_size,
+            result.compressed_size
+        );
+
+        Ok((output_path, result))
+    }
+
+    /// Rebuild the original JPEG from a JPEG XL made by [`Self::transcode_jpeg_to_jxl`].
+    ///
+    /// The bytes come back exactly as repacked, so the metadata policy does not apply.
+    pub fn reconstruct_jpeg_from_jxl(
+        &self,
+        input: &Path,
+        output: Option<PathBuf>,
+    ) -> Result<(PathBuf, CompressionResult)> {
+        self.path_manager.validate_input(input)?;
+        let jxl = std::fs::read(input)?;
+        let jpeg = JxlDecoder::reconstruct_jpeg(&jxl)?;
+
+        let output_path =
+            self.path_manager
+                .generate_unique_path(input, MediaFormat::Jpeg.extension(), output)?;
+        std::fs::write(&output_path, &jpeg)?;
+
+        tracing::info!(
+            "JXL → JPEG reconstruction: {} → {} bytes",
+            jxl.len(),
+            jpeg.len()
+        );
+
+        Ok((
+            output_path,
+            CompressionResult {
+                original_size: jxl.len(),
+                compressed_size: jpeg.len(),
+                ratio: jxl.len() as f32 / jpeg.len() as f32,
+                quality: None,
+                data: jpeg,
+            },
+        ))
+    }
+
+    /// EXIF orientation of a JPEG, read from its headers only
+    fn jpeg_orientation(jpeg: &[u8]) -> Orientation {
+        use image::ImageDecoder as _;
+
+        image::codecs::jpeg::JpegDecoder::new(std::io::Cursor::new(jpeg))
+            .and_then(|mut decoder| decoder.exif_metadata())
+            .ok()
+            .flatten()
+            .and_then(|exif| Orientation::from_exif_chunk(&exif))
+            .unwrap_or(Orientation::NoTransforms)
+    }
+
+    /// Measure how far `candidate` is from `reference`, optionally writing a
+    /// heatmap of where they differ to `heatmap` (format from its extension)
+    pub fn compare(
+        &self,
+        reference: &Path,
+        candidate: &Path,
+        heatmap: Option<&Path>,
+    ) -> Result<QualityMetric> {
+        use image::GenericImageView;
+        use transmute_formats::ImageDecoder;
+
+        self.path_manager.validate_input(reference)?;
+        self.path_manager.validate_input(candidate)?;
+        let (reference_img, _) = ImageDecoder::decode_with(reference, &self.decode_options)?;
+        let (candidate_img, _) = ImageDecoder::decode_with(candidate, &self.decode_options)?;
+
+        if reference_img.dimensions() != candidate_img.dimensions() {
+            return Err(Error::ConversionError(format!(
+                "Cannot compare a {}x{} image with a {}x{} one",
+                reference_img.width(),
+                reference_img.height(),
+                candidate_img.width(),
+                candidate_img.height()
+            )));
+        }
+
+        let metric = QualityMetric::calculate(&reference_img, &candidate_img)?;
+        if let Some(path) = heatmap {
+            QualityMetric::heatmap(&reference_img, &candidate_img)?.save(path)?;
+        }
+
+        tracing::info!(
+            "Compared {:?} with {:?}: SSIM {:.4}, PSNR {:.2} dB",
+            reference,
+            candidate,
+            metric.ssim,
+            metric.psnr
+        );
+        Ok(metric)
+    }
+
+    /// Shrink an existing PDF by downsampling images drawn sharper than `max_dpi`
+    /// (default: the quality's DPI) and re-encoding them as JPEG.
+    ///
+    /// `original_size` is the input file size here. When rewriting does not make
+    /// an unprotected PDF smaller, the original is copied unchanged.
+    pub fn compress_pdf(
+        &self,
+        input: &Path,
+        quality: QualitySettings,
+        max_dpi: Option<f32>,
+        output: Option<PathBuf>,
+    ) -> Result<(PathBuf, CompressionResult, PdfImageStats)> {
+        self.path_manager.validate_input(input)?;
+        let original = std::fs::read(input)?;
+        let original_size = original.len();
+
+        let mut pages = self.open_pdf(input)?;
+        let compressor = ImageCompressor::new(self.use_gpu)?;
+        let stats = compressor.compress_pdf(&mut pages, quality, max_dpi)?;
+        let mut data = pages.to_bytes()?;
+
+        // Protection is added or removed on purpose, so keep the rewrite then
+        let unchanged_protection =
+            self.pdf_password(input).is_none() && self.pdf_options.encryption.is_none();
+        if unchanged_protection && data.len() >= original_size {
+            tracing::info!(
+                "Rewriting did not make {:?} smaller, keeping it as is",
+                input
+            );
+            data = original;
+        }
+
+        let output_path =
+            self.path_manager
+                .generate_unique_path(input, MediaFormat::Pdf.extension(), output)?;
+        std::fs::write(&output_path, &data)?;
+
+        let result = CompressionResult {
+            original_size,
+            compressed_size: data.len(),
+            ratio: original_size as f32 / data.len() as f32,
+            quality: None,
+            data,
+        };
+
+        tracing::info!(
+            "PDF compression: {} of {} images re-encoded ({} downsampled), {:.1}% smaller",
+            stats.recompressed,
+            stats.images,
+            stats.downsampled,
+            result.size_reduction_percent()
+        );
+
+        Ok((output_path, result, stats))
+    }
+
     /// Batch compress with progress tracking
     pub async fn compress_batch(
         &self,
@@ -253,6 +1117,13 @@ impl Converter {
 
         match intent {
             Intent::Convert(conv) => {
+                // A PDF turns into one image per page
+                if MediaFormat::detect(&conv.input) == Some(MediaFormat::Pdf)
+                    && conv.target_format.is_image()
+                {
+                    return self.pdf_to_images(&conv.input, conv.target_format, conv.output, None);
+                }
+
                 let output = self.convert_image(&conv.input, conv.output, conv.target_format)?;
                 Ok(vec![output])
             }
@@ -260,9 +1131,41 @@ impl Converter {
             Intent::Compress(comp) => {
                 let quality = comp.quality.to_settings();
                 let format = comp.target_format.unwrap_or_else(|| {
-                    MediaFormat::from_path(&comp.input).unwrap_or(MediaFormat::Jpeg)
+                    MediaFormat::detect(&comp.input).unwrap_or(MediaFormat::Jpeg)
                 });
 
+                if format == MediaFormat::Pdf {
+                    let target = match comp.quality {
+                        QualitySpec::MaxSize(_) => Some("A file size limit"),
+                        QualitySpec::MinSsim(_) => Some("An SSIM floor"),
+                        _ => None,
+                    };
+                    if let Some(target) = target {
+                        return Err(Error::ConversionError(format!(
+                            "{} does not apply to PDF input; give a quality instead",
+                            target
+                        )));
+                    }
+                }
+
+                if let QualitySpec::MaxSize(target) = comp.quality {
+                    let (output, _) =
+                        self.compress_image_to_size(&comp.input, format, target, comp.output)?;
+                    return Ok(vec![output]);
+                }
+
+                if let QualitySpec::MinSsim(min_ssim) = comp.quality {
+                    let (output, _) =
+                        self.compress_image_to_ssim(&comp.input, format, min_ssim, comp.output)?;
+                    return Ok(vec![output]);
+                }
+
+                if format == MediaFormat::Pdf {
+                    let (output, _, _) =
+                        self.compress_pdf(&comp.input, quality, None, comp.output)?;
+                    return Ok(vec![output]);
+                }
+
                 let (output, result) =
                     self.compress_image(&comp.input, format, quality, comp.output)?;
 
@@ -274,6 +1177,17 @@ impl Converter {
                 Ok(vec![output])
             }
 
+            Intent::Transform(transform) => {
+                let pipeline = Self::pipeline_from_specs(&transform.ops)?;
+                let format = transform.target_format.unwrap_or_else(|| {
+                    MediaFormat::detect(&transform.input).unwrap_or(MediaFormat::Png)
+                });
+
+                let output =
+                    self.convert_image_with(&transform.input, transform.output, format, &pipeline)?;
+                Ok(vec![output])
+            }
+
             Intent::Enhance(_) => {
                 // Only if Phase 4 is implemented
                 Err(Error::ConversionError(
@@ -285,14 +1199,26 @@ impl Converter {
                 use transmute_nlp::PathResolver;
 
                 let resolver = PathResolver::new()?;
-                let files = resolver.resolve_pattern(&batch.pattern)?;
+                let matches = resolver.match_pattern(&batch.pattern)?;
+                let files = matches.files;
 
                 if files.is_empty() {
                     return Err(Error::ConversionError(format!(
-                        "No files matched pattern: {}",
-                        batch.pattern
+                        "No files matched pattern: {}{}",
+                        batch.pattern,
+                        if matches.skipped.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" ({} are not readable media)", matches.skipped.len())
+                        }
                     )));
                 }
+                if !matches.skipped.is_empty() {
+                    tracing::warn!(
+                        "Skipping {} files that are not readable media",
+                        matches.skipped.len()
+                    );
+                }
 
                 tracing::info!("Processing {} files in batch", files.len());
 
@@ -310,13 +1236,80 @@ impl Converter {
                     combine.output
                 );
 
-                let output = self.images_to_pdf(combine.inputs, combine.output, None)?;
+                let output = self.merge_to_pdf(combine.inputs, combine.output, None)?;
+                Ok(vec![output])
+            }
+
+            Intent::ExtractFrames(extract) => {
+                self.extract_frames(&extract.input, extract.target_format, extract.output)
+            }
+
+            Intent::ExtractImages(extract) => Ok(self
+                .extract_pdf_images(&extract.input, extract.output)?
+                .into_iter()
+                .map(|image| image.path)
+                .collect()),
+
+            Intent::ExtractPages(extract) => self.pdf_to_images_with(
+                &extract.input,
+                extract.target_format,
+                extract.output,
+                None,
+                &extract.pages,
+            ),
+
+            Intent::AssembleAnimation(assemble) => {
+                let output = assemble.output.unwrap_or_else(|| {
+                    let parent = assemble.inputs[0]
+                        .parent()
+                        .unwrap_or_else(|| Path::new("."));
+                    parent.join(format!("animation.{}", assemble.target_format.extension()))
+                });
+
+                let output = self.assemble_animation(
+                    assemble.inputs,
+                    output,
+                    assemble.target_format,
+                    assemble.delay_ms.unwrap_or(DEFAULT_FRAME_DELAY_MS),
+                    LoopCount::Infinite,
+                )?;
                 Ok(vec![output])
             }
         }
     }
 }
 
+impl Converter {
+    /// Map parsed NLP transform specs onto pipeline operations
+    fn pipeline_from_specs(specs: &[TransformSpec]) -> Result<TransformPipeline> {
+        let mut pipeline = TransformPipeline::new();
+
+        for spec in specs {
+            let op = match *spec {
+                TransformSpec::Resize { width, height } => ImageOp::Resize { width, height },
+                TransformSpec::MaxEdge(max) => ImageOp::MaxEdge(max),
+                TransformSpec::Crop {
+                    aspect: (width, height),
+                } => ImageOp::CropAspect { width, height },
+                TransformSpec::Rotate(degrees) => {
+                    let rotation = Rotation::from_degrees(degrees).ok_or_else(|| {
+                        Error::ConversionError(format!(
+                            "Rotation must be a multiple of 90 degrees, got {}",
+                            degrees
+                        ))
+                    })?;
+                    ImageOp::Rotate(rotation)
+                }
+                TransformSpec::FlipHorizontal => ImageOp::Flip(FlipAxis::Horizontal),
+                TransformSpec::FlipVertical => ImageOp::Flip(FlipAxis::Vertical),
+            };
+            pipeline.push(op);
+        }
+
+        Ok(pipeline)
+    }
+}
+
 impl Default for Converter {
     fn default() -> Self {
         Self::new().expect("Failed to create Converter")
@@ -393,4 +1386,170 @@ mod tests {
         assert!(result.is_ok());
         assert!(output.exists());
     }
+
+    #[test]
+    fn test_convert_with_transform() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("wide.png");
+        DynamicImage::new_rgb8(1600, 900).save(&input_path).unwrap();
+
+        let mut converter = Converter::new().unwrap();
+        converter.set_transform(
+            TransformPipeline::new()
+                .with_op(ImageOp::MaxEdge(800))
+                .with_op(ImageOp::Rotate(Rotation::Cw90)),
+        );
+
+        let output = converter
+            .convert_image(
+                &input_path,
+                Some(temp_dir.path().to_path_buf()),
+                MediaFormat::Png,
+            )
+            .unwrap();
+
+        let result = image::open(&output).unwrap();
+        assert_eq!((result.width(), result.height()), (450, 800));
+    }
+
+    #[test]
+    fn test_metadata_policy_strips_gps() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("geotagged.jpg");
+
+        // EXIF with a single GPS IFD holding GPSLatitude = 48/1, 51/1, 30/1
+        let mut exif = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        exif.extend_from_slice(&[1, 0, 0x25, 0x88, 4, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0, 0, 0, 0]);
+        exif.extend_from_slice(&[1, 0, 0x02, 0x00, 5, 0, 3, 0, 0, 0, 44, 0, 0, 0, 0, 0, 0, 0]);
+        for v in [48u32, 1, 51, 1, 30, 1] {
+            exif.extend_from_slice(&v.to_le_bytes());
+        }
+
+        let mut jpeg = Vec::new();
+        DynamicImage::new_rgb8(16, 16)
+            .write_to(
+                &mut std::io::Cursor::new(&mut jpeg),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        let source = transmute_common::EmbeddedMetadata {
+            exif: Some(exif.clone()),
+            ..Default::default()
+        };
+        std::fs::write(&input_path, source.embed(jpeg, MediaFormat::Jpeg).unwrap()).unwrap();
+
+        let output_exif = |converter: &Converter, format: MediaFormat| {
+            let output = converter
+                .convert_image(&input_path, Some(temp_dir.path().to_path_buf()), format)
+                .unwrap();
+            ImageDecoder::decode(&output).unwrap().1.embedded.exif
+        };
+
+        let mut converter = Converter::new().unwrap();
+        converter.set_metadata_policy(MetadataPolicy::KeepAll);
+        assert_eq!(
+            output_exif(&converter, MediaFormat::Png),
+            Some(exif.clone())
+        );
+
+        converter.set_metadata_policy(MetadataPolicy::StripPrivate);
+        for format in [MediaFormat::Png, MediaFormat::Jpeg, MediaFormat::Webp] {
+            let scrubbed = output_exif(&converter, format).unwrap();
+            assert!(
+                !scrubbed.windows(4).any(|w| w == 48u32.to_le_bytes()),
+                "GPS data leaked into {format}"
+            );
+        }
+
+        converter.set_metadata_policy(MetadataPolicy::StripAll);
+        let (output, _) = converter
+            .compress_image(
+                &input_path,
+                MediaFormat::Jpeg,
+                QualitySettings::Balanced,
+                Some(temp_dir.path().to_path_buf()),
+            )
+            .unwrap();
+        assert!(ImageDecoder::decode(&output)
+            .unwrap()
+            .1
+            .embedded
+            .exif
+            .is_none());
+    }
+
+    #[test]
+    fn test_lossless_jpeg_keeps_sideways_orientation() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_path = temp_dir.path().join("sideways.jpg");
+
+        // EXIF with Orientation = 6 (rotate 90° clockwise to display)
+        let mut exif = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        exif.extend_from_slice(&[1, 0, 0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0]);
+        let mut jpeg = Vec::new();
+        DynamicImage::new_rgb8(16, 8)
+            .write_to(
+                &mut std::io::Cursor::new(&mut jpeg),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        let source = transmute_common::EmbeddedMetadata {
+            exif: Some(exif),
+            ..Default::default()
+        };
+        std::fs::write(&input_path, source.embed(jpeg, MediaFormat::Jpeg).unwrap()).unwrap();
+
+        let mut converter = Converter::new().unwrap();
+        converter.set_metadata_policy(MetadataPolicy::StripAll);
+        match converter.transcode_jpeg_to_jxl(&input_path, Some(temp_dir.path().to_path_buf())) {
+            Err(Error::ConversionError(msg)) => assert!(msg.contains("orientation"), "{msg}"),
+            other => panic!("expected an orientation error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_animation_assemble_convert_extract() {
+        let temp_dir = TempDir::new().unwrap();
+        let frames: Vec<PathBuf> = [[255, 0, 0], [0, 255, 0], [0, 0, 255]]
+            .iter()
+            .enumerate()
+            .map(|(i, rgb)| {
+                let path = temp_dir.path().join(format!("frame{}.png", i));
+                image::RgbImage::from_pixel(32, 24, image::Rgb(*rgb))
+                    .save(&path)
+                    .unwrap();
+                path
+            })
+            .collect();
+
+        let mut converter = Converter::new().unwrap();
+        let gif = converter
+            .assemble_animation(
+                frames,
+                temp_dir.path().join("anim.gif"),
+                MediaFormat::Gif,
+                70,
+                LoopCount::Finite(2),
+            )
+            .unwrap();
+
+        // GIF → WebP keeps every frame and the timing, with the transform applied per frame
+        converter.set_transform(TransformPipeline::new().with_op(ImageOp::Rotate(Rotation::Cw90)));
+        let webp = converter
+            .convert_image(&gif, Some(temp_dir.path().to_path_buf()), MediaFormat::Webp)
+            .unwrap();
+        let sequence = FrameSequence::decode(&webp).unwrap();
+        assert_eq!(sequence.frames.len(), 3);
+        assert_eq!(sequence.dimensions(), (24, 32));
+        assert_eq!(sequence.duration_ms(), 210);
+        assert_eq!(sequence.loop_count, LoopCount::Finite(2));
+
+        converter.set_transform(TransformPipeline::new());
+        let extracted = converter
+            .extract_frames(&webp, MediaFormat::Png, Some(temp_dir.path().join("out")))
+            .unwrap();
+        assert_eq!(extracted.len(), 3);
+        let stem = webp.file_stem().unwrap().to_str().unwrap();
+        assert!(extracted[2].ends_with(format!("{}_frame_003.png", stem)));
+    }
 }
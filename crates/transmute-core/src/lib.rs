@@ -1,7 +1,16 @@
 pub mod batch;
 pub mod converter;
+pub mod transform;
 
 pub use batch::{BatchJob, BatchProcessor, BatchProgress};
 pub use converter::Converter;
+pub use transform::{FlipAxis, ImageOp, Rotation, TransformPipeline};
 pub use transmute_common::GpuContext;
+pub use transmute_compress::{QualityMetric, QualityThresholds};
+pub use transmute_formats::animation::DEFAULT_FRAME_DELAY_MS;
+pub use transmute_formats::{
+    AvifOptions, EmbeddedImage, FitMode, Grid, JxlOptions, LoopCount, PageEdit, PageLabelStyle,
+    PageLabels, PageOrientation, PageSize, PdfEncryption, PdfImageStats, PdfInfo, PdfOptions,
+    PdfPermissions, WebpOptions,
+};
 pub use transmute_nlp::{CommandParser, Intent};
@@ -0,0 +1,224 @@
+use image::{imageops::FilterType, DynamicImage};
+use transmute_common::{Error, Result};
+
+/// Clockwise rotation in 90° steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    /// Parse from degrees (90/180/270, negative values rotate counter-clockwise)
+    pub fn from_degrees(degrees: i32) -> Option<Self> {
+        match degrees.rem_euclid(360) {
+            90 => Some(Self::Cw90),
+            180 => Some(Self::Cw180),
+            270 => Some(Self::Cw270),
+            _ => None,
+        }
+    }
+}
+
+/// Mirror axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlipAxis {
+    Horizontal,
+    Vertical,
+}
+
+/// Single image transformation step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageOp {
+    /// Resize to the given dimensions; a missing side keeps the aspect ratio
+    Resize {
+        width: Option<u32>,
+        height: Option<u32>,
+    },
+
+    /// Downscale so the longest edge is at most this many pixels (never upscales)
+    MaxEdge(u32),
+
+    /// Center-crop to an aspect ratio (e.g. 16:9)
+    CropAspect {
+        width: u32,
+        height: u32,
+    },
+
+    Rotate(Rotation),
+
+    Flip(FlipAxis),
+}
+
+impl ImageOp {
+    /// Apply this operation to an image
+    pub fn apply(&self, img: DynamicImage) -> Result<DynamicImage> {
+        match *self {
+            Self::Resize { width, height } => {
+                let (src_w, src_h) = (img.width() as f64, img.height() as f64);
+                let (w, h) = match (width, height) {
+                    (Some(w), Some(h)) => (w, h),
+                    (Some(w), None) => (w, (src_h * w as f64 / src_w).round() as u32),
+                    (None, Some(h)) => ((src_w * h as f64 / src_h).round() as u32, h),
+                    (None, None) => {
+                        return Err(Error::ConversionError(
+                            "Resize requires a width or a height".into(),
+                        ))
+                    }
+                };
+
+                if w == 0 || h == 0 {
+                    return Err(Error::ConversionError(format!(
+                        "Invalid resize target: {}x{}",
+                        w, h
+                    )));
+                }
+
+                Ok(img.resize_exact(w, h, FilterType::Lanczos3))
+            }
+
+            Self::MaxEdge(max) => {
+                if max == 0 {
+                    return Err(Error::ConversionError("Max edge must be > 0".into()));
+                }
+
+                if img.width().max(img.height()) <= max {
+                    Ok(img)
+                } else {
+                    // `resize` preserves aspect ratio within the bounding box
+                    Ok(img.resize(max, max, FilterType::Lanczos3))
+                }
+            }
+
+            Self::CropAspect { width, height } => {
+                if width == 0 || height == 0 {
+                    return Err(Error::ConversionError(format!(
+                        "Invalid crop aspect ratio: {}:{}",
+                        width, height
+                    )));
+                }
+
+                let (src_w, src_h) = (img.width() as u64, img.height() as u64);
+                let target = width as f64 / height as f64;
+
+                // Keep the largest centered region with the requested aspect ratio
+                let (crop_w, crop_h) = if (src_w as f64 / src_h as f64) > target {
+                    (((src_h as f64) * target).round() as u64, src_h)
+                } else {
+                    (src_w, ((src_w as f64) / target).round() as u64)
+                };
+                let crop_w = crop_w.clamp(1, src_w) as u32;
+                let crop_h = crop_h.clamp(1, src_h) as u32;
+
+                let x = (img.width() - crop_w) / 2;
+                let y = (img.height() - crop_h) / 2;
+
+                Ok(img.crop_imm(x, y, crop_w, crop_h))
+            }
+
+            Self::Rotate(rotation) => Ok(match rotation {
+                Rotation::Cw90 => img.rotate90(),
+                Rotation::Cw180 => img.rotate180(),
+                Rotation::Cw270 => img.rotate270(),
+            }),
+
+            Self::Flip(axis) => Ok(match axis {
+                FlipAxis::Horizontal => img.fliph(),
+                FlipAxis::Vertical => img.flipv(),
+            }),
+        }
+    }
+}
+
+/// Ordered list of operations applied between decode and encode
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransformPipeline {
+    ops: Vec<ImageOp>,
+}
+
+impl TransformPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an operation (builder style)
+    pub fn with_op(mut self, op: ImageOp) -> Self {
+        self.ops.push(op);
+        self
+    }
+
+    /// Append an operation
+    pub fn push(&mut self, op: ImageOp) {
+        self.ops.push(op);
+    }
+
+    pub fn ops(&self) -> &[ImageOp] {
+        &self.ops
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Run all operations in order
+    pub fn apply(&self, img: DynamicImage) -> Result<DynamicImage> {
+        self.ops.iter().try_fold(img, |img, op| {
+            tracing::debug!(
+                "Applying {:?} to {}x{} image",
+                op,
+                img.width(),
+                img.height()
+            );
+            op.apply(img)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize_keeps_aspect_ratio() {
+        let img = DynamicImage::new_rgb8(1600, 900);
+        let out = ImageOp::Resize {
+            width: Some(800),
+            height: None,
+        }
+        .apply(img)
+        .unwrap();
+
+        assert_eq!((out.width(), out.height()), (800, 450));
+    }
+
+    #[test]
+    fn test_max_edge_never_upscales() {
+        let small = DynamicImage::new_rgb8(300, 200);
+        let out = ImageOp::MaxEdge(1000).apply(small).unwrap();
+        assert_eq!((out.width(), out.height()), (300, 200));
+
+        let large = DynamicImage::new_rgb8(4000, 2000);
+        let out = ImageOp::MaxEdge(1000).apply(large).unwrap();
+        assert_eq!((out.width(), out.height()), (1000, 500));
+    }
+
+    #[test]
+    fn test_pipeline_crop_then_rotate() {
+        let pipeline = TransformPipeline::new()
+            .with_op(ImageOp::CropAspect {
+                width: 1,
+                height: 1,
+            })
+            .with_op(ImageOp::Rotate(Rotation::Cw90))
+            .with_op(ImageOp::Flip(FlipAxis::Horizontal));
+
+        let out = pipeline.apply(DynamicImage::new_rgb8(400, 300)).unwrap();
+        assert_eq!((out.width(), out.height()), (300, 300));
+
+        let rotated = ImageOp::Rotate(Rotation::Cw90)
+            .apply(DynamicImage::new_rgb8(400, 300))
+            .unwrap();
+        assert_eq!((rotated.width(), rotated.height()), (300, 400));
+    }
+}
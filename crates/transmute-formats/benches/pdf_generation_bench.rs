@@ -2,8 +2,9 @@
 // Benchmark comparing old vs new PDF generation performance
 // Tests across different image sizes and formats to measure optimization impact
 
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use image::{DynamicImage, RgbImage};
+use std::hint::black_box;
 use std::path::PathBuf;
 use tempfile::NamedTempFile;
 use transmute_formats::{PdfGenerator, PdfOptions};
@@ -62,9 +63,11 @@ fn bench_pdf_generation(c: &mut Criterion) {
             |b, images| {
                 b.iter(|| {
                     let temp_file = NamedTempFile::new().unwrap();
-                    let mut options = PdfOptions::default();
-                    options.max_image_dimension = u32::MAX; // Disable downscaling
-                    options.compress_images = false; // Use PNG (old behavior)
+                    let options = PdfOptions {
+                        max_image_dimension: u32::MAX, // Disable downscaling
+                        compress_images: false,        // Use PNG (old behavior)
+                        ..Default::default()
+                    };
                     let generator = PdfGenerator::new(options);
                     generator
                         .generate_from_images(black_box(images.clone()), temp_file.path())
@@ -0,0 +1,424 @@
+//! Multi-frame images: animated GIF, animated WebP and APNG
+
+use crate::decoder::ImageDecoder;
+use crate::webp::WebpOptions;
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, Delay, DynamicImage, RgbaImage};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use transmute_common::{Error, MediaFormat, Result};
+
+/// Delay used for frames that don't specify one (matches browsers' GIF clamp)
+pub const DEFAULT_FRAME_DELAY_MS: u32 = 100;
+
+/// How many times an animation plays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoopCount {
+    #[default]
+    Infinite,
+
+    /// Total number of plays (at least 1)
+    Finite(u32),
+}
+
+impl LoopCount {
+    /// Build from a play count where 0 means forever (APNG/WebP convention)
+    pub fn from_plays(plays: u32) -> Self {
+        match plays {
+            0 => Self::Infinite,
+            n => Self::Finite(n),
+        }
+    }
+
+    /// Play count where 0 means forever
+    pub fn plays(self) -> u32 {
+        match self {
+            Self::Infinite => 0,
+            Self::Finite(n) => n.max(1),
+        }
+    }
+}
+
+/// One fully composited frame
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    pub image: RgbaImage,
+
+    /// How long the frame stays on screen
+    pub delay_ms: u32,
+}
+
+impl AnimationFrame {
+    pub fn new(image: RgbaImage, delay_ms: u32) -> Self {
+        Self { image, delay_ms }
+    }
+}
+
+/// Ordered frames plus loop count, the common model for every animated format
+#[derive(Debug, Clone)]
+pub struct FrameSequence {
+    pub frames: Vec<AnimationFrame>,
+    pub loop_count: LoopCount,
+}
+
+impl FrameSequence {
+    pub fn new(frames: Vec<AnimationFrame>, loop_count: LoopCount) -> Self {
+        Self { frames, loop_count }
+    }
+
+    /// Whether `format` can hold more than one frame
+    pub fn supports(format: MediaFormat) -> bool {
+        matches!(
+            format,
+            MediaFormat::Gif | MediaFormat::Webp | MediaFormat::Png
+        )
+    }
+
+    /// Check whether a file holds more than one frame without decoding it fully
+    pub fn is_animated(path: &Path) -> Result<bool> {
+        let reader = open(path)?;
+
+        let animated = match MediaFormat::detect(path) {
+            Some(MediaFormat::Gif) => GifDecoder::new(reader)?.into_frames().take(2).count() > 1,
+            Some(MediaFormat::Webp) => WebPDecoder::new(reader)?.has_animation(),
+            Some(MediaFormat::Png) => PngDecoder::new(reader)?.is_apng()?,
+            _ => false,
+        };
+
+        Ok(animated)
+    }
+
+    /// Decode every frame; still images become a single-frame sequence
+    pub fn decode(path: &Path) -> Result<Self> {
+        let format = MediaFormat::detect(path)
+            .ok_or_else(|| Error::UnsupportedFormat(path.display().to_string()))?;
+
+        if !Self::supports(format) || !Self::is_animated(path)? {
+            let (img, _) = ImageDecoder::decode(path)?;
+            return Ok(Self::new(
+                vec![AnimationFrame::new(img.to_rgba8(), 0)],
+                LoopCount::Infinite,
+            ));
+        }
+
+        let reader = open(path)?;
+        let sequence = match format {
+            MediaFormat::Gif => {
+                let decoder = GifDecoder::new(reader)?;
+                // GIF stores repetitions after the first play, not total plays
+                let loop_count = match decoder.loop_count() {
+                    image::metadata::LoopCount::Infinite => LoopCount::Infinite,
+                    image::metadata::LoopCount::Finite(n) => LoopCount::Finite(n.get() + 1),
+                };
+                collect(decoder, loop_count)?
+            }
+            MediaFormat::Webp => {
+                let decoder = WebPDecoder::new(reader)?;
+                let loop_count = convert_loop_count(decoder.loop_count());
+                collect(decoder, loop_count)?
+            }
+            _ => {
+                let decoder = PngDecoder::new(reader)?.apng()?;
+                let loop_count = convert_loop_count(decoder.loop_count());
+                collect(decoder, loop_count)?
+            }
+        };
+
+        tracing::debug!(
+            "Decoded {} frames from {:?} ({:?})",
+            sequence.frames.len(),
+            path,
+            sequence.loop_count
+        );
+
+        Ok(sequence)
+    }
+
+    /// Canvas size (taken from the first frame)
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.frames
+            .first()
+            .map(|f| f.image.dimensions())
+            .unwrap_or((0, 0))
+    }
+
+    /// Total play time of one loop
+    pub fn duration_ms(&self) -> u64 {
+        self.frames.iter().map(|f| f.delay_ms as u64).sum()
+    }
+
+    /// Run `f` over every frame, keeping delays and loop count
+    pub fn map_frames<F>(self, mut f: F) -> Result<Self>
+    where
+        F: FnMut(DynamicImage) -> Result<DynamicImage>,
+    {
+        let frames = self
+            .frames
+            .into_iter()
+            .map(|frame| {
+                let image = f(DynamicImage::ImageRgba8(frame.image))?.to_rgba8();
+                Ok(AnimationFrame::new(image, frame.delay_ms))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self::new(frames, self.loop_count))
+    }
+
+    /// Encode as an animated GIF, WebP or PNG (APNG)
+    pub fn encode(&self, format: MediaFormat, webp: &WebpOptions) -> Result<Vec<u8>> {
+        self.validate()?;
+
+        match format {
+            MediaFormat::Gif => self.encode_gif(),
+            MediaFormat::Webp => self.encode_webp(webp),
+            MediaFormat::Png => self.encode_apng(),
+            other => Err(Error::UnsupportedFormat(format!(
+                "{} cannot hold an animation",
+                other
+            ))),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let (width, height) = self.dimensions();
+        if self.frames.is_empty() || width == 0 || height == 0 {
+            return Err(Error::ConversionError("Animation has no frames".into()));
+        }
+
+        if let Some(frame) = self
+            .frames
+            .iter()
+            .find(|f| f.image.dimensions() != (width, height))
+        {
+            return Err(Error::ConversionError(format!(
+                "Animation frames differ in size: {}x{} vs {}x{}",
+                frame.image.width(),
+                frame.image.height(),
+                width,
+                height
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn encode_gif(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        {
+            let mut encoder = GifEncoder::new_with_speed(&mut data, 10);
+            // GIF counts repeats after the first play; without the NETSCAPE
+            // extension it plays once, while a repeat count of 0 means forever
+            match self.loop_count {
+                LoopCount::Infinite => encoder.set_repeat(Repeat::Infinite)?,
+                LoopCount::Finite(n) if n > 1 => {
+                    encoder.set_repeat(Repeat::Finite((n - 1).min(u16::MAX as u32) as u16))?
+                }
+                LoopCount::Finite(_) => {}
+            }
+
+            encoder.encode_frames(self.frames.iter().map(|frame| {
+                image::Frame::from_parts(
+                    frame.image.clone(),
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(frame.delay_ms, 1),
+                )
+            }))?;
+        }
+        Ok(data)
+    }
+
+    fn encode_webp(&self, options: &WebpOptions) -> Result<Vec<u8>> {
+        let config = options.to_config()?;
+        let (width, height) = self.dimensions();
+
+        let mut encoder = ::webp::AnimEncoder::new(width, height, &config);
+        encoder.set_loop_count(self.loop_count.plays() as i32);
+
+        // libwebp takes start timestamps rather than per-frame durations
+        let mut timestamp = 0i32;
+        for frame in &self.frames {
+            encoder.add_frame(::webp::AnimFrame::from_rgba(
+                frame.image.as_raw(),
+                width,
+                height,
+                timestamp,
+            ));
+            timestamp = timestamp.saturating_add(frame.delay_ms as i32);
+        }
+
+        let mut data = encoder
+            .try_encode()
+            .map(|data| data.to_vec())
+            .map_err(|e| {
+                Error::ConversionError(format!("Animated WebP encoding failed: {:?}", e))
+            })?;
+
+        // The crate ends the stream without an end timestamp, so libwebp gives
+        // the last frame a default length; restore it from the total duration
+        fix_last_webp_frame_duration(&mut data, self.duration_ms());
+        Ok(data)
+    }
+
+    fn encode_apng(&self) -> Result<Vec<u8>> {
+        let (width, height) = self.dimensions();
+        let png_error =
+            |e: png::EncodingError| Error::ConversionError(format!("APNG encoding failed: {}", e));
+
+        let mut data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut data, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .set_animated(self.frames.len() as u32, self.loop_count.plays())
+                .map_err(png_error)?;
+
+            let mut writer = encoder.write_header().map_err(png_error)?;
+            for frame in &self.frames {
+                writer
+                    .set_frame_delay(frame.delay_ms.min(u16::MAX as u32) as u16, 1000)
+                    .map_err(png_error)?;
+                writer
+                    .write_image_data(frame.image.as_raw())
+                    .map_err(png_error)?;
+            }
+            writer.finish().map_err(png_error)?;
+        }
+        Ok(data)
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+    Ok(BufReader::new(File::open(path)?))
+}
+
+/// Rewrite the last ANMF chunk so all frame durations add up to `total_ms`
+fn fix_last_webp_frame_duration(data: &mut [u8], total_ms: u64) {
+    // ANMF payload: X, Y, width-1, height-1 and duration, 24-bit little endian each
+    const DURATION_OFFSET: usize = 8 + 12;
+
+    let mut frames = Vec::new();
+    let mut offset = 12;
+    while offset + 8 <= data.len() {
+        let size = u32::from_le_bytes([
+            data[offset + 4],
+            data[offset + 5],
+            data[offset + 6],
+            data[offset + 7],
+        ]) as usize;
+        if &data[offset..offset + 4] == b"ANMF" && offset + DURATION_OFFSET + 3 <= data.len() {
+            frames.push(offset + DURATION_OFFSET);
+        }
+        offset += 8 + size + (size & 1);
+    }
+
+    let Some((&last, rest)) = frames.split_last() else {
+        return;
+    };
+    let earlier: u64 = rest
+        .iter()
+        .map(|&at| u32::from_le_bytes([data[at], data[at + 1], data[at + 2], 0]) as u64)
+        .sum();
+    let duration = total_ms.saturating_sub(earlier).min(0xFF_FFFF) as u32;
+    data[last..last + 3].copy_from_slice(&duration.to_le_bytes()[..3]);
+}
+
+fn convert_loop_count(count: image::metadata::LoopCount) -> LoopCount {
+    match count {
+        image::metadata::LoopCount::Infinite => LoopCount::Infinite,
+        image::metadata::LoopCount::Finite(n) => LoopCount::Finite(n.get()),
+    }
+}
+
+fn collect<'a>(decoder: impl AnimationDecoder<'a>, loop_count: LoopCount) -> Result<FrameSequence> {
+    let frames = decoder
+        .into_frames()
+        .map(|frame| {
+            let frame = frame?;
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay_ms = if denom == 0 { 0 } else { numer / denom };
+            Ok(AnimationFrame::new(frame.into_buffer(), delay_ms))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(FrameSequence::new(frames, loop_count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn sample(loop_count: LoopCount) -> FrameSequence {
+        let frames = [(255, 0, 0), (0, 255, 0), (0, 0, 255)]
+            .into_iter()
+            .zip([80, 120, 200])
+            .map(|((r, g, b), delay)| {
+                AnimationFrame::new(RgbaImage::from_pixel(24, 16, Rgba([r, g, b, 255])), delay)
+            })
+            .collect();
+        FrameSequence::new(frames, loop_count)
+    }
+
+    fn roundtrip(format: MediaFormat, sequence: &FrameSequence) -> FrameSequence {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("anim.{}", format.extension()));
+        let data = sequence.encode(format, &WebpOptions::lossless()).unwrap();
+        std::fs::write(&path, data).unwrap();
+
+        assert!(FrameSequence::is_animated(&path).unwrap());
+        FrameSequence::decode(&path).unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip_keeps_frames_delays_and_loops() {
+        for format in [MediaFormat::Gif, MediaFormat::Webp, MediaFormat::Png] {
+            let original = sample(LoopCount::Finite(3));
+            let decoded = roundtrip(format, &original);
+
+            assert_eq!(decoded.frames.len(), 3, "{}", format);
+            assert_eq!(decoded.loop_count, LoopCount::Finite(3), "{}", format);
+            let delays: Vec<_> = decoded.frames.iter().map(|f| f.delay_ms).collect();
+            assert_eq!(delays, [80, 120, 200], "{}", format);
+
+            for (a, b) in original.frames.iter().zip(&decoded.frames) {
+                let (a, b) = (a.image.get_pixel(5, 5), b.image.get_pixel(5, 5));
+                // Animated WebP frames pass through libwebp's RGB->YUV->RGB path
+                let close = a.0.iter().zip(b.0).all(|(x, y)| x.abs_diff(y) <= 2);
+                assert!(close, "{}: {:?} vs {:?}", format, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_infinite_loop_roundtrip() {
+        for format in [MediaFormat::Gif, MediaFormat::Webp, MediaFormat::Png] {
+            let decoded = roundtrip(format, &sample(LoopCount::Infinite));
+            assert_eq!(decoded.loop_count, LoopCount::Infinite, "{}", format);
+        }
+    }
+
+    #[test]
+    fn test_mismatched_frame_sizes_are_rejected() {
+        let mut sequence = sample(LoopCount::Infinite);
+        sequence.frames[1].image = RgbaImage::new(8, 8);
+        assert!(matches!(
+            sequence.encode(MediaFormat::Gif, &WebpOptions::default()),
+            Err(Error::ConversionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_still_image_decodes_as_single_frame() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("still.png");
+        RgbaImage::new(4, 4).save(&path).unwrap();
+
+        assert!(!FrameSequence::is_animated(&path).unwrap());
+        assert_eq!(FrameSequence::decode(&path).unwrap().frames.len(), 1);
+    }
+}
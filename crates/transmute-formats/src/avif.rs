@@ -0,0 +1,98 @@
+use image::codecs::avif::AvifEncoder as RavifEncoder;
+use image::{DynamicImage, ImageEncoder as _};
+use transmute_common::{Error, Result};
+
+/// AVIF encoder configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AvifOptions {
+    /// Quality of colour and alpha planes (1-100)
+    pub quality: u8,
+
+    /// Speed/size trade-off (1 = slowest and smallest, 10 = fastest)
+    pub speed: u8,
+}
+
+impl Default for AvifOptions {
+    fn default() -> Self {
+        // Same defaults as `cavif`
+        Self::new(80, 4)
+    }
+}
+
+impl AvifOptions {
+    pub fn new(quality: u8, speed: u8) -> Self {
+        Self {
+            quality: quality.clamp(1, 100),
+            speed: speed.clamp(1, 10),
+        }
+    }
+}
+
+/// rav1e-backed AVIF encoder shared by conversion and compression
+pub struct AvifEncoder;
+
+impl AvifEncoder {
+    /// Encode to AVIF (8-bit), keeping the alpha channel if the image has one
+    pub fn encode(img: &DynamicImage, options: &AvifOptions) -> Result<Vec<u8>> {
+        tracing::debug!(
+            "AVIF encoding {}x{} (quality: {}, speed: {}, alpha: {})",
+            img.width(),
+            img.height(),
+            options.quality,
+            options.speed,
+            img.color().has_alpha()
+        );
+
+        let mut data = Vec::new();
+        let encoder =
+            RavifEncoder::new_with_speed_quality(&mut data, options.speed, options.quality);
+
+        let result = if img.color().has_alpha() {
+            let rgba = img.to_rgba8();
+            encoder.write_image(
+                rgba.as_raw(),
+                rgba.width(),
+                rgba.height(),
+                image::ExtendedColorType::Rgba8,
+            )
+        } else {
+            let rgb = img.to_rgb8();
+            encoder.write_image(
+                rgb.as_raw(),
+                rgb.width(),
+                rgb.height(),
+                image::ExtendedColorType::Rgb8,
+            )
+        };
+
+        result.map_err(|e| Error::ConversionError(format!("AVIF encoding failed: {}", e)))?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+    use transmute_common::MediaFormat;
+
+    fn gradient() -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(48, 32, |x, y| {
+            Rgb([(x * 5) as u8, (y * 7) as u8, ((x + y) * 3) as u8])
+        }))
+    }
+
+    #[test]
+    fn test_encode_produces_avif_container() {
+        let data = AvifEncoder::encode(&gradient(), &AvifOptions::new(60, 10)).unwrap();
+        assert_eq!(MediaFormat::sniff(&data), Some(MediaFormat::Avif));
+    }
+
+    #[test]
+    fn test_quality_affects_size() {
+        let img = gradient();
+        let low = AvifEncoder::encode(&img, &AvifOptions::new(10, 10)).unwrap();
+        let high = AvifEncoder::encode(&img, &AvifOptions::new(95, 10)).unwrap();
+        assert!(low.len() < high.len(), "{} >= {}", low.len(), high.len());
+    }
+}
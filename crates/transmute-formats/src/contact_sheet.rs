@@ -0,0 +1,253 @@
+//! Raster contact sheets laid out like PDF grid pages, and the file name
+//! captions both of them print
+
+use crate::pdf::PdfOptions;
+use crate::pdf_layout::SheetLayout;
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont, point};
+use image::{DynamicImage, Rgba, RgbaImage, imageops};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use transmute_common::{Error, Result};
+
+const MM_PER_INCH: f32 = 25.4;
+const PT_PER_INCH: f32 = 72.0;
+
+/// Caption font size in points
+pub(crate) const CAPTION_SIZE_PT: f32 = 8.0;
+
+/// Caption text colour
+pub(crate) const CAPTION_GRAY: u8 = 0x33;
+
+/// Helvetica, the font PDF captions are set in, so raster sheets measure text the same way
+fn caption_font() -> Result<&'static FontVec> {
+    static FONT: OnceLock<Option<FontVec>> = OnceLock::new();
+    FONT.get_or_init(|| {
+        let bytes = printpdf::BuiltinFont::Helvetica.get_subset_font().bytes;
+        FontVec::try_from_vec(bytes).ok()
+    })
+    .as_ref()
+    .ok_or_else(|| Error::ConversionError("Failed to load the caption font".into()))
+}
+
+/// Scale that makes `size` the font's em size, as PDF font sizes are
+fn em_scale(font: &FontVec, size: f32) -> PxScale {
+    PxScale::from(size * font.height_unscaled() / font.units_per_em().unwrap_or(1000.0))
+}
+
+/// File name of `path` for a caption at most `max_width_pt` wide, shortened with
+/// "..." if needed, with its width in points
+///
+/// Characters the font cannot show become '?'.
+pub(crate) fn fit_caption(path: &Path, max_width_pt: f32) -> Result<(String, f32)> {
+    let font = caption_font()?;
+    let font = font.as_scaled(em_scale(font, CAPTION_SIZE_PT));
+    let width =
+        |text: &str| -> f32 { text.chars().map(|c| font.h_advance(font.glyph_id(c))).sum() };
+
+    let name: String = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if font.glyph_id(c).0 == 0 { '?' } else { c })
+        .collect();
+    if width(&name) <= max_width_pt {
+        let name_width = width(&name);
+        return Ok((name, name_width));
+    }
+
+    let mut kept: Vec<char> = name.chars().collect();
+    while !kept.is_empty() {
+        kept.pop();
+        let text = format!("{}...", kept.iter().collect::<String>());
+        if width(&text) <= max_width_pt {
+            let text_width = width(&text);
+            return Ok((text, text_width));
+        }
+    }
+    Ok((String::new(), 0.0))
+}
+
+/// Height of a caption's baseline above the bottom of its strip, in millimetres
+pub(crate) fn caption_baseline_mm(strip_height_mm: f32) -> f32 {
+    // Center the capital height (about 0.7 em) in the strip
+    let cap_height_mm = CAPTION_SIZE_PT * 0.7 / PT_PER_INCH * MM_PER_INCH;
+    (strip_height_mm - cap_height_mm) / 2.0
+}
+
+/// Renders images onto raster pages with the same grid, margins, fit and captions as
+/// [`crate::PdfGenerator`]
+pub struct ContactSheet {
+    options: PdfOptions,
+}
+
+impl ContactSheet {
+    pub fn new(options: PdfOptions) -> Self {
+        Self { options }
+    }
+
+    /// One white page per `options.grid.cells()` images, rendered at `options.dpi`
+    pub fn render(&self, images: &[(DynamicImage, PathBuf)]) -> Result<Vec<DynamicImage>> {
+        if images.is_empty() {
+            return Err(Error::ConversionError(
+                "No images provided for the contact sheet".into(),
+            ));
+        }
+
+        let px_per_mm = self.options.dpi / MM_PER_INCH;
+        let to_px = |mm: f32| (mm * px_per_mm).round().max(0.0) as u32;
+
+        let mut pages = Vec::new();
+        for page_images in images.chunks(self.options.grid.cells()) {
+            let (first, _) = &page_images[0];
+            let sheet = SheetLayout::new(&self.options, first.width(), first.height())?;
+            let page_height_mm = sheet.page_height_mm;
+            let mut page = RgbaImage::from_pixel(
+                to_px(sheet.page_width_mm).max(1),
+                to_px(page_height_mm).max(1),
+                Rgba([255, 255, 255, 255]),
+            );
+
+            for (cell, (img, original_path)) in page_images.iter().enumerate() {
+                let layout = sheet.place(cell, img.width(), img.height());
+                let cropped = match layout.crop_pixels(img.width(), img.height()) {
+                    Some((x, y, width, height)) => img.crop_imm(x, y, width, height),
+                    None => img.clone(),
+                };
+                let resized = cropped
+                    .resize_exact(
+                        to_px(layout.width_mm).max(1),
+                        to_px(layout.height_mm).max(1),
+                        imageops::FilterType::Lanczos3,
+                    )
+                    .to_rgba8();
+                // Layout coordinates run up from the bottom of the page
+                let top = page_height_mm - layout.y_mm - layout.height_mm;
+                imageops::overlay(
+                    &mut page,
+                    &resized,
+                    to_px(layout.x_mm) as i64,
+                    to_px(top) as i64,
+                );
+
+                if self.options.captions {
+                    self.draw_caption(&mut page, &sheet, cell, original_path)?;
+                }
+            }
+
+            pages.push(DynamicImage::ImageRgb8(
+                DynamicImage::ImageRgba8(page).to_rgb8(),
+            ));
+        }
+
+        tracing::info!(
+            "Rendered {} images onto {} contact sheet pages",
+            images.len(),
+            pages.len()
+        );
+        Ok(pages)
+    }
+
+    fn draw_caption(
+        &self,
+        page: &mut RgbaImage,
+        sheet: &SheetLayout,
+        cell: usize,
+        path: &Path,
+    ) -> Result<()> {
+        let [x, y, width, height] = sheet.caption_area(cell);
+        let pt_per_mm = PT_PER_INCH / MM_PER_INCH;
+        let (text, text_width_pt) = fit_caption(path, width * pt_per_mm)?;
+
+        let px_per_mm = self.options.dpi / MM_PER_INCH;
+        let px_per_pt = self.options.dpi / PT_PER_INCH;
+        let font = caption_font()?;
+        let scale = em_scale(font, CAPTION_SIZE_PT * px_per_pt);
+        let scaled = font.as_scaled(scale);
+
+        let mut pen_x = (x + width / 2.0) * px_per_mm - text_width_pt * px_per_pt / 2.0;
+        let baseline = (sheet.page_height_mm - y - caption_baseline_mm(height)) * px_per_mm;
+
+        for c in text.chars() {
+            let glyph = scaled
+                .glyph_id(c)
+                .with_scale_and_position(scale, point(pen_x, baseline));
+            pen_x += scaled.h_advance(glyph.id);
+
+            let Some(outlined) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i64 + gx as i64;
+                let py = bounds.min.y as i64 + gy as i64;
+                if px < 0 || py < 0 || px >= page.width() as i64 || py >= page.height() as i64 {
+                    return;
+                }
+                let pixel = page.get_pixel_mut(px as u32, py as u32);
+                for channel in &mut pixel.0[..3] {
+                    let blended =
+                        *channel as f32 * (1.0 - coverage) + CAPTION_GRAY as f32 * coverage;
+                    *channel = blended.round() as u8;
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_layout::{FitMode, Grid, PageSize};
+
+    #[test]
+    fn test_fit_caption_shortens_long_names() {
+        let (text, width) = fit_caption(Path::new("/scans/receipt.jpg"), 200.0).unwrap();
+        assert_eq!(text, "receipt.jpg");
+        assert!(width > 0.0 && width < 200.0);
+
+        let (text, width) =
+            fit_caption(Path::new("a_very_long_file_name_for_a_caption.png"), 40.0).unwrap();
+        assert!(text.ends_with("..."), "{}", text);
+        assert!(width <= 40.0);
+    }
+
+    #[test]
+    fn test_render_grid_pages() {
+        let options = PdfOptions {
+            page_size: PageSize::Custom {
+                width_mm: 100.0,
+                height_mm: 50.0,
+            },
+            margin_mm: 0.0,
+            spacing_mm: 0.0,
+            fit: FitMode::Fill,
+            grid: Grid::new(1, 2).unwrap(),
+            captions: true,
+            dpi: 25.4,
+            ..Default::default()
+        };
+        let images: Vec<_> = (0..3)
+            .map(|i| {
+                let img = RgbaImage::from_pixel(20, 20, Rgba([0, 0, 200, 255]));
+                (
+                    DynamicImage::ImageRgba8(img),
+                    PathBuf::from(format!("{}.png", i)),
+                )
+            })
+            .collect();
+
+        let pages = ContactSheet::new(options).render(&images).unwrap();
+        // Three images two to a page, each 100x50 mm page at 1 px per mm
+        assert_eq!(pages.len(), 2);
+        assert_eq!((pages[0].width(), pages[0].height()), (100, 50));
+
+        let page = pages[0].to_rgb8();
+        // Both cells are filled with the image above the caption strip
+        assert_eq!(page.get_pixel(25, 10).0, [0, 0, 200]);
+        assert_eq!(page.get_pixel(75, 10).0, [0, 0, 200]);
+        // The second page's empty cell stays white
+        assert_eq!(pages[1].to_rgb8().get_pixel(75, 10).0, [255, 255, 255]);
+    }
+}
@@ -1,19 +1,44 @@
+use crate::jxl::JxlDecoder;
 use crate::metadata::ImageMetadata;
-use image::{DynamicImage, ImageReader};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder as _, ImageReader};
 use memmap2::Mmap;
 use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
-use transmute_common::{Error, MediaFormat, Result};
+use transmute_common::{EmbeddedMetadata, Error, MediaFormat, Result};
 
 const TEN_MB_IN_BYTES: u64 = 10 * 1024 * 1024;
 
+/// Decoding behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Physically rotate/flip pixels according to the EXIF Orientation tag,
+    /// then reset the tag so it is not applied twice downstream
+    pub auto_orient: bool,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self { auto_orient: true }
+    }
+}
+
 /// High-performance image decoder with memory-mapped I/O
 pub struct ImageDecoder;
 
 impl ImageDecoder {
     /// Decode image from path using memory-mapped file for large images
     pub fn decode(path: &Path) -> Result<(DynamicImage, ImageMetadata)> {
-        let format = MediaFormat::from_path(path).ok_or_else(|| {
+        Self::decode_with(path, &DecodeOptions::default())
+    }
+
+    /// Decode image with explicit options
+    pub fn decode_with(
+        path: &Path,
+        options: &DecodeOptions,
+    ) -> Result<(DynamicImage, ImageMetadata)> {
+        let format = MediaFormat::detect(path).ok_or_else(|| {
             Error::UnsupportedFormat(
                 path.extension()
                     .and_then(|s| s.to_str())
@@ -28,11 +53,18 @@ impl ImageDecoder {
 
         tracing::debug!("Decoding {format:?} from {path:?}");
 
+        if matches!(format, MediaFormat::Heif | MediaFormat::Avif) {
+            return Self::decode_heif(path, format);
+        }
+        if format == MediaFormat::Jxl {
+            return Self::decode_jxl(path);
+        }
+
         // Use memory-mapped I/O for files > 10MB
         let file = File::open(path)?;
         let metadata = file.metadata()?;
 
-        let img = if metadata.len() > TEN_MB_IN_BYTES {
+        let (mut img, mut embedded) = if metadata.len() > TEN_MB_IN_BYTES {
             // Memory-mapped decoding for large files
             tracing::debug!("Using memory-mapped I/O for large file");
             /*
@@ -47,20 +79,42 @@ impl ImageDecoder {
              * => i.e. bytes load only when accessed and only for the pages you actually touch.
              * */
             let mmap = unsafe { Mmap::map(&file)? };
-            ImageReader::new(std::io::Cursor::new(&mmap[..]))
-                .with_guessed_format()?
-                .decode()?
+            let mut reader = ImageReader::new(std::io::Cursor::new(&mmap[..]));
+            reader.set_format(Self::image_format(format)?);
+            Self::decode_with_metadata(reader.into_decoder()?)?
         } else {
-            // Standard decoding for smaller files
-            image::open(path)?
+            // Standard decoding for smaller files (format comes from content, not extension)
+            let mut reader = ImageReader::open(path)?;
+            reader.set_format(Self::image_format(format)?);
+            Self::decode_with_metadata(reader.into_decoder()?)?
         };
 
+        if format == MediaFormat::Png {
+            embedded.srgb = Self::png_srgb_intent(path);
+        }
+
+        let orientation = embedded
+            .exif
+            .as_deref()
+            .and_then(Orientation::from_exif_chunk)
+            .unwrap_or(Orientation::NoTransforms);
+
+        if options.auto_orient && orientation != Orientation::NoTransforms {
+            tracing::debug!("Applying EXIF orientation {:?}", orientation);
+            img.apply_orientation(orientation);
+            if let Some(exif) = embedded.exif.as_mut() {
+                let _ = Orientation::remove_from_exif_chunk(exif);
+            }
+        }
+
         let img_metadata = ImageMetadata {
             width: img.width(),
             height: img.height(),
             format,
             color_type: img.color(),
             has_alpha: img.color().has_alpha(),
+            orientation,
+            embedded,
         };
 
         tracing::info!(
@@ -76,20 +130,37 @@ impl ImageDecoder {
 
     /// Quick metadata extraction without full decode
     pub fn probe(path: &Path) -> Result<ImageMetadata> {
-        let reader = ImageReader::open(path)?;
-        let format = MediaFormat::from_path(path)
+        let format = MediaFormat::detect(path)
             .ok_or_else(|| Error::UnsupportedFormat("unknown".to_string()))?;
 
+        if matches!(format, MediaFormat::Heif | MediaFormat::Avif) {
+            return Self::probe_heif(path, format);
+        }
+        if format == MediaFormat::Jxl {
+            return Self::decode_jxl(path).map(|(_, metadata)| metadata);
+        }
+
+        let mut reader = ImageReader::open(path)?;
+        reader.set_format(Self::image_format(format)?);
+
         if let Ok((width, height)) = reader.into_dimensions() {
             // Try to get color info without full decode
             // FIX: DANGER, will cause OOM (Out of Memory).
-            let img = image::open(path)?; // Fallback to full decode for color type
+            let mut reader = ImageReader::open(path)?;
+            reader.set_format(Self::image_format(format)?);
+            let mut decoder = reader.into_decoder()?;
+            let orientation = decoder
+                .orientation()
+                .unwrap_or(Orientation::NoTransforms);
+            let img = DynamicImage::from_decoder(decoder)?; // Fallback to full decode for color type
             Ok(ImageMetadata {
                 width,
                 height,
                 format,
                 color_type: img.color(),
                 has_alpha: img.color().has_alpha(),
+                orientation,
+                embedded: EmbeddedMetadata::default(),
             })
         } else {
             Err(Error::ConversionError(
@@ -97,6 +168,137 @@ impl ImageDecoder {
             ))
         }
     }
+
+    /// HEIF and AVIF go through libheif, which applies the container rotation itself
+    #[cfg(feature = "heif")]
+    fn decode_heif(path: &Path, format: MediaFormat) -> Result<(DynamicImage, ImageMetadata)> {
+        let (img, embedded) = crate::heif::decode(path)?;
+
+        let img_metadata = ImageMetadata {
+            width: img.width(),
+            height: img.height(),
+            format,
+            color_type: img.color(),
+            has_alpha: img.color().has_alpha(),
+            orientation: Orientation::NoTransforms,
+            embedded,
+        };
+
+        tracing::info!(
+            "Decoded {}x{} {} image ({:.2}MB in memory)",
+            img_metadata.width,
+            img_metadata.height,
+            img_metadata.format,
+            img_metadata.estimated_memory_mb()
+        );
+
+        Ok((img, img_metadata))
+    }
+
+    #[cfg(feature = "heif")]
+    fn probe_heif(path: &Path, format: MediaFormat) -> Result<ImageMetadata> {
+        let (width, height, has_alpha) = crate::heif::probe(path)?;
+
+        Ok(ImageMetadata {
+            width,
+            height,
+            format,
+            color_type: if has_alpha {
+                image::ColorType::Rgba8
+            } else {
+                image::ColorType::Rgb8
+            },
+            has_alpha,
+            orientation: Orientation::NoTransforms,
+            embedded: EmbeddedMetadata::default(),
+        })
+    }
+
+    #[cfg(not(feature = "heif"))]
+    fn decode_heif(_path: &Path, format: MediaFormat) -> Result<(DynamicImage, ImageMetadata)> {
+        Err(Self::heif_disabled(format))
+    }
+
+    #[cfg(not(feature = "heif"))]
+    fn probe_heif(_path: &Path, format: MediaFormat) -> Result<ImageMetadata> {
+        Err(Self::heif_disabled(format))
+    }
+
+    #[cfg(not(feature = "heif"))]
+    fn heif_disabled(format: MediaFormat) -> Error {
+        Error::UnsupportedFormat(format!(
+            "{format} (build with the `heif` feature to enable it)"
+        ))
+    }
+
+    /// JPEG XL goes through libjxl, which applies the orientation itself
+    fn decode_jxl(path: &Path) -> Result<(DynamicImage, ImageMetadata)> {
+        let (img, embedded) = JxlDecoder::decode(&std::fs::read(path)?)?;
+
+        let img_metadata = ImageMetadata {
+            width: img.width(),
+            height: img.height(),
+            format: MediaFormat::Jxl,
+            color_type: img.color(),
+            has_alpha: img.color().has_alpha(),
+            orientation: Orientation::NoTransforms,
+            embedded,
+        };
+
+        tracing::info!(
+            "Decoded {}x{} JXL image ({:.2}MB in memory)",
+            img_metadata.width,
+            img_metadata.height,
+            img_metadata.estimated_memory_mb()
+        );
+
+        Ok((img, img_metadata))
+    }
+
+    /// Read EXIF/XMP/ICC blocks before consuming the decoder for pixels
+    fn decode_with_metadata(
+        mut decoder: impl image::ImageDecoder,
+    ) -> Result<(DynamicImage, EmbeddedMetadata)> {
+        let mut embedded = EmbeddedMetadata::default();
+
+        // Metadata is best-effort: a malformed block should not fail the decode
+        if let Ok(Some(exif)) = decoder.exif_metadata() {
+            embedded.set_exif(exif);
+        }
+        embedded.xmp = decoder.xmp_metadata().ok().flatten();
+        embedded.icc = decoder.icc_profile().ok().flatten();
+
+        let img = DynamicImage::from_decoder(decoder)?;
+        Ok((img, embedded))
+    }
+
+    /// Rendering intent of a PNG's `sRGB` chunk, which the decoder does not
+    /// expose; the chunk precedes the image data, so only the headers are read
+    fn png_srgb_intent(path: &Path) -> Option<u8> {
+        let mut file = BufReader::new(File::open(path).ok()?);
+        file.seek(SeekFrom::Start(8)).ok()?;
+        loop {
+            let mut header = [0u8; 8];
+            file.read_exact(&mut header).ok()?;
+            let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+            match &header[4..] {
+                b"sRGB" => {
+                    let mut intent = [0u8];
+                    file.read_exact(&mut intent).ok()?;
+                    return Some(intent[0]);
+                }
+                b"IDAT" | b"IEND" => return None,
+                // Skip the body and its CRC
+                _ => file.seek_relative(len as i64 + 4).ok()?,
+            }
+        }
+    }
+
+    fn image_format(format: MediaFormat) -> Result<image::ImageFormat> {
+        format
+            .to_image_format()
+            .ok_or_else(|| Error::UnsupportedFormat(format.to_string()))
+    }
 }
 
 #[cfg(test)]
@@ -119,4 +321,139 @@ mod tests {
         assert_eq!(metadata.height, 1);
         assert_eq!(metadata.format, MediaFormat::Png);
     }
+
+    #[test]
+    fn test_decode_reads_png_srgb_intent() {
+        let temp = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(2, 2)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let intent = |path: &Path| ImageDecoder::decode(path).unwrap().1.embedded.srgb;
+        std::fs::write(temp.path(), &png).unwrap();
+        assert_eq!(intent(temp.path()), None);
+
+        let srgb = EmbeddedMetadata {
+            srgb: Some(0),
+            ..Default::default()
+        };
+        std::fs::write(temp.path(), srgb.embed(png, MediaFormat::Png).unwrap()).unwrap();
+        assert_eq!(intent(temp.path()), Some(0));
+    }
+
+    #[test]
+    fn test_decode_mislabeled_extension() {
+        // PNG content saved under a .jpg name
+        let temp = tempfile::Builder::new()
+            .suffix(".jpg")
+            .tempfile()
+            .unwrap();
+        let img = image::DynamicImage::new_rgb8(3, 2);
+        img.save_with_format(temp.path(), image::ImageFormat::Png)
+            .unwrap();
+
+        let (_, metadata) = ImageDecoder::decode(temp.path()).unwrap();
+        assert_eq!((metadata.width, metadata.height), (3, 2));
+        assert_eq!(metadata.format, MediaFormat::Png);
+
+        let probed = ImageDecoder::probe(temp.path()).unwrap();
+        assert_eq!(probed.format, MediaFormat::Png);
+    }
+
+    /// 4x2 JPEG whose EXIF says "rotate 90° clockwise to display"
+    fn rotated_jpeg() -> tempfile::NamedTempFile {
+        let mut exif = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        exif.extend_from_slice(&[1, 0, 0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut jpeg = Vec::new();
+        image::DynamicImage::new_rgb8(4, 2)
+            .write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+        let embedded = EmbeddedMetadata {
+            exif: Some(exif),
+            ..Default::default()
+        };
+
+        let temp = tempfile::Builder::new().suffix(".jpg").tempfile().unwrap();
+        std::fs::write(temp.path(), embedded.embed(jpeg, MediaFormat::Jpeg).unwrap()).unwrap();
+        temp
+    }
+
+    #[test]
+    fn test_decode_applies_exif_orientation() {
+        let temp = rotated_jpeg();
+
+        let (img, metadata) = ImageDecoder::decode(temp.path()).unwrap();
+        assert_eq!((img.width(), img.height()), (2, 4));
+        assert_eq!(metadata.orientation, Orientation::Rotate90);
+
+        // Tag is reset so the output is not rotated a second time
+        let exif = metadata.embedded.exif.unwrap();
+        assert_eq!(
+            Orientation::from_exif_chunk(&exif),
+            Some(Orientation::NoTransforms)
+        );
+
+        let options = DecodeOptions { auto_orient: false };
+        let (img, metadata) = ImageDecoder::decode_with(temp.path(), &options).unwrap();
+        assert_eq!((img.width(), img.height()), (4, 2));
+        assert_eq!(
+            Orientation::from_exif_chunk(&metadata.embedded.exif.unwrap()),
+            Some(Orientation::Rotate90)
+        );
+    }
+
+    #[cfg(not(feature = "heif"))]
+    #[test]
+    fn test_heif_without_feature_is_reported() {
+        let temp = tempfile::Builder::new().suffix(".heic").tempfile().unwrap();
+        std::fs::write(temp.path(), b"\0\0\0\x18ftypheic\0\0\0\0mif1heic").unwrap();
+
+        match ImageDecoder::decode(temp.path()) {
+            Err(Error::UnsupportedFormat(msg)) => assert!(msg.contains("heif"), "{msg}"),
+            other => panic!("expected UnsupportedFormat, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[cfg(not(feature = "jxl"))]
+    #[test]
+    fn test_jxl_without_feature_is_reported() {
+        let temp = tempfile::Builder::new().suffix(".jxl").tempfile().unwrap();
+        std::fs::write(temp.path(), b"\xff\x0a\xfa\x7f\x01\x90\x08").unwrap();
+
+        for result in [
+            ImageDecoder::decode(temp.path()).map(|_| ()),
+            ImageDecoder::probe(temp.path()).map(|_| ()),
+        ] {
+            match result {
+                Err(Error::UnsupportedFormat(msg)) => assert!(msg.contains("jxl"), "{msg}"),
+                other => panic!("expected UnsupportedFormat, got {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(not(feature = "heif"))]
+    #[test]
+    fn test_avif_without_feature_is_reported() {
+        let temp = tempfile::Builder::new().suffix(".heic").tempfile().unwrap();
+
+        // Also with the generic HEIF major brand: still AVIF, still libheif
+        for header in [
+            &b"\0\0\0\x1cftypavif\0\0\0\0avifmif1miaf"[..],
+            &b"\0\0\0\x1cftypmif1\0\0\0\0mif1miafavif"[..],
+        ] {
+            std::fs::write(temp.path(), header).unwrap();
+            for result in [
+                ImageDecoder::decode(temp.path()).map(|_| ()),
+                ImageDecoder::probe(temp.path()).map(|_| ()),
+            ] {
+                match result {
+                    Err(Error::UnsupportedFormat(msg)) => {
+                        assert!(msg.contains("AVIF") && msg.contains("heif"), "{msg}")
+                    }
+                    other => panic!("expected UnsupportedFormat, got {:?}", other),
+                }
+            }
+        }
+    }
 }
@@ -1,8 +1,11 @@
+use crate::avif::{AvifEncoder, AvifOptions};
+use crate::jxl::{JxlEncoder, JxlOptions};
+use crate::webp::{WebpEncoder, WebpOptions};
 use image::{DynamicImage, ImageEncoder as _, ImageFormat};
 use std::fs::File;
-use std::io::BufWriter;
+use std::io::{BufWriter, Cursor, Seek, Write};
 use std::path::Path;
-use transmute_common::{Error, MediaFormat, Result};
+use transmute_common::{EmbeddedMetadata, Error, MediaFormat, Result};
 
 const EIGHT_MB_IN_BYTES: usize = 8 * 1024 * 1024;
 
@@ -21,8 +24,90 @@ impl ImageEncoder {
         // Use buffered writer for better I/O performance
         let file = File::create(output_path)?;
         let writer = BufWriter::with_capacity(EIGHT_MB_IN_BYTES, file); // 8MB buffer
+        Self::encode_to_writer(img, writer, image_format)?;
 
-        // Format-specific encoding with optimizations
+        tracing::info!("Successfully encoded to {:?}", output_path);
+        Ok(())
+    }
+
+    /// Encode and embed EXIF/XMP/ICC metadata (already filtered by policy)
+    pub fn encode_with_metadata(
+        img: &DynamicImage,
+        output_path: &Path,
+        format: MediaFormat,
+        metadata: &EmbeddedMetadata,
+    ) -> Result<()> {
+        if metadata.is_empty() {
+            return Self::encode(img, output_path, format);
+        }
+
+        let image_format = format
+            .to_image_format()
+            .ok_or_else(|| Error::UnsupportedFormat(format.to_string()))?;
+
+        tracing::debug!(
+            "Encoding to {:?} at {:?} with metadata",
+            format,
+            output_path
+        );
+
+        // Metadata is spliced into the container after the codec has run
+        let mut buffer = Vec::new();
+        Self::encode_to_writer(img, Cursor::new(&mut buffer), image_format)?;
+        std::fs::write(output_path, metadata.embed(buffer, format)?)?;
+
+        tracing::info!("Successfully encoded to {:?}", output_path);
+        Ok(())
+    }
+
+    /// Encode WebP with explicit lossy/lossless settings
+    pub fn encode_webp(
+        img: &DynamicImage,
+        output_path: &Path,
+        options: &WebpOptions,
+        metadata: &EmbeddedMetadata,
+    ) -> Result<()> {
+        let data = WebpEncoder::encode(img, options)?;
+        std::fs::write(output_path, metadata.embed(data, MediaFormat::Webp)?)?;
+
+        tracing::info!("Successfully encoded to {:?}", output_path);
+        Ok(())
+    }
+
+    /// Encode AVIF with explicit quality/speed settings
+    pub fn encode_avif(
+        img: &DynamicImage,
+        output_path: &Path,
+        options: &AvifOptions,
+        metadata: &EmbeddedMetadata,
+    ) -> Result<()> {
+        let data = AvifEncoder::encode(img, options)?;
+        std::fs::write(output_path, metadata.embed(data, MediaFormat::Avif)?)?;
+
+        tracing::info!("Successfully encoded to {:?}", output_path);
+        Ok(())
+    }
+
+    /// Encode JPEG XL with explicit quality/effort settings
+    pub fn encode_jxl(
+        img: &DynamicImage,
+        output_path: &Path,
+        options: &JxlOptions,
+        metadata: &EmbeddedMetadata,
+    ) -> Result<()> {
+        let data = JxlEncoder::encode(img, options)?;
+        std::fs::write(output_path, metadata.embed(data, MediaFormat::Jxl)?)?;
+
+        tracing::info!("Successfully encoded to {:?}", output_path);
+        Ok(())
+    }
+
+    /// Format-specific encoding with optimizations
+    fn encode_to_writer<W: Write + Seek>(
+        img: &DynamicImage,
+        mut writer: W,
+        image_format: ImageFormat,
+    ) -> Result<()> {
         match image_format {
             ImageFormat::Png => {
                 let encoder = image::codecs::png::PngEncoder::new(writer);
@@ -42,16 +127,18 @@ impl ImageEncoder {
                     img.color().into(),
                 )?;
             }
+            ImageFormat::WebP => {
+                writer.write_all(&WebpEncoder::encode(img, &WebpOptions::default())?)?;
+            }
+            ImageFormat::Avif => {
+                writer.write_all(&AvifEncoder::encode(img, &AvifOptions::default())?)?;
+            }
             _ => {
                 // Fallback to generic encoder for other formats
-                img.write_to(
-                    &mut std::io::BufWriter::new(File::create(output_path)?),
-                    image_format,
-                )?;
+                img.write_to(&mut writer, image_format)?;
             }
         }
 
-        tracing::info!("Successfully encoded to {:?}", output_path);
         Ok(())
     }
 
@@ -62,6 +149,25 @@ impl ImageEncoder {
         format: MediaFormat,
         quality: u8,
     ) -> Result<()> {
+        if format == MediaFormat::Webp {
+            let options = WebpOptions::lossy(quality as f32);
+            return Self::encode_webp(img, output_path, &options, &EmbeddedMetadata::default());
+        }
+        if format == MediaFormat::Avif {
+            let options = AvifOptions {
+                quality: quality.clamp(1, 100),
+                ..AvifOptions::default()
+            };
+            return Self::encode_avif(img, output_path, &options, &EmbeddedMetadata::default());
+        }
+        if format == MediaFormat::Jxl {
+            let options = JxlOptions {
+                quality: quality.min(100),
+                ..JxlOptions::default()
+            };
+            return Self::encode_jxl(img, output_path, &options, &EmbeddedMetadata::default());
+        }
+
         let image_format = format
             .to_image_format()
             .ok_or_else(|| Error::UnsupportedFormat(format.to_string()))?;
@@ -79,14 +185,6 @@ impl ImageEncoder {
                     img.color().into(),
                 )?;
             }
-            ImageFormat::WebP => {
-                // WebP supports quality parameter
-                // FIX: Where to use qualiity parameter
-                img.write_to(
-                    &mut std::io::BufWriter::new(File::create(output_path)?),
-                    image_format,
-                )?;
-            }
             _ => {
                 // Formats without quality control fall back to default
                 Self::encode(img, output_path, format)?;
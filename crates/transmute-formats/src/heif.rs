@@ -0,0 +1,182 @@
+//! HEIF/HEIC and AVIF decoding through libheif (`heif` feature)
+//!
+//! AVIF is HEIF with AV1-coded items, so libheif decodes it with its dav1d or
+//! libaom plugin.
+
+use image::metadata::Orientation;
+use image::{DynamicImage, RgbImage, RgbaImage};
+use libheif_rs::{ColorSpace, HeifContext, ImageHandle, LibHeif, RgbChroma};
+use std::path::Path;
+use transmute_common::{EmbeddedMetadata, Error, Result};
+
+const XMP_CONTENT_TYPE: &str = "application/rdf+xml";
+
+fn heif_error(e: libheif_rs::HeifError) -> Error {
+    Error::ConversionError(format!("HEIF decoding failed: {}", e))
+}
+
+/// Decode the primary image to 8-bit RGB(A) together with its EXIF/XMP/ICC blocks.
+///
+/// libheif already applies the container's rotation/mirror properties, so the
+/// EXIF Orientation tag is reset to avoid rotating the pixels a second time.
+pub(crate) fn decode(path: &Path) -> Result<(DynamicImage, EmbeddedMetadata)> {
+    let bytes = std::fs::read(path)?;
+    let ctx = HeifContext::read_from_bytes(&bytes).map_err(heif_error)?;
+    let handle = ctx.primary_image_handle().map_err(heif_error)?;
+
+    let has_alpha = handle.has_alpha_channel();
+    let chroma = if has_alpha {
+        RgbChroma::Rgba
+    } else {
+        RgbChroma::Rgb
+    };
+
+    let image = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(chroma), None)
+        .map_err(heif_error)?;
+    let (width, height) = (image.width(), image.height());
+
+    let planes = image.planes();
+    let plane = planes
+        .interleaved
+        .ok_or_else(|| Error::ConversionError("HEIF image has no interleaved plane".into()))?;
+
+    // Rows are padded to `stride`; copy out the visible bytes only
+    let channels = if has_alpha { 4 } else { 3 };
+    let row_len = width as usize * channels;
+    let mut pixels = Vec::with_capacity(row_len * height as usize);
+    for row in plane.data.chunks(plane.stride).take(height as usize) {
+        pixels.extend_from_slice(&row[..row_len]);
+    }
+
+    let img = if has_alpha {
+        RgbaImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8)
+    } else {
+        RgbImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8)
+    }
+    .ok_or_else(|| Error::ConversionError("HEIF pixel buffer has the wrong size".into()))?;
+
+    Ok((img, read_metadata(&handle)))
+}
+
+/// Width, height and alpha of the primary image without decoding pixels
+pub(crate) fn probe(path: &Path) -> Result<(u32, u32, bool)> {
+    let bytes = std::fs::read(path)?;
+    let ctx = HeifContext::read_from_bytes(&bytes).map_err(heif_error)?;
+    let handle = ctx.primary_image_handle().map_err(heif_error)?;
+
+    Ok((handle.width(), handle.height(), handle.has_alpha_channel()))
+}
+
+fn read_metadata(handle: &ImageHandle) -> EmbeddedMetadata {
+    let mut embedded = EmbeddedMetadata::default();
+
+    for item in handle.all_metadata() {
+        if &item.item_type.0 == b"Exif" {
+            // Exif items start with a big-endian offset to the TIFF header
+            let Some(offset) = item
+                .raw_data
+                .get(..4)
+                .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            else {
+                continue;
+            };
+            if let Some(tiff) = item.raw_data.get(4 + offset..) {
+                let mut exif = tiff.to_vec();
+                let _ = Orientation::remove_from_exif_chunk(&mut exif);
+                embedded.set_exif(exif);
+            }
+        } else if item.content_type == XMP_CONTENT_TYPE {
+            embedded.xmp = Some(item.raw_data);
+        }
+    }
+
+    embedded.icc = handle.color_profile_raw().map(|profile| profile.data);
+    embedded
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AvifEncoder, AvifOptions, ImageDecoder};
+    use image::{DynamicImage, RgbImage};
+    use libheif_rs::{
+        Channel, ColorSpace, CompressionFormat, HeifContext, Image, LibHeif, RgbChroma,
+    };
+    use transmute_common::MediaFormat;
+
+    /// HEVC-coded HEIF from libheif's own encoder, or `None` without its x265 plugin
+    fn encode_heic(width: u32, height: u32) -> Option<Vec<u8>> {
+        let lib_heif = LibHeif::new();
+        let mut encoder = lib_heif.encoder_for_format(CompressionFormat::Hevc).ok()?;
+        let mut image = Image::new(width, height, ColorSpace::Rgb(RgbChroma::Rgb)).ok()?;
+        image
+            .create_plane(Channel::Interleaved, width, height, 24)
+            .ok()?;
+        let plane = image.planes_mut().interleaved?;
+        for (y, row) in plane.data.chunks_mut(plane.stride).enumerate() {
+            for (x, pixel) in row.chunks_exact_mut(3).take(width as usize).enumerate() {
+                pixel.copy_from_slice(&[(x * 4) as u8, (y * 4) as u8, 128]);
+            }
+        }
+
+        let mut ctx = HeifContext::new().ok()?;
+        ctx.encode_image(&image, &mut encoder, None).ok()?;
+        ctx.write_to_bytes().ok()
+    }
+
+    #[test]
+    fn test_decode_heic() {
+        let Some(heic) = encode_heic(48, 32) else {
+            eprintln!("libheif has no HEVC encoder, skipping");
+            return;
+        };
+        assert_eq!(MediaFormat::sniff(&heic), Some(MediaFormat::Heif));
+
+        let temp = tempfile::Builder::new().suffix(".heic").tempfile().unwrap();
+        std::fs::write(temp.path(), &heic).unwrap();
+
+        let (img, metadata) = ImageDecoder::decode(temp.path()).unwrap();
+        assert_eq!((img.width(), img.height()), (48, 32));
+        assert_eq!(metadata.format, MediaFormat::Heif);
+        assert!(!metadata.has_alpha);
+    }
+
+    #[test]
+    fn test_avif_round_trip() {
+        if LibHeif::new()
+            .decoder_descriptors(1, Some(CompressionFormat::Av1))
+            .is_empty()
+        {
+            eprintln!("libheif has no AV1 decoder, skipping");
+            return;
+        }
+
+        let original = DynamicImage::ImageRgb8(RgbImage::from_fn(48, 32, |x, y| {
+            image::Rgb([(x * 5) as u8, (y * 7) as u8, 128])
+        }));
+        let avif = AvifEncoder::encode(&original, &AvifOptions::new(95, 8)).unwrap();
+        assert_eq!(MediaFormat::sniff(&avif), Some(MediaFormat::Avif));
+
+        let temp = tempfile::Builder::new().suffix(".avif").tempfile().unwrap();
+        std::fs::write(temp.path(), &avif).unwrap();
+
+        let probed = ImageDecoder::probe(temp.path()).unwrap();
+        assert_eq!((probed.width, probed.height), (48, 32));
+        assert_eq!(probed.format, MediaFormat::Avif);
+
+        let (img, metadata) = ImageDecoder::decode(temp.path()).unwrap();
+        assert_eq!(metadata.format, MediaFormat::Avif);
+        assert_eq!((img.width(), img.height()), (48, 32));
+
+        // Lossy, but a smooth gradient at quality 95 stays close
+        let decoded = img.to_rgb8();
+        let error = decoded
+            .as_raw()
+            .iter()
+            .zip(original.to_rgb8().as_raw())
+            .map(|(&a, &b)| (a as i32 - b as i32).unsigned_abs())
+            .sum::<u32>() as f64
+            / decoded.as_raw().len() as f64;
+        assert!(error < 4.0, "mean error {error}");
+    }
+}
@@ -0,0 +1,292 @@
+//! JPEG XL encoding and decoding through libjxl (`jxl` feature)
+
+use image::DynamicImage;
+use transmute_common::{EmbeddedMetadata, Error, MediaFormat, Result};
+
+/// JPEG XL encoder configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JxlOptions {
+    /// Visual quality (0-100, 100 = mathematically lossless)
+    pub quality: u8,
+
+    /// Encoder effort (1 = fastest, 9 = smallest)
+    pub effort: u8,
+}
+
+impl Default for JxlOptions {
+    fn default() -> Self {
+        // libjxl defaults (distance 1.0 ≈ quality 90, effort 7)
+        Self::new(90, 7)
+    }
+}
+
+impl JxlOptions {
+    pub fn new(quality: u8, effort: u8) -> Self {
+        Self {
+            quality: quality.min(100),
+            effort: effort.clamp(1, 9),
+        }
+    }
+}
+
+/// JPEG XL encoder shared by conversion and compression
+pub struct JxlEncoder;
+
+impl JxlEncoder {
+    /// Encode decoded pixels to JPEG XL
+    pub fn encode(img: &DynamicImage, options: &JxlOptions) -> Result<Vec<u8>> {
+        tracing::debug!(
+            "JXL encoding {}x{} (quality: {}, effort: {})",
+            img.width(),
+            img.height(),
+            options.quality,
+            options.effort
+        );
+
+        imp::encode(img, options)
+    }
+
+    /// Repack a JPEG bitstream as JPEG XL without decoding its pixels.
+    ///
+    /// The result reconstructs the original JPEG bit-exactly
+    /// ([`JxlDecoder::reconstruct_jpeg`]).
+    pub fn transcode_jpeg(jpeg: &[u8]) -> Result<Vec<u8>> {
+        if MediaFormat::sniff(jpeg) != Some(MediaFormat::Jpeg) {
+            return Err(Error::UnsupportedFormat(
+                "lossless JPEG XL transcoding needs JPEG input".into(),
+            ));
+        }
+
+        tracing::debug!("JXL lossless transcode of {} byte JPEG", jpeg.len());
+        imp::transcode_jpeg(jpeg)
+    }
+}
+
+/// JPEG XL decoder
+pub struct JxlDecoder;
+
+impl JxlDecoder {
+    /// Decode to 8-bit pixels with the ICC profile, orientation already applied
+    pub fn decode(jxl: &[u8]) -> Result<(DynamicImage, EmbeddedMetadata)> {
+        tracing::debug!("JXL decoding {} bytes", jxl.len());
+        imp::decode(jxl)
+    }
+
+    /// The original JPEG of a file made by [`JxlEncoder::transcode_jpeg`],
+    /// byte for byte
+    pub fn reconstruct_jpeg(jxl: &[u8]) -> Result<Vec<u8>> {
+        tracing::debug!("JXL → JPEG reconstruction of {} bytes", jxl.len());
+        imp::reconstruct_jpeg(jxl)
+    }
+}
+
+#[cfg(feature = "jxl")]
+mod imp {
+    use super::JxlOptions;
+    use image::{DynamicImage, GrayAlphaImage, GrayImage, RgbImage, RgbaImage};
+    use jpegxl_rs::decode::Data;
+    use jpegxl_rs::encode::{EncoderFrame, EncoderResult, EncoderSpeed};
+    use jpegxl_rs::{ThreadsRunner, decoder_builder, encoder_builder};
+    use transmute_common::{EmbeddedMetadata, Error, Result};
+
+    fn encode_error(e: jpegxl_rs::EncodeError) -> Error {
+        Error::ConversionError(format!("JXL encoding failed: {}", e))
+    }
+
+    fn decode_error(e: jpegxl_rs::DecodeError) -> Error {
+        Error::ConversionError(format!("JXL decoding failed: {}", e))
+    }
+
+    fn speed(effort: u8) -> EncoderSpeed {
+        match effort {
+            1 => EncoderSpeed::Lightning,
+            2 => EncoderSpeed::Thunder,
+            3 => EncoderSpeed::Falcon,
+            4 => EncoderSpeed::Cheetah,
+            5 => EncoderSpeed::Hare,
+            6 => EncoderSpeed::Wombat,
+            7 => EncoderSpeed::Squirrel,
+            8 => EncoderSpeed::Kitten,
+            _ => EncoderSpeed::Tortoise,
+        }
+    }
+
+    pub(super) fn encode(img: &DynamicImage, options: &JxlOptions) -> Result<Vec<u8>> {
+        let runner = ThreadsRunner::default();
+        let has_alpha = img.color().has_alpha();
+
+        let mut builder = encoder_builder();
+        builder
+            .parallel_runner(&runner)
+            .has_alpha(has_alpha)
+            .speed(speed(options.effort));
+        if options.quality == 100 {
+            // As `cjxl --quality=100`: modular lossless, not just distance 0
+            builder.lossless(true).uses_original_profile(true);
+        } else {
+            builder.jpeg_quality(options.quality as f32);
+        }
+        let mut encoder = builder.build().map_err(encode_error)?;
+
+        let (width, height) = (img.width(), img.height());
+        let result: EncoderResult<u8> = if has_alpha {
+            let rgba = img.to_rgba8();
+            let frame = EncoderFrame::new(rgba.as_raw()).num_channels(4);
+            encoder.encode_frame(&frame, width, height)
+        } else {
+            encoder.encode(img.to_rgb8().as_raw(), width, height)
+        }
+        .map_err(encode_error)?;
+
+        Ok(result.data)
+    }
+
+    pub(super) fn transcode_jpeg(jpeg: &[u8]) -> Result<Vec<u8>> {
+        let runner = ThreadsRunner::default();
+        // The container holds the `jbrd` box needed to rebuild the JPEG
+        let mut encoder = encoder_builder()
+            .parallel_runner(&runner)
+            .use_container(true)
+            .uses_original_profile(true)
+            .build()
+            .map_err(encode_error)?;
+
+        Ok(encoder.encode_jpeg(jpeg).map_err(encode_error)?.data)
+    }
+
+    pub(super) fn decode(jxl: &[u8]) -> Result<(DynamicImage, EmbeddedMetadata)> {
+        let runner = ThreadsRunner::default();
+        let decoder = decoder_builder()
+            .parallel_runner(&runner)
+            .icc_profile(true)
+            .build()
+            .map_err(decode_error)?;
+        let (metadata, pixels) = decoder.decode_with::<u8>(jxl).map_err(decode_error)?;
+
+        let (width, height) = (metadata.width, metadata.height);
+        let channels = metadata.num_color_channels + u32::from(metadata.has_alpha_channel);
+        let img = match channels {
+            1 => GrayImage::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+            2 => GrayAlphaImage::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8),
+            3 => RgbImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+            _ => RgbaImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8),
+        }
+        .ok_or_else(|| Error::ConversionError("JXL pixel buffer has the wrong size".into()))?;
+
+        let embedded = EmbeddedMetadata {
+            icc: metadata.icc_profile,
+            ..Default::default()
+        };
+        Ok((img, embedded))
+    }
+
+    pub(super) fn reconstruct_jpeg(jxl: &[u8]) -> Result<Vec<u8>> {
+        let runner = ThreadsRunner::default();
+        let decoder = decoder_builder()
+            .parallel_runner(&runner)
+            .build()
+            .map_err(decode_error)?;
+
+        match decoder.reconstruct(jxl).map_err(decode_error)? {
+            (_, Data::Jpeg(jpeg)) => Ok(jpeg),
+            (_, Data::Pixels(_)) => Err(Error::ConversionError(
+                "JXL file has no JPEG reconstruction data (it was not made from a JPEG)".into(),
+            )),
+        }
+    }
+}
+
+#[cfg(not(feature = "jxl"))]
+mod imp {
+    use super::JxlOptions;
+    use image::DynamicImage;
+    use transmute_common::{EmbeddedMetadata, Error, Result};
+
+    fn disabled() -> Error {
+        Error::UnsupportedFormat("JXL (build with the `jxl` feature to enable it)".into())
+    }
+
+    pub(super) fn encode(_img: &DynamicImage, _options: &JxlOptions) -> Result<Vec<u8>> {
+        Err(disabled())
+    }
+
+    pub(super) fn transcode_jpeg(_jpeg: &[u8]) -> Result<Vec<u8>> {
+        Err(disabled())
+    }
+
+    pub(super) fn decode(_jxl: &[u8]) -> Result<(DynamicImage, EmbeddedMetadata)> {
+        Err(disabled())
+    }
+
+    pub(super) fn reconstruct_jpeg(_jxl: &[u8]) -> Result<Vec<u8>> {
+        Err(disabled())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcode_rejects_non_jpeg() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert!(matches!(
+            JxlEncoder::transcode_jpeg(png),
+            Err(Error::UnsupportedFormat(_))
+        ));
+    }
+
+    /// A textured 128x96 baseline JPEG
+    #[cfg(feature = "jxl")]
+    fn sample_jpeg() -> Vec<u8> {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(128, 96, |x, y| {
+            image::Rgb([(x * 2) as u8, (y * 2) as u8, ((x ^ y) * 2) as u8])
+        }));
+        let mut jpeg = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut jpeg),
+            image::ImageFormat::Jpeg,
+        )
+        .unwrap();
+        jpeg
+    }
+
+    #[cfg(feature = "jxl")]
+    #[test]
+    fn test_transcode_jpeg_is_smaller() {
+        let jpeg = sample_jpeg();
+        let jxl = JxlEncoder::transcode_jpeg(&jpeg).unwrap();
+        assert_eq!(MediaFormat::sniff(&jxl), Some(MediaFormat::Jxl));
+        assert!(jxl.len() < jpeg.len());
+    }
+
+    #[cfg(feature = "jxl")]
+    #[test]
+    fn test_reconstructed_jpeg_is_identical() {
+        let jpeg = sample_jpeg();
+        let jxl = JxlEncoder::transcode_jpeg(&jpeg).unwrap();
+        assert_eq!(JxlDecoder::reconstruct_jpeg(&jxl).unwrap(), jpeg);
+
+        // The repacked file also decodes to pixels like any other JXL
+        let (img, _) = JxlDecoder::decode(&jxl).unwrap();
+        assert_eq!((img.width(), img.height()), (128, 96));
+    }
+
+    #[cfg(feature = "jxl")]
+    #[test]
+    fn test_lossless_round_trip() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(40, 24, |x, y| {
+            image::Rgba([(x * 6) as u8, (y * 10) as u8, 200, (x * y) as u8])
+        }));
+        let jxl = JxlEncoder::encode(&img, &JxlOptions::new(100, 3)).unwrap();
+
+        let (decoded, _) = JxlDecoder::decode(&jxl).unwrap();
+        assert_eq!(decoded.to_rgba8(), img.to_rgba8());
+
+        // Made from pixels, so there is no JPEG to rebuild
+        assert!(matches!(
+            JxlDecoder::reconstruct_jpeg(&jxl),
+            Err(Error::ConversionError(_))
+        ));
+    }
+}
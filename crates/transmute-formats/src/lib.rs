@@ -1,9 +1,33 @@
+pub mod animation;
+pub mod avif;
+pub mod contact_sheet;
 pub mod decoder;
 pub mod encoder;
+#[cfg(feature = "heif")]
+mod heif;
+pub mod jxl;
 pub mod metadata;
 pub mod pdf;
+pub mod pdf_compress;
+mod pdf_images;
+pub mod pdf_info;
+pub mod pdf_layout;
+pub mod pdf_ops;
+pub mod pdf_security;
+pub mod pdfa;
+pub mod webp;
 
-pub use decoder::ImageDecoder;
+pub use animation::{AnimationFrame, FrameSequence, LoopCount};
+pub use avif::{AvifEncoder, AvifOptions};
+pub use contact_sheet::ContactSheet;
+pub use decoder::{DecodeOptions, ImageDecoder};
 pub use encoder::ImageEncoder;
+pub use jxl::{JxlDecoder, JxlEncoder, JxlOptions};
 pub use metadata::ImageMetadata;
-pub use pdf::{PdfExtractor, PdfGenerator, PdfOptions};
+pub use pdf::{EmbeddedImage, PdfExtractor, PdfGenerator, PdfOptions};
+pub use pdf_compress::PdfImageStats;
+pub use pdf_info::{PageLabelStyle, PageLabels, PdfInfo};
+pub use pdf_layout::{FitMode, Grid, PageLayout, PageOrientation, PageSize, SheetLayout};
+pub use pdf_ops::{PageEdit, PdfPages};
+pub use pdf_security::{PdfEncryption, PdfPermissions};
+pub use webp::{WebpEncoder, WebpMode, WebpOptions};
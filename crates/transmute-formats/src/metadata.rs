@@ -1,4 +1,5 @@
-use transmute_common::MediaFormat;
+use image::metadata::Orientation;
+use transmute_common::{EmbeddedMetadata, MediaFormat};
 
 /// Image metadata extracted during decoding
 #[derive(Debug, Clone)]
@@ -8,6 +9,12 @@ pub struct ImageMetadata {
     pub format: MediaFormat,
     pub color_type: image::ColorType,
     pub has_alpha: bool,
+
+    /// EXIF orientation of the source (already applied when auto-orienting)
+    pub orientation: Orientation,
+
+    /// EXIF/XMP/ICC blocks read from the source file
+    pub embedded: EmbeddedMetadata,
 }
 
 impl ImageMetadata {
@@ -15,8 +22,12 @@ impl ImageMetadata {
         (self.width as usize) * (self.height as usize)
     }
 
+    /// Size of the decoded pixel buffer in bytes
+    pub fn decoded_size(&self) -> usize {
+        self.pixel_count() * self.color_type.bytes_per_pixel() as usize
+    }
+
     pub fn estimated_memory_mb(&self) -> f32 {
-        let bytes = self.pixel_count() * self.color_type.bytes_per_pixel() as usize;
-        bytes as f32 / (1024.0 * 1024.0)
+        self.decoded_size() as f32 / (1024.0 * 1024.0)
     }
 }
@@ -1,8 +1,19 @@
-use image::{DynamicImage, ImageBuffer, Rgba, imageops::FilterType};
-use printpdf::{Mm, Op, PdfDocument, PdfPage, PdfSaveOptions, Pt, RawImage, XObjectTransform};
-use std::path::{Path, PathBuf};
+use crate::pdf_info::{self, Bookmark, PageLabels, PdfInfo};
+use crate::pdf_layout::{FitMode, Grid, PageLayout, PageOrientation, PageSize, SheetLayout};
+use crate::pdf_security::PdfEncryption;
+use crate::{contact_sheet, pdf_images, pdf_security, pdfa};
+use image::codecs::jpeg::JpegDecoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageBuffer, ImageDecoder as _, Rgba, imageops::FilterType};
+use printpdf::{
+    BuiltinFont, Color, Mm, Op, PdfDocument, PdfPage, PdfSaveOptions, Point, Pt, RawImage, Rgb,
+    TextItem, XObjectTransform,
+};
+use std::collections::HashSet;
 use std::fs;
-use transmute_common::{Error, Result};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use transmute_common::{Error, PageSelection, Result};
 
 // Type alias for clarity
 type RgbaImage = ImageBuffer<Rgba<u8>, Vec<u8>>;
@@ -11,32 +22,119 @@ type RgbaImage = ImageBuffer<Rgba<u8>, Vec<u8>>;
 #[derive(Debug, Clone)]
 pub struct PdfOptions {
     /// Page size (default: A4)
-    pub page_width_mm: f32,
-    pub page_height_mm: f32,
+    pub page_size: PageSize,
+
+    /// Page orientation (default: follow each image)
+    pub orientation: PageOrientation,
+
+    /// Blank space on each side of the image in mm (default: 10)
+    pub margin_mm: f32,
+
+    /// How images are sized on the page (default: fit inside the margins)
+    pub fit: FitMode,
+
+    /// Images per page as rows x columns (default: one)
+    pub grid: Grid,
+
+    /// Gap between grid cells in mm (default: 5)
+    pub spacing_mm: f32,
+
+    /// Print each image's file name below it
+    pub captions: bool,
 
     /// DPI for image rendering (default: 300)
     pub dpi: f32,
 
-    /// Title metadata
-    pub title: String,
+    /// Document properties; the creation date defaults to now and the producer to Transmute
+    pub info: PdfInfo,
+
+    /// Add an outline entry for each image, titled from its file name
+    pub bookmarks: bool,
+
+    /// Page labels shown by viewers (default: none, pages are numbered 1, 2, 3)
+    pub page_labels: Option<PageLabels>,
+
+    /// Write PDF/A-2b for long-term archiving; captions are refused, as their font
+    /// is not embedded
+    pub pdfa: bool,
+
+    /// Password-protect the document with AES-256 (not with `pdfa`)
+    pub encryption: Option<PdfEncryption>,
 
     /// Compress images in PDF
     pub compress_images: bool,
 
+    /// Embed JPEG sources as stored instead of re-encoding them; turn off when the
+    /// decoded pixels were changed (rotated, resized, ...) before reaching the PDF
+    pub jpeg_passthrough: bool,
+
     /// Maximum image dimension before downscaling (default: 2400px for 300 DPI at A4 width)
     /// Images larger than this will be downscaled to save memory and reduce PDF size
     pub max_image_dimension: u32,
+
+    /// Page width in mm; with `page_height_mm` it overrides `page_size` when both are
+    /// above 0 (the default)
+    #[deprecated(note = "use `page_size: PageSize::Custom { width_mm, height_mm }`")]
+    pub page_width_mm: f32,
+
+    /// Page height in mm, see `page_width_mm`
+    #[deprecated(note = "use `page_size: PageSize::Custom { width_mm, height_mm }`")]
+    pub page_height_mm: f32,
+
+    /// Document title, used when `info` has none (default: empty)
+    #[deprecated(note = "use `info.title`")]
+    pub title: String,
+}
+
+impl PdfOptions {
+    /// Page size to lay pages out on, including the deprecated `page_width_mm` and
+    /// `page_height_mm`
+    #[allow(deprecated)]
+    pub fn resolved_page_size(&self) -> PageSize {
+        if self.page_width_mm > 0.0 && self.page_height_mm > 0.0 {
+            PageSize::Custom {
+                width_mm: self.page_width_mm,
+                height_mm: self.page_height_mm,
+            }
+        } else {
+            self.page_size
+        }
+    }
+
+    /// Document properties, with the deprecated `title` filling in a missing title
+    #[allow(deprecated)]
+    pub fn resolved_info(&self) -> PdfInfo {
+        let mut info = self.info.clone();
+        if info.title.is_none() && !self.title.is_empty() {
+            info.title = Some(self.title.clone());
+        }
+        info
+    }
 }
 
 impl Default for PdfOptions {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
-            page_width_mm: 210.0,  // A4 width
-            page_height_mm: 297.0, // A4 height
+            page_size: PageSize::A4,
+            orientation: PageOrientation::Auto,
+            margin_mm: 10.0,
+            fit: FitMode::Fit,
+            grid: Grid::default(),
+            spacing_mm: 5.0,
+            captions: false,
             dpi: 300.0,
-            title: "Transmute Generated PDF".into(),
+            info: PdfInfo::default(),
+            bookmarks: true,
+            page_labels: None,
+            pdfa: false,
+            encryption: None,
             compress_images: true,
+            jpeg_passthrough: true,
             max_image_dimension: 2400, // ~8 inches at 300 DPI
+            page_width_mm: 0.0,
+            page_height_mm: 0.0,
+            title: String::new(),
         }
     }
 }
@@ -57,90 +155,202 @@ impl PdfGenerator {
         images: Vec<(DynamicImage, PathBuf)>, // (image, original_path)
         output_path: &Path,
     ) -> Result<()> {
+        let pdf_bytes = self.generate_bytes(images)?;
+        std::fs::write(output_path, pdf_bytes)?;
+
+        tracing::info!("PDF generated successfully at {:?}", output_path);
+        Ok(())
+    }
+
+    /// Generate PDF from multiple images, returning the file contents
+    pub fn generate_bytes(&self, images: Vec<(DynamicImage, PathBuf)>) -> Result<Vec<u8>> {
         if images.is_empty() {
             return Err(Error::ConversionError(
                 "No images provided for PDF generation".into(),
             ));
         }
 
-        tracing::info!(
-            "Generating PDF with {} pages at {:?}",
-            images.len(),
-            output_path
-        );
+        if self.options.pdfa && self.options.captions {
+            return Err(Error::ConversionError(
+                "PDF/A output cannot have captions: their font is not embedded".into(),
+            ));
+        }
+        if self.options.pdfa && self.options.encryption.is_some() {
+            return Err(Error::ConversionError(
+                "PDF/A output cannot be encrypted".into(),
+            ));
+        }
+
+        tracing::info!("Generating PDF with {} images", images.len());
 
         // Create PDF document
-        let mut doc = PdfDocument::new(&self.options.title);
+        let mut doc = PdfDocument::new("");
         let mut pages = Vec::new();
+        let mut bookmarks = Vec::new();
+
+        // Lay images out one grid of cells per page
+        for (page_index, page_images) in images.chunks(self.options.grid.cells()).enumerate() {
+            let (first, _) = &page_images[0];
+            let sheet = SheetLayout::new(&self.options, first.width(), first.height())?;
+            let mut ops = Vec::new();
+
+            for (cell, (img, original_path)) in page_images.iter().enumerate() {
+                tracing::debug!(
+                    "Adding image {}/{} on page {}: {:?} ({}x{})",
+                    page_index * self.options.grid.cells() + cell + 1,
+                    images.len(),
+                    page_index + 1,
+                    original_path,
+                    img.width(),
+                    img.height()
+                );
+
+                // Geometry comes from the source dimensions, so DPI-based sizes are exact
+                let layout = sheet.place(cell, img.width(), img.height());
+                ops.push(self.place_image(&mut doc, img, original_path, &layout)?);
+                bookmarks.push(Bookmark {
+                    title: original_path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| format!("Image {}", bookmarks.len() + 1)),
+                    page: page_index as u32 + 1,
+                    left_pt: layout.x_mm * 2.834645,
+                    top_pt: (layout.y_mm + layout.height_mm) * 2.834645,
+                });
+
+                if self.options.captions {
+                    ops.extend(self.caption_ops(&sheet, cell, original_path)?);
+                }
+            }
+
+            pages.push(PdfPage::new(Mm(sheet.page_width_mm), Mm(sheet.page_height_mm), ops));
+        }
 
-        // Add each image as a page
-        for (idx, (img, original_path)) in images.iter().enumerate() {
-            tracing::debug!(
-                "Adding page {}/{}: {:?} ({}x{})",
-                idx + 1,
-                images.len(),
-                original_path,
-                img.width(),
-                img.height()
-            );
+        let bytes = doc
+            .with_pages(pages)
+            .save(&PdfSaveOptions::default(), &mut Vec::new());
+        self.finish(&bytes, &bookmarks)
+    }
 
-            // Optimization 1: Downscale large images to reduce memory and PDF size
-            // Images larger than max_image_dimension are scaled down, preserving aspect ratio
-            let processed_img = self.maybe_downscale_image(img);
+    /// Replace printpdf's placeholder Info dictionary with the configured one and add
+    /// the outline, page labels, PDF/A structures and encryption
+    fn finish(&self, bytes: &[u8], bookmarks: &[Bookmark]) -> Result<Vec<u8>> {
+        let mut document = lopdf::Document::load_mem(bytes).map_err(pdf_images::pdf_error)?;
+        document.trailer.remove(b"Info");
+        let info = self.options.resolved_info().for_new_document();
+        info.apply(&mut document)?;
 
-            // Optimization 2: JPEG passthrough - if source is JPEG, embed directly without re-encoding
-            // This avoids generation loss and is significantly faster (no decode-encode cycle)
-            let raw_image = if self.is_jpeg_source(original_path) && self.options.compress_images {
-                tracing::debug!("Using JPEG passthrough for {:?}", original_path);
-                self.load_jpeg_direct(original_path)?
-            } else {
-                // Optimization 3: Use JPEG encoding for non-JPEG sources when compression enabled
-                // JPEG is faster to encode/decode than PNG and results in smaller PDFs
-                self.encode_image_for_pdf(&processed_img)?
-            };
-
-            // Add image to document resources and get ID
-            let image_id = doc.add_image(&raw_image);
-
-            // Calculate scaling to fit page while preserving aspect ratio
-            // Use processed_img dimensions for correct scaling
-            let (fit_width_mm, fit_height_mm) =
-                self.calculate_fit_dimensions(processed_img.width() as f32, processed_img.height() as f32);
-
-            // Center image on page
-            let x_offset = (self.options.page_width_mm - fit_width_mm) / 2.0;
-            let y_offset = (self.options.page_height_mm - fit_height_mm) / 2.0;
-
-            // Create page operations
-            let ops = vec![Op::UseXobject {
-                id: image_id,
-                transform: XObjectTransform {
-                    translate_x: Some(Pt(x_offset * 2.834645)), // mm to pt conversion
-                    translate_y: Some(Pt(y_offset * 2.834645)),
-                    scale_x: Some(fit_width_mm / (processed_img.width() as f32 / self.options.dpi * 25.4)),
-                    scale_y: Some(fit_height_mm / (processed_img.height() as f32 / self.options.dpi * 25.4)),
-                    dpi: Some(self.options.dpi),
-                    ..Default::default()
-                },
-            }];
+        if self.options.bookmarks {
+            pdf_info::set_outline(&mut document, bookmarks)?;
+        }
+        if let Some(labels) = &self.options.page_labels {
+            labels.apply(&mut document)?;
+        }
+        if self.options.pdfa {
+            pdfa::make_conformant(&mut document, &info)?;
+        }
 
-            // Create page
-            let page = PdfPage::new(
-                Mm(self.options.page_width_mm),
-                Mm(self.options.page_height_mm),
-                ops,
-            );
-            pages.push(page);
+        document.prune_objects();
+        if let Some(encryption) = &self.options.encryption {
+            pdf_security::encrypt(&mut document, encryption)?;
         }
+        let mut output = Vec::new();
+        document.save_to(&mut output)?;
+        Ok(output)
+    }
 
-        // Write PDF to file
-        let pdf_bytes = doc
-            .with_pages(pages)
-            .save(&PdfSaveOptions::default(), &mut Vec::new());
-        std::fs::write(output_path, pdf_bytes)?;
+    /// Embed `img` in `doc` and return the operation drawing it at `layout`
+    fn place_image(
+        &self,
+        doc: &mut PdfDocument,
+        img: &DynamicImage,
+        original_path: &Path,
+        layout: &PageLayout,
+    ) -> Result<Op> {
+        // Optimization 1: Downscale large images to reduce memory and PDF size
+        // Images larger than max_image_dimension are scaled down, preserving aspect ratio
+        let mut processed_img = self.maybe_downscale_image(img);
+        if let Some((x, y, width, height)) =
+            layout.crop_pixels(processed_img.width(), processed_img.height())
+        {
+            processed_img = std::borrow::Cow::Owned(processed_img.crop_imm(x, y, width, height));
+        }
 
-        tracing::info!("PDF generated successfully at {:?}", output_path);
-        Ok(())
+        // Optimization 2: JPEG passthrough - if source is JPEG, embed directly without re-encoding
+        // This avoids generation loss and is significantly faster (no decode-encode cycle)
+        // Cropped images need the cropped pixels, so they are always re-encoded
+        let passthrough = if self.is_jpeg_source(original_path)
+            && self.options.compress_images
+            && self.options.jpeg_passthrough
+            && layout.crop.is_none()
+        {
+            self.load_jpeg_direct(original_path)?
+        } else {
+            None
+        };
+
+        let raw_image = match passthrough {
+            Some(raw_image) => {
+                tracing::debug!("Using JPEG passthrough for {:?}", original_path);
+                raw_image
+            }
+            // Optimization 3: Use JPEG encoding for non-JPEG sources when compression enabled
+            // JPEG is faster to encode/decode than PNG and results in smaller PDFs
+            None => self.encode_image_for_pdf(&processed_img)?,
+        };
+
+        // Scale from the embedded image's own size at the configured DPI
+        let embedded_width_mm = raw_image.width as f32 / self.options.dpi * 25.4;
+        let embedded_height_mm = raw_image.height as f32 / self.options.dpi * 25.4;
+
+        // Add image to document resources and get ID
+        let image_id = doc.add_image(&raw_image);
+
+        Ok(Op::UseXobject {
+            id: image_id,
+            transform: XObjectTransform {
+                translate_x: Some(Pt(layout.x_mm * 2.834645)), // mm to pt conversion
+                translate_y: Some(Pt(layout.y_mm * 2.834645)),
+                scale_x: Some(layout.width_mm / embedded_width_mm),
+                scale_y: Some(layout.height_mm / embedded_height_mm),
+                dpi: Some(self.options.dpi),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Operations writing `path`'s file name centered in the caption strip of `cell`
+    fn caption_ops(&self, sheet: &SheetLayout, cell: usize, path: &Path) -> Result<Vec<Op>> {
+        let [x, y, width, height] = sheet.caption_area(cell);
+        let (text, text_width_pt) = contact_sheet::fit_caption(path, width * 2.834645)?;
+        let gray = contact_sheet::CAPTION_GRAY as f32 / 255.0;
+
+        Ok(vec![
+            Op::StartTextSection,
+            Op::SetFontSizeBuiltinFont {
+                size: Pt(contact_sheet::CAPTION_SIZE_PT),
+                font: BuiltinFont::Helvetica,
+            },
+            Op::SetFillColor {
+                col: Color::Rgb(Rgb {
+                    r: gray,
+                    g: gray,
+                    b: gray,
+                    icc_profile: None,
+                }),
+            },
+            Op::SetTextCursor {
+                pos: Point {
+                    x: Pt((x + width / 2.0) * 2.834645 - text_width_pt / 2.0),
+                    y: Pt((y + contact_sheet::caption_baseline_mm(height)) * 2.834645),
+                },
+            },
+            Op::WriteTextBuiltinFont {
+                items: vec![TextItem::Text(text)],
+                font: BuiltinFont::Helvetica,
+            },
+            Op::EndTextSection,
+        ])
     }
 
     /// Check if source file is JPEG based on extension
@@ -153,9 +363,21 @@ impl PdfGenerator {
 
     /// Load JPEG file directly without re-encoding (passthrough optimization)
     /// This avoids decode-encode cycles and preserves original JPEG quality
-    fn load_jpeg_direct(&self, path: &Path) -> Result<RawImage> {
+    /// Returns `None` if the EXIF orientation requires rotation: the raw stream would
+    /// be embedded sideways, so the caller falls back to the (oriented) decoded image
+    fn load_jpeg_direct(&self, path: &Path) -> Result<Option<RawImage>> {
         let jpeg_bytes = fs::read(path)?;
+
+        let orientation = JpegDecoder::new(Cursor::new(&jpeg_bytes))
+            .and_then(|mut decoder| decoder.orientation())
+            .unwrap_or(Orientation::NoTransforms);
+        if orientation != Orientation::NoTransforms {
+            tracing::debug!("Skipping JPEG passthrough for {:?} ({:?})", path, orientation);
+            return Ok(None);
+        }
+
         RawImage::decode_from_bytes(&jpeg_bytes, &mut Vec::new())
+            .map(Some)
             .map_err(|e| Error::ConversionError(format!("Failed to load JPEG: {:?}", e)))
     }
 
@@ -208,60 +430,124 @@ impl PdfGenerator {
         RawImage::decode_from_bytes(&bytes, &mut Vec::new())
             .map_err(|e| Error::ConversionError(format!("Failed to encode image: {:?}", e)))
     }
+}
 
-    /// Calculate dimensions to fit image on page while preserving aspect ratio
-    fn calculate_fit_dimensions(&self, img_width: f32, img_height: f32) -> (f32, f32) {
-        let page_width = self.options.page_width_mm - 20.0; // 10mm margin each side
-        let page_height = self.options.page_height_mm - 20.0;
-
-        let img_aspect = img_width / img_height;
-        let page_aspect = page_width / page_height;
-
-        if img_aspect > page_aspect {
-            // Image is wider - fit to width
-            (page_width, page_width / img_aspect)
-        } else {
-            // Image is taller - fit to height
-            (page_height * img_aspect, page_height)
-        }
-    }
+/// Image file written by [`PdfExtractor::extract_embedded_images`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedImage {
+    /// First page (1-based) that draws the image
+    pub page: u32,
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// File holds the PDF's stored bytes unchanged (JPEG, JPEG 2000, JBIG2)
+    pub passthrough: bool,
 }
 
 /// PDF extraction to images (GPU-accelerated rasterization)
 pub struct PdfExtractor {
     dpi: f32,
+    password: Option<String>,
 }
 
 impl PdfExtractor {
     pub fn new(dpi: f32) -> Self {
-        Self { dpi }
+        Self {
+            dpi,
+            password: None,
+        }
     }
 
-    /// Extract all pages from PDF as images
-    #[cfg(all(feature = "pdf-extract", not(target_arch = "wasm32")))]
+    /// Password for opening protected PDFs
+    pub fn set_password(&mut self, password: Option<String>) {
+        self.password = password;
+    }
+
+    /// Extract all pages from PDF as images.
+    ///
+    /// Every page is held in memory at once; prefer [`Self::for_each_page`] for
+    /// long documents.
     pub fn extract_pages(&self, pdf_path: &Path) -> Result<Vec<DynamicImage>> {
+        let mut images = Vec::new();
+        self.for_each_page(pdf_path, &PageSelection::all(), |_, img| {
+            images.push(img);
+            Ok(())
+        })?;
+        Ok(images)
+    }
+
+    /// Render the selected pages one at a time, handing each (with its 1-based
+    /// number) to `on_page` before the next one is rendered.
+    ///
+    /// Renders with pdfium when its library can be loaded; otherwise (or in builds
+    /// without the `pdf-extract` feature) falls back to [`Self::extract_pages_fallback`].
+    pub fn for_each_page<F>(&self, pdf_path: &Path, pages: &PageSelection, mut on_page: F) -> Result<()>
+    where
+        F: FnMut(u32, DynamicImage) -> Result<()>,
+    {
+        #[cfg(all(feature = "pdf-extract", not(target_arch = "wasm32")))]
+        match Self::bind_pdfium() {
+            Ok(pdfium) => return self.for_each_page_pdfium(&pdfium, pdf_path, pages, &mut on_page),
+            Err(e) => tracing::warn!("pdfium unavailable ({}), using built-in PDF renderer", e),
+        }
+
+        self.for_each_page_fallback(pdf_path, pages, &mut on_page)
+    }
+
+    /// Number of pages in the PDF, opened with `password` if it is protected
+    pub fn page_count(pdf_path: &Path, password: Option<&str>) -> Result<u32> {
+        let document = pdf_security::load(pdf_path, password)?;
+        Ok(document.get_pages().len() as u32)
+    }
+
+    /// Load pdfium from the working directory or the system, like `Pdfium::default`
+    /// but reporting a missing library instead of panicking
+    #[cfg(all(feature = "pdf-extract", not(target_arch = "wasm32")))]
+    fn bind_pdfium() -> Result<pdfium_render::prelude::Pdfium> {
         use pdfium_render::prelude::*;
 
-        tracing::info!("Extracting pages from PDF: {:?}", pdf_path);
+        let bindings = Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path("./"))
+            .or_else(|_| Pdfium::bind_to_system_library())
+            .map_err(|e| Error::ConversionError(format!("{:?}", e)))?;
 
-        // Initialize Pdfium (attempts to use system library)
-        let pdfium = Pdfium::default();
+        Ok(Pdfium::new(bindings))
+    }
 
-        let document = pdfium
-            .load_pdf_from_file(pdf_path, None)
-            .map_err(|e| Error::ConversionError(format!("Failed to load PDF: {:?}", e)))?;
+    #[cfg(all(feature = "pdf-extract", not(target_arch = "wasm32")))]
+    fn for_each_page_pdfium(
+        &self,
+        pdfium: &pdfium_render::prelude::Pdfium,
+        pdf_path: &Path,
+        pages: &PageSelection,
+        on_page: &mut dyn FnMut(u32, DynamicImage) -> Result<()>,
+    ) -> Result<()> {
+        use pdfium_render::prelude::*;
 
-        let page_count = document.pages().len();
-        tracing::info!("PDF has {} pages", page_count);
+        tracing::info!("Extracting pages from PDF: {:?}", pdf_path);
 
-        let mut images = Vec::with_capacity(page_count as usize);
+        let document = pdfium
+            .load_pdf_from_file(pdf_path, self.password.as_deref())
+            .map_err(|e| match e {
+                PdfiumError::PdfiumLibraryInternalError(PdfiumInternalError::PasswordError) => {
+                    match self.password {
+                        Some(_) => Error::IncorrectPassword(pdf_path.to_path_buf()),
+                        None => Error::PasswordRequired(pdf_path.to_path_buf()),
+                    }
+                }
+                e => Error::ConversionError(format!("Failed to load PDF: {:?}", e)),
+            })?;
+
+        let page_count = document.pages().len() as u32;
+        let selected = pages.resolve(page_count)?;
+        tracing::info!("PDF has {} pages, rendering {}", page_count, selected.len());
 
         // Render each page
-        for page_idx in 0..page_count {
-            tracing::debug!("Rendering page {}/{}", page_idx + 1, page_count);
+        for page_number in selected {
+            tracing::debug!("Rendering page {}/{}", page_number, page_count);
+            let page_idx = (page_number - 1) as PdfPageIndex;
 
             let page = document.pages().get(page_idx).map_err(|e| {
-                Error::ConversionError(format!("Failed to get page {}: {:?}", page_idx, e))
+                Error::ConversionError(format!("Failed to get page {}: {:?}", page_number, e))
             })?;
 
             // Render page to bitmap with GPU acceleration (if available)
@@ -273,7 +559,7 @@ impl PdfExtractor {
                         .render_form_data(false),
                 )
                 .map_err(|e| {
-                    Error::ConversionError(format!("Failed to render page {}: {:?}", page_idx, e))
+                    Error::ConversionError(format!("Failed to render page {}: {:?}", page_number, e))
                 })?;
 
             // Convert bitmap to DynamicImage
@@ -285,19 +571,109 @@ impl PdfExtractor {
                 Error::ConversionError("Failed to create image from bitmap".into())
             })?;
 
-            images.push(DynamicImage::ImageRgba8(img));
+            on_page(page_number, DynamicImage::ImageRgba8(img))?;
         }
 
-        tracing::info!("Successfully extracted {} pages", images.len());
+        Ok(())
+    }
+
+    /// Pure-Rust rendering through lopdf, no native library needed.
+    ///
+    /// Each page's embedded images are drawn where the page places them, which is
+    /// exact for scanned documents. Text and vector graphics are not drawn, and a
+    /// page with no image to draw is an error rather than a blank picture.
+    pub fn extract_pages_fallback(&self, pdf_path: &Path) -> Result<Vec<DynamicImage>> {
+        let mut images = Vec::new();
+        self.for_each_page_fallback(pdf_path, &PageSelection::all(), &mut |_, img| {
+            images.push(img);
+            Ok(())
+        })?;
         Ok(images)
     }
 
-    /// Fallback when pdf-extract feature is disabled or on WASM
-    #[cfg(not(all(feature = "pdf-extract", not(target_arch = "wasm32"))))]
-    pub fn extract_pages(&self, _pdf_path: &Path) -> Result<Vec<DynamicImage>> {
-        Err(Error::ConversionError(
-            "PDF extraction not available in this build".into(),
-        ))
+    fn for_each_page_fallback(
+        &self,
+        pdf_path: &Path,
+        pages: &PageSelection,
+        on_page: &mut dyn FnMut(u32, DynamicImage) -> Result<()>,
+    ) -> Result<()> {
+        tracing::info!("Extracting page images from PDF: {:?}", pdf_path);
+
+        let document = pdf_security::load(pdf_path, self.password.as_deref())?;
+        let page_ids = document.get_pages();
+        let selected = pages.resolve(page_ids.len() as u32)?;
+        tracing::info!("PDF has {} pages, rendering {}", page_ids.len(), selected.len());
+
+        for page_number in selected {
+            let img = pdf_images::render_page(&document, page_ids[&page_number], self.dpi)?
+                .ok_or_else(|| {
+                    Error::ConversionError(format!(
+                        "PDF page {} has no raster content; without pdfium only embedded images are rendered",
+                        page_number
+                    ))
+                })?;
+            on_page(page_number, img)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write every image embedded in the PDF to `output_dir` without re-rendering.
+    ///
+    /// JPEG (DCT), JPEG 2000 and JBIG2 streams are saved byte-for-byte as `.jpg`,
+    /// `.jp2` and `.jb2`; Flate/LZW/raw bitmaps are decoded to `.png`. Images
+    /// reused on several pages are written once. Protected PDFs are opened with
+    /// `password`.
+    pub fn extract_embedded_images(
+        pdf_path: &Path,
+        password: Option<&str>,
+        output_dir: &Path,
+    ) -> Result<Vec<EmbeddedImage>> {
+        tracing::info!("Extracting embedded images from PDF: {:?}", pdf_path);
+
+        let document = pdf_security::load(pdf_path, password)?;
+        let base_name = pdf_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("image");
+        fs::create_dir_all(output_dir)?;
+
+        let mut seen = HashSet::new();
+        let mut extracted = Vec::new();
+        for (page, page_id) in document.get_pages() {
+            let mut index = 0;
+            for (id, stream) in pdf_images::page_images(&document, page_id) {
+                if !seen.insert(id) {
+                    continue;
+                }
+
+                let image = match pdf_images::export_image(&document, stream) {
+                    Ok(image) => image,
+                    Err(e) => {
+                        tracing::warn!("Skipping image {:?} on page {}: {}", id, page, e);
+                        continue;
+                    }
+                };
+
+                index += 1;
+                let path = output_dir.join(format!(
+                    "{}_page_{:03}_image_{:02}.{}",
+                    base_name, page, index, image.extension
+                ));
+                fs::write(&path, &image.data)?;
+
+                extracted.push(EmbeddedImage {
+                    page,
+                    path,
+                    width: image.width,
+                    height: image.height,
+                    passthrough: image.passthrough,
+                });
+            }
+        }
+
+        tracing::info!("Extracted {} embedded images", extracted.len());
+        Ok(extracted)
     }
 }
 
@@ -340,8 +716,10 @@ mod tests {
         let img = DynamicImage::new_rgb8(3840, 2160);
         let images = vec![(img, PathBuf::from("large.png"))];
 
-        let mut options = PdfOptions::default();
-        options.max_image_dimension = 1920; // Force downscaling
+        let options = PdfOptions {
+            max_image_dimension: 1920, // Force downscaling
+            ..Default::default()
+        };
 
         let generator = PdfGenerator::new(options);
         let result = generator.generate_from_images(images, temp_pdf.path());
@@ -353,4 +731,138 @@ mod tests {
         let file_size = std::fs::metadata(temp_pdf.path()).unwrap().len();
         assert!(file_size < 5_000_000); // Should be much smaller than uncompressed 4K
     }
+
+    #[test]
+    fn test_document_info_outline_and_labels() {
+        let options = PdfOptions {
+            info: PdfInfo {
+                title: Some("Trip".into()),
+                author: Some("Renée".into()),
+                ..Default::default()
+            },
+            grid: Grid::new(1, 2).unwrap(),
+            page_labels: Some(PageLabels {
+                style: crate::PageLabelStyle::LowerRoman,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let images = ["beach.png", "dunes.png", "harbour.png"]
+            .iter()
+            .map(|name| (DynamicImage::new_rgb8(40, 30), PathBuf::from(name)))
+            .collect();
+        let bytes = PdfGenerator::new(options).generate_bytes(images).unwrap();
+        let document = lopdf::Document::load_mem(&bytes).unwrap();
+
+        let info = PdfInfo::read(&document);
+        assert_eq!(info.title.as_deref(), Some("Trip"));
+        assert_eq!(info.author.as_deref(), Some("Renée"));
+        assert_eq!(info.producer.as_deref(), Some(pdf_info::PRODUCER));
+        assert!(info.creation_date.is_some());
+        assert_eq!(info.subject, None);
+
+        // One bookmark per image, in input order, pointing at the image's page
+        let catalog = document.catalog().unwrap();
+        let outline = catalog.get(b"Outlines").and_then(|o| o.as_reference()).unwrap();
+        let mut entry = document.get_dictionary(outline).unwrap().get(b"First").ok().cloned();
+        let pages = document.get_pages();
+        let mut entries = Vec::new();
+        while let Some(lopdf::Object::Reference(id)) = entry {
+            let item = document.get_dictionary(id).unwrap();
+            let title = lopdf::decode_text_string(item.get(b"Title").unwrap()).unwrap();
+            let target = item.get(b"Dest").unwrap().as_array().unwrap()[0].as_reference().unwrap();
+            let page = pages.iter().find(|(_, id)| **id == target).map(|(n, _)| *n).unwrap();
+            entries.push((title, page));
+            entry = item.get(b"Next").ok().cloned();
+        }
+        assert_eq!(
+            entries,
+            [("beach".into(), 1), ("dunes".into(), 1), ("harbour".into(), 2)]
+        );
+
+        assert!(catalog.get(b"PageLabels").is_ok());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_page_and_title_fields() {
+        let options = PdfOptions {
+            page_width_mm: 100.0,
+            page_height_mm: 150.0,
+            orientation: PageOrientation::Portrait,
+            title: "Receipts".into(),
+            ..Default::default()
+        };
+        let images = vec![(DynamicImage::new_rgb8(40, 30), PathBuf::from("a.png"))];
+        let bytes = PdfGenerator::new(options).generate_bytes(images).unwrap();
+        let document = lopdf::Document::load_mem(&bytes).unwrap();
+
+        assert_eq!(PdfInfo::read(&document).title.as_deref(), Some("Receipts"));
+        let page_id = document.get_pages()[&1];
+        let (width, height) = pdf_images::page_size(&document, page_id);
+        assert!((width - 283.5).abs() < 1.0 && (height - 425.2).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_jpeg_passthrough_respects_orientation() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut jpeg = Vec::new();
+        DynamicImage::new_rgb8(40, 20)
+            .write_to(&mut Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+
+        let upright = dir.path().join("upright.jpg");
+        std::fs::write(&upright, &jpeg).unwrap();
+
+        // EXIF Orientation = 6 (rotate 90° CW)
+        let mut exif = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        exif.extend_from_slice(&[1, 0, 0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0]);
+        let embedded = transmute_common::EmbeddedMetadata {
+            exif: Some(exif),
+            ..Default::default()
+        };
+        let sideways = dir.path().join("sideways.jpg");
+        std::fs::write(&sideways, embedded.embed(jpeg, transmute_common::MediaFormat::Jpeg).unwrap())
+            .unwrap();
+
+        let generator = PdfGenerator::new(PdfOptions::default());
+        assert!(generator.load_jpeg_direct(&upright).unwrap().is_some());
+        assert!(generator.load_jpeg_direct(&sideways).unwrap().is_none());
+
+        // Falls back to the already-oriented pixels
+        let temp_pdf = NamedTempFile::new().unwrap();
+        let images = vec![(DynamicImage::new_rgb8(20, 40), sideways)];
+        assert!(generator.generate_from_images(images, temp_pdf.path()).is_ok());
+    }
+    #[test]
+    fn test_extract_images_drawn_inside_forms() {
+        use crate::pdf_images::tests::{scanned_pdf, wrap_in_form};
+        use lopdf::{Stream, dictionary};
+
+        let dir = tempfile::tempdir().unwrap();
+        let stream = Stream::new(
+            dictionary! {
+                "Subtype" => "Image",
+                "Width" => 4,
+                "Height" => 2,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![0, 50, 100, 150, 200, 250, 200, 150],
+        );
+        let mut doc = scanned_pdf(stream, [0, 0, 72, 36]);
+        let page_id = doc.get_pages()[&1];
+        wrap_in_form(&mut doc, page_id, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+        let pdf_path = dir.path().join("scan.pdf");
+        doc.save(&pdf_path).unwrap();
+
+        let images =
+            PdfExtractor::extract_embedded_images(&pdf_path, None, &dir.path().join("images"))
+                .unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(
+            (images[0].page, images[0].width, images[0].height),
+            (1, 4, 2)
+        );
+    }
 }
@@ -0,0 +1,314 @@
+//! Shrinking existing PDFs: images drawn sharper than needed are downsampled and
+//! re-encoded as JPEG, without touching text or vector content
+
+use crate::pdf_images;
+use image::DynamicImage;
+use image::imageops::FilterType;
+use lopdf::{Document, Object, ObjectId, Stream};
+use std::collections::{BTreeMap, HashMap};
+use transmute_common::{Error, Result};
+
+/// Images are only resampled when sharper than the target by more than this
+/// factor, so a slightly oversized image is not blurred for a negligible saving
+const DOWNSAMPLE_THRESHOLD: f32 = 1.1;
+
+/// Identity transformation matrix `[a b c d e f]`
+const IDENTITY: [f32; 6] = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// What [`crate::PdfPages::compress_images`] changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PdfImageStats {
+    /// Image XObjects drawn by the document's pages
+    pub images: usize,
+
+    /// Images replaced by a smaller JPEG
+    pub recompressed: usize,
+
+    /// Replaced images that were also scaled down
+    pub downsampled: usize,
+}
+
+pub(crate) fn compress_images(
+    doc: &mut Document,
+    max_dpi: f32,
+    encode_jpeg: &mut dyn FnMut(&DynamicImage) -> Result<Vec<u8>>,
+) -> Result<PdfImageStats> {
+    if !max_dpi.is_finite() || max_dpi <= 0.0 {
+        return Err(Error::ConversionError(format!(
+            "DPI must be positive, got {}",
+            max_dpi
+        )));
+    }
+
+    // Largest size each image is shown at, in points
+    let mut shown: BTreeMap<ObjectId, (f32, f32)> = BTreeMap::new();
+    for page_id in doc.page_iter().collect::<Vec<_>>() {
+        let placed = placements(doc, page_id);
+        // Images drawn inside forms or patterns could cover the whole page
+        let page = pdf_images::page_size(doc, page_id);
+        for (id, _) in pdf_images::page_images(doc, page_id) {
+            let (width, height) = placed.get(&id).copied().unwrap_or(page);
+            let size = shown.entry(id).or_insert((0.0, 0.0));
+            *size = (size.0.max(width), size.1.max(height));
+        }
+    }
+
+    let mut stats = PdfImageStats {
+        images: shown.len(),
+        ..Default::default()
+    };
+    for (id, size) in shown {
+        let Ok(stream) = doc.get_object(id).and_then(Object::as_stream) else {
+            continue;
+        };
+        if !recompressible(doc, stream) {
+            continue;
+        }
+        let img = match pdf_images::decode_image(doc, stream) {
+            Ok(img) => img,
+            Err(e) => {
+                tracing::debug!("Keeping PDF image {:?} as is: {}", id, e);
+                continue;
+            }
+        };
+
+        let scale = downsample_scale(img.width(), img.height(), size, max_dpi);
+        let img = match scale {
+            Some(scale) => {
+                let width = ((img.width() as f32 * scale).round() as u32).max(1);
+                let height = ((img.height() as f32 * scale).round() as u32).max(1);
+                img.resize_exact(width, height, FilterType::Lanczos3)
+            }
+            None => img,
+        };
+
+        let jpeg = encode_jpeg(&img)?;
+        if jpeg.len() >= stream.content.len() {
+            continue;
+        }
+
+        let mut dict = stream.dict.clone();
+        dict.set("Width", img.width() as i64);
+        dict.set("Height", img.height() as i64);
+        dict.set("BitsPerComponent", 8);
+        dict.set("Filter", "DCTDecode");
+        dict.remove(b"DecodeParms");
+        doc.objects
+            .insert(id, Object::Stream(Stream::new(dict, jpeg)));
+
+        stats.recompressed += 1;
+        if scale.is_some() {
+            stats.downsampled += 1;
+        }
+    }
+
+    doc.compress();
+    Ok(stats)
+}
+
+/// Whether an image can become a JPEG without changing how it looks
+fn recompressible(doc: &Document, stream: &Stream) -> bool {
+    let dict = &stream.dict;
+    // Stencil masks, colour key masks and Decode arrays depend on exact sample values
+    let is_mask = dict
+        .get(b"ImageMask")
+        .and_then(Object::as_bool)
+        .unwrap_or(false);
+    let color_key = dict.get(b"Mask").and_then(Object::as_array).is_ok();
+    if is_mask || color_key || dict.has(b"Decode") {
+        return false;
+    }
+
+    // Line art and palette images are smaller and sharper as they are
+    let bits = dict
+        .get(b"BitsPerComponent")
+        .and_then(Object::as_i64)
+        .unwrap_or(8);
+    bits >= 8 && pdf_images::is_gray_or_rgb(doc, stream)
+}
+
+/// Factor that brings an image shown at `shown` points down to `max_dpi`, when
+/// it is sharper than that
+fn downsample_scale(width: u32, height: u32, shown: (f32, f32), max_dpi: f32) -> Option<f32> {
+    // The less dense axis decides, so neither ends up below the target
+    let dpi = (width as f32 * 72.0 / shown.0).min(height as f32 * 72.0 / shown.1);
+    (dpi > max_dpi * DOWNSAMPLE_THRESHOLD).then(|| max_dpi / dpi)
+}
+
+/// Largest size, in points, each image XObject is drawn at by the page's own content
+fn placements(doc: &Document, page_id: ObjectId) -> HashMap<ObjectId, (f32, f32)> {
+    let mut sizes = HashMap::new();
+    let Ok(content) = doc.get_and_decode_page_content(page_id) else {
+        return sizes;
+    };
+
+    let mut ctm = IDENTITY;
+    let mut saved = Vec::new();
+    for op in &content.operations {
+        match op.operator.as_str() {
+            "q" => saved.push(ctm),
+            "Q" => ctm = saved.pop().unwrap_or(ctm),
+            "cm" => {
+                let values: Vec<f32> = op
+                    .operands
+                    .iter()
+                    .filter_map(|v| v.as_float().ok())
+                    .collect();
+                if let Ok(matrix) = <[f32; 6]>::try_from(values) {
+                    ctm = multiply(matrix, ctm);
+                }
+            }
+            "Do" => {
+                let Some(id) = op
+                    .operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| xobject_id(doc, page_id, name))
+                else {
+                    continue;
+                };
+                // Images fill the unit square, so the CTM's axes are the drawn size
+                let (width, height) = (ctm[0].hypot(ctm[1]), ctm[2].hypot(ctm[3]));
+                if width > 0.0 && height > 0.0 {
+                    let size = sizes.entry(id).or_insert((0.0, 0.0));
+                    *size = (f32::max(size.0, width), f32::max(size.1, height));
+                }
+            }
+            _ => {}
+        }
+    }
+    sizes
+}
+
+/// `m × ctm`, the matrix a `cm` operator makes current
+fn multiply(m: [f32; 6], ctm: [f32; 6]) -> [f32; 6] {
+    [
+        m[0] * ctm[0] + m[1] * ctm[2],
+        m[0] * ctm[1] + m[1] * ctm[3],
+        m[2] * ctm[0] + m[3] * ctm[2],
+        m[2] * ctm[1] + m[3] * ctm[3],
+        m[4] * ctm[0] + m[5] * ctm[2] + ctm[4],
+        m[4] * ctm[1] + m[5] * ctm[3] + ctm[5],
+    ]
+}
+
+/// The XObject a page's resources call `name`
+fn xobject_id(doc: &Document, page_id: ObjectId, name: &[u8]) -> Option<ObjectId> {
+    let (inline, inherited) = doc.get_page_resources(page_id).ok()?;
+    inline
+        .into_iter()
+        .chain(
+            inherited
+                .into_iter()
+                .filter_map(|id| doc.get_dictionary(id).ok()),
+        )
+        .find_map(|resources| {
+            let (_, xobjects) = doc.dereference(resources.get(b"XObject").ok()?).ok()?;
+            xobjects.as_dict().ok()?.get(name).ok()?.as_reference().ok()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_images::tests::{scanned_pdf, wrap_in_form};
+    use image::{Rgb, RgbImage};
+    use lopdf::dictionary;
+    use std::io::Cursor;
+
+    fn noisy_rgb(width: u32, height: u32) -> Stream {
+        let img = RgbImage::from_fn(width, height, |x, y| {
+            Rgb([
+                (x * 7 % 256) as u8,
+                (y * 13 % 256) as u8,
+                ((x ^ y) % 256) as u8,
+            ])
+        });
+        let mut stream = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => width as i64,
+                "Height" => height as i64,
+                "ColorSpace" => "DeviceRGB",
+                "BitsPerComponent" => 8,
+            },
+            img.into_raw(),
+        );
+        stream.compress().unwrap();
+        stream
+    }
+
+    fn encode(img: &DynamicImage) -> Result<Vec<u8>> {
+        let mut jpeg = Vec::new();
+        img.write_to(&mut Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)?;
+        Ok(jpeg)
+    }
+
+    fn image_size(doc: &Document) -> (i64, i64) {
+        let page_id = doc.get_pages()[&1];
+        let (_, stream) = pdf_images::page_images(doc, page_id)[0];
+        let int = |key: &[u8]| stream.dict.get(key).unwrap().as_i64().unwrap();
+        assert_eq!(pdf_images::filters(stream), ["DCTDecode"]);
+        (int(b"Width"), int(b"Height"))
+    }
+
+    #[test]
+    fn test_multiply_applies_scale_then_translation() {
+        let ctm = multiply(
+            [2.0, 0.0, 0.0, 3.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0, 1.0, 10.0, 20.0],
+        );
+        assert_eq!(ctm, [2.0, 0.0, 0.0, 3.0, 10.0, 20.0]);
+        let ctm = multiply([1.0, 0.0, 0.0, 1.0, 5.0, 5.0], ctm);
+        assert_eq!(ctm, [2.0, 0.0, 0.0, 3.0, 20.0, 35.0]);
+    }
+
+    #[test]
+    fn test_downsamples_above_target_dpi() {
+        // 400x200 pixels drawn over a 72x36 pt (1x0.5 inch) page: 400 DPI
+        let mut doc = scanned_pdf(noisy_rgb(400, 200), [0, 0, 72, 36]);
+        let stats = compress_images(&mut doc, 100.0, &mut encode).unwrap();
+
+        assert_eq!(
+            stats,
+            PdfImageStats {
+                images: 1,
+                recompressed: 1,
+                downsampled: 1
+            }
+        );
+        assert_eq!(image_size(&doc), (100, 50));
+    }
+
+    #[test]
+    fn test_recompresses_images_inside_forms() {
+        let mut doc = scanned_pdf(noisy_rgb(400, 200), [0, 0, 72, 36]);
+        let page_id = doc.get_pages()[&1];
+        wrap_in_form(&mut doc, page_id, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+        let stats = compress_images(&mut doc, 300.0, &mut encode).unwrap();
+
+        assert_eq!((stats.images, stats.recompressed), (1, 1));
+    }
+
+    #[test]
+    fn test_keeps_resolution_at_or_below_target() {
+        let mut doc = scanned_pdf(noisy_rgb(105, 52), [0, 0, 72, 36]);
+        let stats = compress_images(&mut doc, 100.0, &mut encode).unwrap();
+
+        assert_eq!(stats.downsampled, 0);
+        assert_eq!(image_size(&doc), (105, 52));
+    }
+
+    #[test]
+    fn test_keeps_images_with_decode_arrays() {
+        let mut stream = noisy_rgb(64, 64);
+        stream.dict.set("Decode", vec![1.into(), 0.into()]);
+        let mut doc = scanned_pdf(stream, [0, 0, 72, 72]);
+        let before = doc.clone();
+
+        let stats = compress_images(&mut doc, 10.0, &mut encode).unwrap();
+        assert_eq!(stats.recompressed, 0);
+        assert_eq!(doc.objects, before.objects);
+    }
+}
@@ -0,0 +1,1188 @@
+//! Image XObjects inside existing PDFs, read with lopdf (no native libraries)
+
+use image::codecs::jpeg::JpegDecoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, GrayImage, Rgb, RgbImage};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::HashSet;
+use std::io::Cursor;
+use transmute_common::{Error, Result};
+
+/// US Letter, used when a page has no readable MediaBox
+const DEFAULT_PAGE_SIZE_PT: (f32, f32) = (612.0, 792.0);
+
+/// Largest page raster `render_page` allocates (256 Mpx, about 768 MB of RGB);
+/// page sizes come from the file and cannot be trusted
+const MAX_RENDER_PIXELS: u32 = 1 << 28;
+
+/// ID string that opens a standalone JBIG2 file
+const JBIG2_FILE_ID: [u8; 8] = [0x97, 0x4A, 0x42, 0x32, 0x0D, 0x0A, 0x1A, 0x0A];
+
+pub(crate) fn pdf_error(e: lopdf::Error) -> Error {
+    Error::ConversionError(format!("PDF parsing failed: {}", e))
+}
+
+/// Image XObjects drawn by a page, looked up in its (possibly inherited) resources
+/// and in those of the form XObjects it draws.
+///
+/// Generators often share one resource dictionary between pages, so only names
+/// used by a `Do` operator in the page's content count when it can be parsed.
+pub(crate) fn page_images(doc: &Document, page_id: ObjectId) -> Vec<(ObjectId, &Stream)> {
+    let Ok((inline, inherited)) = doc.get_page_resources(page_id) else {
+        return Vec::new();
+    };
+
+    let drawn = doc
+        .get_and_decode_page_content(page_id)
+        .ok()
+        .map(|content| drawn_names(&content.operations));
+    let resources: Vec<&Dictionary> = inline
+        .into_iter()
+        .chain(
+            inherited
+                .into_iter()
+                .filter_map(|id| doc.get_dictionary(id).ok()),
+        )
+        .collect();
+
+    let mut images = Vec::new();
+    collect_images(
+        doc,
+        &xobject_dicts(doc, &resources),
+        drawn.as_ref(),
+        &mut HashSet::new(),
+        &mut images,
+    );
+    images
+}
+
+/// Images named in `xobjects`, recursing into forms; `visited` holds every
+/// XObject already seen, so shared or self-referencing forms are walked once
+fn collect_images<'a>(
+    doc: &'a Document,
+    xobjects: &[&'a Dictionary],
+    drawn: Option<&HashSet<Vec<u8>>>,
+    visited: &mut HashSet<ObjectId>,
+    images: &mut Vec<(ObjectId, &'a Stream)>,
+) {
+    for (name, value) in xobjects.iter().flat_map(|xobjects| xobjects.iter()) {
+        if drawn.is_some_and(|drawn| !drawn.contains(name)) {
+            continue;
+        }
+        let Ok(id) = value.as_reference() else {
+            continue;
+        };
+        let Ok(stream) = doc.get_object(id).and_then(Object::as_stream) else {
+            continue;
+        };
+        if !visited.insert(id) {
+            continue;
+        }
+
+        match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+            Ok(b"Image") => images.push((id, stream)),
+            Ok(b"Form") => {
+                let drawn = form_content(stream).map(|operations| drawn_names(&operations));
+                let resources = form_resources(doc, stream);
+                collect_images(
+                    doc,
+                    &xobject_dicts(doc, &resources),
+                    drawn.as_ref(),
+                    visited,
+                    images,
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Names passed to `Do` operators
+fn drawn_names(operations: &[Operation]) -> HashSet<Vec<u8>> {
+    operations
+        .iter()
+        .filter(|op| op.operator == "Do")
+        .filter_map(|op| op.operands.first()?.as_name().ok().map(<[u8]>::to_vec))
+        .collect()
+}
+
+/// `/XObject` dictionaries of the given resource dictionaries
+fn xobject_dicts<'a>(doc: &'a Document, resources: &[&'a Dictionary]) -> Vec<&'a Dictionary> {
+    resources
+        .iter()
+        .filter_map(|resources| {
+            let (_, obj) = doc.dereference(resources.get(b"XObject").ok()?).ok()?;
+            obj.as_dict().ok()
+        })
+        .collect()
+}
+
+/// A form XObject's own `/Resources`, if any
+fn form_resources<'a>(doc: &'a Document, form: &'a Stream) -> Vec<&'a Dictionary> {
+    form.dict
+        .get(b"Resources")
+        .ok()
+        .and_then(|obj| doc.dereference(obj).ok())
+        .and_then(|(_, obj)| obj.as_dict().ok())
+        .into_iter()
+        .collect()
+}
+
+/// Operations of a form XObject's content stream
+fn form_content(form: &Stream) -> Option<Vec<Operation>> {
+    // Unfiltered streams make decompressed_content fail, as in lopdf's page content
+    let data = form
+        .decompressed_content()
+        .unwrap_or_else(|_| form.content.clone());
+    Content::decode(&data)
+        .ok()
+        .map(|content| content.operations)
+}
+
+/// Stream filters in decoding order
+pub(crate) fn filters(stream: &Stream) -> Vec<String> {
+    stream
+        .filters()
+        .map(|names| {
+            names
+                .into_iter()
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Decode an image XObject to pixels
+pub(crate) fn decode_image(doc: &Document, stream: &Stream) -> Result<DynamicImage> {
+    let dict = &stream.dict;
+    let width = int(dict, b"Width").unwrap_or(0) as u32;
+    let height = int(dict, b"Height").unwrap_or(0) as u32;
+    if width == 0 || height == 0 {
+        return Err(Error::ConversionError("PDF image has no size".into()));
+    }
+
+    let filters = filters(stream);
+    match filters.last().map(String::as_str) {
+        Some("DCTDecode") if filters.len() == 1 => {
+            let decoder = JpegDecoder::new(Cursor::new(&stream.content))?;
+            return Ok(DynamicImage::from_decoder(decoder)?);
+        }
+        Some(filter @ ("DCTDecode" | "JPXDecode" | "JBIG2Decode" | "CCITTFaxDecode")) => {
+            return Err(Error::UnsupportedFormat(format!(
+                "PDF image filter {}",
+                filter
+            )));
+        }
+        _ => {}
+    }
+
+    let data = stream.get_plain_content().map_err(pdf_error)?;
+
+    let is_mask = dict
+        .get(b"ImageMask")
+        .and_then(Object::as_bool)
+        .unwrap_or(false);
+    let bits = if is_mask {
+        1
+    } else {
+        int(dict, b"BitsPerComponent").unwrap_or(8) as u8
+    };
+    if !matches!(bits, 1 | 2 | 4 | 8 | 16) {
+        return Err(Error::ConversionError(format!(
+            "Unsupported PDF image depth: {} bits",
+            bits
+        )));
+    }
+
+    let color_space = if is_mask {
+        ColorSpace::Gray
+    } else {
+        dict.get(b"ColorSpace")
+            .map(|cs| ColorSpace::parse(doc, cs))
+            .unwrap_or(Ok(ColorSpace::Gray))?
+    };
+
+    let samples = unpack_samples(
+        &data,
+        width as usize,
+        height as usize,
+        color_space.components(),
+        bits,
+        matches!(color_space, ColorSpace::Indexed { .. }),
+    )?;
+
+    // [1 0] inverts 1-component images (common for scans); a stencil mask paints 0s
+    let inverted = is_mask
+        || dict
+            .get(b"Decode")
+            .and_then(Object::as_array)
+            .ok()
+            .and_then(|d| d.first().map(|v| v.as_float().unwrap_or(0.0) > 0.5))
+            .unwrap_or(false);
+
+    let img = match color_space {
+        ColorSpace::Gray => {
+            let pixels = if inverted {
+                samples.into_iter().map(|v| 255 - v).collect()
+            } else {
+                samples
+            };
+            DynamicImage::ImageLuma8(image_from_raw(GrayImage::from_raw(width, height, pixels))?)
+        }
+        ColorSpace::Rgb => {
+            DynamicImage::ImageRgb8(image_from_raw(RgbImage::from_raw(width, height, samples))?)
+        }
+        ColorSpace::Cmyk => {
+            let pixels = samples
+                .chunks_exact(4)
+                .flat_map(|cmyk| {
+                    let k = 255 - cmyk[3] as u16;
+                    [0, 1, 2].map(|i| ((255 - cmyk[i] as u16) * k / 255) as u8)
+                })
+                .collect();
+            DynamicImage::ImageRgb8(image_from_raw(RgbImage::from_raw(width, height, pixels))?)
+        }
+        ColorSpace::Indexed { palette } => {
+            let pixels = samples
+                .into_iter()
+                .flat_map(|index| {
+                    palette
+                        .get(index as usize)
+                        .copied()
+                        .unwrap_or(Rgb([0, 0, 0]))
+                        .0
+                })
+                .collect();
+            DynamicImage::ImageRgb8(image_from_raw(RgbImage::from_raw(width, height, pixels))?)
+        }
+    };
+
+    Ok(img)
+}
+
+/// An image XObject as a standalone file
+pub(crate) struct ExportedImage {
+    pub extension: &'static str,
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// `data` is the PDF stream's bytes, unchanged
+    pub passthrough: bool,
+}
+
+/// Turn an image XObject back into a file of its own.
+///
+/// JPEG, JPEG 2000 and JBIG2 streams are copied as stored; everything else is
+/// decoded (with its soft mask as alpha) and written as PNG.
+pub(crate) fn export_image(doc: &Document, stream: &Stream) -> Result<ExportedImage> {
+    let width = int(&stream.dict, b"Width").unwrap_or(0) as u32;
+    let height = int(&stream.dict, b"Height").unwrap_or(0) as u32;
+    let original = |extension, data| ExportedImage {
+        extension,
+        data,
+        width,
+        height,
+        passthrough: true,
+    };
+
+    let filters = filters(stream);
+    if let [filter] = filters.as_slice() {
+        match filter.as_str() {
+            "DCTDecode" => return Ok(original("jpg", stream.content.clone())),
+            "JPXDecode" => return Ok(original("jp2", stream.content.clone())),
+            "JBIG2Decode" => return Ok(original("jb2", jbig2_file(doc, stream))),
+            _ => {}
+        }
+    }
+
+    let img = with_soft_mask(doc, stream, decode_image(doc, stream)?);
+    let mut data = Vec::new();
+    img.write_to(&mut Cursor::new(&mut data), image::ImageFormat::Png)?;
+
+    Ok(ExportedImage {
+        extension: "png",
+        data,
+        width: img.width(),
+        height: img.height(),
+        passthrough: false,
+    })
+}
+
+/// Prefix an embedded JBIG2 stream with a file header and its shared
+/// JBIG2Globals segments, giving a sequential single-page `.jb2` file
+fn jbig2_file(doc: &Document, stream: &Stream) -> Vec<u8> {
+    let mut file = JBIG2_FILE_ID.to_vec();
+    // Sequential organisation, page count follows
+    file.push(0x01);
+    file.extend_from_slice(&1u32.to_be_bytes());
+
+    let globals = stream
+        .dict
+        .get(b"DecodeParms")
+        .and_then(|parms| doc.dereference(parms))
+        .and_then(|(_, parms)| parms.as_dict())
+        .and_then(|parms| parms.get(b"JBIG2Globals"))
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_object(id))
+        .and_then(Object::as_stream);
+    if let Ok(globals) = globals {
+        match globals.get_plain_content() {
+            Ok(segments) => file.extend_from_slice(&segments),
+            Err(e) => tracing::warn!("Ignoring unreadable JBIG2Globals: {}", e),
+        }
+    }
+
+    file.extend_from_slice(&stream.content);
+    file
+}
+
+/// Apply the image's /SMask, if any, as an alpha channel
+fn with_soft_mask(doc: &Document, stream: &Stream, img: DynamicImage) -> DynamicImage {
+    let Ok(mask) = stream
+        .dict
+        .get(b"SMask")
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_object(id))
+        .and_then(Object::as_stream)
+    else {
+        return img;
+    };
+
+    let mask = match decode_image(doc, mask) {
+        Ok(mask) => mask,
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable PDF soft mask: {}", e);
+            return img;
+        }
+    };
+    // Masks may be stored at a different resolution than the image
+    let mask = if mask.dimensions() == img.dimensions() {
+        mask.to_luma8()
+    } else {
+        mask.resize_exact(img.width(), img.height(), FilterType::Triangle)
+            .to_luma8()
+    };
+
+    let mut rgba = img.to_rgba8();
+    for (pixel, alpha) in rgba.pixels_mut().zip(mask.pixels()) {
+        pixel.0[3] = alpha.0[0];
+    }
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// Best-effort page raster without pdfium: every image the page draws, placed
+/// where its content stream puts it on a white page at `dpi`, then turned by
+/// the page's /Rotate.
+///
+/// Vector content and text are not drawn. Returns `None` when the page draws no
+/// decodable image, so callers can report it instead of emitting a blank page.
+pub(crate) fn render_page(
+    doc: &Document,
+    page_id: ObjectId,
+    dpi: f32,
+) -> Result<Option<DynamicImage>> {
+    let [x0, y0, x1, y1] = media_box(doc, page_id);
+    let rotation = inherited(doc, page_id, b"Rotate")
+        .and_then(|obj| obj.as_i64().ok())
+        .unwrap_or(0)
+        .rem_euclid(360);
+
+    let scale = dpi / 72.0;
+    // Float-to-int casts saturate, so huge or garbage boxes end up over the limit
+    let target_w = (((x1 - x0) * scale).round() as u32).max(1);
+    let target_h = (((y1 - y0) * scale).round() as u32).max(1);
+    if target_w
+        .checked_mul(target_h)
+        .is_none_or(|pixels| pixels > MAX_RENDER_PIXELS)
+    {
+        return Err(Error::ConversionError(format!(
+            "Page is too large to render at {} DPI ({}x{} pixels)",
+            dpi, target_w, target_h
+        )));
+    }
+    // Page space (y up, from the MediaBox corner) to pixels (y down)
+    let device = Matrix([scale, 0.0, 0.0, -scale, -x0 * scale, y1 * scale]);
+
+    let placements = image_placements(doc, page_id).unwrap_or_else(|| {
+        // Unreadable content: stretch the largest image over the page
+        page_images(doc, page_id)
+            .into_iter()
+            .max_by_key(|(_, stream)| {
+                int(&stream.dict, b"Width").unwrap_or(0) * int(&stream.dict, b"Height").unwrap_or(0)
+            })
+            .map(|(id, stream)| (Matrix([x1 - x0, 0.0, 0.0, y1 - y0, x0, y0]), id, stream))
+            .into_iter()
+            .collect()
+    });
+
+    let mut page = RgbImage::from_pixel(target_w, target_h, Rgb([255, 255, 255]));
+    let mut drawn = false;
+    for (ctm, id, stream) in placements {
+        match decode_image(doc, stream) {
+            Ok(img) => {
+                let img = with_soft_mask(doc, stream, img);
+                drawn |= draw_image(&mut page, &img, ctm.then(&device));
+            }
+            Err(e) => tracing::warn!("Skipping PDF image {:?}: {}", id, e),
+        }
+    }
+    if !drawn {
+        return Ok(None);
+    }
+
+    let img = DynamicImage::ImageRgb8(page);
+    Ok(Some(match rotation {
+        90 => img.rotate90(),
+        180 => img.rotate180(),
+        270 => img.rotate270(),
+        _ => img,
+    }))
+}
+
+/// PDF transformation matrix `[a b c d e f]`, mapping (x, y) to
+/// (a·x + c·y + e, b·x + d·y + f)
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Matrix([f32; 6]);
+
+impl Matrix {
+    const IDENTITY: Self = Self([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    /// This transform followed by `next`
+    fn then(&self, next: &Self) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        let [na, nb, nc, nd, ne, nf] = next.0;
+        Self([
+            a * na + b * nc,
+            a * nb + b * nd,
+            c * na + d * nc,
+            c * nb + d * nd,
+            e * na + f * nc + ne,
+            e * nb + f * nd + nf,
+        ])
+    }
+
+    fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    fn invert(&self) -> Option<Self> {
+        let [a, b, c, d, e, f] = self.0;
+        let det = a * d - b * c;
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        Some(Self([
+            d / det,
+            -b / det,
+            -c / det,
+            a / det,
+            (c * f - d * e) / det,
+            (b * e - a * f) / det,
+        ]))
+    }
+}
+
+/// Image XObjects drawn by the page's content stream, each with the
+/// transformation current at its `Do`, following form XObjects. `None` when the
+/// content cannot be read.
+///
+/// Inline images are not followed.
+fn image_placements(doc: &Document, page_id: ObjectId) -> Option<Vec<(Matrix, ObjectId, &Stream)>> {
+    let content = doc.get_and_decode_page_content(page_id).ok()?;
+    let images = page_images(doc, page_id);
+    let (inline, inherited) = doc.get_page_resources(page_id).ok()?;
+    let resources: Vec<&Dictionary> = inline
+        .into_iter()
+        .chain(
+            inherited
+                .into_iter()
+                .filter_map(|id| doc.get_dictionary(id).ok()),
+        )
+        .collect();
+
+    let mut placements = Vec::new();
+    place_images(
+        doc,
+        &content.operations,
+        &xobject_dicts(doc, &resources),
+        Matrix::IDENTITY,
+        &images,
+        &mut HashSet::new(),
+        &mut placements,
+    );
+    Some(placements)
+}
+
+/// Walk one content stream for [`image_placements`]; `forms` holds the forms
+/// being drawn, so a form that draws itself is not followed again
+fn place_images<'a>(
+    doc: &'a Document,
+    operations: &[Operation],
+    xobjects: &[&'a Dictionary],
+    mut ctm: Matrix,
+    images: &[(ObjectId, &'a Stream)],
+    forms: &mut HashSet<ObjectId>,
+    placements: &mut Vec<(Matrix, ObjectId, &'a Stream)>,
+) {
+    let mut stack = Vec::new();
+    for op in operations {
+        match op.operator.as_str() {
+            "q" => stack.push(ctm),
+            "Q" => ctm = stack.pop().unwrap_or(ctm),
+            "cm" => {
+                if let Some(matrix) = matrix(&op.operands) {
+                    ctm = matrix.then(&ctm);
+                }
+            }
+            "Do" => {
+                let Some(name) = op.operands.first().and_then(|v| v.as_name().ok()) else {
+                    continue;
+                };
+                // The first resource dictionary naming it wins, as for page_images
+                let Some(id) = xobjects
+                    .iter()
+                    .find_map(|xobjects| xobjects.get(name).ok()?.as_reference().ok())
+                else {
+                    continue;
+                };
+                if let Some(&(id, stream)) = images.iter().find(|(seen, _)| *seen == id) {
+                    placements.push((ctm, id, stream));
+                    continue;
+                }
+
+                let Ok(form) = doc.get_object(id).and_then(Object::as_stream) else {
+                    continue;
+                };
+                let is_form = form
+                    .dict
+                    .get(b"Subtype")
+                    .and_then(Object::as_name)
+                    .is_ok_and(|name| name == b"Form");
+                if !is_form || !forms.insert(id) {
+                    continue;
+                }
+                if let Some(operations) = form_content(form) {
+                    let form_matrix = form
+                        .dict
+                        .get(b"Matrix")
+                        .and_then(Object::as_array)
+                        .ok()
+                        .and_then(|values| matrix(values))
+                        .unwrap_or(Matrix::IDENTITY);
+                    place_images(
+                        doc,
+                        &operations,
+                        &xobject_dicts(doc, &form_resources(doc, form)),
+                        form_matrix.then(&ctm),
+                        images,
+                        forms,
+                        placements,
+                    );
+                }
+                forms.remove(&id);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Six numbers as a transformation matrix
+fn matrix(values: &[Object]) -> Option<Matrix> {
+    let values: Vec<f32> = values.iter().filter_map(|v| v.as_float().ok()).collect();
+    <[f32; 6]>::try_from(values).ok().map(Matrix)
+}
+
+/// Paint `img` (the unit square in image space) through `transform` onto
+/// `page`, blending by alpha. Returns whether any pixel was covered.
+fn draw_image(page: &mut RgbImage, img: &DynamicImage, transform: Matrix) -> bool {
+    let Some(inverse) = transform.invert() else {
+        return false;
+    };
+
+    // Pixel bounds of the transformed unit square
+    let corners =
+        [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)].map(|(x, y)| transform.apply(x, y));
+    let (min_x, max_x, min_y, max_y) = corners.iter().fold(
+        (f32::MAX, f32::MIN, f32::MAX, f32::MIN),
+        |(min_x, max_x, min_y, max_y), &(x, y)| {
+            (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))
+        },
+    );
+    let left = min_x.floor().max(0.0) as u32;
+    let top = min_y.floor().max(0.0) as u32;
+    let right = (max_x.ceil().max(0.0) as u32).min(page.width());
+    let bottom = (max_y.ceil().max(0.0) as u32).min(page.height());
+    if left >= right || top >= bottom {
+        return false;
+    }
+
+    // Resample once to the drawn size, then look pixels up without filtering
+    let [a, b, c, d, _, _] = transform.0;
+    let width = (a.hypot(b).round() as u32).clamp(1, page.width().max(page.height()) * 2);
+    let height = (c.hypot(d).round() as u32).clamp(1, page.width().max(page.height()) * 2);
+    let img = if (width, height) == img.dimensions() {
+        img.to_rgba8()
+    } else {
+        img.resize_exact(width, height, FilterType::Lanczos3)
+            .to_rgba8()
+    };
+
+    let mut covered = false;
+    for y in top..bottom {
+        for x in left..right {
+            let (u, v) = inverse.apply(x as f32 + 0.5, y as f32 + 0.5);
+            if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+                continue;
+            }
+            // Image rows run top to bottom, from v = 1 down to v = 0
+            let source = img.get_pixel(
+                ((u * width as f32) as u32).min(width - 1),
+                (((1.0 - v) * height as f32) as u32).min(height - 1),
+            );
+            let alpha = source.0[3] as u16;
+            let pixel = page.get_pixel_mut(x, y);
+            for i in 0..3 {
+                pixel.0[i] =
+                    ((source.0[i] as u16 * alpha + pixel.0[i] as u16 * (255 - alpha)) / 255) as u8;
+            }
+            covered = true;
+        }
+    }
+    covered
+}
+
+/// MediaBox width and height in points
+pub(crate) fn page_size(doc: &Document, page_id: ObjectId) -> (f32, f32) {
+    let [x0, y0, x1, y1] = media_box(doc, page_id);
+    (x1 - x0, y1 - y0)
+}
+
+/// MediaBox corners as `[left, bottom, right, top]` in points
+fn media_box(doc: &Document, page_id: ObjectId) -> [f32; 4] {
+    inherited(doc, page_id, b"MediaBox")
+        .and_then(|obj| doc.dereference(obj).ok())
+        .and_then(|(_, obj)| obj.as_array().ok())
+        .and_then(|values| {
+            let v: Vec<f32> = values.iter().filter_map(|v| v.as_float().ok()).collect();
+            (v.len() == 4).then(|| {
+                [
+                    v[0].min(v[2]),
+                    v[1].min(v[3]),
+                    v[0].max(v[2]),
+                    v[1].max(v[3]),
+                ]
+            })
+        })
+        .filter(|[x0, y0, x1, y1]| x1 > x0 && y1 > y0)
+        .unwrap_or([0.0, 0.0, DEFAULT_PAGE_SIZE_PT.0, DEFAULT_PAGE_SIZE_PT.1])
+}
+
+/// Page attribute that may be set on the page or any ancestor in the page tree
+pub(crate) fn inherited<'a>(
+    doc: &'a Document,
+    page_id: ObjectId,
+    key: &[u8],
+) -> Option<&'a Object> {
+    let mut node = doc.get_dictionary(page_id).ok();
+    // Guard against reference cycles in broken files
+    for _ in 0..64 {
+        let dict = node?;
+        if let Ok(value) = dict.get(key) {
+            return Some(value);
+        }
+        node = dict
+            .get(b"Parent")
+            .and_then(Object::as_reference)
+            .and_then(|id| doc.get_dictionary(id))
+            .ok();
+    }
+    None
+}
+
+/// Whether an image's samples are plain gray or RGB (no palette, CMYK or spot colours)
+pub(crate) fn is_gray_or_rgb(doc: &Document, stream: &Stream) -> bool {
+    stream.dict.get(b"ColorSpace").is_ok_and(|cs| {
+        matches!(
+            ColorSpace::parse(doc, cs),
+            Ok(ColorSpace::Gray | ColorSpace::Rgb)
+        )
+    })
+}
+
+fn int(dict: &Dictionary, key: &[u8]) -> Option<i64> {
+    dict.get(key).and_then(Object::as_i64).ok()
+}
+
+fn image_from_raw<T>(img: Option<T>) -> Result<T> {
+    img.ok_or_else(|| Error::ConversionError("PDF image data is truncated".into()))
+}
+
+enum ColorSpace {
+    Gray,
+    Rgb,
+    Cmyk,
+    Indexed { palette: Vec<Rgb<u8>> },
+}
+
+impl ColorSpace {
+    fn parse(doc: &Document, obj: &Object) -> Result<Self> {
+        let (_, obj) = doc.dereference(obj).map_err(pdf_error)?;
+
+        match obj {
+            Object::Name(name) => Self::from_name(name),
+            Object::Array(parts) => {
+                let family = parts
+                    .first()
+                    .and_then(|p| p.as_name().ok())
+                    .unwrap_or_default();
+
+                match family {
+                    // [/ICCBased stream]: fall back on the component count
+                    b"ICCBased" => {
+                        let components = parts
+                            .get(1)
+                            .and_then(|p| p.as_reference().ok())
+                            .and_then(|id| doc.get_object(id).and_then(Object::as_stream).ok())
+                            .and_then(|s| int(&s.dict, b"N"))
+                            .unwrap_or(3);
+                        Ok(match components {
+                            1 => Self::Gray,
+                            4 => Self::Cmyk,
+                            _ => Self::Rgb,
+                        })
+                    }
+                    // [/Indexed base hival lookup]
+                    b"Indexed" | b"I" => {
+                        let base = match parts.get(1) {
+                            Some(base) => Self::parse(doc, base)?,
+                            None => Self::Rgb,
+                        };
+                        let lookup = match parts.get(3).map(|l| doc.dereference(l)) {
+                            Some(Ok((_, Object::String(bytes, _)))) => bytes.clone(),
+                            Some(Ok((_, Object::Stream(s)))) => {
+                                s.get_plain_content().map_err(pdf_error)?
+                            }
+                            _ => Vec::new(),
+                        };
+                        Ok(Self::Indexed {
+                            palette: base.palette(&lookup),
+                        })
+                    }
+                    b"CalGray" => Ok(Self::Gray),
+                    b"CalRGB" | b"Lab" => Ok(Self::Rgb),
+                    other => Self::from_name(other),
+                }
+            }
+            _ => Ok(Self::Rgb),
+        }
+    }
+
+    fn from_name(name: &[u8]) -> Result<Self> {
+        match name {
+            b"DeviceGray" | b"G" | b"CalGray" => Ok(Self::Gray),
+            b"DeviceRGB" | b"RGB" | b"CalRGB" => Ok(Self::Rgb),
+            b"DeviceCMYK" | b"CMYK" => Ok(Self::Cmyk),
+            other => Err(Error::UnsupportedFormat(format!(
+                "PDF colour space {}",
+                String::from_utf8_lossy(other)
+            ))),
+        }
+    }
+
+    fn components(&self) -> usize {
+        match self {
+            Self::Gray | Self::Indexed { .. } => 1,
+            Self::Rgb => 3,
+            Self::Cmyk => 4,
+        }
+    }
+
+    /// Expand an Indexed lookup table (in this base colour space) to RGB
+    fn palette(&self, lookup: &[u8]) -> Vec<Rgb<u8>> {
+        match self {
+            Self::Gray => lookup.iter().map(|&v| Rgb([v, v, v])).collect(),
+            Self::Cmyk => lookup
+                .chunks_exact(4)
+                .map(|c| {
+                    let k = 255 - c[3] as u16;
+                    Rgb([0, 1, 2].map(|i| ((255 - c[i] as u16) * k / 255) as u8))
+                })
+                .collect(),
+            _ => lookup
+                .chunks_exact(3)
+                .map(|c| Rgb([c[0], c[1], c[2]]))
+                .collect(),
+        }
+    }
+}
+
+/// Unpack rows of `bits`-deep samples (rows padded to whole bytes) to one byte each.
+///
+/// Colour samples are scaled to 0-255; palette indices are kept as-is.
+fn unpack_samples(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    components: usize,
+    bits: u8,
+    indexed: bool,
+) -> Result<Vec<u8>> {
+    let per_row = width * components;
+    let row_bytes = (per_row * bits as usize).div_ceil(8);
+    if data.len() < row_bytes * height {
+        return Err(Error::ConversionError("PDF image data is truncated".into()));
+    }
+
+    let mut samples = Vec::with_capacity(per_row * height);
+    for row in data.chunks_exact(row_bytes).take(height) {
+        match bits {
+            8 => samples.extend_from_slice(&row[..per_row]),
+            16 => samples.extend(row.chunks_exact(2).take(per_row).map(|pair| pair[0])),
+            _ => {
+                let max = (1u16 << bits) - 1;
+                for i in 0..per_row {
+                    let bit = i * bits as usize;
+                    let shift = 8 - bits as usize - bit % 8;
+                    let value = (row[bit / 8] >> shift) as u16 & max;
+                    samples.push(if indexed {
+                        value as u8
+                    } else {
+                        (value * 255 / max) as u8
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(samples)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use lopdf::{Stream, dictionary};
+
+    /// One-page PDF whose only content is `image`, drawn full-page
+    pub(crate) fn scanned_pdf(image: Stream, media_box: [i64; 4]) -> Document {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let image_id = doc.add_object(image);
+        let content_id = doc.add_object(Stream::new(
+            dictionary! {},
+            format!("q {} 0 0 {} 0 0 cm /Im0 Do Q", media_box[2], media_box[3]).into_bytes(),
+        ));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+            "Resources" => dictionary! { "XObject" => dictionary! { "Im0" => image_id } },
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+                "MediaBox" => media_box.iter().map(|&v| v.into()).collect::<Vec<Object>>(),
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        doc
+    }
+
+    /// Move the page's drawing into a form XObject that the page draws through
+    /// `matrix`. The form also lists itself as a resource, as a cycle guard check.
+    pub(crate) fn wrap_in_form(doc: &mut Document, page_id: ObjectId, matrix: [f32; 6]) {
+        let page = doc.get_dictionary(page_id).unwrap();
+        let content_id = page
+            .get(b"Contents")
+            .and_then(Object::as_reference)
+            .unwrap();
+        let image_id = page
+            .get(b"Resources")
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"XObject"))
+            .and_then(Object::as_dict)
+            .and_then(|xobjects| xobjects.get(b"Im0"))
+            .and_then(Object::as_reference)
+            .unwrap();
+        let content = doc
+            .get_object(content_id)
+            .and_then(Object::as_stream)
+            .unwrap();
+        let mut form_content = content.content.clone();
+        form_content.extend_from_slice(b" /Fm0 Do");
+
+        let form_id = doc.new_object_id();
+        let form = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![0.into(), 0.into(), 1000.into(), 1000.into()],
+                "Matrix" => matrix.iter().map(|&v| v.into()).collect::<Vec<Object>>(),
+                "Resources" => dictionary! {
+                    "XObject" => dictionary! { "Im0" => image_id, "Fm0" => form_id },
+                },
+            },
+            form_content,
+        );
+        doc.objects.insert(form_id, Object::Stream(form));
+        let content_id = doc.add_object(Stream::new(dictionary! {}, b"/Fm0 Do".to_vec()));
+
+        let page = doc.get_dictionary_mut(page_id).unwrap();
+        page.set("Contents", content_id);
+        page.set(
+            "Resources",
+            dictionary! { "XObject" => dictionary! { "Fm0" => form_id } },
+        );
+    }
+
+    #[test]
+    fn test_unpack_one_bit_rows_are_byte_padded() {
+        // 3 pixels per row: 101 padded to 1010_0000, then 010 padded
+        let samples = unpack_samples(&[0b1010_0000, 0b0100_0000], 3, 2, 1, 1, false).unwrap();
+        assert_eq!(samples, [255, 0, 255, 0, 255, 0]);
+    }
+
+    #[test]
+    fn test_render_flate_gray_page() {
+        let mut stream = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 4,
+                "Height" => 2,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![0, 0, 0, 0, 200, 200, 200, 200],
+        );
+        stream.compress().unwrap();
+        let doc = scanned_pdf(stream, [0, 0, 72, 36]);
+        let page_id = doc.get_pages()[&1];
+
+        let page = render_page(&doc, page_id, 144.0).unwrap().unwrap();
+        assert_eq!((page.width(), page.height()), (144, 72));
+        let luma = page.to_luma8();
+        assert!(luma.get_pixel(70, 5).0[0] < 20);
+        assert!(luma.get_pixel(70, 66).0[0] > 180);
+    }
+
+    #[test]
+    fn test_render_jpeg_page_with_rotation() {
+        let mut jpeg = Vec::new();
+        DynamicImage::new_rgb8(40, 20)
+            .write_to(&mut Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+        let stream = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 40,
+                "Height" => 20,
+                "ColorSpace" => "DeviceRGB",
+                "BitsPerComponent" => 8,
+                "Filter" => "DCTDecode",
+            },
+            jpeg,
+        );
+        let mut doc = scanned_pdf(stream, [0, 0, 40, 20]);
+        let page_id = doc.get_pages()[&1];
+        doc.get_dictionary_mut(page_id).unwrap().set("Rotate", 90);
+
+        let page = render_page(&doc, page_id, 72.0).unwrap().unwrap();
+        assert_eq!((page.width(), page.height()), (20, 40));
+    }
+
+    #[test]
+    fn test_render_draws_every_placed_image() {
+        let swatch = |value: u8| {
+            Stream::new(
+                dictionary! {
+                    "Subtype" => "Image",
+                    "Width" => 1,
+                    "Height" => 1,
+                    "ColorSpace" => "DeviceGray",
+                    "BitsPerComponent" => 8,
+                },
+                vec![value],
+            )
+        };
+        // Black on the left half, gray in the top right quarter
+        let mut doc = scanned_pdf(swatch(0), [0, 0, 100, 50]);
+        let page_id = doc.get_pages()[&1];
+        let gray_id = doc.add_object(swatch(128));
+        let content = b"q 50 0 0 50 0 0 cm /Im0 Do Q q 50 0 0 25 50 25 cm /Im1 Do Q".to_vec();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let page = doc.get_dictionary_mut(page_id).unwrap();
+        page.set("Contents", content_id);
+        page.get_mut(b"Resources")
+            .and_then(Object::as_dict_mut)
+            .and_then(|resources| resources.get_mut(b"XObject"))
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("Im1", gray_id);
+
+        let luma = render_page(&doc, page_id, 72.0)
+            .unwrap()
+            .unwrap()
+            .to_luma8();
+        assert_eq!(luma.dimensions(), (100, 50));
+        assert_eq!(luma.get_pixel(10, 40).0[0], 0);
+        assert_eq!(luma.get_pixel(75, 10).0[0], 128);
+        assert_eq!(luma.get_pixel(75, 40).0[0], 255);
+    }
+
+    #[test]
+    fn test_images_inside_forms_are_found_and_placed() {
+        let black = Stream::new(
+            dictionary! {
+                "Subtype" => "Image",
+                "Width" => 1,
+                "Height" => 1,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![0],
+        );
+        let mut doc = scanned_pdf(black, [0, 0, 100, 50]);
+        let page_id = doc.get_pages()[&1];
+        // The form squeezes the full-page image into the right half
+        wrap_in_form(&mut doc, page_id, [0.5, 0.0, 0.0, 1.0, 50.0, 0.0]);
+
+        assert_eq!(page_images(&doc, page_id).len(), 1);
+        let luma = render_page(&doc, page_id, 72.0)
+            .unwrap()
+            .unwrap()
+            .to_luma8();
+        assert_eq!(luma.get_pixel(75, 25).0[0], 0);
+        assert_eq!(luma.get_pixel(25, 25).0[0], 255);
+    }
+
+    #[test]
+    fn test_render_rejects_oversized_page() {
+        let swatch = Stream::new(
+            dictionary! {
+                "Subtype" => "Image",
+                "Width" => 1,
+                "Height" => 1,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![0],
+        );
+        // The largest page PDF allows, at print resolution: about 10 GB of RGB
+        let doc = scanned_pdf(swatch.clone(), [0, 0, 14_400, 14_400]);
+        let page_id = doc.get_pages()[&1];
+        assert!(matches!(
+            render_page(&doc, page_id, 300.0),
+            Err(Error::ConversionError(_))
+        ));
+
+        // Sizes past u32::MAX must not wrap around
+        let doc = scanned_pdf(swatch, [0, 0, 1_000_000_000, 1_000_000_000]);
+        let page_id = doc.get_pages()[&1];
+        assert!(matches!(
+            render_page(&doc, page_id, 72.0),
+            Err(Error::ConversionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_render_page_without_images_is_none() {
+        let mut doc = scanned_pdf(Stream::new(dictionary! {}, Vec::new()), [0, 0, 72, 72]);
+        let page_id = doc.get_pages()[&1];
+        let content_id = doc.add_object(Stream::new(
+            dictionary! {},
+            b"BT /F1 12 Tf (Hi) Tj ET".to_vec(),
+        ));
+        doc.get_dictionary_mut(page_id)
+            .unwrap()
+            .set("Contents", content_id);
+
+        assert!(render_page(&doc, page_id, 72.0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_export_jpeg_is_byte_identical() {
+        let mut jpeg = Vec::new();
+        DynamicImage::new_rgb8(40, 20)
+            .write_to(&mut Cursor::new(&mut jpeg), image::ImageFormat::Jpeg)
+            .unwrap();
+        let stream = Stream::new(
+            dictionary! {
+                "Subtype" => "Image",
+                "Width" => 40,
+                "Height" => 20,
+                "Filter" => "DCTDecode",
+            },
+            jpeg.clone(),
+        );
+
+        let exported = export_image(&Document::new(), &stream).unwrap();
+        assert_eq!(exported.extension, "jpg");
+        assert!(exported.passthrough);
+        assert_eq!(exported.data, jpeg);
+    }
+
+    #[test]
+    fn test_export_flate_with_soft_mask_as_png() {
+        let mut doc = Document::new();
+        let mut mask = Stream::new(
+            dictionary! {
+                "Subtype" => "Image",
+                "Width" => 2,
+                "Height" => 1,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![0, 255],
+        );
+        mask.compress().unwrap();
+        let mask_id = doc.add_object(mask);
+        let mut stream = Stream::new(
+            dictionary! {
+                "Subtype" => "Image",
+                "Width" => 2,
+                "Height" => 1,
+                "ColorSpace" => "DeviceRGB",
+                "BitsPerComponent" => 8,
+                "SMask" => mask_id,
+            },
+            vec![255, 0, 0, 0, 0, 255],
+        );
+        stream.compress().unwrap();
+
+        let exported = export_image(&doc, &stream).unwrap();
+        assert_eq!(exported.extension, "png");
+        assert!(!exported.passthrough);
+
+        let png = image::load_from_memory(&exported.data).unwrap().to_rgba8();
+        assert_eq!(png.get_pixel(0, 0).0, [255, 0, 0, 0]);
+        assert_eq!(png.get_pixel(1, 0).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_export_jbig2_prepends_header_and_globals() {
+        let mut doc = Document::new();
+        let globals_id = doc.add_object(Stream::new(dictionary! {}, vec![0xAA, 0xBB]));
+        let stream = Stream::new(
+            dictionary! {
+                "Subtype" => "Image",
+                "Width" => 8,
+                "Height" => 8,
+                "Filter" => "JBIG2Decode",
+                "DecodeParms" => dictionary! { "JBIG2Globals" => globals_id },
+            },
+            vec![0xCC],
+        );
+
+        let exported = export_image(&doc, &stream).unwrap();
+        assert_eq!(exported.extension, "jb2");
+        assert_eq!(&exported.data[..8], &JBIG2_FILE_ID);
+        assert_eq!(&exported.data[8..], &[0x01, 0, 0, 0, 1, 0xAA, 0xBB, 0xCC]);
+    }
+}
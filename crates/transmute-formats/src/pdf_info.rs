@@ -0,0 +1,630 @@
+//! Document-level PDF metadata: the Info dictionary, the outline and page labels
+
+use crate::pdf_images::pdf_error;
+use crate::pdfa;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone};
+use lopdf::{Dictionary, Document, Object, ObjectId, dictionary};
+use std::fmt;
+use std::str::FromStr;
+use transmute_common::{Error, Result};
+
+/// Application name written as the Producer of generated PDFs
+pub const PRODUCER: &str = "Transmute";
+
+/// Document properties stored in a PDF's Info dictionary
+///
+/// Applying a `PdfInfo` leaves properties that are `None` untouched and removes
+/// those set to an empty string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PdfInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+
+    /// Comma-separated keywords
+    pub keywords: Option<String>,
+
+    /// Application the original content was made with
+    pub creator: Option<String>,
+
+    /// Application that wrote the PDF
+    pub producer: Option<String>,
+
+    pub creation_date: Option<DateTime<FixedOffset>>,
+    pub modification_date: Option<DateTime<FixedOffset>>,
+}
+
+impl PdfInfo {
+    /// Properties of `document`; text that cannot be decoded is skipped
+    pub fn read(document: &Document) -> Self {
+        let Some(dict) = info_dictionary(document) else {
+            return Self::default();
+        };
+        let text = |key: &[u8]| {
+            dict.get(key)
+                .ok()
+                .and_then(|value| document.dereference(value).ok())
+                .and_then(|(_, value)| lopdf::decode_text_string(value).ok())
+        };
+        let date = |key: &[u8]| {
+            dict.get(key)
+                .and_then(Object::as_str)
+                .ok()
+                .and_then(parse_pdf_date)
+        };
+
+        Self {
+            title: text(b"Title"),
+            author: text(b"Author"),
+            subject: text(b"Subject"),
+            keywords: text(b"Keywords"),
+            creator: text(b"Creator"),
+            producer: text(b"Producer"),
+            creation_date: date(b"CreationDate"),
+            modification_date: date(b"ModDate"),
+        }
+    }
+
+    /// Write the properties that are set into `document`'s Info dictionary.
+    ///
+    /// XMP metadata in the catalog is rewritten to match, keeping any PDF/A
+    /// identification it declares.
+    pub fn apply(&self, document: &mut Document) -> Result<()> {
+        let info_id = match document.trailer.get(b"Info") {
+            Ok(Object::Reference(id)) if document.get_dictionary(*id).is_ok() => *id,
+            existing => {
+                // Info written inline (or missing) becomes an object of its own
+                let dict = match existing {
+                    Ok(Object::Dictionary(dict)) => dict.clone(),
+                    _ => Dictionary::new(),
+                };
+                let id = document.add_object(dict);
+                document.trailer.set("Info", id);
+                id
+            }
+        };
+
+        let dict = document.get_dictionary_mut(info_id).map_err(pdf_error)?;
+        for (key, value) in self.text_fields() {
+            match value.as_deref() {
+                Some("") => {
+                    dict.remove(key);
+                }
+                Some(text) => dict.set(key, lopdf::text_string(text)),
+                None => {}
+            }
+        }
+        for (key, value) in [
+            (&b"CreationDate"[..], self.creation_date),
+            (&b"ModDate"[..], self.modification_date),
+        ] {
+            if let Some(date) = value {
+                dict.set(key, Object::string_literal(format_pdf_date(&date)));
+            }
+        }
+        pdfa::sync_metadata(document)
+    }
+
+    /// No property is set
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// These properties as written into a new document: dated now (if no dates are
+    /// set) and produced by Transmute (unless another producer is given)
+    pub fn for_new_document(&self) -> Self {
+        let mut info = self.clone();
+        let now = Local::now().fixed_offset();
+        let created = *info.creation_date.get_or_insert(now);
+        info.modification_date.get_or_insert(created);
+        info.producer.get_or_insert_with(|| PRODUCER.into());
+        info
+    }
+
+    /// These properties as written into an existing document: modified now unless
+    /// another modification date is given
+    pub fn for_edited_document(&self) -> Self {
+        let mut info = self.clone();
+        info.modification_date
+            .get_or_insert_with(|| Local::now().fixed_offset());
+        info
+    }
+
+    /// Parse a date given as `2024-01-31`, `2024-01-31 09:30[:00]` (local time),
+    /// RFC 3339, a PDF date string (`D:20240131093000Z`) or `now`
+    pub fn parse_date(text: &str) -> Result<DateTime<FixedOffset>> {
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("now") {
+            return Ok(Local::now().fixed_offset());
+        }
+        if text.starts_with("D:")
+            && let Some(date) = parse_pdf_date(text.as_bytes())
+        {
+            return Ok(date);
+        }
+        if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+            return Ok(date);
+        }
+
+        let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+            .or_else(|| {
+                NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            });
+        naive
+            .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+            .map(|date| date.fixed_offset())
+            .ok_or_else(|| {
+                Error::ConversionError(format!(
+                    "Invalid date '{}' (expected e.g. 2024-01-31, 2024-01-31 09:30 or 2024-01-31T09:30:00+01:00)",
+                    text
+                ))
+            })
+    }
+
+    fn text_fields(&self) -> [(&'static [u8], &Option<String>); 6] {
+        [
+            (b"Title", &self.title),
+            (b"Author", &self.author),
+            (b"Subject", &self.subject),
+            (b"Keywords", &self.keywords),
+            (b"Creator", &self.creator),
+            (b"Producer", &self.producer),
+        ]
+    }
+}
+
+fn info_dictionary(document: &Document) -> Option<&Dictionary> {
+    let info = document.trailer.get(b"Info").ok()?;
+    let (_, info) = document.dereference(info).ok()?;
+    info.as_dict().ok()
+}
+
+/// `date` as a PDF date string, e.g. `D:20240131093000+01'00'`
+pub fn format_pdf_date(date: &DateTime<FixedOffset>) -> String {
+    let offset = date.offset().local_minus_utc() / 60;
+    let zone = if offset == 0 {
+        "Z".to_string()
+    } else {
+        let sign = if offset < 0 { '-' } else { '+' };
+        format!("{}{:02}'{:02}'", sign, offset.abs() / 60, offset.abs() % 60)
+    };
+    format!("D:{}{}", date.format("%Y%m%d%H%M%S"), zone)
+}
+
+/// Parse a PDF date string; everything after the year is optional, and a
+/// missing time zone is taken as UTC
+pub fn parse_pdf_date(text: &[u8]) -> Option<DateTime<FixedOffset>> {
+    let text = std::str::from_utf8(text).ok()?.trim();
+    let text = text.strip_prefix("D:").unwrap_or(text);
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (stamp, zone) = text.split_at(digits);
+    if stamp.len() < 4 || stamp.len() % 2 != 0 || stamp.len() > 14 {
+        return None;
+    }
+
+    let field = |start: usize, default: u32| -> Option<u32> {
+        match stamp.get(start..start + 2) {
+            Some(value) => value.parse().ok(),
+            None => Some(default),
+        }
+    };
+    let date = NaiveDate::from_ymd_opt(stamp[..4].parse().ok()?, field(4, 1)?, field(6, 1)?)?
+        .and_hms_opt(field(8, 0)?, field(10, 0)?, field(12, 0)?)?;
+
+    let offset_seconds = match zone.chars().next() {
+        None | Some('Z') => 0,
+        Some(sign @ ('+' | '-')) => {
+            let mut parts = zone[1..].split('\'').filter(|part| !part.is_empty());
+            let hours: i32 = parts.next()?.parse().ok()?;
+            let minutes: i32 = parts.next().map_or(Some(0), |m| m.parse().ok())?;
+            let seconds = (hours * 60 + minutes) * 60;
+            if sign == '-' { -seconds } else { seconds }
+        }
+        Some(_) => return None,
+    };
+    FixedOffset::east_opt(offset_seconds)?
+        .from_local_datetime(&date)
+        .single()
+}
+
+/// How page label numbers are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PageLabelStyle {
+    /// 1, 2, 3
+    #[default]
+    Decimal,
+    /// I, II, III
+    UpperRoman,
+    /// i, ii, iii
+    LowerRoman,
+    /// A, B, ..., Z, AA
+    UpperLetters,
+    /// a, b, ..., z, aa
+    LowerLetters,
+    /// Prefix only, no number
+    None,
+}
+
+impl PageLabelStyle {
+    /// Value of the label dictionary's /S entry
+    fn pdf_name(self) -> Option<&'static str> {
+        match self {
+            Self::Decimal => Some("D"),
+            Self::UpperRoman => Some("R"),
+            Self::LowerRoman => Some("r"),
+            Self::UpperLetters => Some("A"),
+            Self::LowerLetters => Some("a"),
+            Self::None => None,
+        }
+    }
+}
+
+impl FromStr for PageLabelStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // Single-character forms are case sensitive, like the numbers they stand for
+        match s.trim() {
+            "1" => return Ok(Self::Decimal),
+            "I" => return Ok(Self::UpperRoman),
+            "i" => return Ok(Self::LowerRoman),
+            "A" => return Ok(Self::UpperLetters),
+            "a" => return Ok(Self::LowerLetters),
+            _ => {}
+        }
+        match s.trim().to_lowercase().as_str() {
+            "decimal" | "arabic" => Ok(Self::Decimal),
+            "upper-roman" => Ok(Self::UpperRoman),
+            "roman" | "lower-roman" => Ok(Self::LowerRoman),
+            "upper-letters" => Ok(Self::UpperLetters),
+            "letters" | "lower-letters" => Ok(Self::LowerLetters),
+            "none" => Ok(Self::None),
+            _ => Err(Error::UnsupportedFormat(format!(
+                "Unknown page label style '{}' (expected decimal, roman, upper-roman, letters, upper-letters or none)",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for PageLabelStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Decimal => "decimal",
+            Self::UpperRoman => "upper-roman",
+            Self::LowerRoman => "roman",
+            Self::UpperLetters => "upper-letters",
+            Self::LowerLetters => "letters",
+            Self::None => "none",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Labels viewers show instead of plain page numbers, e.g. `i, ii, iii` or `A-1, A-2`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageLabels {
+    pub style: PageLabelStyle,
+
+    /// Text before each number
+    pub prefix: String,
+
+    /// Number of the first page (default: 1)
+    pub start: u32,
+}
+
+impl Default for PageLabels {
+    fn default() -> Self {
+        Self {
+            style: PageLabelStyle::Decimal,
+            prefix: String::new(),
+            start: 1,
+        }
+    }
+}
+
+impl PageLabels {
+    /// Label of the page at 0-based `index`, as a viewer shows it
+    pub fn label(&self, index: u32) -> String {
+        let number = self.start.max(1) + index;
+        let number = match self.style {
+            PageLabelStyle::Decimal => number.to_string(),
+            PageLabelStyle::UpperRoman => roman(number),
+            PageLabelStyle::LowerRoman => roman(number).to_lowercase(),
+            PageLabelStyle::UpperLetters => letters(number),
+            PageLabelStyle::LowerLetters => letters(number).to_lowercase(),
+            PageLabelStyle::None => String::new(),
+        };
+        format!("{}{}", self.prefix, number)
+    }
+
+    /// Label every page of `document` this way
+    pub fn apply(&self, document: &mut Document) -> Result<()> {
+        let mut label = Dictionary::new();
+        if let Some(name) = self.style.pdf_name() {
+            label.set("S", Object::Name(name.into()));
+        }
+        if !self.prefix.is_empty() {
+            label.set("P", lopdf::text_string(&self.prefix));
+        }
+        if self.start > 1 {
+            label.set("St", self.start as i64);
+        }
+
+        document.catalog_mut().map_err(pdf_error)?.set(
+            "PageLabels",
+            dictionary! { "Nums" => vec![0.into(), Object::Dictionary(label)] },
+        );
+        Ok(())
+    }
+}
+
+fn roman(mut number: u32) -> String {
+    const NUMERALS: [(u32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut text = String::new();
+    for (value, numeral) in NUMERALS {
+        while number >= value {
+            text.push_str(numeral);
+            number -= value;
+        }
+    }
+    text
+}
+
+/// A to Z, then AA to ZZ, then AAA..., as PDF viewers count
+fn letters(number: u32) -> String {
+    let letter = (b'A' + ((number - 1) % 26) as u8) as char;
+    letter.to_string().repeat(((number - 1) / 26 + 1) as usize)
+}
+
+/// Outline entry jumping to a point on a page
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Bookmark {
+    pub title: String,
+    /// 1-based page number
+    pub page: u32,
+    /// Top-left corner of the target, in points from the bottom-left of the page
+    pub left_pt: f32,
+    pub top_pt: f32,
+}
+
+/// Replace `document`'s outline with a flat list of `bookmarks`
+pub(crate) fn set_outline(document: &mut Document, bookmarks: &[Bookmark]) -> Result<()> {
+    if bookmarks.is_empty() {
+        return Ok(());
+    }
+
+    let pages = document.get_pages();
+    let outline_id = document.new_object_id();
+    let item_ids: Vec<ObjectId> = bookmarks.iter().map(|_| document.new_object_id()).collect();
+
+    for (index, bookmark) in bookmarks.iter().enumerate() {
+        let page_id = *pages.get(&bookmark.page).ok_or_else(|| {
+            Error::ConversionError(format!("Bookmark to missing page {}", bookmark.page))
+        })?;
+        let mut item = dictionary! {
+            "Title" => lopdf::text_string(&bookmark.title),
+            "Parent" => outline_id,
+            "Dest" => vec![
+                page_id.into(),
+                Object::Name(b"XYZ".to_vec()),
+                bookmark.left_pt.into(),
+                bookmark.top_pt.into(),
+                Object::Null,
+            ],
+        };
+        if index > 0 {
+            item.set("Prev", item_ids[index - 1]);
+        }
+        if let Some(&next) = item_ids.get(index + 1) {
+            item.set("Next", next);
+        }
+        document
+            .objects
+            .insert(item_ids[index], Object::Dictionary(item));
+    }
+
+    document.objects.insert(
+        outline_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => item_ids[0],
+            "Last" => item_ids[item_ids.len() - 1],
+            "Count" => item_ids.len() as i64,
+        }),
+    );
+
+    let catalog = document.catalog_mut().map_err(pdf_error)?;
+    catalog.set("Outlines", outline_id);
+    // Open the outline panel when there is more than one entry to jump between
+    if bookmarks.len() > 1 {
+        catalog.set("PageMode", Object::Name(b"UseOutlines".to_vec()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_document() -> Document {
+        let mut document = Document::with_version("1.7");
+        let pages_id = document.new_object_id();
+        let page_id = document.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id });
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id =
+            document.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        document.trailer.set("Root", catalog_id);
+        document
+    }
+
+    #[test]
+    fn test_pdf_dates() {
+        let date = PdfInfo::parse_date("2024-01-31T09:30:15+05:30").unwrap();
+        assert_eq!(format_pdf_date(&date), "D:20240131093015+05'30'");
+        assert_eq!(parse_pdf_date(b"D:20240131093015+05'30'"), Some(date));
+
+        let utc = parse_pdf_date(b"D:2023").unwrap();
+        assert_eq!(format_pdf_date(&utc), "D:20230101000000Z");
+        assert_eq!(
+            parse_pdf_date(b"D:19991231235959-08'00").map(|d| d.to_rfc3339()),
+            Some("1999-12-31T23:59:59-08:00".into())
+        );
+        for invalid in [&b"D:20241"[..], b"D:20241301", b"yesterday", b"D:2024+x"] {
+            assert_eq!(parse_pdf_date(invalid), None, "{:?}", invalid);
+        }
+
+        assert!(PdfInfo::parse_date("2024-02-29").is_ok());
+        assert!(PdfInfo::parse_date("2024-02-29 18:05").is_ok());
+        assert!(PdfInfo::parse_date("2023-02-29").is_err());
+        assert_eq!(PdfInfo::parse_date("D:2023").unwrap(), utc);
+    }
+
+    #[test]
+    fn test_info_round_trip_and_removal() {
+        let mut document = empty_document();
+        let created = PdfInfo::parse_date("2024-05-01T12:00:00Z").unwrap();
+        let info = PdfInfo {
+            title: Some("Holiday 2024".into()),
+            author: Some("Zoë Ångström".into()),
+            keywords: Some("beach, family".into()),
+            creation_date: Some(created),
+            ..Default::default()
+        };
+        info.apply(&mut document).unwrap();
+        assert_eq!(PdfInfo::read(&document), info);
+
+        // Non-ASCII text is stored as UTF-16 with a byte order mark
+        let stored = info_dictionary(&document).unwrap().get(b"Author").unwrap();
+        assert!(stored.as_str().unwrap().starts_with(b"\xFE\xFF"));
+
+        PdfInfo {
+            title: Some("Holiday".into()),
+            keywords: Some(String::new()),
+            ..Default::default()
+        }
+        .apply(&mut document)
+        .unwrap();
+        let edited = PdfInfo::read(&document);
+        assert_eq!(edited.title.as_deref(), Some("Holiday"));
+        assert_eq!(edited.author, info.author);
+        assert_eq!(edited.keywords, None);
+        assert_eq!(edited.creation_date, Some(created));
+    }
+
+    #[test]
+    fn test_new_document_defaults() {
+        let info = PdfInfo::default().for_new_document();
+        assert_eq!(info.producer.as_deref(), Some(PRODUCER));
+        assert!(info.creation_date.is_some());
+        assert_eq!(info.modification_date, info.creation_date);
+
+        let custom = PdfInfo {
+            producer: Some("Scanner".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            custom.for_new_document().producer.as_deref(),
+            Some("Scanner")
+        );
+    }
+
+    #[test]
+    fn test_page_labels() {
+        let roman = PageLabels {
+            style: "roman".parse().unwrap(),
+            ..Default::default()
+        };
+        let labels: Vec<String> = [0, 3, 8, 13].iter().map(|&i| roman.label(i)).collect();
+        assert_eq!(labels, ["i", "iv", "ix", "xiv"]);
+
+        let appendix = PageLabels {
+            style: "A".parse().unwrap(),
+            prefix: "App. ".into(),
+            start: 26,
+        };
+        assert_eq!(appendix.label(0), "App. Z");
+        assert_eq!(appendix.label(1), "App. AA");
+        assert!("roman numerals".parse::<PageLabelStyle>().is_err());
+
+        let mut document = empty_document();
+        appendix.apply(&mut document).unwrap();
+        let labels = document.catalog().unwrap().get(b"PageLabels").unwrap();
+        let nums = labels
+            .as_dict()
+            .unwrap()
+            .get(b"Nums")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        let first = nums[1].as_dict().unwrap();
+        assert_eq!(first.get(b"S").unwrap().as_name().unwrap(), b"A");
+        assert_eq!(first.get(b"St").unwrap().as_i64().unwrap(), 26);
+    }
+
+    #[test]
+    fn test_outline() {
+        let mut document = empty_document();
+        let bookmarks = [
+            Bookmark {
+                title: "first".into(),
+                page: 1,
+                left_pt: 10.0,
+                top_pt: 800.0,
+            },
+            Bookmark {
+                title: "café".into(),
+                page: 1,
+                left_pt: 10.0,
+                top_pt: 400.0,
+            },
+        ];
+        set_outline(&mut document, &bookmarks).unwrap();
+
+        let catalog = document.catalog().unwrap();
+        let outline_id = catalog.get(b"Outlines").unwrap().as_reference().unwrap();
+        let outline = document.get_dictionary(outline_id).unwrap();
+        assert_eq!(outline.get(b"Count").unwrap().as_i64().unwrap(), 2);
+
+        let last_id = outline.get(b"Last").unwrap().as_reference().unwrap();
+        let last = document.get_dictionary(last_id).unwrap();
+        assert_eq!(
+            lopdf::decode_text_string(last.get(b"Title").unwrap()).unwrap(),
+            "café"
+        );
+        assert!(last.get(b"Prev").is_ok() && last.get(b"Next").is_err());
+
+        let missing = [Bookmark {
+            page: 2,
+            ..bookmarks[0].clone()
+        }];
+        assert!(set_outline(&mut empty_document(), &missing).is_err());
+    }
+}
@@ -0,0 +1,601 @@
+//! Page geometry for images placed on PDF pages: sizes, orientation, margins and fit
+
+use crate::pdf::PdfOptions;
+use std::fmt;
+use std::str::FromStr;
+use transmute_common::{Error, Result};
+
+const MM_PER_INCH: f32 = 25.4;
+
+/// Paper size of generated pages
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PageSize {
+    A3,
+    #[default]
+    A4,
+    A5,
+    Letter,
+    Legal,
+
+    /// Any size, in millimetres
+    Custom {
+        width_mm: f32,
+        height_mm: f32,
+    },
+}
+
+impl PageSize {
+    /// Width and height in millimetres
+    pub fn dimensions_mm(self) -> (f32, f32) {
+        match self {
+            Self::A3 => (297.0, 420.0),
+            Self::A4 => (210.0, 297.0),
+            Self::A5 => (148.0, 210.0),
+            Self::Letter => (215.9, 279.4),
+            Self::Legal => (215.9, 355.6),
+            Self::Custom {
+                width_mm,
+                height_mm,
+            } => (width_mm, height_mm),
+        }
+    }
+}
+
+impl FromStr for PageSize {
+    type Err = Error;
+
+    /// Parse a named size (`a4`, `letter`, ...) or `WIDTHxHEIGHT` in millimetres
+    fn from_str(text: &str) -> Result<Self> {
+        let text = text.trim().to_lowercase();
+        let size = match text.as_str() {
+            "a3" => Self::A3,
+            "a4" => Self::A4,
+            "a5" => Self::A5,
+            "letter" => Self::Letter,
+            "legal" => Self::Legal,
+            custom => {
+                let invalid = || {
+                    Error::ConversionError(format!(
+                        "Unknown page size '{}' (expected a3, a4, a5, letter, legal or WIDTHxHEIGHT in mm)",
+                        text
+                    ))
+                };
+                let (width, height) = custom
+                    .trim_end_matches("mm")
+                    .split_once('x')
+                    .ok_or_else(invalid)?;
+                let width_mm: f32 = width.trim().parse().map_err(|_| invalid())?;
+                let height_mm: f32 = height.trim().parse().map_err(|_| invalid())?;
+                if [width_mm, height_mm]
+                    .iter()
+                    .any(|mm| !mm.is_finite() || *mm <= 0.0)
+                {
+                    return Err(invalid());
+                }
+                Self::Custom {
+                    width_mm,
+                    height_mm,
+                }
+            }
+        };
+        Ok(size)
+    }
+}
+
+impl fmt::Display for PageSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::A3 => write!(f, "A3"),
+            Self::A4 => write!(f, "A4"),
+            Self::A5 => write!(f, "A5"),
+            Self::Letter => write!(f, "Letter"),
+            Self::Legal => write!(f, "Legal"),
+            Self::Custom {
+                width_mm,
+                height_mm,
+            } => write!(f, "{}x{}mm", width_mm, height_mm),
+        }
+    }
+}
+
+/// Which way round pages are turned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PageOrientation {
+    /// Landscape for wide images, portrait otherwise
+    #[default]
+    Auto,
+    Portrait,
+    Landscape,
+}
+
+impl FromStr for PageOrientation {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "portrait" => Ok(Self::Portrait),
+            "landscape" => Ok(Self::Landscape),
+            _ => Err(Error::ConversionError(format!(
+                "Unknown orientation '{}' (expected auto, portrait or landscape)",
+                text.trim()
+            ))),
+        }
+    }
+}
+
+/// How an image is sized on its page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FitMode {
+    /// Scale to fit inside the margins, keeping the whole image
+    #[default]
+    Fit,
+
+    /// Scale to cover the area inside the margins, cropping the overflow
+    Fill,
+
+    /// Print at its size at the configured DPI, shrunk only if it would not fit
+    Original,
+
+    /// Make the page the image's size at the configured DPI, plus margins
+    ImageSize,
+}
+
+impl FromStr for FitMode {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "fit" => Ok(Self::Fit),
+            "fill" | "crop" => Ok(Self::Fill),
+            "original" | "actual" => Ok(Self::Original),
+            "image" | "image-size" => Ok(Self::ImageSize),
+            _ => Err(Error::ConversionError(format!(
+                "Unknown fit mode '{}' (expected fit, fill, original or image)",
+                text.trim()
+            ))),
+        }
+    }
+}
+
+/// Rows and columns of images on each page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grid {
+    pub rows: u32,
+    pub columns: u32,
+}
+
+impl Grid {
+    pub fn new(rows: u32, columns: u32) -> Result<Self> {
+        if rows == 0 || columns == 0 {
+            return Err(Error::ConversionError(format!(
+                "A grid needs at least one row and column, got {}x{}",
+                rows, columns
+            )));
+        }
+        Ok(Self { rows, columns })
+    }
+
+    /// Images per page
+    pub fn cells(self) -> usize {
+        self.rows as usize * self.columns as usize
+    }
+
+    pub fn is_single(self) -> bool {
+        self.cells() == 1
+    }
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            rows: 1,
+            columns: 1,
+        }
+    }
+}
+
+impl FromStr for Grid {
+    type Err = Error;
+
+    /// Parse `ROWSxCOLUMNS` (e.g. `2x2`) or an N-up count (`2-up`, `4up`)
+    fn from_str(text: &str) -> Result<Self> {
+        let text = text.trim().to_lowercase();
+        let invalid = || {
+            Error::ConversionError(format!(
+                "Invalid grid '{}' (expected ROWSxCOLUMNS like 2x2, or 2-up, 4-up, 6-up, 8-up, 9-up, 16-up)",
+                text
+            ))
+        };
+
+        if let Some(count) = text.strip_suffix("up") {
+            return match count.trim_end_matches('-') {
+                "1" => Self::new(1, 1),
+                "2" => Self::new(2, 1),
+                "4" => Self::new(2, 2),
+                "6" => Self::new(3, 2),
+                "8" => Self::new(4, 2),
+                "9" => Self::new(3, 3),
+                "16" => Self::new(4, 4),
+                _ => Err(invalid()),
+            };
+        }
+
+        let (rows, columns) = text.split_once('x').ok_or_else(invalid)?;
+        Self::new(
+            rows.trim().parse().map_err(|_| invalid())?,
+            columns.trim().parse().map_err(|_| invalid())?,
+        )
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.rows, self.columns)
+    }
+}
+
+/// Where one image lands on its page, in millimetres from the bottom-left corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageLayout {
+    pub page_width_mm: f32,
+    pub page_height_mm: f32,
+    pub x_mm: f32,
+    pub y_mm: f32,
+    pub width_mm: f32,
+    pub height_mm: f32,
+
+    /// Part of the image to keep as `[x, y, width, height]` fractions from its
+    /// top-left corner; `None` keeps the whole image
+    pub crop: Option<[f32; 4]>,
+}
+
+impl PageLayout {
+    /// Lay out a `width_px` x `height_px` image alone on its page, ignoring the grid
+    pub fn new(options: &PdfOptions, width_px: u32, height_px: u32) -> Result<Self> {
+        SheetLayout::build(options, Grid::default(), false, width_px, height_px)
+            .map(|sheet| sheet.place(0, width_px, height_px))
+    }
+
+    /// Pixel rectangle `(x, y, width, height)` of [`Self::crop`] in a
+    /// `width_px` x `height_px` image
+    pub fn crop_pixels(&self, width_px: u32, height_px: u32) -> Option<(u32, u32, u32, u32)> {
+        self.crop.map(|[x, y, width, height]| {
+            let scale = |fraction: f32, size: u32| (fraction * size as f32).round() as u32;
+            let crop_width = scale(width, width_px).clamp(1, width_px);
+            let crop_height = scale(height, height_px).clamp(1, height_px);
+            (
+                scale(x, width_px).min(width_px - crop_width),
+                scale(y, height_px).min(height_px - crop_height),
+                crop_width,
+                crop_height,
+            )
+        })
+    }
+}
+
+/// Height of the caption strip under each image, in millimetres
+const CAPTION_HEIGHT_MM: f32 = 5.0;
+
+/// The cells of one page of images laid out on a [`Grid`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetLayout {
+    pub page_width_mm: f32,
+    pub page_height_mm: f32,
+
+    /// Image area of each cell in reading order, as `[x, y, width, height]` in
+    /// millimetres from the bottom-left corner
+    pub cells: Vec<[f32; 4]>,
+
+    /// Height of the caption strip directly below each image area (0 without captions)
+    pub caption_height_mm: f32,
+
+    fit: FitMode,
+    dpi: f32,
+}
+
+impl SheetLayout {
+    /// Lay out a page of `options.grid`; `width_px` x `height_px` is its first image,
+    /// which decides automatic orientation and image-sized pages when the grid has one cell
+    pub fn new(options: &PdfOptions, width_px: u32, height_px: u32) -> Result<Self> {
+        Self::build(options, options.grid, options.captions, width_px, height_px)
+    }
+
+    fn build(
+        options: &PdfOptions,
+        grid: Grid,
+        captions: bool,
+        width_px: u32,
+        height_px: u32,
+    ) -> Result<Self> {
+        if width_px == 0 || height_px == 0 {
+            return Err(Error::ConversionError(
+                "Cannot lay out an empty image".into(),
+            ));
+        }
+        if !options.dpi.is_finite() || options.dpi <= 0.0 {
+            return Err(Error::ConversionError(format!(
+                "DPI must be positive, got {}",
+                options.dpi
+            )));
+        }
+
+        let margin = options.margin_mm.max(0.0);
+        let spacing = options.spacing_mm.max(0.0);
+        let caption_height = if captions { CAPTION_HEIGHT_MM } else { 0.0 };
+
+        if options.fit == FitMode::ImageSize {
+            if !grid.is_single() {
+                return Err(Error::ConversionError(
+                    "Image-sized pages hold a single image; pick another fit for a grid".into(),
+                ));
+            }
+            let width = width_px as f32 / options.dpi * MM_PER_INCH;
+            let height = height_px as f32 / options.dpi * MM_PER_INCH;
+            return Ok(Self {
+                page_width_mm: width + 2.0 * margin,
+                page_height_mm: height + caption_height + 2.0 * margin,
+                cells: vec![[margin, margin + caption_height, width, height]],
+                caption_height_mm: caption_height,
+                fit: options.fit,
+                dpi: options.dpi,
+            });
+        }
+
+        let (short, long) = {
+            let (width, height) = options.resolved_page_size().dimensions_mm();
+            (width.min(height), width.max(height))
+        };
+        let landscape = match options.orientation {
+            PageOrientation::Auto if grid.is_single() => width_px > height_px,
+            PageOrientation::Auto => grid.columns > grid.rows,
+            PageOrientation::Portrait => false,
+            PageOrientation::Landscape => true,
+        };
+        let (page_width, page_height) = if landscape {
+            (long, short)
+        } else {
+            (short, long)
+        };
+
+        let cell_width =
+            (page_width - 2.0 * margin - spacing * (grid.columns - 1) as f32) / grid.columns as f32;
+        let cell_height =
+            (page_height - 2.0 * margin - spacing * (grid.rows - 1) as f32) / grid.rows as f32;
+        if cell_width <= 0.0 || cell_height - caption_height <= 0.0 {
+            return Err(Error::ConversionError(format!(
+                "A {} mm margin and {} mm spacing leave no room for a {} grid on a {}x{} mm page",
+                margin, spacing, grid, page_width, page_height
+            )));
+        }
+
+        let mut cells = Vec::with_capacity(grid.cells());
+        for row in 0..grid.rows {
+            for column in 0..grid.columns {
+                let x = margin + column as f32 * (cell_width + spacing);
+                // Rows run down from the top of the page
+                let bottom =
+                    page_height - margin - (row + 1) as f32 * cell_height - row as f32 * spacing;
+                cells.push([
+                    x,
+                    bottom + caption_height,
+                    cell_width,
+                    cell_height - caption_height,
+                ]);
+            }
+        }
+
+        Ok(Self {
+            page_width_mm: page_width,
+            page_height_mm: page_height,
+            cells,
+            caption_height_mm: caption_height,
+            fit: options.fit,
+            dpi: options.dpi,
+        })
+    }
+
+    /// Place a `width_px` x `height_px` image in cell `cell`, centered
+    pub fn place(&self, cell: usize, width_px: u32, height_px: u32) -> PageLayout {
+        let [area_x, area_y, area_width, area_height] = self.cells[cell];
+        let natural_width = width_px as f32 / self.dpi * MM_PER_INCH;
+        let natural_height = height_px as f32 / self.dpi * MM_PER_INCH;
+
+        let image_aspect = width_px as f32 / height_px as f32;
+        let area_aspect = area_width / area_height;
+        let mut crop = None;
+        let (width, height) = match self.fit {
+            FitMode::Fit if image_aspect > area_aspect => (area_width, area_width / image_aspect),
+            FitMode::Fit => (area_height * image_aspect, area_height),
+            FitMode::Fill => {
+                if image_aspect > area_aspect {
+                    let kept = area_aspect / image_aspect;
+                    crop = Some([(1.0 - kept) / 2.0, 0.0, kept, 1.0]);
+                } else if image_aspect < area_aspect {
+                    let kept = image_aspect / area_aspect;
+                    crop = Some([0.0, (1.0 - kept) / 2.0, 1.0, kept]);
+                }
+                (area_width, area_height)
+            }
+            FitMode::Original | FitMode::ImageSize => {
+                let shrink = (area_width / natural_width)
+                    .min(area_height / natural_height)
+                    .min(1.0);
+                (natural_width * shrink, natural_height * shrink)
+            }
+        };
+
+        PageLayout {
+            page_width_mm: self.page_width_mm,
+            page_height_mm: self.page_height_mm,
+            x_mm: area_x + (area_width - width) / 2.0,
+            y_mm: area_y + (area_height - height) / 2.0,
+            width_mm: width,
+            height_mm: height,
+            crop,
+        }
+    }
+
+    /// Caption strip below cell `cell`, as `[x, y, width, height]` in millimetres
+    pub fn caption_area(&self, cell: usize) -> [f32; 4] {
+        let [x, y, width, _] = self.cells[cell];
+        [x, y - self.caption_height_mm, width, self.caption_height_mm]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn test_parse_page_settings() {
+        assert_eq!("Letter".parse::<PageSize>().unwrap(), PageSize::Letter);
+        assert_eq!(
+            "100x150mm".parse::<PageSize>().unwrap(),
+            PageSize::Custom {
+                width_mm: 100.0,
+                height_mm: 150.0
+            }
+        );
+        assert!("b5".parse::<PageSize>().is_err());
+        assert!("0x10".parse::<PageSize>().is_err());
+
+        assert_eq!(
+            "landscape".parse::<PageOrientation>().unwrap(),
+            PageOrientation::Landscape
+        );
+        assert_eq!("crop".parse::<FitMode>().unwrap(), FitMode::Fill);
+        assert!("stretch".parse::<FitMode>().is_err());
+    }
+
+    #[test]
+    fn test_fit_and_orientation() {
+        let options = PdfOptions::default();
+
+        // Wide image on an auto-oriented A4 page: landscape, fit to width inside 10 mm margins
+        let wide = PageLayout::new(&options, 2000, 1000).unwrap();
+        assert_eq!((wide.page_width_mm, wide.page_height_mm), (297.0, 210.0));
+        assert!(close(wide.width_mm, 277.0) && close(wide.height_mm, 138.5));
+        assert!(close(wide.y_mm, (210.0 - 138.5) / 2.0));
+        assert!(wide.crop.is_none());
+
+        let portrait = PdfOptions {
+            orientation: PageOrientation::Portrait,
+            margin_mm: 0.0,
+            ..Default::default()
+        };
+        let layout = PageLayout::new(&portrait, 2000, 1000).unwrap();
+        assert_eq!(
+            (layout.page_width_mm, layout.page_height_mm),
+            (210.0, 297.0)
+        );
+        assert!(close(layout.x_mm, 0.0) && close(layout.width_mm, 210.0));
+
+        let huge_margin = PdfOptions {
+            margin_mm: 150.0,
+            ..Default::default()
+        };
+        assert!(PageLayout::new(&huge_margin, 10, 10).is_err());
+    }
+
+    #[test]
+    fn test_fill_crops_overflow() {
+        let options = PdfOptions {
+            orientation: PageOrientation::Portrait,
+            margin_mm: 0.0,
+            fit: FitMode::Fill,
+            ..Default::default()
+        };
+        let layout = PageLayout::new(&options, 1000, 1000).unwrap();
+        assert!(close(layout.width_mm, 210.0) && close(layout.height_mm, 297.0));
+
+        // A square image keeps 210/297 of its width, centered
+        let (x, y, width, height) = layout.crop_pixels(1000, 1000).unwrap();
+        assert_eq!((y, height), (0, 1000));
+        assert_eq!(width, 707);
+        assert_eq!(x, 146);
+    }
+
+    #[test]
+    fn test_original_and_image_sized_pages() {
+        let options = PdfOptions {
+            dpi: 254.0,
+            fit: FitMode::Original,
+            ..Default::default()
+        };
+        // 1000 px at 254 DPI is 100 mm
+        let layout = PageLayout::new(&options, 1000, 500).unwrap();
+        assert!(close(layout.width_mm, 100.0) && close(layout.height_mm, 50.0));
+
+        // Too large for the page: shrunk to fit
+        let layout = PageLayout::new(&options, 10_000, 500).unwrap();
+        assert!(close(layout.width_mm, 277.0));
+
+        let options = PdfOptions {
+            fit: FitMode::ImageSize,
+            margin_mm: 5.0,
+            ..options
+        };
+        let layout = PageLayout::new(&options, 1000, 500).unwrap();
+        assert!(close(layout.page_width_mm, 110.0) && close(layout.page_height_mm, 60.0));
+        assert_eq!((layout.x_mm, layout.y_mm), (5.0, 5.0));
+    }
+
+    #[test]
+    fn test_parse_grid() {
+        assert_eq!("2x3".parse::<Grid>().unwrap(), Grid::new(2, 3).unwrap());
+        assert_eq!("4-up".parse::<Grid>().unwrap(), Grid::new(2, 2).unwrap());
+        assert_eq!("2up".parse::<Grid>().unwrap(), Grid::new(2, 1).unwrap());
+        for spec in ["0x2", "3-up", "2x", "two"] {
+            assert!(spec.parse::<Grid>().is_err(), "{} should not parse", spec);
+        }
+
+        // The cell count does not fit a u32
+        let grid = "65536x65536".parse::<Grid>().unwrap();
+        assert_eq!(grid.cells(), 1 << 32);
+    }
+
+    #[test]
+    fn test_grid_cells_with_captions() {
+        let options = PdfOptions {
+            grid: Grid::new(2, 2).unwrap(),
+            spacing_mm: 10.0,
+            captions: true,
+            ..Default::default()
+        };
+        let sheet = SheetLayout::new(&options, 100, 100).unwrap();
+        // 2x2 stays portrait; cells are (210 - 20 - 10) / 2 = 90 wide, (297 - 20 - 10) / 2 = 133.5 tall
+        assert_eq!((sheet.page_width_mm, sheet.page_height_mm), (210.0, 297.0));
+        assert_eq!(sheet.cells.len(), 4);
+        let [x, y, width, height] = sheet.cells[1];
+        assert!(close(x, 110.0) && close(width, 90.0));
+        assert!(close(height, 133.5 - CAPTION_HEIGHT_MM));
+        assert!(close(y + height, 287.0));
+        let [_, caption_y, _, caption_height] = sheet.caption_area(1);
+        assert!(close(caption_y + caption_height, y));
+
+        // A square image in a tall cell fits its width and is centered vertically
+        let placed = sheet.place(2, 100, 100);
+        assert!(close(placed.width_mm, 90.0) && close(placed.height_mm, 90.0));
+        assert!(close(placed.x_mm, 10.0));
+        let [_, cell_y, _, cell_height] = sheet.cells[2];
+        assert!(close(placed.y_mm - cell_y, (cell_height - 90.0) / 2.0));
+
+        let wide_grid = PdfOptions {
+            grid: Grid::new(1, 2).unwrap(),
+            ..Default::default()
+        };
+        let sheet = SheetLayout::new(&wide_grid, 100, 1000).unwrap();
+        assert_eq!((sheet.page_width_mm, sheet.page_height_mm), (297.0, 210.0));
+
+        let image_sized = PdfOptions {
+            fit: FitMode::ImageSize,
+            ..options
+        };
+        assert!(SheetLayout::new(&image_sized, 100, 100).is_err());
+    }
+}
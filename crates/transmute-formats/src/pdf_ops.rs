@@ -0,0 +1,402 @@
+//! Page-level editing of existing PDFs through lopdf: merge, split, reorder,
+//! rotate and delete without rasterizing anything
+
+use crate::pdf_compress::{self, PdfImageStats};
+use crate::pdf_images::{self, pdf_error};
+use crate::pdf_info::PdfInfo;
+use crate::pdf_security::{self, PdfEncryption};
+use image::DynamicImage;
+use lopdf::{Document, Object, ObjectId};
+use std::path::Path;
+use transmute_common::{Error, PageSelection, Result};
+
+/// Page attributes a page may inherit from its ancestors in the page tree
+const INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// A page-level edit, applied with [`PdfPages::apply`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageEdit {
+    /// Keep only these pages, in the order given
+    Select(PageSelection),
+
+    /// Move these pages to the front in the order given; the others follow unchanged
+    Reorder(PageSelection),
+
+    /// Turn pages clockwise by a multiple of 90 degrees
+    Rotate { pages: PageSelection, degrees: i32 },
+
+    /// Remove these pages
+    Delete(PageSelection),
+}
+
+/// An existing PDF opened for page-level edits
+#[derive(Debug, Clone)]
+pub struct PdfPages {
+    document: Document,
+    encryption: Option<PdfEncryption>,
+}
+
+impl PdfPages {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::open(path, None)
+    }
+
+    /// Load a PDF, decrypting it with `password` if it is protected
+    pub fn open(path: &Path, password: Option<&str>) -> Result<Self> {
+        Self::from_document(pdf_security::load(path, password)?)
+    }
+
+    pub fn load_mem(bytes: &[u8]) -> Result<Self> {
+        Self::from_document(Document::load_mem(bytes).map_err(pdf_error)?)
+    }
+
+    fn from_document(document: Document) -> Result<Self> {
+        if document.is_encrypted() {
+            return Err(Error::ConversionError(
+                "Cannot edit an encrypted PDF".into(),
+            ));
+        }
+
+        let mut pages = Self {
+            document,
+            encryption: None,
+        };
+        // Give every page its inherited attributes and hang it directly off the root
+        pages.set_pages(pages.page_ids())?;
+        Ok(pages)
+    }
+
+    pub fn page_count(&self) -> u32 {
+        self.document.get_pages().len() as u32
+    }
+
+    pub fn apply(&mut self, edit: &PageEdit) -> Result<()> {
+        match edit {
+            PageEdit::Select(pages) => self.select(pages),
+            PageEdit::Reorder(pages) => self.reorder(pages),
+            PageEdit::Rotate { pages, degrees } => self.rotate(pages, *degrees),
+            PageEdit::Delete(pages) => self.delete(pages),
+        }
+    }
+
+    /// Keep only the selected pages, in selection order
+    pub fn select(&mut self, pages: &PageSelection) -> Result<()> {
+        let ids = self.page_ids();
+        let selected = self.resolve(pages)?;
+        self.set_pages(
+            selected
+                .iter()
+                .map(|&page| ids[page as usize - 1])
+                .collect(),
+        )
+    }
+
+    /// Move the selected pages to the front, in selection order
+    pub fn reorder(&mut self, pages: &PageSelection) -> Result<()> {
+        let ids = self.page_ids();
+        let mut order = self.resolve(pages)?;
+        for page in 1..=ids.len() as u32 {
+            if !order.contains(&page) {
+                order.push(page);
+            }
+        }
+        self.set_pages(order.iter().map(|&page| ids[page as usize - 1]).collect())
+    }
+
+    pub fn rotate(&mut self, pages: &PageSelection, degrees: i32) -> Result<()> {
+        if degrees % 90 != 0 {
+            return Err(Error::ConversionError(format!(
+                "Pages can only be rotated by multiples of 90 degrees, not {}",
+                degrees
+            )));
+        }
+
+        let ids = self.page_ids();
+        for page in self.resolve(pages)? {
+            let page_id = ids[page as usize - 1];
+            let page = self
+                .document
+                .get_dictionary_mut(page_id)
+                .map_err(pdf_error)?;
+            let current = page.get(b"Rotate").and_then(Object::as_i64).unwrap_or(0);
+            page.set("Rotate", (current + degrees as i64).rem_euclid(360));
+        }
+        Ok(())
+    }
+
+    pub fn delete(&mut self, pages: &PageSelection) -> Result<()> {
+        let doomed = self.resolve(pages)?;
+        let remaining: Vec<ObjectId> = self
+            .page_ids()
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !doomed.contains(&(*index as u32 + 1)))
+            .map(|(_, id)| id)
+            .collect();
+
+        if remaining.is_empty() {
+            return Err(Error::ConversionError(
+                "Cannot delete every page of a PDF".into(),
+            ));
+        }
+        self.set_pages(remaining)
+    }
+
+    /// Copy of the selected pages as a document of their own
+    pub fn extract(&self, pages: &PageSelection) -> Result<Self> {
+        let mut part = self.clone();
+        part.select(pages)?;
+        Ok(part)
+    }
+
+    /// Add another document's pages after this one's
+    pub fn append(&mut self, other: Self) -> Result<()> {
+        let mut other = other.document;
+        other.renumber_objects_with(self.document.max_id + 1);
+        let other_ids: Vec<ObjectId> = other.page_iter().collect();
+
+        if other.version > self.document.version {
+            self.document.version = other.version.clone();
+        }
+        self.document.max_id = other.max_id;
+        // The other catalog and page tree root become unreferenced and are pruned on save
+        self.document.objects.extend(other.objects);
+
+        let mut ids = self.page_ids();
+        ids.extend(other_ids);
+        self.set_pages(ids)
+    }
+
+    /// Concatenate documents in order
+    pub fn merge(documents: Vec<Self>) -> Result<Self> {
+        let mut documents = documents.into_iter();
+        let mut merged = documents
+            .next()
+            .ok_or_else(|| Error::ConversionError("No PDFs provided to merge".into()))?;
+        for document in documents {
+            merged.append(document)?;
+        }
+        Ok(merged)
+    }
+
+    /// Properties from the document's Info dictionary
+    pub fn info(&self) -> PdfInfo {
+        PdfInfo::read(&self.document)
+    }
+
+    /// Set or remove Info dictionary properties (see [`PdfInfo::apply`])
+    pub fn set_info(&mut self, info: &PdfInfo) -> Result<()> {
+        info.apply(&mut self.document)
+    }
+
+    /// Password-protect the saved document (default: saved unprotected, even when
+    /// the loaded one was protected)
+    pub fn set_encryption(&mut self, encryption: Option<PdfEncryption>) {
+        self.encryption = encryption;
+    }
+
+    /// Downsample images drawn sharper than `max_dpi` and re-encode them with
+    /// `encode_jpeg`, keeping each new stream only when it is smaller
+    pub fn compress_images(
+        &mut self,
+        max_dpi: f32,
+        mut encode_jpeg: impl FnMut(&DynamicImage) -> Result<Vec<u8>>,
+    ) -> Result<PdfImageStats> {
+        pdf_compress::compress_images(&mut self.document, max_dpi, &mut encode_jpeg)
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// The document as it would be saved
+    pub fn to_bytes(&mut self) -> Result<Vec<u8>> {
+        self.document.prune_objects();
+        let mut bytes = Vec::new();
+        match &self.encryption {
+            Some(encryption) => {
+                // Encrypt a copy, so this document can still be edited and saved again
+                let mut document = self.document.clone();
+                pdf_security::encrypt(&mut document, encryption)?;
+                document.save_to(&mut bytes)?;
+            }
+            None => {
+                self.document.save_to(&mut bytes)?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn page_ids(&self) -> Vec<ObjectId> {
+        self.document.page_iter().collect()
+    }
+
+    fn resolve(&self, pages: &PageSelection) -> Result<Vec<u32>> {
+        pages.resolve(self.page_count())
+    }
+
+    /// Make `ids` the document's pages, as direct children of the page tree root
+    fn set_pages(&mut self, ids: Vec<ObjectId>) -> Result<()> {
+        let root_id = self
+            .document
+            .catalog()
+            .and_then(|catalog| catalog.get(b"Pages"))
+            .and_then(Object::as_reference)
+            .map_err(pdf_error)?;
+
+        // Copy inherited attributes down while the old parents are still linked
+        let inherited: Vec<Vec<(&[u8], Object)>> = ids
+            .iter()
+            .map(|&id| {
+                INHERITABLE
+                    .iter()
+                    .filter_map(|&key| {
+                        pdf_images::inherited(&self.document, id, key)
+                            .map(|value| (key, value.clone()))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for (&id, attributes) in ids.iter().zip(inherited) {
+            let page = self.document.get_dictionary_mut(id).map_err(pdf_error)?;
+            for (key, value) in attributes {
+                page.set(key, value);
+            }
+            page.set("Parent", root_id);
+        }
+
+        let root = self
+            .document
+            .get_dictionary_mut(root_id)
+            .map_err(pdf_error)?;
+        root.set(
+            "Kids",
+            ids.iter()
+                .map(|&id| Object::Reference(id))
+                .collect::<Vec<_>>(),
+        );
+        root.set("Count", ids.len() as i64);
+        root.remove(b"Parent");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{Stream, dictionary};
+
+    /// `count`-page PDF whose pages hold their number in a /Label entry, split
+    /// across two intermediate page tree nodes with an inherited MediaBox
+    fn numbered_pdf(count: i64) -> PdfPages {
+        let mut doc = Document::with_version("1.5");
+        let root_id = doc.new_object_id();
+        let mut nodes = Vec::new();
+        for half in [1..=count / 2, count / 2 + 1..=count] {
+            let node_id = doc.new_object_id();
+            let kids: Vec<Object> = half
+                .clone()
+                .map(|n| {
+                    let content = doc.add_object(Stream::new(dictionary! {}, Vec::new()));
+                    doc.add_object(dictionary! {
+                        "Type" => "Page",
+                        "Parent" => node_id,
+                        "Contents" => content,
+                        "Label" => n,
+                    })
+                    .into()
+                })
+                .collect();
+            doc.objects.insert(
+                node_id,
+                Object::Dictionary(dictionary! {
+                    "Type" => "Pages",
+                    "Parent" => root_id,
+                    "Count" => kids.len() as i64,
+                    "Kids" => kids,
+                }),
+            );
+            nodes.push(Object::Reference(node_id));
+        }
+        doc.objects.insert(
+            root_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => nodes,
+                "Count" => count,
+                "MediaBox" => vec![0.into(), 0.into(), 200.into(), 100.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => root_id });
+        doc.trailer.set("Root", catalog_id);
+
+        PdfPages::from_document(doc).unwrap()
+    }
+
+    fn labels(pages: &PdfPages) -> Vec<i64> {
+        pages
+            .page_ids()
+            .into_iter()
+            .map(|id| {
+                let page = pages.document.get_dictionary(id).unwrap();
+                page.get(b"Label").unwrap().as_i64().unwrap()
+            })
+            .collect()
+    }
+
+    fn selection(spec: &str) -> PageSelection {
+        spec.parse().unwrap()
+    }
+
+    #[test]
+    fn test_select_reorder_delete() {
+        let mut pdf = numbered_pdf(6);
+        pdf.reorder(&selection("last,2")).unwrap();
+        assert_eq!(labels(&pdf), [6, 2, 1, 3, 4, 5]);
+
+        pdf.delete(&selection("1,3-4")).unwrap();
+        assert_eq!(labels(&pdf), [2, 4, 5]);
+
+        pdf.select(&selection("3-1")).unwrap();
+        assert_eq!(labels(&pdf), [5, 4, 2]);
+
+        assert!(pdf.delete(&selection("all")).is_err());
+    }
+
+    #[test]
+    fn test_rotate_and_inherited_attributes() {
+        let mut pdf = numbered_pdf(4);
+        pdf.rotate(&selection("2"), -90).unwrap();
+        pdf.rotate(&selection("2-3"), 180).unwrap();
+        assert!(pdf.rotate(&selection("1"), 45).is_err());
+
+        let rotations: Vec<i64> = pdf
+            .page_ids()
+            .into_iter()
+            .map(|id| {
+                let page = pdf.document.get_dictionary(id).unwrap();
+                assert!(page.get(b"MediaBox").is_ok());
+                page.get(b"Rotate").and_then(Object::as_i64).unwrap_or(0)
+            })
+            .collect();
+        assert_eq!(rotations, [0, 90, 180, 0]);
+    }
+
+    #[test]
+    fn test_merge_and_save_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("merged.pdf");
+
+        let mut merged = PdfPages::merge(vec![numbered_pdf(2), numbered_pdf(4)]).unwrap();
+        merged.save(&path).unwrap();
+
+        let reloaded = PdfPages::load(&path).unwrap();
+        assert_eq!(labels(&reloaded), [1, 2, 1, 2, 3, 4]);
+
+        let part = reloaded.extract(&selection("3-4")).unwrap();
+        assert_eq!(labels(&part), [1, 2]);
+        assert_eq!(reloaded.page_count(), 6);
+    }
+}
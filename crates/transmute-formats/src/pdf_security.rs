@@ -0,0 +1,511 @@
+//! Password protection of PDFs: AES-256 encryption of the documents Transmute
+//! writes, and decryption of protected inputs
+
+use crate::pdf_images::pdf_error;
+use lopdf::encryption::crypt_filters::{Aes256CryptFilter, CryptFilter};
+use lopdf::{
+    Dictionary, Document, EncryptionState, EncryptionVersion, Object, ObjectId, Permissions,
+    Reader, dictionary,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use transmute_common::{Error, Result};
+
+/// Stands in for the trailer's `/Encrypt` key while parsing; the same length, so
+/// byte offsets still hold
+const HIDDEN_ENCRYPT: &[u8] = b"/EncrypT";
+
+/// Type given to object streams while their contents are still encrypted
+const HIDDEN_OBJECT_STREAM: &[u8] = b"EncryptedObjStm";
+
+/// What readers who open a protected PDF with its user password may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PdfPermissions {
+    pub print: bool,
+    pub copy: bool,
+    pub modify: bool,
+    pub annotate: bool,
+    pub fill_forms: bool,
+    pub assemble: bool,
+}
+
+impl Default for PdfPermissions {
+    fn default() -> Self {
+        Self {
+            print: true,
+            copy: true,
+            modify: true,
+            annotate: true,
+            fill_forms: true,
+            assemble: true,
+        }
+    }
+}
+
+impl PdfPermissions {
+    /// Names accepted by [`Self::deny`]
+    pub const NAMES: [&str; 6] = ["print", "copy", "modify", "annotate", "forms", "assemble"];
+
+    /// Withhold a permission by name: print, copy, modify, annotate, forms or assemble
+    pub fn deny(&mut self, name: &str) -> Result<()> {
+        let permission = match name.trim().to_ascii_lowercase().as_str() {
+            "print" => &mut self.print,
+            "copy" => &mut self.copy,
+            "modify" => &mut self.modify,
+            "annotate" => &mut self.annotate,
+            "forms" => &mut self.fill_forms,
+            "assemble" => &mut self.assemble,
+            _ => {
+                return Err(Error::ConversionError(format!(
+                    "Unknown PDF permission '{}' (expected one of {})",
+                    name,
+                    Self::NAMES.join(", ")
+                )));
+            }
+        };
+        *permission = false;
+        Ok(())
+    }
+
+    fn flags(self) -> Permissions {
+        // Assistive technology may always extract content
+        let mut flags = Permissions::COPYABLE_FOR_ACCESSIBILITY;
+        for (allowed, flag) in [
+            (
+                self.print,
+                Permissions::PRINTABLE | Permissions::PRINTABLE_IN_HIGH_QUALITY,
+            ),
+            (self.copy, Permissions::COPYABLE),
+            (self.modify, Permissions::MODIFIABLE),
+            (self.annotate, Permissions::ANNOTABLE),
+            (self.fill_forms, Permissions::FILLABLE),
+            (self.assemble, Permissions::ASSEMBLABLE),
+        ] {
+            if allowed {
+                flags |= flag;
+            }
+        }
+        flags
+    }
+}
+
+/// AES-256 password protection of a written PDF
+#[derive(Clone, Default)]
+pub struct PdfEncryption {
+    /// Password needed to open the document; if empty anyone can open it, with
+    /// `permissions` applied
+    pub user_password: String,
+
+    /// Password that lifts the restrictions (default: a random one nobody knows)
+    pub owner_password: Option<String>,
+
+    /// What readers who open the document with the user password may do
+    pub permissions: PdfPermissions,
+}
+
+impl fmt::Debug for PdfEncryption {
+    // Options are logged, passwords must not be
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PdfEncryption")
+            .field("user_password", &"***")
+            .field(
+                "owner_password",
+                &self.owner_password.as_ref().map(|_| "***"),
+            )
+            .field("permissions", &self.permissions)
+            .finish()
+    }
+}
+
+/// Encrypt every string and stream of `doc` with AES-256 (security handler
+/// revision 6); done last, just before saving
+pub(crate) fn encrypt(doc: &mut Document, encryption: &PdfEncryption) -> Result<()> {
+    let mut file_key = [0u8; 32];
+    random_bytes(&mut file_key)?;
+    let owner_password = match &encryption.owner_password {
+        Some(password) => password.clone(),
+        None => {
+            let mut bytes = [0u8; 24];
+            random_bytes(&mut bytes)?;
+            bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+        }
+    };
+
+    let filter: Arc<dyn CryptFilter> = Arc::new(Aes256CryptFilter);
+    let state = EncryptionState::try_from(EncryptionVersion::V5 {
+        encrypt_metadata: true,
+        crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), filter)]),
+        file_encryption_key: &file_key,
+        stream_filter: b"StdCF".to_vec(),
+        string_filter: b"StdCF".to_vec(),
+        owner_password: &owner_password,
+        user_password: &encryption.user_password,
+        permissions: encryption.permissions.flags(),
+    })
+    .map_err(pdf_error)?;
+
+    // Revision 6 is PDF 2.0, which PDF 1.7 readers know as Adobe extension level 8
+    if older_than(&doc.version, 1, 7) {
+        doc.version = "1.7".into();
+    }
+    let catalog = doc.catalog_mut().map_err(pdf_error)?;
+    let mut extensions = catalog
+        .get(b"Extensions")
+        .and_then(Object::as_dict)
+        .cloned()
+        .unwrap_or_else(|_| Dictionary::new());
+    extensions.set(
+        "ADBE",
+        dictionary! {
+            "BaseVersion" => Object::Name(b"1.7".to_vec()),
+            "ExtensionLevel" => 8,
+        },
+    );
+    catalog.set("Extensions", extensions);
+
+    doc.encrypt(&state).map_err(pdf_error)
+}
+
+fn random_bytes(buffer: &mut [u8]) -> Result<()> {
+    getrandom::fill(buffer).map_err(|e| {
+        Error::ConversionError(format!("No random numbers for the encryption key: {}", e))
+    })
+}
+
+/// Whether `password` opens the PDF at `path`; unprotected PDFs open with any password
+pub fn check_password(path: &Path, password: &str) -> Result<bool> {
+    let document = Document::load(path).map_err(pdf_error)?;
+    Ok(!document.is_encrypted() || document.authenticate_password(password).is_ok())
+}
+
+/// Whether the PDF at `path` is protected by a password needed to open it
+pub fn needs_password(path: &Path) -> Result<bool> {
+    Ok(!check_password(path, "")?)
+}
+
+/// Open the PDF at `path`, decrypting it with `password` (or the empty password)
+/// if it is protected.
+///
+/// The document comes back unencrypted, so it is saved without protection unless
+/// it is encrypted again.
+pub fn load(path: &Path, password: Option<&str>) -> Result<Document> {
+    let bytes = std::fs::read(path)?;
+    let document = Document::load_mem(&bytes).map_err(pdf_error)?;
+    if !document.is_encrypted() {
+        return Ok(document);
+    }
+
+    let password = password.unwrap_or_default();
+    if document.authenticate_password(password).is_err() {
+        return Err(if password.is_empty() {
+            Error::PasswordRequired(path.to_path_buf())
+        } else {
+            Error::IncorrectPassword(path.to_path_buf())
+        });
+    }
+
+    let mut document = load_undecrypted(&bytes)?;
+    document.decrypt(password).map_err(pdf_error)?;
+    tracing::debug!("Decrypted {:?}", path);
+    Ok(document)
+}
+
+/// Parse an encrypted PDF with every object as stored.
+///
+/// lopdf only reads the objects of encrypted files that open with the empty
+/// password, so the trailer's `/Encrypt` key is hidden while parsing and put back
+/// afterwards, ready for [`Document::decrypt`].
+fn load_undecrypted(bytes: &[u8]) -> Result<Document> {
+    let mut bytes = bytes.to_vec();
+    hide_trailer_encrypt(&mut bytes)?;
+
+    let mut document = Reader {
+        buffer: &bytes,
+        document: Document::new(),
+        encryption_state: None,
+        raw_objects: BTreeMap::new(),
+    }
+    .read(Some(hide_object_stream))
+    .map_err(pdf_error)?;
+
+    if let Some(encrypt) = document.trailer.remove(&HIDDEN_ENCRYPT[1..]) {
+        document.trailer.set("Encrypt", encrypt);
+    }
+    for object in document.objects.values_mut() {
+        if let Object::Stream(stream) = object
+            && stream.dict.has_type(HIDDEN_OBJECT_STREAM)
+        {
+            stream.dict.set("Type", Object::Name(b"ObjStm".to_vec()));
+        }
+    }
+    Ok(document)
+}
+
+/// Rename `/Encrypt` in the newest trailer, the only one lopdf reads it from.
+///
+/// Only that dictionary is touched: the `trailer` after a classic xref table, or
+/// the dictionary of an xref stream, both found through `startxref`. Object and
+/// stream bytes elsewhere in the file stay as they are.
+fn hide_trailer_encrypt(bytes: &mut [u8]) -> Result<()> {
+    let missing = || Error::ConversionError("Cannot find the PDF's encryption dictionary".into());
+    let trailer = trailer_range(bytes).ok_or_else(missing)?;
+
+    let key = &b"/Encrypt"[..];
+    let at = bytes[trailer.clone()]
+        .windows(key.len() + 1)
+        // Longer names such as /EncryptMetadata stay
+        .position(|window| window.starts_with(key) && !window[key.len()].is_ascii_alphanumeric())
+        .ok_or_else(missing)?
+        + trailer.start;
+    bytes[at..at + key.len()].copy_from_slice(HIDDEN_ENCRYPT);
+    Ok(())
+}
+
+/// Byte range from the newest cross-reference section to its end: the table and
+/// `trailer` dictionary, or an xref stream's dictionary up to its data
+fn trailer_range(bytes: &[u8]) -> Option<std::ops::Range<usize>> {
+    let find = |haystack: &[u8], needle: &[u8]| {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    };
+
+    // Offsets count from the header, as lopdf reads them
+    let header = find(bytes, b"%PDF-").unwrap_or(0);
+    let tail = bytes.len().saturating_sub(1024);
+    let startxref = tail
+        + bytes[tail..]
+            .windows(9)
+            .rposition(|window| window == b"startxref")?;
+    let digits: String = bytes[startxref + 9..]
+        .iter()
+        .map(|&byte| byte as char)
+        .skip_while(char::is_ascii_whitespace)
+        .take_while(char::is_ascii_digit)
+        .collect();
+
+    let start = header.checked_add(digits.parse().ok()?)?;
+    let section = bytes.get(start..startxref)?;
+    let len = if section.starts_with(b"xref") {
+        section.len()
+    } else {
+        find(section, b"stream").unwrap_or(section.len())
+    };
+    Some(start..start + len)
+}
+
+/// Parsing filter keeping object streams from being unpacked, and dropped as
+/// unreadable, before they are decrypted
+fn hide_object_stream(id: ObjectId, object: &mut Object) -> Option<(ObjectId, Object)> {
+    if let Object::Stream(stream) = object
+        && stream.dict.has_type(b"ObjStm")
+    {
+        stream
+            .dict
+            .set("Type", Object::Name(HIDDEN_OBJECT_STREAM.to_vec()));
+    }
+    Some((id, object.clone()))
+}
+
+/// Whether a `%PDF-` header version is older than `major.minor`
+fn older_than(version: &str, major: u32, minor: u32) -> bool {
+    let mut parts = version
+        .split('.')
+        .map(|part| part.trim().parse::<u32>().unwrap_or(0));
+    (parts.next().unwrap_or(0), parts.next().unwrap_or(0)) < (major, minor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::content::{Content, Operation};
+    use lopdf::{Stream, StringFormat};
+
+    /// A one-page document with `text` in its Info title and page content
+    fn sample(text: &str) -> Document {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let content = Content {
+            operations: vec![Operation::new(
+                "Tj",
+                vec![Object::String(text.into(), StringFormat::Literal)],
+            )],
+        };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+            "Contents" => content_id,
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        let info_id = doc.add_object(dictionary! {
+            "Title" => Object::string_literal(text),
+        });
+        doc.trailer.set("Info", info_id);
+        doc
+    }
+
+    fn save_encrypted(mut doc: Document, encryption: &PdfEncryption) -> tempfile::NamedTempFile {
+        encrypt(&mut doc, encryption).unwrap();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        doc.save(file.path()).unwrap();
+        file
+    }
+
+    fn title(doc: &Document) -> Vec<u8> {
+        let info = doc
+            .trailer
+            .get(b"Info")
+            .and_then(Object::as_reference)
+            .and_then(|id| doc.get_dictionary(id))
+            .unwrap();
+        info.get(b"Title")
+            .and_then(Object::as_str)
+            .unwrap()
+            .to_vec()
+    }
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let file = save_encrypted(
+            sample("Quarterly figures"),
+            &PdfEncryption {
+                user_password: "open sesame".into(),
+                ..Default::default()
+            },
+        );
+
+        // Nothing readable is left in the file
+        let bytes = std::fs::read(file.path()).unwrap();
+        assert!(!bytes.windows(9).any(|window| window == b"Quarterly"));
+        assert!(bytes.windows(6).any(|window| window == b"/AESV3"));
+
+        assert!(needs_password(file.path()).unwrap());
+        assert!(!check_password(file.path(), "wrong").unwrap());
+        assert!(check_password(file.path(), "open sesame").unwrap());
+        assert!(matches!(
+            load(file.path(), None),
+            Err(Error::PasswordRequired(_))
+        ));
+        assert!(matches!(
+            load(file.path(), Some("wrong")),
+            Err(Error::IncorrectPassword(_))
+        ));
+
+        let doc = load(file.path(), Some("open sesame")).unwrap();
+        assert!(!doc.is_encrypted());
+        assert_eq!(title(&doc), b"Quarterly figures");
+        let page_id = doc.get_pages()[&1];
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        assert_eq!(
+            content.operations[0].operands[0].as_str().unwrap(),
+            b"Quarterly figures"
+        );
+    }
+
+    #[test]
+    fn test_owner_password_and_permissions() {
+        let mut permissions = PdfPermissions::default();
+        permissions.deny("print").unwrap();
+        permissions.deny("Copy").unwrap();
+        assert!(permissions.deny("fly").is_err());
+
+        let file = save_encrypted(
+            sample("Draft"),
+            &PdfEncryption {
+                user_password: String::new(),
+                owner_password: Some("owner".into()),
+                permissions,
+            },
+        );
+
+        // An empty user password opens without asking, under the restrictions
+        assert!(!needs_password(file.path()).unwrap());
+        let doc = Document::load(file.path()).unwrap();
+        let p = doc
+            .get_encrypted()
+            .and_then(|dict| dict.get(b"P"))
+            .and_then(Object::as_i64)
+            .unwrap();
+        assert_eq!(p & 0b100, 0, "printing is denied");
+        assert_eq!(p & 0b1_0000, 0, "copying is denied");
+        assert_ne!(p & 0b1000, 0, "modifying is allowed");
+        assert!(doc.authenticate_owner_password("owner").is_ok());
+
+        assert_eq!(title(&load(file.path(), None).unwrap()), b"Draft");
+        assert_eq!(title(&load(file.path(), Some("owner")).unwrap()), b"Draft");
+    }
+
+    #[test]
+    fn test_only_trailer_encrypt_is_hidden() {
+        // An object whose bytes happen to spell the key must not be touched
+        let body = b"%PDF-1.7\n1 0 obj\n<< /Length 9 >>\nstream\n/Encrypt \nendstream\nendobj\n";
+        let trailer = b"xref\n0 1\n0000000000 65535 f \ntrailer\n<< /Size 2 /Encrypt 2 0 R >>\n";
+        let mut bytes = body.to_vec();
+        bytes.extend_from_slice(trailer);
+        bytes.extend_from_slice(format!("startxref\n{}\n%%EOF\n", body.len()).as_bytes());
+
+        let original = bytes.clone();
+        hide_trailer_encrypt(&mut bytes).unwrap();
+
+        let changed: Vec<usize> = (0..bytes.len())
+            .filter(|&i| bytes[i] != original[i])
+            .collect();
+        let key = body.len() + trailer.windows(8).position(|w| w == b"/Encrypt").unwrap();
+        assert_eq!(
+            changed,
+            vec![key + 7],
+            "only the trailer key's last letter changes"
+        );
+    }
+
+    #[test]
+    fn test_xref_stream_round_trip() {
+        let mut doc = sample("Minutes");
+        encrypt(
+            &mut doc,
+            &PdfEncryption {
+                user_password: "pw".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let options = lopdf::SaveOptions::builder().use_xref_streams(true).build();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        doc.save_with_options(&mut std::fs::File::create(file.path()).unwrap(), options)
+            .unwrap();
+
+        assert!(needs_password(file.path()).unwrap());
+        assert_eq!(title(&load(file.path(), Some("pw")).unwrap()), b"Minutes");
+    }
+
+    #[test]
+    fn test_version_comparison_is_numeric() {
+        assert!(older_than("1.4", 1, 7));
+        assert!(!older_than("1.7", 1, 7));
+        assert!(!older_than("1.10", 1, 7));
+        assert!(!older_than("2.0", 1, 7));
+    }
+
+    #[test]
+    fn test_debug_hides_passwords() {
+        let encryption = PdfEncryption {
+            user_password: "secret".into(),
+            owner_password: Some("also secret".into()),
+            ..Default::default()
+        };
+        assert!(!format!("{:?}", encryption).contains("secret"));
+    }
+}
@@ -0,0 +1,934 @@
+//! PDF/A-2b archival output: XMP metadata matching the Info dictionary, an sRGB
+//! output intent, and a structural check of the rules a document can break
+
+use crate::pdf_images::pdf_error;
+use crate::pdf_info::PdfInfo;
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+use lopdf::{Dictionary, Document, Object, Stream, StringFormat, dictionary};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use transmute_common::Result;
+
+/// Name of the output condition the embedded profile describes
+const OUTPUT_CONDITION: &str = "sRGB IEC61966-2.1";
+
+/// Add the structures PDF/A-2b requires to a document whose Info dictionary holds `info`
+pub(crate) fn make_conformant(document: &mut Document, info: &PdfInfo) -> Result<()> {
+    document.version = "1.7".into();
+
+    if document.trailer.get(b"ID").is_err() {
+        let id = Object::String(document_id(), StringFormat::Hexadecimal);
+        document.trailer.set("ID", vec![id.clone(), id]);
+    }
+
+    let metadata_id = document.add_object(metadata_stream(info, Some(("2", "B"))));
+
+    let mut profile = Stream::new(dictionary! { "N" => 3 }, srgb_profile());
+    profile.compress().map_err(pdf_error)?;
+    let profile_id = document.add_object(profile);
+    let intent_id = document.add_object(dictionary! {
+        "Type" => "OutputIntent",
+        "S" => "GTS_PDFA1",
+        "OutputConditionIdentifier" => Object::string_literal(OUTPUT_CONDITION),
+        "Info" => Object::string_literal(OUTPUT_CONDITION),
+        "DestOutputProfile" => profile_id,
+    });
+
+    let catalog = document.catalog_mut().map_err(pdf_error)?;
+    catalog.set("Metadata", metadata_id);
+    catalog.set("OutputIntents", vec![intent_id.into()]);
+    Ok(())
+}
+
+/// XMP properties written from the Info dictionary
+const INFO_PROPERTIES: [&str; 8] = [
+    "dc:title",
+    "dc:creator",
+    "dc:description",
+    "pdf:Keywords",
+    "xmp:CreatorTool",
+    "pdf:Producer",
+    "xmp:CreateDate",
+    "xmp:ModifyDate",
+];
+
+/// Update the catalog's XMP metadata, if there is any, from the Info dictionary
+/// so the two do not disagree after the Info properties change.
+///
+/// Only the properties mapped from Info are replaced; everything else in the
+/// packet (PDF/A identification, document and instance IDs, extension schemas)
+/// is kept. A packet that cannot be edited is rewritten, keeping only its
+/// PDF/A identification.
+pub(crate) fn sync_metadata(document: &mut Document) -> Result<()> {
+    let Ok(catalog) = document.catalog() else {
+        return Ok(());
+    };
+    let Ok(metadata) = catalog.get(b"Metadata").cloned() else {
+        return Ok(());
+    };
+    let old = document
+        .dereference(&metadata)
+        .ok()
+        .and_then(|(_, metadata)| metadata.as_stream().ok())
+        .and_then(|stream| stream.get_plain_content().ok())
+        .map(|xml| String::from_utf8_lossy(&xml).into_owned())
+        .unwrap_or_default();
+
+    let info = PdfInfo::read(document);
+    let xml = updated_xmp(&old, &info).unwrap_or_else(|| {
+        let part = xmp_property(&old, "pdfaid:part");
+        let conformance = xmp_property(&old, "pdfaid:conformance");
+        xmp_metadata(&info, part.as_deref().zip(conformance.as_deref()))
+    });
+
+    let stream = xmp_stream(xml);
+    match metadata {
+        Object::Reference(id) => {
+            document.objects.insert(id, Object::Stream(stream));
+        }
+        _ => {
+            let id = document.add_object(stream);
+            document
+                .catalog_mut()
+                .map_err(pdf_error)?
+                .set("Metadata", id);
+        }
+    }
+    Ok(())
+}
+
+/// `xml` with the Info-mapped properties removed and those of `info` added in
+/// a description of their own; `None` when it is not an RDF packet
+fn updated_xmp(xml: &str, info: &PdfInfo) -> Option<String> {
+    xml.rfind("</rdf:RDF>")?;
+
+    let mut xml = xml.to_string();
+    for name in INFO_PROPERTIES {
+        while remove_xmp_property(&mut xml, name)? {}
+    }
+
+    let properties = xmp_properties(info);
+    if !properties.is_empty() {
+        let end = xml.rfind("</rdf:RDF>")?;
+        xml.insert_str(
+            end,
+            &format!(
+                " <rdf:Description rdf:about=\"\"\n    \
+                 xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n    \
+                 xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"\n    \
+                 xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\">\n\
+                 {}  </rdf:Description>\n ",
+                properties
+            ),
+        );
+    }
+    Some(xml)
+}
+
+/// Remove the first occurrence of property `name`, as an element or as an
+/// attribute of rdf:Description. Returns whether one was found, or `None` for
+/// an element that is not closed.
+fn remove_xmp_property(xml: &mut String, name: &str) -> Option<bool> {
+    let open = format!("<{}", name);
+    let mut from = 0;
+    while let Some(found) = xml[from..].find(&open) {
+        let start = from + found;
+        let after = start + open.len();
+        // `<dc:title` must not match `<dc:titles`
+        if !xml[after..].starts_with(['>', '/', ' ', '\t', '\r', '\n']) {
+            from = after;
+            continue;
+        }
+        let tag_end = after + xml[after..].find('>')?;
+        let end = if xml[..tag_end].ends_with('/') {
+            tag_end + 1
+        } else {
+            let close = format!("</{}>", name);
+            tag_end + xml[tag_end..].find(&close)? + close.len()
+        };
+        // Take the line's indentation along
+        let start = xml[..start].trim_end_matches([' ', '\t']).len();
+        let end = end + xml[end..].len() - xml[end..].trim_start_matches([' ', '\t']).len();
+        let end = end + usize::from(xml[end..].starts_with('\n'));
+        xml.replace_range(start..end, "");
+        return Some(true);
+    }
+
+    for quote in ['"', '\''] {
+        let attribute = format!(" {}={}", name, quote);
+        if let Some(start) = xml.find(&attribute) {
+            let value = start + attribute.len();
+            if let Some(len) = xml[value..].find(quote) {
+                xml.replace_range(start..value + len + 1, "");
+                return Some(true);
+            }
+        }
+    }
+    Some(false)
+}
+
+/// Metadata stream for `info`, declaring PDF/A `(part, conformance)` if given
+fn metadata_stream(info: &PdfInfo, pdfa: Option<(&str, &str)>) -> Stream {
+    xmp_stream(xmp_metadata(info, pdfa))
+}
+
+/// Metadata stream holding an XMP packet.
+///
+/// It stays uncompressed so archive tools can read it without a PDF parser.
+fn xmp_stream(xml: String) -> Stream {
+    Stream::new(
+        dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+        xml.into_bytes(),
+    )
+    .with_compression(false)
+}
+
+/// A file identifier for documents that have none
+fn document_id() -> Vec<u8> {
+    let mut hasher = DefaultHasher::new();
+    std::time::SystemTime::now().hash(&mut hasher);
+    std::process::id().hash(&mut hasher);
+    let first = hasher.finish();
+    first.hash(&mut hasher);
+    [first, hasher.finish()]
+        .iter()
+        .flat_map(|half| half.to_be_bytes())
+        .collect()
+}
+
+/// XMP packet with the same properties as `info`, identifying the document as
+/// PDF/A `(part, conformance)` when given
+fn xmp_metadata(info: &PdfInfo, pdfa: Option<(&str, &str)>) -> String {
+    let (namespace, identification) = match pdfa {
+        Some((part, conformance)) => (
+            "\n    xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\"",
+            format!(
+                "   <pdfaid:part>{}</pdfaid:part>\n   <pdfaid:conformance>{}</pdfaid:conformance>\n",
+                escape_xml(part),
+                escape_xml(conformance)
+            ),
+        ),
+        None => ("", String::new()),
+    };
+
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n \
+         <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n  \
+         <rdf:Description rdf:about=\"\"{}\n    \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n    \
+         xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"\n    \
+         xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\">\n\
+         {}   <dc:format>application/pdf</dc:format>\n\
+         {}  </rdf:Description>\n \
+         </rdf:RDF>\n\
+         </x:xmpmeta>\n\
+         <?xpacket end=\"w\"?>",
+        namespace,
+        identification,
+        xmp_properties(info)
+    )
+}
+
+/// The Info-mapped XMP property elements for `info`, one per line
+fn xmp_properties(info: &PdfInfo) -> String {
+    let text = |value: &Option<String>| {
+        value
+            .as_deref()
+            .filter(|value| !value.is_empty())
+            .map(escape_xml)
+    };
+    let date = |value: &Option<DateTime<FixedOffset>>| {
+        value.map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true))
+    };
+
+    let mut properties = String::new();
+    let mut add = |value: Option<String>, open: &str, close: &str| {
+        if let Some(value) = value {
+            properties.push_str(&format!("   {}{}{}\n", open, value, close));
+        }
+    };
+    add(
+        text(&info.title),
+        "<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">",
+        "</rdf:li></rdf:Alt></dc:title>",
+    );
+    add(
+        text(&info.author),
+        "<dc:creator><rdf:Seq><rdf:li>",
+        "</rdf:li></rdf:Seq></dc:creator>",
+    );
+    add(
+        text(&info.subject),
+        "<dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">",
+        "</rdf:li></rdf:Alt></dc:description>",
+    );
+    add(text(&info.keywords), "<pdf:Keywords>", "</pdf:Keywords>");
+    add(
+        text(&info.creator),
+        "<xmp:CreatorTool>",
+        "</xmp:CreatorTool>",
+    );
+    add(text(&info.producer), "<pdf:Producer>", "</pdf:Producer>");
+    add(
+        date(&info.creation_date),
+        "<xmp:CreateDate>",
+        "</xmp:CreateDate>",
+    );
+    add(
+        date(&info.modification_date),
+        "<xmp:ModifyDate>",
+        "</xmp:ModifyDate>",
+    );
+
+    properties
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// ICC v2 display profile for sRGB, the colour space images are embedded in
+fn srgb_profile() -> Vec<u8> {
+    fn s15_fixed16(value: f64) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+    fn xyz(x: f64, y: f64, z: f64) -> Vec<u8> {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        for value in [x, y, z] {
+            tag.extend(s15_fixed16(value));
+        }
+        tag
+    }
+
+    let mut desc = b"desc\0\0\0\0".to_vec();
+    desc.extend((OUTPUT_CONDITION.len() as u32 + 1).to_be_bytes());
+    desc.extend(OUTPUT_CONDITION.as_bytes());
+    // NUL terminator, empty Unicode and ScriptCode descriptions
+    desc.extend([0u8; 1 + 8 + 3 + 67]);
+
+    let mut cprt = b"text\0\0\0\0".to_vec();
+    cprt.extend(b"No copyright, use freely\0");
+
+    // The sRGB transfer curve, sampled
+    const CURVE_POINTS: u32 = 1024;
+    let mut curve = b"curv\0\0\0\0".to_vec();
+    curve.extend(CURVE_POINTS.to_be_bytes());
+    for i in 0..CURVE_POINTS {
+        let v = i as f64 / (CURVE_POINTS - 1) as f64;
+        let linear = if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        };
+        curve.extend(((linear * 65535.0).round() as u16).to_be_bytes());
+    }
+
+    // Colorants adapted to the D50 connection space, as in the IEC 61966-2.1 profile
+    let elements = [
+        desc,
+        cprt,
+        xyz(0.9505, 1.0, 1.0891),
+        xyz(0.4361, 0.2225, 0.0139),
+        xyz(0.3851, 0.7169, 0.0971),
+        xyz(0.1431, 0.0606, 0.7141),
+        curve,
+    ];
+    let tags: [(&[u8; 4], usize); 9] = [
+        (b"desc", 0),
+        (b"cprt", 1),
+        (b"wtpt", 2),
+        (b"rXYZ", 3),
+        (b"gXYZ", 4),
+        (b"bXYZ", 5),
+        (b"rTRC", 6),
+        (b"gTRC", 6),
+        (b"bTRC", 6),
+    ];
+
+    let data_start = 128 + 4 + 12 * tags.len();
+    let mut data = Vec::new();
+    let mut offsets = Vec::new();
+    for element in &elements {
+        offsets.push(data_start + data.len());
+        data.extend(element);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+
+    let mut profile = Vec::with_capacity(data_start + data.len());
+    profile.extend(((data_start + data.len()) as u32).to_be_bytes());
+    profile.extend([0; 4]); // CMM
+    profile.extend([2, 0x10, 0, 0]); // Version 2.1
+    profile.extend(b"mntrRGB XYZ ");
+    profile.extend([0x07, 0xE8, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0]); // 2024-01-01
+    profile.extend(b"acsp");
+    profile.extend([0; 28]); // Platform, flags, device and rendering intent
+    profile.extend(s15_fixed16(0.9642));
+    profile.extend(s15_fixed16(1.0));
+    profile.extend(s15_fixed16(0.8249));
+    profile.resize(128, 0);
+
+    profile.extend((tags.len() as u32).to_be_bytes());
+    for (signature, element) in tags {
+        profile.extend(signature);
+        profile.extend((offsets[element] as u32).to_be_bytes());
+        profile.extend((elements[element].len() as u32).to_be_bytes());
+    }
+    profile.extend(data);
+    profile
+}
+
+/// Ways `document` breaks the PDF/A-2b rules that apply to its structure: the
+/// identification and metadata, output intent, fonts, colour spaces, actions,
+/// annotations and filters. An empty list means none were found; content streams
+/// are not examined.
+pub fn conformance_issues(document: &Document) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    let version_ok = document
+        .version
+        .strip_prefix("1.")
+        .and_then(|minor| minor.parse::<u8>().ok())
+        .is_some_and(|minor| minor <= 7);
+    if !version_ok {
+        issues.push(format!(
+            "PDF version {} is newer than 1.7",
+            document.version
+        ));
+    }
+    if document.trailer.get(b"Encrypt").is_ok() {
+        issues.push("Document is encrypted".into());
+    }
+    let has_id = document
+        .trailer
+        .get(b"ID")
+        .and_then(Object::as_array)
+        .is_ok_and(|id| id.len() == 2);
+    if !has_id {
+        issues.push("Trailer has no file identifier (ID)".into());
+    }
+
+    let Ok(catalog) = document.catalog() else {
+        issues.push("Document has no catalog".into());
+        return issues;
+    };
+    if catalog.has(b"AA") {
+        issues.push("Catalog has additional actions (AA)".into());
+    }
+    let has_javascript = catalog
+        .get(b"Names")
+        .ok()
+        .and_then(|names| document.dereference(names).ok())
+        .and_then(|(_, names)| names.as_dict().ok())
+        .is_some_and(|names| names.has(b"JavaScript"));
+    if has_javascript {
+        issues.push("Document contains JavaScript".into());
+    }
+
+    check_metadata(document, catalog, &mut issues);
+    let components = output_intent_components(document, catalog, &mut issues);
+    for (id, object) in &document.objects {
+        let dict = match object {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => {
+                check_stream(stream, &mut issues, *id);
+                &stream.dict
+            }
+            _ => continue,
+        };
+        check_dictionary(document, dict, components, &mut issues, *id);
+    }
+    issues
+}
+
+/// The XMP packet identifies PDF/A-2b and agrees with the Info dictionary
+fn check_metadata(document: &Document, catalog: &Dictionary, issues: &mut Vec<String>) {
+    let stream = catalog
+        .get(b"Metadata")
+        .ok()
+        .and_then(|metadata| document.dereference(metadata).ok())
+        .and_then(|(_, metadata)| metadata.as_stream().ok());
+    let Some(stream) = stream else {
+        issues.push("Catalog has no XMP metadata stream".into());
+        return;
+    };
+    let name = |key: &[u8]| stream.dict.get(key).and_then(Object::as_name).ok();
+    if name(b"Type") != Some(b"Metadata") || name(b"Subtype") != Some(b"XML") {
+        issues.push("Metadata stream is not of type Metadata/XML".into());
+    }
+    if stream.dict.has(b"Filter") {
+        issues.push("Metadata stream is compressed".into());
+    }
+    let Ok(xml) = std::str::from_utf8(&stream.content) else {
+        issues.push("XMP metadata is not UTF-8".into());
+        return;
+    };
+
+    if !xml.starts_with("<?xpacket begin=") {
+        issues.push("XMP metadata is not wrapped in an xpacket".into());
+    }
+    if xmp_property(xml, "pdfaid:part").as_deref() != Some("2") {
+        issues.push("XMP metadata does not declare pdfaid:part 2".into());
+    }
+    if xmp_property(xml, "pdfaid:conformance").as_deref() != Some("B") {
+        issues.push("XMP metadata does not declare pdfaid:conformance B".into());
+    }
+
+    let info = PdfInfo::read(document);
+    let text_properties = [
+        ("Title", &info.title, "dc:title"),
+        ("Author", &info.author, "dc:creator"),
+        ("Subject", &info.subject, "dc:description"),
+        ("Keywords", &info.keywords, "pdf:Keywords"),
+        ("Creator", &info.creator, "xmp:CreatorTool"),
+        ("Producer", &info.producer, "pdf:Producer"),
+    ];
+    for (key, value, property) in text_properties {
+        if let Some(value) = value
+            && xmp_property(xml, property).as_ref() != Some(value)
+        {
+            issues.push(format!("Info {} does not match XMP {}", key, property));
+        }
+    }
+    let date_properties = [
+        ("CreationDate", info.creation_date, "xmp:CreateDate"),
+        ("ModDate", info.modification_date, "xmp:ModifyDate"),
+    ];
+    for (key, value, property) in date_properties {
+        let xmp_date =
+            xmp_property(xml, property).and_then(|date| DateTime::parse_from_rfc3339(&date).ok());
+        if let Some(value) = value
+            && xmp_date != Some(value)
+        {
+            issues.push(format!("Info {} does not match XMP {}", key, property));
+        }
+    }
+}
+
+/// Text of a simple XMP property, or the first item of an array property
+fn xmp_property(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{}>", name);
+    if let Some(start) = xml.find(&open) {
+        let rest = &xml[start + open.len()..];
+        let mut value = &rest[..rest.find(&format!("</{}>", name))?];
+        if let Some(item) = value.find("<rdf:li") {
+            let item = &value[item..];
+            let text = &item[item.find('>')? + 1..];
+            value = &text[..text.find("</rdf:li>")?];
+        }
+        return Some(unescape_xml(value));
+    }
+
+    // Properties may also be written as attributes of rdf:Description
+    let attribute = format!("{}=\"", name);
+    let start = xml.find(&attribute)? + attribute.len();
+    let end = xml[start..].find('"')?;
+    Some(unescape_xml(&xml[start..start + end]))
+}
+
+/// Number of colour components of the PDF/A output intent's profile, after checking it
+fn output_intent_components(
+    document: &Document,
+    catalog: &Dictionary,
+    issues: &mut Vec<String>,
+) -> Option<i64> {
+    let intents = catalog
+        .get(b"OutputIntents")
+        .ok()
+        .and_then(|intents| document.dereference(intents).ok())
+        .and_then(|(_, intents)| intents.as_array().ok());
+    let intent = intents.into_iter().flatten().find_map(|intent| {
+        let (_, intent) = document.dereference(intent).ok()?;
+        let intent = intent.as_dict().ok()?;
+        let kind = intent.get(b"S").and_then(Object::as_name).ok()?;
+        (kind == b"GTS_PDFA1").then_some(intent)
+    });
+    let Some(intent) = intent else {
+        issues.push("Catalog has no GTS_PDFA1 output intent".into());
+        return None;
+    };
+
+    let profile = intent
+        .get(b"DestOutputProfile")
+        .ok()
+        .and_then(|profile| document.dereference(profile).ok())
+        .and_then(|(_, profile)| profile.as_stream().ok());
+    let Some(profile) = profile else {
+        issues.push("Output intent has no destination profile".into());
+        return None;
+    };
+    let components = profile.dict.get(b"N").and_then(Object::as_i64).ok();
+    let Ok(icc) = profile.get_plain_content() else {
+        issues.push("Output intent profile cannot be decoded".into());
+        return None;
+    };
+
+    let space = match components {
+        Some(1) => &b"GRAY"[..],
+        Some(3) => b"RGB ",
+        Some(4) => b"CMYK",
+        _ => {
+            issues.push("Output intent profile has no valid component count (N)".into());
+            return None;
+        }
+    };
+    let valid = icc.len() >= 132
+        && u32::from_be_bytes([icc[0], icc[1], icc[2], icc[3]]) as usize == icc.len()
+        && icc[8] <= 4
+        && matches!(&icc[12..16], b"mntr" | b"prtr")
+        && &icc[16..20] == space
+        && &icc[36..40] == b"acsp";
+    if !valid {
+        issues.push("Output intent profile is not a valid output ICC profile".into());
+    }
+    components
+}
+
+fn check_stream(stream: &Stream, issues: &mut Vec<String>, id: lopdf::ObjectId) {
+    if stream
+        .filters()
+        .is_ok_and(|filters| filters.contains(&&b"LZWDecode"[..]))
+    {
+        issues.push(format!("Object {:?} uses LZW compression", id));
+    }
+    if stream.dict.has(b"F") || stream.dict.has(b"FFilter") {
+        issues.push(format!("Object {:?} refers to an external file", id));
+    }
+}
+
+fn check_dictionary(
+    document: &Document,
+    dict: &Dictionary,
+    components: Option<i64>,
+    issues: &mut Vec<String>,
+    id: lopdf::ObjectId,
+) {
+    let name = |key: &[u8]| dict.get(key).and_then(Object::as_name).ok();
+
+    const FORBIDDEN_ACTIONS: [&[u8]; 7] = [
+        b"JavaScript",
+        b"Launch",
+        b"Sound",
+        b"Movie",
+        b"ResetForm",
+        b"ImportData",
+        b"Hide",
+    ];
+    if dict.has(b"JS") || name(b"S").is_some_and(|action| FORBIDDEN_ACTIONS.contains(&action)) {
+        issues.push(format!("Object {:?} is a forbidden action", id));
+    }
+
+    match name(b"Type") {
+        Some(b"Font") => {
+            if let Some(font) = unembedded_font(document, dict) {
+                issues.push(format!("Font {} is not embedded", font));
+            }
+        }
+        Some(b"Annot") => {
+            let flags = dict.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+            // Print (4) must be set; Invisible (1), Hidden (2) and NoView (32) must not
+            if name(b"Subtype") != Some(b"Popup") && (flags & 4 == 0 || flags & 0b10_0011 != 0) {
+                issues.push(format!("Annotation {:?} is not set to print", id));
+            }
+        }
+        Some(b"ExtGState") => {
+            let default_transfer = dict
+                .get(b"TR2")
+                .map_or(true, |tr2| tr2.as_name().is_ok_and(|tr2| tr2 == b"Default"));
+            if dict.has(b"TR") || !default_transfer {
+                issues.push(format!("Graphics state {:?} sets a transfer function", id));
+            }
+        }
+        _ => {}
+    }
+
+    if name(b"Subtype") == Some(b"Image") {
+        if dict
+            .get(b"Interpolate")
+            .and_then(Object::as_bool)
+            .is_ok_and(|interpolate| interpolate)
+        {
+            issues.push(format!("Image {:?} requests interpolation", id));
+        }
+        if dict.has(b"Alternates") || dict.has(b"OPI") {
+            issues.push(format!("Image {:?} has alternates or OPI", id));
+        }
+        let device_components = match name(b"ColorSpace") {
+            Some(b"DeviceRGB") => Some(3),
+            Some(b"DeviceCMYK") => Some(4),
+            _ => None,
+        };
+        if let Some(needed) = device_components
+            && components.is_some_and(|components| components != needed)
+        {
+            issues.push(format!(
+                "Image {:?} uses a device colour space that does not match the output intent",
+                id
+            ));
+        }
+    }
+}
+
+/// Base name of `font` if its glyphs are not embedded in the file
+fn unembedded_font(document: &Document, font: &Dictionary) -> Option<String> {
+    fn dict_at<'a>(document: &'a Document, object: &'a Object) -> Option<&'a Dictionary> {
+        let (_, object) = document.dereference(object).ok()?;
+        object.as_dict().ok()
+    }
+    let glyphs_font = match font.get(b"Subtype").and_then(Object::as_name) {
+        // Type 3 glyphs are content streams
+        Ok(b"Type3") => return None,
+        Ok(b"Type0") => font
+            .get(b"DescendantFonts")
+            .and_then(Object::as_array)
+            .ok()
+            .and_then(|fonts| fonts.first())
+            .and_then(|descendant| dict_at(document, descendant)),
+        _ => Some(font),
+    };
+    let embedded = glyphs_font
+        .and_then(|font| font.get(b"FontDescriptor").ok())
+        .and_then(|descriptor| dict_at(document, descriptor))
+        .is_some_and(|descriptor| {
+            [&b"FontFile"[..], b"FontFile2", b"FontFile3"]
+                .iter()
+                .any(|key| descriptor.has(key))
+        });
+    if embedded {
+        return None;
+    }
+    let name = font
+        .get(b"BaseFont")
+        .and_then(Object::as_name)
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .unwrap_or_else(|_| "(unnamed)".into());
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::{PdfGenerator, PdfOptions};
+    use image::{DynamicImage, Rgba, RgbaImage};
+    use std::path::PathBuf;
+
+    fn images() -> Vec<(DynamicImage, PathBuf)> {
+        let translucent = RgbaImage::from_pixel(30, 20, Rgba([200, 40, 40, 128]));
+        vec![
+            (DynamicImage::new_rgb8(40, 30), PathBuf::from("scan 1.png")),
+            (
+                DynamicImage::ImageRgba8(translucent),
+                PathBuf::from("stamp.png"),
+            ),
+            (DynamicImage::new_luma8(20, 20), PathBuf::from("gray.png")),
+        ]
+    }
+
+    fn generate(options: PdfOptions) -> Document {
+        let bytes = PdfGenerator::new(options).generate_bytes(images()).unwrap();
+        Document::load_mem(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_archival_output_passes_check() {
+        let document = generate(PdfOptions {
+            pdfa: true,
+            info: PdfInfo {
+                title: Some("Scans <2024> & more".into()),
+                author: Some("Łukasz".into()),
+                keywords: Some("archive".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        assert_eq!(conformance_issues(&document), Vec::<String>::new());
+        assert_eq!(document.version, "1.7");
+
+        let catalog = document.catalog().unwrap();
+        let metadata = catalog.get(b"Metadata").unwrap().as_reference().unwrap();
+        let xml = document.get_object(metadata).unwrap().as_stream().unwrap();
+        let xml = std::str::from_utf8(&xml.content).unwrap();
+        assert_eq!(
+            xmp_property(xml, "dc:title").as_deref(),
+            Some("Scans <2024> & more")
+        );
+        assert_eq!(xmp_property(xml, "dc:creator").as_deref(), Some("Łukasz"));
+    }
+
+    #[test]
+    fn test_check_finds_missing_structures() {
+        let issues = conformance_issues(&generate(PdfOptions::default()));
+        for expected in ["XMP metadata", "output intent"] {
+            assert!(
+                issues.iter().any(|issue| issue.contains(expected)),
+                "no {} issue in {:?}",
+                expected,
+                issues
+            );
+        }
+
+        // Captions are set in a standard font that is not embedded
+        let issues = conformance_issues(&generate(PdfOptions {
+            captions: true,
+            ..Default::default()
+        }));
+        assert!(issues.iter().any(|issue| issue.contains("not embedded")));
+    }
+
+    #[test]
+    fn test_check_finds_mismatched_metadata() {
+        let mut document = generate(PdfOptions {
+            pdfa: true,
+            ..Default::default()
+        });
+        let info_id = document
+            .trailer
+            .get(b"Info")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        document
+            .get_dictionary_mut(info_id)
+            .unwrap()
+            .set("Title", Object::string_literal("Renamed"));
+
+        assert_eq!(
+            conformance_issues(&document),
+            ["Info Title does not match XMP dc:title"]
+        );
+    }
+
+    #[test]
+    fn test_info_changes_rewrite_xmp() {
+        let mut document = generate(PdfOptions::default());
+        let metadata = document.add_object(metadata_stream(&PdfInfo::default(), None));
+        document.catalog_mut().unwrap().set("Metadata", metadata);
+
+        PdfInfo {
+            title: Some("Renamed".into()),
+            author: Some("Ada".into()),
+            ..Default::default()
+        }
+        .apply(&mut document)
+        .unwrap();
+
+        let xml = document.get_object(metadata).unwrap().as_stream().unwrap();
+        let xml = std::str::from_utf8(&xml.content).unwrap();
+        assert_eq!(xmp_property(xml, "dc:title").as_deref(), Some("Renamed"));
+        assert_eq!(xmp_property(xml, "dc:creator").as_deref(), Some("Ada"));
+        // Metadata without a PDF/A identification does not gain one
+        assert_eq!(xmp_property(xml, "pdfaid:part"), None);
+    }
+
+    #[test]
+    fn test_info_changes_keep_unrelated_xmp() {
+        let packet = "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>
+<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">
+ <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">
+  <rdf:Description rdf:about=\"\" pdf:Producer=\"Scanner 2.0\"
+    xmlns:dc=\"http://purl.org/dc/elements/1.1/\"
+    xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\"
+    xmlns:xmpMM=\"http://ns.adobe.com/xap/1.0/mm/\"
+    xmlns:acme=\"http://example.com/acme/\">
+   <dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">Old</rdf:li></rdf:Alt></dc:title>
+   <xmpMM:DocumentID>uuid:1234</xmpMM:DocumentID>
+   <acme:Batch>42</acme:Batch>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end=\"w\"?>";
+        let mut document = generate(PdfOptions::default());
+        let metadata = document.add_object(xmp_stream(packet.into()));
+        document.catalog_mut().unwrap().set("Metadata", metadata);
+
+        PdfInfo {
+            title: Some("Renamed".into()),
+            producer: Some("Transmute".into()),
+            ..Default::default()
+        }
+        .apply(&mut document)
+        .unwrap();
+
+        let xml = document.get_object(metadata).unwrap().as_stream().unwrap();
+        let xml = std::str::from_utf8(&xml.content).unwrap();
+        assert_eq!(xmp_property(xml, "dc:title").as_deref(), Some("Renamed"));
+        assert_eq!(
+            xmp_property(xml, "pdf:Producer").as_deref(),
+            Some("Transmute")
+        );
+        assert_eq!(xml.matches("<dc:title>").count(), 1);
+        assert!(!xml.contains("Scanner 2.0"));
+        assert_eq!(
+            xmp_property(xml, "xmpMM:DocumentID").as_deref(),
+            Some("uuid:1234")
+        );
+        assert_eq!(xmp_property(xml, "acme:Batch").as_deref(), Some("42"));
+        assert!(xml.trim_end().ends_with("<?xpacket end=\"w\"?>"));
+    }
+
+    #[test]
+    fn test_info_changes_keep_pdfa_conformance() {
+        let mut document = generate(PdfOptions {
+            pdfa: true,
+            ..Default::default()
+        });
+        PdfInfo {
+            title: Some("Renamed".into()),
+            keywords: Some("".into()),
+            ..Default::default()
+        }
+        .for_edited_document()
+        .apply(&mut document)
+        .unwrap();
+
+        assert_eq!(conformance_issues(&document), Vec::<String>::new());
+        let metadata = document
+            .catalog()
+            .unwrap()
+            .get(b"Metadata")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let xml = document.get_object(metadata).unwrap().as_stream().unwrap();
+        let xml = std::str::from_utf8(&xml.content).unwrap();
+        assert_eq!(xmp_property(xml, "pdfaid:part").as_deref(), Some("2"));
+        assert_eq!(
+            xmp_property(xml, "pdfaid:conformance").as_deref(),
+            Some("B")
+        );
+    }
+
+    #[test]
+    fn test_conformance_breaking_options_are_refused() {
+        let generator = PdfGenerator::new(PdfOptions {
+            pdfa: true,
+            captions: true,
+            ..Default::default()
+        });
+        assert!(generator.generate_bytes(images()).is_err());
+    }
+
+    #[test]
+    fn test_srgb_profile_header() {
+        let icc = srgb_profile();
+        assert_eq!(
+            u32::from_be_bytes(icc[..4].try_into().unwrap()) as usize,
+            icc.len()
+        );
+        assert_eq!(&icc[12..24], b"mntrRGB XYZ ");
+        assert_eq!(&icc[36..40], b"acsp");
+        assert_eq!(icc.len() % 4, 0);
+    }
+}
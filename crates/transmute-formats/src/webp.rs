@@ -0,0 +1,164 @@
+use image::DynamicImage;
+use transmute_common::{Error, Result};
+
+/// WebP compression mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WebpMode {
+    /// VP8 lossy encoding with quality 0-100
+    Lossy { quality: f32 },
+
+    /// VP8L lossless encoding, pixel-exact (including RGB under transparent pixels)
+    Lossless,
+
+    /// Lossless container with lossy preprocessing: 0 = strongest, 100 = off
+    NearLossless { level: u8 },
+}
+
+/// WebP encoder configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WebpOptions {
+    pub mode: WebpMode,
+
+    /// Quality of the alpha plane in lossy mode (0-100)
+    pub alpha_quality: u8,
+
+    /// Speed/size trade-off (0 = fastest, 6 = smallest)
+    pub method: u8,
+}
+
+impl Default for WebpOptions {
+    fn default() -> Self {
+        Self::lossy(90.0)
+    }
+}
+
+impl WebpOptions {
+    pub fn lossy(quality: f32) -> Self {
+        Self {
+            mode: WebpMode::Lossy {
+                quality: quality.clamp(0.0, 100.0),
+            },
+            alpha_quality: 100,
+            method: 4,
+        }
+    }
+
+    pub fn lossless() -> Self {
+        Self {
+            mode: WebpMode::Lossless,
+            ..Self::lossy(75.0)
+        }
+    }
+
+    pub fn near_lossless(level: u8) -> Self {
+        Self {
+            mode: WebpMode::NearLossless {
+                level: level.min(100),
+            },
+            ..Self::lossy(75.0)
+        }
+    }
+
+    pub(crate) fn to_config(self) -> Result<::webp::WebPConfig> {
+        let mut config = ::webp::WebPConfig::new()
+            .map_err(|_| Error::ConversionError("Failed to initialize WebP config".into()))?;
+
+        config.method = self.method.min(6) as i32;
+        config.alpha_quality = self.alpha_quality.min(100) as i32;
+
+        match self.mode {
+            WebpMode::Lossy { quality } => {
+                config.lossless = 0;
+                config.quality = quality;
+            }
+            WebpMode::Lossless => {
+                config.lossless = 1;
+                config.exact = 1;
+            }
+            WebpMode::NearLossless { level } => {
+                config.lossless = 1;
+                config.near_lossless = level as i32;
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// libwebp-backed encoder shared by conversion and compression
+pub struct WebpEncoder;
+
+impl WebpEncoder {
+    /// Encode to WebP, keeping the alpha channel if the image has one
+    pub fn encode(img: &DynamicImage, options: &WebpOptions) -> Result<Vec<u8>> {
+        let config = options.to_config()?;
+
+        tracing::debug!(
+            "WebP encoding {}x{} ({:?}, alpha: {})",
+            img.width(),
+            img.height(),
+            options.mode,
+            img.color().has_alpha()
+        );
+
+        let encoded = if img.color().has_alpha() {
+            let rgba = img.to_rgba8();
+            ::webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height())
+                .encode_advanced(&config)
+        } else {
+            let rgb = img.to_rgb8();
+            ::webp::Encoder::from_rgb(rgb.as_raw(), rgb.width(), rgb.height())
+                .encode_advanced(&config)
+        };
+
+        encoded
+            .map(|data| data.to_vec())
+            .map_err(|e| Error::ConversionError(format!("WebP encoding failed: {:?}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn gradient_rgba() -> DynamicImage {
+        let img = RgbaImage::from_fn(64, 48, |x, y| {
+            Rgba([
+                (x * 4) as u8,
+                (y * 5) as u8,
+                ((x + y) * 2) as u8,
+                (x * 3) as u8,
+            ])
+        });
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn test_lossless_roundtrip_keeps_alpha() {
+        let img = gradient_rgba();
+        let data = WebpEncoder::encode(&img, &WebpOptions::lossless()).unwrap();
+
+        let decoded = image::load_from_memory(&data).unwrap();
+        assert!(decoded.color().has_alpha());
+        assert_eq!(decoded.to_rgba8(), img.to_rgba8());
+    }
+
+    #[test]
+    fn test_lossy_quality_affects_size() {
+        let img = DynamicImage::ImageRgb8(gradient_rgba().to_rgb8());
+
+        let low = WebpEncoder::encode(&img, &WebpOptions::lossy(10.0)).unwrap();
+        let high = WebpEncoder::encode(&img, &WebpOptions::lossy(95.0)).unwrap();
+
+        assert!(low.len() < high.len());
+        assert!(!image::load_from_memory(&high).unwrap().color().has_alpha());
+    }
+
+    #[test]
+    fn test_near_lossless_is_valid_webp() {
+        let data = WebpEncoder::encode(&gradient_rgba(), &WebpOptions::near_lossless(60)).unwrap();
+        let decoded = image::load_from_memory(&data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (64, 48));
+    }
+}
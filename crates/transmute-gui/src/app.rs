@@ -1,12 +1,13 @@
 use crate::preview::{
     create_texture, format_file_size, CachedImage, ImageLoader, LoadingState, TextureCache,
 };
-use crate::state::{AppState, FileStatus, Operation, ProcessingState};
+use crate::state::{AppState, FileStatus, Operation, ProcessingState, Settings};
 use crate::theme::Theme;
 use crate::widgets;
 use egui::{CentralPanel, ScrollArea, SidePanel, TopBottomPanel};
 use std::path::PathBuf;
 use std::sync::Arc;
+use transmute_common::{MediaFormat, MetadataPolicy};
 use transmute_core::Converter;
 
 pub struct TransmuteApp {
@@ -27,12 +28,12 @@ impl TransmuteApp {
         // Configure theme
         Theme::configure(&cc.egui_ctx);
 
-        // Initialize converter
-        let converter = Converter::new().expect("Failed to initialize converter");
+        let state = AppState::new();
+        let converter = Self::build_converter(&state.settings());
 
         Self {
-            state: AppState::new(),
-            converter: Arc::new(converter),
+            state,
+            converter,
             show_settings: false,
             settings_button_rect: None,
             processing_handle: None,
@@ -43,6 +44,18 @@ impl TransmuteApp {
         }
     }
 
+    /// Build a converter reflecting the user's settings
+    fn build_converter(settings: &Settings) -> Arc<Converter> {
+        Arc::new(Self::configured_converter(settings))
+    }
+
+    fn configured_converter(settings: &Settings) -> Converter {
+        let mut converter = Converter::new().expect("Failed to initialize converter");
+        converter.set_gpu_enabled(settings.use_gpu);
+        converter.set_metadata_policy(settings.metadata_policy);
+        converter
+    }
+
     /// Process completed thumbnail/image loads from the background thread
     fn process_thumbnail_results(&mut self, ctx: &egui::Context) {
         let responses = self.image_loader.poll_responses();
@@ -75,9 +88,9 @@ impl TransmuteApp {
     }
 
     /// Ensure a full-size preview is loaded for the given path
-    fn ensure_full_loaded(&mut self, path: &PathBuf) {
+    fn ensure_full_loaded(&mut self, path: &std::path::Path) {
         if !self.texture_cache.has_full(path) && !self.image_loader.is_pending(path) {
-            self.image_loader.request_full(path.clone());
+            self.image_loader.request_full(path.to_path_buf());
         }
     }
 
@@ -201,8 +214,17 @@ impl TransmuteApp {
                         ui.add_space(8.0);
 
                         let mut quality = self.state.quality();
-                        if widgets::quality_selector(ui, &mut quality) {
+                        let mut size_target = self.state.size_target();
+                        let mut min_ssim = self.state.min_ssim();
+                        if widgets::quality_selector(
+                            ui,
+                            &mut quality,
+                            &mut size_target,
+                            &mut min_ssim,
+                        ) {
                             self.state.set_quality(quality);
+                            self.state.set_size_target(size_target);
+                            self.state.set_min_ssim(min_ssim);
                         }
                     }
 
@@ -243,14 +265,25 @@ impl TransmuteApp {
                         );
                         ui.add_space(8.0);
 
-                        ui.label("Merge all images into a single PDF document");
-                        ui.add_space(4.0);
+                        ui.label("Merge all images and PDFs into a single PDF document");
+                        ui.add_space(8.0);
 
-                        ui.label(
-                            egui::RichText::new("Output DPI: 300")
-                                .size(12.0)
-                                .color(Theme::TEXT_SECONDARY)
-                        );
+                        let mut options = self.state.pdf_options();
+                        if widgets::pdf_layout_selector(ui, &mut options) {
+                            self.state.set_pdf_options(options);
+                        }
+
+                        ui.add_space(8.0);
+                        let mut password = self.state.pdf_password();
+                        ui.horizontal(|ui| {
+                            ui.label("Password of protected PDFs");
+                            if ui
+                                .add(egui::TextEdit::singleline(&mut password).password(true))
+                                .changed()
+                            {
+                                self.state.set_pdf_password(password.clone());
+                            }
+                        });
                     }
                 }
             });
@@ -263,9 +296,9 @@ impl TransmuteApp {
         if response.clicked() {
             // Open file picker - allow both images and PDFs
             if let Some(paths) = rfd::FileDialog::new()
-                .add_filter("Images", &["png", "jpg", "jpeg", "webp", "tiff", "bmp"])
+                .add_filter("Images", &["png", "jpg", "jpeg", "webp", "tiff", "bmp", "heic", "heif"])
                 .add_filter("PDF", &["pdf"])
-                .add_filter("All Supported", &["png", "jpg", "jpeg", "webp", "tiff", "bmp", "pdf"])
+                .add_filter("All Supported", &["png", "jpg", "jpeg", "webp", "tiff", "bmp", "heic", "heif", "pdf"])
                 .pick_files()
             {
                 self.state.add_files(paths);
@@ -281,17 +314,8 @@ impl TransmuteApp {
                     .iter()
                     .filter_map(|f| f.path.clone())
                     .filter(|p| {
-                        // Accept files with supported extensions (case-insensitive)
-                        p.extension()
-                            .and_then(|ext| ext.to_str())
-                            .map(|ext| {
-                                let ext_lower = ext.to_lowercase();
-                                matches!(
-                                    ext_lower.as_str(),
-                                    "png" | "jpg" | "jpeg" | "webp" | "tiff" | "bmp" | "pdf"
-                                )
-                            })
-                            .unwrap_or(false)
+                        // Accept files whose content (or, failing that, extension) can be read
+                        MediaFormat::detect(p).is_some()
                     })
                     .collect();
                 self.state.add_files(paths);
@@ -425,10 +449,10 @@ impl TransmuteApp {
 
                         // === RIGHT SIDE: Remove button (completely separate) ===
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            if matches!(file.status, FileStatus::Pending) {
-                                if ui.small_button("Remove").clicked() {
-                                    file_to_remove = Some(idx);
-                                }
+                            if matches!(file.status, FileStatus::Pending)
+                                && ui.small_button("Remove").clicked()
+                            {
+                                file_to_remove = Some(idx);
                             }
 
                             // Error message
@@ -507,18 +531,24 @@ impl TransmuteApp {
                 }
             }
 
-            if self.state.output_dir().is_some() {
-                if ui.button("Reset to Default").clicked() {
-                    self.state.set_output_dir(None);
-                }
+            if self.state.output_dir().is_some() && ui.button("Reset to Default").clicked() {
+                self.state.set_output_dir(None);
             }
         });
     }
 
     fn render_action_buttons(&mut self, ui: &mut egui::Ui) {
         let files = self.state.input_files();
-        let can_process =
-            !files.is_empty() && matches!(self.state.processing_state(), ProcessingState::Idle);
+        // An empty password would protect nothing, so encryption waits for one
+        let missing_password = self.state.operation() == Operation::Merge
+            && self
+                .state
+                .pdf_options()
+                .encryption
+                .is_some_and(|encryption| encryption.user_password.is_empty());
+        let can_process = !files.is_empty()
+            && !missing_password
+            && matches!(self.state.processing_state(), ProcessingState::Idle);
 
         ui.horizontal(|ui| {
             // Primary action button with prominent styling
@@ -726,6 +756,34 @@ impl TransmuteApp {
                         .color(Theme::TEXT_SECONDARY)
                 );
 
+                ui.add_space(16.0);
+
+                // Metadata policy setting
+                ui.horizontal(|ui| {
+                    ui.label(
+                        egui::RichText::new("Metadata")
+                            .size(14.0)
+                    );
+                    egui::ComboBox::from_id_salt("metadata_policy")
+                        .selected_text(settings.metadata_policy.label())
+                        .show_ui(ui, |ui| {
+                            for policy in MetadataPolicy::ALL {
+                                ui.selectable_value(
+                                    &mut settings.metadata_policy,
+                                    policy,
+                                    policy.label(),
+                                );
+                            }
+                        });
+                });
+
+                ui.add_space(4.0);
+                ui.label(
+                    egui::RichText::new("EXIF, XMP and color profiles carried into outputs")
+                        .size(11.0)
+                        .color(Theme::TEXT_SECONDARY)
+                );
+
                 ui.add_space(20.0);
                 ui.separator();
                 ui.add_space(12.0);
@@ -761,6 +819,7 @@ impl TransmuteApp {
         if save_settings {
             // Save the temp settings to state
             if let Some(settings) = self.temp_settings.take() {
+                self.converter = Self::build_converter(&settings);
                 self.state.update_settings(|s| *s = settings);
             }
         }
@@ -986,8 +1045,18 @@ impl TransmuteApp {
 
     fn start_processing(&mut self) {
         let state = self.state.clone();
-        let converter = Arc::clone(&self.converter);
         let operation = state.operation();
+        let password = state.pdf_password();
+        let converter = if operation == Operation::Merge && !password.is_empty() {
+            // Protected input PDFs open with the password from the PDF settings
+            let mut converter = Self::configured_converter(&state.settings());
+            for file in state.input_files() {
+                converter.set_pdf_password(file.path, password.clone());
+            }
+            Arc::new(converter)
+        } else {
+            Arc::clone(&self.converter)
+        };
 
         // Spawn background thread for processing (egui runs without tokio runtime)
         let handle = std::thread::spawn(move || {
@@ -999,7 +1068,7 @@ impl TransmuteApp {
             let mut success = 0;
             let mut failed = 0;
 
-            // Special handling for merge operation - combine all images into single PDF
+            // Special handling for merge operation - combine all images and PDFs into single PDF
             if operation == Operation::Merge {
                 // Mark all files as processing
                 for idx in 0..files.len() {
@@ -1020,8 +1089,12 @@ impl TransmuteApp {
                     default_dir.join("merged.pdf")
                 };
 
-                // Perform merge
-                let result = converter.images_to_pdf(input_paths, output_path.clone(), None);
+                // Perform merge (pages of input PDFs are copied, not rasterized)
+                let result = converter.merge_to_pdf(
+                    input_paths,
+                    output_path.clone(),
+                    Some(state.pdf_options()),
+                );
 
                 match result {
                     Ok(pdf_path) => {
@@ -1069,14 +1142,27 @@ impl TransmuteApp {
                         state.target_format(),
                     ),
 
-                    Operation::Compress => converter
-                        .compress_image(
+                    Operation::Compress => match (state.size_target(), state.min_ssim()) {
+                        (Some(target), _) => converter.compress_image_to_size(
+                            &file.path,
+                            state.target_format(),
+                            target,
+                            state.output_dir(),
+                        ),
+                        (None, Some(min_ssim)) => converter.compress_image_to_ssim(
+                            &file.path,
+                            state.target_format(),
+                            min_ssim,
+                            state.output_dir(),
+                        ),
+                        (None, None) => converter.compress_image(
                             &file.path,
                             state.target_format(),
                             state.quality(),
                             state.output_dir(),
-                        )
-                        .map(|(path, _)| path),
+                        ),
+                    }
+                    .map(|(path, _)| path),
 
                     Operation::Enhance => Err(transmute_common::Error::ConversionError(
                         "Enhancement not implemented".into(),
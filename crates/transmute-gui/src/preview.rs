@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::thread::{self, JoinHandle};
+use transmute_common::MediaFormat;
 
 /// Loading state for images
 #[derive(Debug, Clone, PartialEq)]
@@ -193,16 +194,29 @@ impl ImageLoader {
             .map(|m| m.len())
             .unwrap_or(0);
 
-        // Load image using image crate
-        let img = image::open(path).map_err(|e| format!("Failed to open image: {}", e))?;
+        let detected = MediaFormat::detect(path);
+
+        // Load image using image crate, sniffing the format from file contents.
+        // HEIF, AVIF and JPEG XL have no image-crate decoder and go through libheif/libjxl.
+        let img = if matches!(
+            detected,
+            Some(MediaFormat::Heif | MediaFormat::Avif | MediaFormat::Jxl)
+        ) {
+            transmute_formats::ImageDecoder::decode(path)
+                .map(|(img, _)| img)
+                .map_err(|e| format!("Failed to open image: {}", e))?
+        } else {
+            image::ImageReader::open(path)
+                .and_then(|r| r.with_guessed_format())
+                .map_err(|e| format!("Failed to open image: {}", e))?
+                .decode()
+                .map_err(|e| format!("Failed to open image: {}", e))?
+        };
 
         let (width, height) = img.dimensions();
 
-        // Extract format from extension
-        let format = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(|s| s.to_uppercase())
+        let format = detected
+            .map(|f| f.to_string())
             .unwrap_or_else(|| "Unknown".to_string());
 
         // Determine color type
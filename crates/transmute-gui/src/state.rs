@@ -1,8 +1,9 @@
 use parking_lot::Mutex;
 use std::path::PathBuf;
 use std::sync::Arc;
-use transmute_common::MediaFormat;
-use transmute_compress::QualitySettings;
+use transmute_common::{MediaFormat, MetadataPolicy};
+use transmute_compress::{QualitySettings, SizeTarget};
+use transmute_formats::PdfOptions;
 
 /// Application state (shared across UI and background tasks)
 #[derive(Clone)]
@@ -23,9 +24,21 @@ struct AppStateInner {
     /// Quality setting for compression
     pub quality: QualitySettings,
 
+    /// File size limit that replaces the quality setting when set
+    pub size_target: Option<SizeTarget>,
+
+    /// SSIM floor that replaces the quality setting when set
+    pub min_ssim: Option<f64>,
+
     /// Scale factor for enhancement
     pub scale_factor: u32,
 
+    /// Page layout for merged PDFs
+    pub pdf_options: PdfOptions,
+
+    /// Password that opens protected input PDFs
+    pub pdf_password: String,
+
     /// Output directory
     pub output_dir: Option<PathBuf>,
 
@@ -84,6 +97,7 @@ pub struct Settings {
     pub use_gpu: bool,
     pub auto_open_output: bool,
     pub dark_mode: bool,
+    pub metadata_policy: MetadataPolicy,
 }
 
 impl Default for Settings {
@@ -92,6 +106,7 @@ impl Default for Settings {
             use_gpu: false,
             auto_open_output: false,
             dark_mode: true,
+            metadata_policy: MetadataPolicy::default(),
         }
     }
 }
@@ -104,7 +119,11 @@ impl AppState {
                 operation: Operation::Convert,
                 target_format: MediaFormat::Jpeg,
                 quality: QualitySettings::High,
+                size_target: None,
+                min_ssim: None,
                 scale_factor: 2,
+                pdf_options: PdfOptions::default(),
+                pdf_password: String::new(),
                 output_dir: None,
                 processing: ProcessingState::Idle,
                 nl_command: String::new(),
@@ -200,6 +219,26 @@ impl AppState {
         self.inner.lock().quality = quality;
     }
 
+    /// Get file size limit
+    pub fn size_target(&self) -> Option<SizeTarget> {
+        self.inner.lock().size_target
+    }
+
+    /// Set file size limit
+    pub fn set_size_target(&self, target: Option<SizeTarget>) {
+        self.inner.lock().size_target = target;
+    }
+
+    /// Get SSIM floor
+    pub fn min_ssim(&self) -> Option<f64> {
+        self.inner.lock().min_ssim
+    }
+
+    /// Set SSIM floor
+    pub fn set_min_ssim(&self, min_ssim: Option<f64>) {
+        self.inner.lock().min_ssim = min_ssim;
+    }
+
     /// Get scale factor
     pub fn scale_factor(&self) -> u32 {
         self.inner.lock().scale_factor
@@ -210,6 +249,26 @@ impl AppState {
         self.inner.lock().scale_factor = scale;
     }
 
+    /// Get PDF page layout
+    pub fn pdf_options(&self) -> PdfOptions {
+        self.inner.lock().pdf_options.clone()
+    }
+
+    /// Set PDF page layout
+    pub fn set_pdf_options(&self, options: PdfOptions) {
+        self.inner.lock().pdf_options = options;
+    }
+
+    /// Get password for protected input PDFs
+    pub fn pdf_password(&self) -> String {
+        self.inner.lock().pdf_password.clone()
+    }
+
+    /// Set password for protected input PDFs
+    pub fn set_pdf_password(&self, password: String) {
+        self.inner.lock().pdf_password = password;
+    }
+
     /// Get output directory
     pub fn output_dir(&self) -> Option<PathBuf> {
         self.inner.lock().output_dir.clone()
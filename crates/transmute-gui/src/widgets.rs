@@ -85,7 +85,11 @@ pub fn drop_zone(ui: &mut Ui, hovered: bool) -> Response {
 
         // Hint text below
         if !hovered && !response.hovered() {
-            let hint = "Supported: PNG, JPEG, WebP, TIFF, BMP";
+            let hint = if cfg!(feature = "heif") {
+                "Supported: PNG, JPEG, WebP, TIFF, BMP, HEIC"
+            } else {
+                "Supported: PNG, JPEG, WebP, TIFF, BMP"
+            };
             ui.painter().text(
                 rect.center() + Vec2::new(0.0, 25.0),
                 egui::Align2::CENTER_CENTER,
@@ -105,6 +109,7 @@ pub fn format_selector(ui: &mut Ui, selected: &mut transmute_common::MediaFormat
         transmute_common::MediaFormat::Png,
         transmute_common::MediaFormat::Jpeg,
         transmute_common::MediaFormat::Webp,
+        transmute_common::MediaFormat::Avif,
         transmute_common::MediaFormat::Tiff,
         transmute_common::MediaFormat::Bmp,
         transmute_common::MediaFormat::Pdf,
@@ -128,8 +133,20 @@ pub fn format_selector(ui: &mut Ui, selected: &mut transmute_common::MediaFormat
     changed
 }
 
-/// Quality selector with presets and slider
-pub fn quality_selector(ui: &mut Ui, quality: &mut transmute_compress::QualitySettings) -> bool {
+/// Size a newly enabled file size limit starts at
+const DEFAULT_SIZE_LIMIT_KB: u64 = 500;
+
+/// SSIM a newly enabled visual quality target starts at
+const DEFAULT_MIN_SSIM: f64 = 0.95;
+
+/// Quality selector with presets, slider and an optional file size limit or
+/// SSIM floor, which exclude each other
+pub fn quality_selector(
+    ui: &mut Ui,
+    quality: &mut transmute_compress::QualitySettings,
+    size_target: &mut Option<transmute_compress::SizeTarget>,
+    min_ssim: &mut Option<f64>,
+) -> bool {
     let mut changed = false;
 
     ui.label("Quality Level");
@@ -195,6 +212,203 @@ pub fn quality_selector(ui: &mut Ui, quality: &mut transmute_compress::QualitySe
         changed = true;
     }
 
+    ui.add_space(8.0);
+    let mut limit = size_target.is_some();
+    if ui
+        .checkbox(&mut limit, "Limit file size (overrides quality)")
+        .changed()
+    {
+        *size_target = limit.then(|| {
+            transmute_compress::SizeTarget::new(transmute_common::FileSize::from_bytes(
+                DEFAULT_SIZE_LIMIT_KB * 1000,
+            ))
+        });
+        if limit {
+            *min_ssim = None;
+        }
+        changed = true;
+    }
+    if let Some(target) = size_target {
+        let mut max_kb = (target.max_size.bytes() / 1000).max(1);
+        if ui
+            .add(
+                egui::Slider::new(&mut max_kb, 10..=20_000)
+                    .logarithmic(true)
+                    .text("Max size")
+                    .suffix(" KB")
+            )
+            .changed()
+        {
+            target.max_size = transmute_common::FileSize::from_bytes(max_kb * 1000);
+            changed = true;
+        }
+        if ui
+            .checkbox(&mut target.allow_downscale, "Shrink dimensions if needed")
+            .changed()
+        {
+            changed = true;
+        }
+    }
+
+    ui.add_space(8.0);
+    let mut floor = min_ssim.is_some();
+    if ui
+        .checkbox(&mut floor, "Smallest file that keeps SSIM (overrides quality)")
+        .changed()
+    {
+        *min_ssim = floor.then_some(DEFAULT_MIN_SSIM);
+        if floor {
+            *size_target = None;
+        }
+        changed = true;
+    }
+    if let Some(value) = min_ssim {
+        if ui
+            .add(
+                egui::Slider::new(value, 0.80..=0.995)
+                    .text("Min SSIM")
+                    .fixed_decimals(3)
+            )
+            .changed()
+        {
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+/// Page size, orientation, margin, fit and DPI for generated PDF pages
+pub fn pdf_layout_selector(ui: &mut Ui, options: &mut transmute_formats::PdfOptions) -> bool {
+    use transmute_formats::{FitMode, PageOrientation, PageSize};
+
+    let mut changed = false;
+
+    egui::ComboBox::from_label("Page Size")
+        .selected_text(options.page_size.to_string())
+        .show_ui(ui, |ui| {
+            for size in [
+                PageSize::A3,
+                PageSize::A4,
+                PageSize::A5,
+                PageSize::Letter,
+                PageSize::Legal,
+            ] {
+                if ui
+                    .selectable_value(&mut options.page_size, size, size.to_string())
+                    .clicked()
+                {
+                    changed = true;
+                }
+            }
+        });
+
+    ui.add_space(6.0);
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 6.0;
+
+        for (orientation, label) in [
+            (PageOrientation::Auto, "Auto"),
+            (PageOrientation::Portrait, "Portrait"),
+            (PageOrientation::Landscape, "Landscape"),
+        ] {
+            if ui
+                .selectable_label(options.orientation == orientation, label)
+                .clicked()
+            {
+                options.orientation = orientation;
+                changed = true;
+            }
+        }
+    });
+
+    ui.add_space(6.0);
+    egui::ComboBox::from_label("Image Fit")
+        .selected_text(match options.fit {
+            FitMode::Fit => "Fit to page",
+            FitMode::Fill => "Fill page (crop)",
+            FitMode::Original => "Original size",
+            FitMode::ImageSize => "Page matches image",
+        })
+        .show_ui(ui, |ui| {
+            for (fit, label) in [
+                (FitMode::Fit, "Fit to page"),
+                (FitMode::Fill, "Fill page (crop)"),
+                (FitMode::Original, "Original size"),
+                (FitMode::ImageSize, "Page matches image"),
+            ] {
+                if ui.selectable_value(&mut options.fit, fit, label).clicked() {
+                    changed = true;
+                }
+            }
+        });
+
+    ui.add_space(6.0);
+    if ui
+        .add(
+            egui::Slider::new(&mut options.margin_mm, 0.0..=50.0)
+                .text("Margin")
+                .suffix(" mm")
+        )
+        .changed()
+    {
+        changed = true;
+    }
+
+    if ui
+        .add(
+            egui::Slider::new(&mut options.dpi, 72.0..=600.0)
+                .text("DPI")
+        )
+        .changed()
+    {
+        changed = true;
+    }
+
+    ui.add_space(6.0);
+    if ui
+        .checkbox(&mut options.pdfa, "PDF/A-2b (archival)")
+        .changed()
+    {
+        changed = true;
+    }
+
+    ui.add_space(6.0);
+    let mut encrypt = options.encryption.is_some();
+    if ui
+        .checkbox(&mut encrypt, "Password protect (AES-256)")
+        .changed()
+    {
+        options.encryption = encrypt.then(transmute_formats::PdfEncryption::default);
+        changed = true;
+    }
+    if let Some(encryption) = &mut options.encryption {
+        ui.horizontal(|ui| {
+            ui.label("Password");
+            if ui
+                .add(egui::TextEdit::singleline(&mut encryption.user_password).password(true))
+                .changed()
+            {
+                changed = true;
+            }
+        });
+        if encryption.user_password.is_empty() {
+            ui.colored_label(
+                crate::theme::Theme::ERROR,
+                egui::RichText::new("Enter a password to protect the PDF").size(12.0),
+            );
+        }
+        for (allowed, label) in [
+            (&mut encryption.permissions.print, "Allow printing"),
+            (&mut encryption.permissions.copy, "Allow copying text and images"),
+            (&mut encryption.permissions.modify, "Allow changes"),
+        ] {
+            if ui.checkbox(allowed, label).changed() {
+                changed = true;
+            }
+        }
+    }
+
     changed
 }
 
@@ -1,8 +1,8 @@
 use image::{DynamicImage, GenericImageView};
 use tempfile::TempDir;
-use transmute_common::MediaFormat;
-use transmute_compress::QualitySettings;
-use transmute_core::Converter;
+use transmute_common::{FileSize, MediaFormat};
+use transmute_compress::{QualityMetric, QualitySettings, QualityThresholds, SizeTarget};
+use transmute_core::{Converter, ImageOp, TransformPipeline};
 
 #[test]
 fn test_jpeg_quality_ssim() {
@@ -93,6 +93,220 @@ fn test_png_lossless() {
     assert_eq!(original.to_rgba8().as_raw(), compressed.to_rgba8().as_raw());
 }
 
+#[test]
+fn test_original_size_is_the_decoded_source() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("deep.png");
+
+    // 16-bit RGBA takes 8 bytes a pixel, and the resize shrinks it before encoding
+    let img = image::ImageBuffer::<image::Rgba<u16>, _>::from_fn(200, 100, |x, y| {
+        image::Rgba([(x * 300) as u16, (y * 600) as u16, 20_000, 65_535])
+    });
+    image::DynamicImage::ImageRgba16(img)
+        .save(&input_path)
+        .unwrap();
+
+    let mut converter = Converter::new().unwrap();
+    converter.set_transform(TransformPipeline::new().with_op(ImageOp::MaxEdge(50)));
+    let (_, result) = converter
+        .compress_image(
+            &input_path,
+            MediaFormat::Jpeg,
+            QualitySettings::High,
+            Some(temp_dir.path().to_path_buf()),
+        )
+        .unwrap();
+
+    assert_eq!(result.original_size, 200 * 100 * 8);
+    assert_eq!(
+        result.ratio,
+        result.original_size as f32 / result.compressed_size as f32
+    );
+}
+
+#[test]
+fn test_png_palette_keeps_alpha() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("sticker.png");
+
+    // Busy artwork on a transparent margin
+    let img = image::RgbaImage::from_fn(400, 300, |x, y| {
+        let noise = (x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)) >> 7;
+        let inside = (50..350).contains(&x) && (50..250).contains(&y);
+        image::Rgba([
+            (x + noise) as u8,
+            (y ^ noise) as u8,
+            (noise >> 3) as u8,
+            if inside { 255 } else { 0 },
+        ])
+    });
+    img.save(&input_path).unwrap();
+
+    let converter = Converter::new().unwrap();
+    let compress = |quality, dir: &str| {
+        let output_dir = temp_dir.path().join(dir);
+        std::fs::create_dir_all(&output_dir).unwrap();
+        converter
+            .compress_image(&input_path, MediaFormat::Png, quality, Some(output_dir))
+            .unwrap()
+    };
+    let (_, lossless) = compress(QualitySettings::Maximum, "lossless");
+    let (output, lossy) = compress(QualitySettings::Balanced, "palette");
+
+    assert!(lossy.compressed_size < lossless.compressed_size);
+
+    let compressed = image::open(&output).unwrap().to_rgba8();
+    for (original, quantized) in img.pixels().zip(compressed.pixels()) {
+        assert_eq!(original[3], quantized[3]);
+    }
+}
+
+#[test]
+fn test_compress_to_size_budget() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("upload.png");
+
+    let img = image::RgbImage::from_fn(800, 600, |x, y| {
+        image::Rgb([
+            (x * 3 % 256) as u8,
+            (y * 7 % 256) as u8,
+            ((x * y) % 256) as u8,
+        ])
+    });
+    img.save(&input_path).unwrap();
+
+    let converter = Converter::new().unwrap();
+    let target = SizeTarget::new(FileSize::parse("50KB").unwrap());
+
+    let (output, result) = converter
+        .compress_image_to_size(
+            &input_path,
+            MediaFormat::Jpeg,
+            target,
+            Some(temp_dir.path().to_path_buf()),
+        )
+        .unwrap();
+
+    assert!(result.compressed_size <= 50_000);
+    assert_eq!(
+        std::fs::metadata(&output).unwrap().len() as usize,
+        result.compressed_size
+    );
+    // Without downscaling the dimensions are kept
+    assert_eq!(image::open(&output).unwrap().dimensions(), (800, 600));
+}
+
+#[test]
+fn test_compress_to_ssim_floor() {
+    let temp_dir = TempDir::new().unwrap();
+    let converter = Converter::new().unwrap();
+
+    // A smooth and a busy image each get their own quality for the same floor
+    let smooth = image::RgbImage::from_fn(320, 240, |x, y| {
+        image::Rgb([(x * 255 / 320) as u8, (y * 255 / 240) as u8, 128])
+    });
+    let busy = image::RgbImage::from_fn(320, 240, |x, y| {
+        image::Rgb([
+            (x * 3 % 256) as u8,
+            (y * 7 % 256) as u8,
+            ((x * y) % 256) as u8,
+        ])
+    });
+
+    for (name, img) in [("smooth.png", smooth), ("busy.png", busy)] {
+        let input_path = temp_dir.path().join(name);
+        img.save(&input_path).unwrap();
+
+        let (output, result) = converter
+            .compress_image_to_ssim(
+                &input_path,
+                MediaFormat::Jpeg,
+                0.8,
+                Some(temp_dir.path().to_path_buf()),
+            )
+            .unwrap();
+
+        let original = image::open(&input_path).unwrap();
+        let compressed = image::open(&output).unwrap();
+        let metric = QualityMetric::calculate(&original, &compressed).unwrap();
+        assert!(metric.meets_threshold(0.8), "{}: {:?}", name, metric);
+        assert_eq!(result.quality.unwrap().ssim, metric.ssim);
+    }
+}
+
+#[test]
+fn test_batch_compress_to_ssim() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_dir = temp_dir.path().join("out");
+    std::fs::create_dir(&output_dir).unwrap();
+
+    let inputs: Vec<_> = (0..3u32)
+        .map(|i| {
+            let path = temp_dir.path().join(format!("photo{}.png", i));
+            image::RgbImage::from_fn(160, 120, |x, y| {
+                image::Rgb([(x + i * 40) as u8, (y * 2) as u8, ((x * y) % 256) as u8])
+            })
+            .save(&path)
+            .unwrap();
+            path
+        })
+        .collect();
+
+    let converter = Converter::new().unwrap();
+    let results =
+        converter.compress_batch_to_ssim(inputs, MediaFormat::Webp, 0.9, Some(output_dir.clone()));
+
+    assert_eq!(results.len(), 3);
+    for result in results {
+        let (output, result) = result.unwrap();
+        assert!(output.starts_with(&output_dir));
+        assert!(result.quality.unwrap().ssim >= 0.9);
+    }
+}
+
+#[test]
+fn test_compare_images() {
+    let temp_dir = TempDir::new().unwrap();
+    let reference = temp_dir.path().join("reference.png");
+    let img = image::RgbImage::from_fn(200, 150, |x, y| {
+        image::Rgb([(x + y) as u8, (x * 2) as u8, ((x ^ y) * 3) as u8])
+    });
+    img.save(&reference).unwrap();
+
+    let converter = Converter::new().unwrap();
+    let (candidate, _) = converter
+        .compress_image(
+            &reference,
+            MediaFormat::Jpeg,
+            QualitySettings::Custom(20),
+            Some(temp_dir.path().to_path_buf()),
+        )
+        .unwrap();
+
+    let heatmap = temp_dir.path().join("diff.png");
+    let metric = converter
+        .compare(&reference, &candidate, Some(&heatmap))
+        .unwrap();
+    assert!(metric.ssim < 1.0 && metric.psnr.is_finite() && metric.mse > 0.0);
+    assert_eq!(image::open(&heatmap).unwrap().dimensions(), (200, 150));
+
+    let same = converter.compare(&reference, &reference, None).unwrap();
+    assert_eq!(same.ssim, 1.0);
+    assert_eq!(same.psnr, f64::INFINITY);
+
+    let thresholds = QualityThresholds {
+        min_ssim: Some(0.999),
+        max_mse: Some(metric.mse + 1.0),
+        ..Default::default()
+    };
+    assert_eq!(thresholds.violations(&metric).len(), 1);
+    assert!(thresholds.violations(&same).is_empty());
+
+    let small = temp_dir.path().join("small.png");
+    image::RgbImage::new(20, 20).save(&small).unwrap();
+    assert!(converter.compare(&reference, &small, None).is_err());
+}
+
 #[tokio::test]
 async fn test_batch_compression() {
     let temp_dir = TempDir::new().unwrap();
@@ -43,6 +43,90 @@ fn test_compress_command_with_percentage() {
     assert!(outputs[0].exists());
 }
 
+#[test]
+fn test_compress_to_under_size() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("photo.jpg");
+
+    let img = image::RgbImage::from_fn(1200, 900, |x, y| {
+        image::Rgb([
+            (x * 7 % 256) as u8,
+            (y * 5 % 256) as u8,
+            ((x ^ y) % 256) as u8,
+        ])
+    });
+    img.save(&input_path).unwrap();
+
+    let converter = Converter::new().unwrap();
+
+    let command = format!("compress {} to under 30kb", input_path.display());
+    let outputs = converter.execute_command(&command).unwrap();
+
+    assert!(std::fs::metadata(&outputs[0]).unwrap().len() <= 30_000);
+}
+
+#[test]
+fn test_compress_to_under_size_downscales_only_when_asked() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("noise.png");
+
+    let img = image::RgbImage::from_fn(256, 256, |x, y| {
+        let noise = (x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)) >> 7;
+        image::Rgb([(x + noise) as u8, (y ^ noise) as u8, (noise >> 3) as u8])
+    });
+    img.save(&input_path).unwrap();
+
+    let converter = Converter::new().unwrap();
+
+    let command = format!("compress {} to under 2kb", input_path.display());
+    assert!(converter.execute_command(&command).is_err());
+
+    let command = format!("compress {} to under 2kb and resize", input_path.display());
+    let outputs = converter.execute_command(&command).unwrap();
+    assert!(std::fs::metadata(&outputs[0]).unwrap().len() <= 2_000);
+    assert!(image::open(&outputs[0]).unwrap().width() < 256);
+}
+
+#[test]
+fn test_crop_command() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("wide.png");
+    let output_dir = temp_dir.path().join("out");
+    std::fs::create_dir(&output_dir).unwrap();
+
+    DynamicImage::new_rgb8(400, 300).save(&input_path).unwrap();
+
+    let converter = Converter::new().unwrap();
+
+    let command = format!(
+        "crop {} to 1:1 in {}",
+        input_path.display(),
+        output_dir.display()
+    );
+    let outputs = converter.execute_command(&command).unwrap();
+
+    let cropped = image::open(&outputs[0]).unwrap();
+    assert_eq!((cropped.width(), cropped.height()), (300, 300));
+}
+
+#[test]
+fn test_compress_pdf_rejects_size_and_ssim_targets() {
+    let temp_dir = TempDir::new().unwrap();
+    let image_path = temp_dir.path().join("page.png");
+    DynamicImage::new_rgb8(100, 100).save(&image_path).unwrap();
+
+    let converter = Converter::new().unwrap();
+    let pdf_path = converter
+        .images_to_pdf(vec![image_path], temp_dir.path().join("doc.pdf"), None)
+        .unwrap();
+
+    for target in ["to under 100kb", "keeping ssim 0.95"] {
+        let command = format!("compress {} {}", pdf_path.display(), target);
+        let err = converter.execute_command(&command).unwrap_err();
+        assert!(err.to_string().contains("PDF input"), "{}: {}", target, err);
+    }
+}
+
 #[test]
 fn test_compress_with_quality_preset() {
     let temp_dir = TempDir::new().unwrap();
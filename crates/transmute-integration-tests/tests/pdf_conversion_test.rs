@@ -2,7 +2,7 @@
 use image::DynamicImage;
 use tempfile::TempDir;
 use transmute_common::MediaFormat;
-use transmute_core::Converter;
+use transmute_core::{Converter, ImageOp, Rotation, TransformPipeline};
 
 #[test]
 fn test_image_to_pdf_conversion() {
@@ -15,7 +15,7 @@ fn test_image_to_pdf_conversion() {
 
     let converter = Converter::new().unwrap();
     let output_path = converter
-        .convert_image(&input_path, MediaFormat::Pdf, None)
+        .convert_image(&input_path, None, MediaFormat::Pdf)
         .expect("Failed to convert image to PDF");
 
     assert!(output_path.exists());
@@ -34,7 +34,7 @@ fn test_image_to_pdf_with_custom_output() {
 
     let converter = Converter::new().unwrap();
     let result = converter
-        .convert_image(&input_path, MediaFormat::Pdf, Some(output_path.clone()))
+        .convert_image(&input_path, Some(output_path.clone()), MediaFormat::Pdf)
         .expect("Failed to convert image to PDF");
 
     assert_eq!(result, output_path);
@@ -63,3 +63,544 @@ fn test_multiple_images_to_pdf() {
     assert_eq!(result, output);
     assert!(output.exists());
 }
+
+#[test]
+fn test_pdf_pages_back_to_images() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut inputs = Vec::new();
+
+    // A dark and a light "scan", so each page can be told apart
+    for (i, shade) in [40u8, 220].into_iter().enumerate() {
+        let path = temp_dir.path().join(format!("scan{}.png", i));
+        image::RgbImage::from_pixel(600, 800, image::Rgb([shade; 3]))
+            .save(&path)
+            .unwrap();
+        inputs.push(path);
+    }
+
+    let pdf = temp_dir.path().join("scans.pdf");
+    let converter = Converter::new().unwrap();
+    converter.images_to_pdf(inputs, pdf.clone(), None).unwrap();
+
+    // Works with pdfium or, when it can't be loaded, the built-in fallback
+    let pages = converter
+        .pdf_to_images(
+            &pdf,
+            MediaFormat::Png,
+            Some(temp_dir.path().join("pages")),
+            Some(72.0),
+        )
+        .expect("Failed to extract PDF pages");
+    assert_eq!(pages.len(), 2);
+
+    let centre_luma = |path: &std::path::Path| {
+        let img = image::open(path).unwrap().to_luma8();
+        img.get_pixel(img.width() / 2, img.height() / 2).0[0]
+    };
+    assert!(centre_luma(&pages[0]) < 80);
+    assert!(centre_luma(&pages[1]) > 180);
+}
+
+#[test]
+fn test_extract_embedded_images_from_pdf() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut inputs = Vec::new();
+    for (i, (w, h)) in [(300u32, 200u32), (120, 160)].into_iter().enumerate() {
+        let path = temp_dir.path().join(format!("photo{}.jpg", i));
+        image::RgbImage::from_fn(w, h, |x, y| image::Rgb([x as u8, y as u8, 128]))
+            .save(&path)
+            .unwrap();
+        inputs.push(path);
+    }
+
+    let pdf = temp_dir.path().join("album.pdf");
+    let converter = Converter::new().unwrap();
+    converter.images_to_pdf(inputs, pdf.clone(), None).unwrap();
+
+    let images = converter
+        .extract_pdf_images(&pdf, Some(temp_dir.path().join("images")))
+        .expect("Failed to extract embedded images");
+    assert_eq!(images.len(), 2);
+
+    for (image, (page, (w, h))) in images.iter().zip([(1, (300, 200)), (2, (120, 160))]) {
+        assert_eq!(image.page, page);
+        assert_eq!((image.width, image.height), (w, h));
+        let decoded = image::open(&image.path).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (w, h));
+    }
+}
+
+#[test]
+fn test_transformed_jpeg_is_reencoded_in_pdf() {
+    let temp_dir = TempDir::new().unwrap();
+    let photo = temp_dir.path().join("photo.jpg");
+    image::RgbImage::from_fn(300, 200, |x, y| image::Rgb([x as u8, y as u8, 128]))
+        .save(&photo)
+        .unwrap();
+    let notes = temp_dir.path().join("notes.pdf");
+
+    let mut converter = Converter::new().unwrap();
+    converter
+        .images_to_pdf(vec![photo.clone()], notes.clone(), None)
+        .unwrap();
+    converter.set_transform(TransformPipeline::new().with_op(ImageOp::Rotate(Rotation::Cw90)));
+
+    // Both the image-only and the merging path must embed the rotated pixels
+    let rotated = temp_dir.path().join("rotated.pdf");
+    converter
+        .images_to_pdf(vec![photo.clone()], rotated.clone(), None)
+        .unwrap();
+    let merged = temp_dir.path().join("merged.pdf");
+    converter
+        .merge_to_pdf(vec![notes, photo], merged.clone(), None)
+        .unwrap();
+
+    for (pdf, count) in [(rotated, 1), (merged, 2)] {
+        let images = converter
+            .extract_pdf_images(&pdf, Some(temp_dir.path().join("images")))
+            .unwrap();
+        assert_eq!(images.len(), count);
+        let last = images.last().unwrap();
+        assert_eq!((last.width, last.height), (200, 300), "{:?}", pdf);
+    }
+}
+
+#[test]
+fn test_pdf_page_selection() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut inputs = Vec::new();
+    for i in 0..3 {
+        let path = temp_dir.path().join(format!("page{}.png", i));
+        DynamicImage::new_rgb8(200, 300).save(&path).unwrap();
+        inputs.push(path);
+    }
+
+    let pdf = temp_dir.path().join("doc.pdf");
+    let converter = Converter::new().unwrap();
+    converter.images_to_pdf(inputs, pdf.clone(), None).unwrap();
+
+    let pages = converter
+        .pdf_to_images_with(
+            &pdf,
+            MediaFormat::Jpeg,
+            Some(temp_dir.path().join("pages")),
+            Some(36.0),
+            &"last,2".parse().unwrap(),
+        )
+        .expect("Failed to extract selected pages");
+
+    let names: Vec<_> = pages
+        .iter()
+        .map(|p| p.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(names, ["doc_page_003.jpg", "doc_page_002.jpg"]);
+
+    assert!(converter
+        .pdf_to_images_with(&pdf, MediaFormat::Png, None, None, &"4".parse().unwrap())
+        .is_err());
+}
+
+#[test]
+fn test_merge_split_and_edit_pdfs() {
+    use transmute_common::PageSelection;
+    use transmute_core::PageEdit;
+
+    let temp_dir = TempDir::new().unwrap();
+    let converter = Converter::new().unwrap();
+
+    let mut images = Vec::new();
+    for i in 0..3 {
+        let path = temp_dir.path().join(format!("scan{}.png", i));
+        DynamicImage::new_rgb8(200, 300).save(&path).unwrap();
+        images.push(path);
+    }
+    let existing = temp_dir.path().join("existing.pdf");
+    converter
+        .images_to_pdf(images[..2].to_vec(), existing.clone(), None)
+        .unwrap();
+
+    // 2 PDF pages followed by 1 image page
+    let merged = converter
+        .merge_to_pdf(
+            vec![existing, images[2].clone()],
+            temp_dir.path().join("merged.pdf"),
+            None,
+        )
+        .expect("Failed to merge PDF and image");
+    let page_count =
+        |path: &std::path::Path| lopdf::Document::load(path).unwrap().get_pages().len();
+    assert_eq!(page_count(&merged), 3);
+
+    let parts = converter
+        .split_pdf(
+            &merged,
+            &[
+                PageSelection::parse("1-2").unwrap(),
+                PageSelection::parse("last").unwrap(),
+            ],
+            Some(temp_dir.path().join("parts")),
+        )
+        .unwrap();
+    assert_eq!(
+        parts.iter().map(|p| page_count(p)).collect::<Vec<_>>(),
+        [2, 1]
+    );
+
+    let edited = converter
+        .edit_pdf(
+            &merged,
+            &[
+                PageEdit::Delete(PageSelection::parse("2").unwrap()),
+                PageEdit::Rotate {
+                    pages: PageSelection::parse("1").unwrap(),
+                    degrees: 90,
+                },
+            ],
+            Some(temp_dir.path().join("edited.pdf")),
+        )
+        .unwrap();
+    let doc = lopdf::Document::load(&edited).unwrap();
+    assert_eq!(doc.get_pages().len(), 2);
+    let first = doc.get_dictionary(doc.get_pages()[&1]).unwrap();
+    assert_eq!(first.get(b"Rotate").unwrap().as_i64().unwrap(), 90);
+}
+
+#[test]
+fn test_pdf_page_layout_options() {
+    use transmute_core::{FitMode, PageSize, PdfOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let wide = temp_dir.path().join("wide.png");
+    DynamicImage::new_rgb8(600, 300).save(&wide).unwrap();
+
+    // Page size in points, rounded
+    let media_box = |path: &std::path::Path| -> Vec<i64> {
+        let doc = lopdf::Document::load(path).unwrap();
+        let page = doc.get_dictionary(doc.get_pages()[&1]).unwrap();
+        page.get(b"MediaBox")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_float().unwrap().round() as i64)
+            .collect()
+    };
+
+    let mut converter = Converter::new().unwrap();
+    converter.set_pdf_options(PdfOptions {
+        page_size: PageSize::Letter,
+        ..Default::default()
+    });
+    let letter = converter
+        .convert_image(
+            &wide,
+            Some(temp_dir.path().join("letter.pdf")),
+            MediaFormat::Pdf,
+        )
+        .unwrap();
+    // Letter, turned landscape for the wide image
+    assert_eq!(media_box(&letter), [0, 0, 792, 612]);
+
+    // 600x300 px at 300 DPI is 2x1 inches, plus a 1 inch (25.4 mm) margin all round
+    let sized = converter
+        .images_to_pdf(
+            vec![wide],
+            temp_dir.path().join("sized.pdf"),
+            Some(PdfOptions {
+                fit: FitMode::ImageSize,
+                margin_mm: 25.4,
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+    assert_eq!(media_box(&sized), [0, 0, 288, 216]);
+}
+
+#[test]
+fn test_n_up_pdf_and_contact_sheet() {
+    use transmute_core::{Grid, PdfOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let mut images = Vec::new();
+    for i in 0..5 {
+        let path = temp_dir.path().join(format!("receipt{}.png", i));
+        DynamicImage::new_rgb8(300, 400).save(&path).unwrap();
+        images.push(path);
+    }
+    let options = PdfOptions {
+        grid: Grid::new(2, 2).unwrap(),
+        captions: true,
+        dpi: 72.0,
+        ..Default::default()
+    };
+
+    let converter = Converter::new().unwrap();
+    let pdf = converter
+        .contact_sheet(
+            images.clone(),
+            temp_dir.path().join("receipts.pdf"),
+            MediaFormat::Pdf,
+            Some(options.clone()),
+        )
+        .unwrap();
+    // Five images four to a page
+    let doc = lopdf::Document::load(&pdf[0]).unwrap();
+    assert_eq!(doc.get_pages().len(), 2);
+    let first_page = doc.get_page_content(doc.get_pages()[&1]).unwrap();
+    assert!(String::from_utf8_lossy(&first_page).contains("(receipt3.png) Tj"));
+
+    let sheets = converter
+        .contact_sheet(
+            images,
+            temp_dir.path().join("sheet.jpg"),
+            MediaFormat::Jpeg,
+            Some(options),
+        )
+        .unwrap();
+    let names: Vec<_> = sheets
+        .iter()
+        .map(|p| p.file_name().unwrap().to_str().unwrap().to_string())
+        .collect();
+    assert_eq!(names, ["sheet_001.jpg", "sheet_002.jpg"]);
+    // A4 at 72 DPI
+    let page = image::open(&sheets[0]).unwrap();
+    assert_eq!((page.width(), page.height()), (595, 842));
+}
+
+#[test]
+fn test_pdf_document_info() {
+    use transmute_core::{PdfInfo, PdfOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let mut images = Vec::new();
+    for name in ["front", "back"] {
+        let path = temp_dir.path().join(format!("{}.png", name));
+        DynamicImage::new_rgb8(60, 40).save(&path).unwrap();
+        images.push(path);
+    }
+    let created = PdfInfo::parse_date("2024-03-01T10:00:00+01:00").unwrap();
+
+    let mut converter = Converter::new().unwrap();
+    converter.set_pdf_options(PdfOptions {
+        info: PdfInfo {
+            title: Some("Postcard".into()),
+            author: Some("José".into()),
+            creation_date: Some(created),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    let pdf = converter
+        .images_to_pdf(images, temp_dir.path().join("postcard.pdf"), None)
+        .unwrap();
+
+    let info = converter.pdf_info(&pdf).unwrap();
+    assert_eq!(info.title.as_deref(), Some("Postcard"));
+    assert_eq!(info.author.as_deref(), Some("José"));
+    assert_eq!(info.producer.as_deref(), Some("Transmute"));
+    assert_eq!(info.creation_date, Some(created));
+
+    let doc = lopdf::Document::load(&pdf).unwrap();
+    assert!(doc.catalog().unwrap().get(b"Outlines").is_ok());
+
+    // Set the subject, drop the author, keep the rest
+    let edited = converter
+        .edit_pdf_info(
+            &pdf,
+            &PdfInfo {
+                subject: Some("Greetings".into()),
+                author: Some(String::new()),
+                ..Default::default()
+            },
+            Some(temp_dir.path().join("edited.pdf")),
+        )
+        .unwrap();
+    let info = converter.pdf_info(&edited).unwrap();
+    assert_eq!(info.title.as_deref(), Some("Postcard"));
+    assert_eq!(info.subject.as_deref(), Some("Greetings"));
+    assert_eq!(info.author, None);
+    assert!(info.modification_date.unwrap() > created);
+}
+
+#[test]
+fn test_pdfa_output() {
+    use transmute_core::{PdfInfo, PdfOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let scan = temp_dir.path().join("scan.jpg");
+    DynamicImage::new_rgb8(200, 300).save(&scan).unwrap();
+
+    let mut converter = Converter::new().unwrap();
+    converter.set_pdf_options(PdfOptions {
+        pdfa: true,
+        info: PdfInfo {
+            title: Some("Invoice 2024-117".into()),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    let pdf = converter
+        .images_to_pdf(
+            vec![scan.clone()],
+            temp_dir.path().join("archive.pdf"),
+            None,
+        )
+        .unwrap();
+
+    let doc = lopdf::Document::load(&pdf).unwrap();
+    assert_eq!(
+        transmute_formats::pdfa::conformance_issues(&doc),
+        Vec::<String>::new()
+    );
+
+    // Pages copied from other PDFs cannot be vouched for
+    let merged = converter.merge_to_pdf(vec![pdf, scan], temp_dir.path().join("merged.pdf"), None);
+    assert!(merged.is_err());
+}
+
+#[test]
+fn test_pdf_password_protection() {
+    use transmute_common::Error;
+    use transmute_core::{PageEdit, PdfEncryption, PdfOptions};
+
+    let temp_dir = TempDir::new().unwrap();
+    let scan = temp_dir.path().join("statement.png");
+    DynamicImage::new_rgb8(200, 300).save(&scan).unwrap();
+
+    let mut converter = Converter::new().unwrap();
+    converter.set_pdf_options(PdfOptions {
+        encryption: Some(PdfEncryption {
+            user_password: "hunter2".into(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    let locked = converter
+        .images_to_pdf(vec![scan], temp_dir.path().join("locked.pdf"), None)
+        .unwrap();
+    assert!(lopdf::Document::load(&locked).unwrap().is_encrypted());
+
+    let mut converter = Converter::new().unwrap();
+    let pages_dir = temp_dir.path().join("pages");
+    assert!(converter.pdf_needs_password(&locked).unwrap());
+    assert!(matches!(
+        converter.pdf_to_images(
+            &locked,
+            MediaFormat::Png,
+            Some(pages_dir.clone()),
+            Some(72.0)
+        ),
+        Err(Error::PasswordRequired(_))
+    ));
+
+    converter.set_pdf_password(&locked, "wrong");
+    assert!(converter.pdf_needs_password(&locked).unwrap());
+    assert!(matches!(
+        converter.pdf_info(&locked),
+        Err(Error::IncorrectPassword(_))
+    ));
+
+    converter.set_pdf_password(&locked, "hunter2");
+    assert!(!converter.pdf_needs_password(&locked).unwrap());
+    let pages = converter
+        .pdf_to_images(&locked, MediaFormat::Png, Some(pages_dir), Some(72.0))
+        .unwrap();
+    assert_eq!(pages.len(), 1);
+    assert_eq!(
+        converter
+            .extract_pdf_images(&locked, Some(temp_dir.path().join("images")))
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // Edits are saved unprotected unless encryption is configured
+    let rotated = converter
+        .edit_pdf(
+            &locked,
+            &[PageEdit::Rotate {
+                pages: transmute_common::PageSelection::all(),
+                degrees: 90,
+            }],
+            Some(temp_dir.path().join("rotated.pdf")),
+        )
+        .unwrap();
+    let rotated = lopdf::Document::load(&rotated).unwrap();
+    assert!(!rotated.is_encrypted());
+    assert_eq!(rotated.get_pages().len(), 1);
+
+    // PDF/A forbids encryption
+    let refused = converter.images_to_pdf(
+        pages,
+        temp_dir.path().join("archive.pdf"),
+        Some(PdfOptions {
+            pdfa: true,
+            encryption: Some(PdfEncryption::default()),
+            ..Default::default()
+        }),
+    );
+    assert!(refused.is_err());
+}
+
+#[test]
+fn test_compress_pdf_images() {
+    use transmute_compress::QualitySettings;
+
+    let temp_dir = TempDir::new().unwrap();
+    let photo = temp_dir.path().join("photo.png");
+    image::RgbImage::from_fn(1200, 900, |x, y| {
+        image::Rgb([
+            (x * 7 % 256) as u8,
+            (y * 5 % 256) as u8,
+            ((x ^ y) % 256) as u8,
+        ])
+    })
+    .save(&photo)
+    .unwrap();
+
+    let converter = Converter::new().unwrap();
+    let pdf = converter
+        .images_to_pdf(vec![photo], temp_dir.path().join("photo.pdf"), None)
+        .unwrap();
+
+    let (small, result, stats) = converter
+        .compress_pdf(
+            &pdf,
+            QualitySettings::Low,
+            Some(50.0),
+            Some(temp_dir.path().join("small.pdf")),
+        )
+        .unwrap();
+    assert_eq!(
+        (stats.images, stats.recompressed, stats.downsampled),
+        (1, 1, 1)
+    );
+    assert_eq!(
+        result.original_size as u64,
+        std::fs::metadata(&pdf).unwrap().len()
+    );
+    assert_eq!(
+        result.compressed_size as u64,
+        std::fs::metadata(&small).unwrap().len()
+    );
+    assert!(result.size_reduction_percent() > 50.0);
+
+    // The page keeps its size while the image is stored as a smaller JPEG
+    let images = converter
+        .extract_pdf_images(&small, Some(temp_dir.path().join("images")))
+        .unwrap();
+    assert_eq!(images.len(), 1);
+    assert!(images[0].passthrough);
+    assert!(images[0].width < 1200);
+
+    // Nothing left to gain: the already compressed file is kept as it is
+    let (_, again, _) = converter
+        .compress_pdf(
+            &small,
+            QualitySettings::Maximum,
+            None,
+            Some(temp_dir.path().join("again.pdf")),
+        )
+        .unwrap();
+    assert!(again.compressed_size <= again.original_size);
+}
@@ -12,14 +12,17 @@
 //! to Java RuntimeExceptions via `env.throw_new()`.
 
 use jni::objects::{JClass, JObject, JObjectArray, JString};
-use jni::sys::{jstring, jobjectArray};
+use jni::sys::{jfloat, jstring, jobjectArray};
 use jni::JNIEnv;
 use std::path::PathBuf;
 use std::sync::{Arc, OnceLock};
 
-use transmute_common::{MediaFormat, PathManager};
+use transmute_common::{MediaFormat, PageSelection, PathManager};
 use transmute_compress::{ImageCompressor, QualitySettings};
-use transmute_formats::{ImageDecoder, ImageEncoder, PdfGenerator, PdfOptions};
+use transmute_formats::{
+    FitMode, ImageDecoder, ImageEncoder, PageOrientation, PageSize, PdfExtractor, PdfGenerator,
+    PdfOptions,
+};
 
 // ---------------------------------------------------------------------------
 // Global PathManager — set once by `init()`, read by all other functions.
@@ -64,6 +67,33 @@ macro_rules! throw {
     }};
 }
 
+/// Build a Java String[] from Rust strings, throwing a RuntimeException on failure.
+/// Returns a null array on error (caller should return immediately).
+fn strings_to_jarray(env: &mut JNIEnv, strings: &[String]) -> jobjectArray {
+    let null_ret = JObject::null().into_raw() as jobjectArray;
+
+    let string_class = match env.find_class("java/lang/String") {
+        Ok(c) => c,
+        Err(e) => throw!(env, format!("Class lookup failed: {e}"), null_ret),
+    };
+    let out_array = match env.new_object_array(strings.len() as i32, &string_class, JObject::null()) {
+        Ok(a) => a,
+        Err(e) => throw!(env, format!("Array creation failed: {e}"), null_ret),
+    };
+
+    for (i, string) in strings.iter().enumerate() {
+        let js = match env.new_string(string) {
+            Ok(s) => s,
+            Err(e) => throw!(env, format!("String creation failed: {e}"), null_ret),
+        };
+        if let Err(e) = env.set_object_array_element(&out_array, i as i32, js) {
+            throw!(env, format!("Array set failed: {e}"), null_ret);
+        }
+    }
+
+    out_array.into_raw()
+}
+
 // ---------------------------------------------------------------------------
 // 1. init(outputDir: String)
 // ---------------------------------------------------------------------------
@@ -311,26 +341,7 @@ pub extern "system" fn Java_com_transmute_TransmuteLib_batchConvert(
         .collect();
 
     // Build a Java String[] with the results
-    let string_class = match env.find_class("java/lang/String") {
-        Ok(c) => c,
-        Err(e) => throw!(env, format!("Class lookup failed: {e}"), null_ret),
-    };
-    let out_array = match env.new_object_array(results.len() as i32, &string_class, JObject::null()) {
-        Ok(a) => a,
-        Err(e) => throw!(env, format!("Array creation failed: {e}"), null_ret),
-    };
-
-    for (i, result) in results.iter().enumerate() {
-        let js = match env.new_string(result) {
-            Ok(s) => s,
-            Err(e) => throw!(env, format!("String creation failed: {e}"), null_ret),
-        };
-        if let Err(e) = env.set_object_array_element(&out_array, i as i32, js) {
-            throw!(env, format!("Array set failed: {e}"), null_ret);
-        }
-    }
-
-    out_array.into_raw()
+    strings_to_jarray(&mut env, &results)
 }
 
 // ---------------------------------------------------------------------------
@@ -352,10 +363,20 @@ pub extern "system" fn Java_com_transmute_TransmuteLib_imagesToPdf(
     _class: JClass,
     input_paths: JObjectArray,
     output_path: JString,
+) -> jstring {
+    images_to_pdf(&mut env, input_paths, output_path, PdfOptions::default())
+}
+
+/// Shared body of `imagesToPdf` and `imagesToPdfWithLayout`.
+fn images_to_pdf(
+    env: &mut JNIEnv,
+    input_paths: JObjectArray,
+    output_path: JString,
+    options: PdfOptions,
 ) -> jstring {
     let null_ret = JObject::null().into_raw() as jstring;
 
-    let out_str = match jstring_to_string(&mut env, output_path) {
+    let out_str = match jstring_to_string(env, output_path) {
         Ok(s) => s,
         Err(()) => return null_ret,
     };
@@ -373,7 +394,7 @@ pub extern "system" fn Java_com_transmute_TransmuteLib_imagesToPdf(
             Err(e) => throw!(env, format!("Array element {i} failed: {e}"), null_ret),
         };
         let js: JString = elem.into();
-        let path_str = match jstring_to_string(&mut env, js) {
+        let path_str = match jstring_to_string(env, js) {
             Ok(s) => s,
             Err(()) => return null_ret,
         };
@@ -386,14 +407,14 @@ pub extern "system" fn Java_com_transmute_TransmuteLib_imagesToPdf(
     }
 
     let output_path = PathBuf::from(&out_str);
-    let generator = PdfGenerator::new(PdfOptions::default());
+    let generator = PdfGenerator::new(options);
 
     if let Err(e) = generator.generate_from_images(images, &output_path) {
         throw!(env, format!("PDF generation failed: {e}"), null_ret);
     }
 
     log::info!("imagesToPdf: {} images → {:?}", len, output_path);
-    string_to_jstring(&mut env, &out_str)
+    string_to_jstring(env, &out_str)
 }
 
 // ---------------------------------------------------------------------------
@@ -402,27 +423,149 @@ pub extern "system" fn Java_com_transmute_TransmuteLib_imagesToPdf(
 
 /// Report whether PDF-to-image extraction is available in this build.
 ///
-/// # Why this always returns `false` on Android
-///
-/// `PdfExtractor` (PDF → images) is backed by `pdfium-render`, which wraps
-/// Google's `libpdfium.so`. That native library:
-///   - Is ~30 MB per ABI and not distributed via crates.io for Android targets.
-///   - Requires manual download, compilation, and APK bundling that is out of
-///     scope for this build.
-///   - Is gated behind the `pdf-extract` feature flag, which `transmute-jni`
-///     does NOT enable (see `transmute-jni/Cargo.toml`).
-///
-/// The Kotlin UI must call this function once (e.g. in `onCreate`) and
-/// hide or disable any "Extract PDF" controls when it returns `false`.
-///
-/// If pdfium support is ever added to the Android build, this function should
-/// be updated to return `true` and the UI will automatically become visible.
+/// Always `true`: without pdfium (the `pdf-extract` feature, which this build
+/// does not enable) `extractPdfPages` uses the pure-Rust fallback in
+/// `PdfExtractor`. That renders the images embedded in each page, which covers
+/// scanned documents; pages with only text or vector graphics are reported as
+/// errors rather than returned blank.
 #[unsafe(no_mangle)]
 pub extern "system" fn Java_com_transmute_TransmuteLib_pdfExtractSupported(
     _env: JNIEnv,
     _class: JClass,
 ) -> jni::sys::jboolean {
-    // pdfium-render is not compiled into this Android build — extraction is
-    // unavailable. Return JNI_FALSE so Kotlin can gate the UI accordingly.
-    jni::sys::JNI_FALSE
+    jni::sys::JNI_TRUE
+}
+
+// ---------------------------------------------------------------------------
+// 7. imagesToPdfWithLayout(inputPaths: Array<String>, outputPath: String,
+//    pageSize: String, orientation: String, marginMm: Float, fit: String): String
+// ---------------------------------------------------------------------------
+
+/// Combine multiple images into a single PDF with a chosen page layout.
+///
+/// - `pageSize`: `"a3"`, `"a4"`, `"a5"`, `"letter"`, `"legal"` or `"WIDTHxHEIGHT"` in mm
+/// - `orientation`: `"auto"` (follow each image), `"portrait"` or `"landscape"`
+/// - `marginMm`: blank space on each side of the image
+/// - `fit`: `"fit"`, `"fill"` (crop to the page), `"original"` (size at 300 DPI)
+///   or `"image"` (page sized to the image)
+///
+/// Returns `outputPath` on success, or throws RuntimeException on error.
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_com_transmute_TransmuteLib_imagesToPdfWithLayout(
+    mut env: JNIEnv,
+    _class: JClass,
+    input_paths: JObjectArray,
+    output_path: JString,
+    page_size: JString,
+    orientation: JString,
+    margin_mm: jfloat,
+    fit: JString,
+) -> jstring {
+    let null_ret = JObject::null().into_raw() as jstring;
+
+    let mut settings = Vec::with_capacity(3);
+    for js in [page_size, orientation, fit] {
+        match jstring_to_string(&mut env, js) {
+            Ok(s) => settings.push(s),
+            Err(()) => return null_ret,
+        }
+    }
+
+    let page_size: PageSize = match settings[0].parse() {
+        Ok(size) => size,
+        Err(e) => throw!(env, format!("{e}"), null_ret),
+    };
+    let orientation: PageOrientation = match settings[1].parse() {
+        Ok(orientation) => orientation,
+        Err(e) => throw!(env, format!("{e}"), null_ret),
+    };
+    let fit: FitMode = match settings[2].parse() {
+        Ok(fit) => fit,
+        Err(e) => throw!(env, format!("{e}"), null_ret),
+    };
+
+    let options = PdfOptions {
+        page_size,
+        orientation,
+        margin_mm,
+        fit,
+        ..PdfOptions::default()
+    };
+    images_to_pdf(&mut env, input_paths, output_path, options)
+}
+
+// ---------------------------------------------------------------------------
+// 8. extractPdfPages(pdfPath: String, format: String, dpi: Float): Array<String>
+// ---------------------------------------------------------------------------
+
+/// Render every page of a PDF to an image file in the output directory.
+///
+/// Pages are named `<name>_page_001.<format>` and so on. Without pdfium each
+/// page is drawn from its embedded images only; a page with nothing to draw
+/// (text or vector graphics) throws a RuntimeException naming the page.
+///
+/// Returns a Java String[] of output paths in page order.
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_transmute_TransmuteLib_extractPdfPages(
+    mut env: JNIEnv,
+    _class: JClass,
+    pdf_path: JString,
+    format: JString,
+    dpi: jfloat,
+) -> jobjectArray {
+    let null_ret = JObject::null().into_raw() as jobjectArray;
+
+    let pdf_str = match jstring_to_string(&mut env, pdf_path) {
+        Ok(s) => s,
+        Err(()) => return null_ret,
+    };
+    let format_str = match jstring_to_string(&mut env, format) {
+        Ok(s) => s,
+        Err(()) => return null_ret,
+    };
+
+    let pm = match get_path_manager() {
+        Some(pm) => pm,
+        None => throw!(env, "TransmuteLib.init() was not called", null_ret),
+    };
+
+    let target_format = match MediaFormat::from_extension(&format_str) {
+        Some(f) if f.is_image() => f,
+        _ => throw!(env, format!("Unsupported format: {format_str}"), null_ret),
+    };
+
+    let pdf_path = PathBuf::from(&pdf_str);
+    let base_name = pdf_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("page")
+        .to_string();
+    let output_dir = pm.default_output_dir();
+
+    let extractor = PdfExtractor::new(dpi);
+    let mut outputs = Vec::new();
+    let result = extractor.for_each_page(&pdf_path, &PageSelection::all(), |page, img| {
+        let output_path = output_dir.join(format!(
+            "{}_page_{:03}.{}",
+            base_name,
+            page,
+            target_format.extension()
+        ));
+        // JPEG has no alpha channel
+        let img = if target_format == MediaFormat::Jpeg {
+            img.to_rgb8().into()
+        } else {
+            img
+        };
+        ImageEncoder::encode(&img, &output_path, target_format)?;
+        outputs.push(output_path.to_string_lossy().into_owned());
+        Ok(())
+    });
+    if let Err(e) = result {
+        throw!(env, format!("PDF extraction failed: {e}"), null_ret);
+    }
+
+    log::info!("extractPdfPages: {:?} → {} images", pdf_path, outputs.len());
+    strings_to_jarray(&mut env, &outputs)
 }
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
-use transmute_common::MediaFormat;
+use transmute_common::{MediaFormat, PageSelection};
+use transmute_compress::SizeTarget;
 
 /// Parsed command intent
 #[derive(Debug, Clone, PartialEq)]
@@ -9,6 +10,11 @@ pub enum Intent {
     Enhance(EnhanceIntent),
     Batch(BatchIntent),
     CombineToPdf(CombineToPdfIntent),
+    Transform(TransformIntent),
+    ExtractFrames(ExtractFramesIntent),
+    AssembleAnimation(AssembleAnimationIntent),
+    ExtractImages(ExtractImagesIntent),
+    ExtractPages(ExtractPagesIntent),
 }
 
 /// Convert one format to another
@@ -32,6 +38,8 @@ pub struct CompressIntent {
 pub enum QualitySpec {
     Percentage(u8),        // e.g., "80%"
     Preset(QualityPreset), // e.g., "high"
+    MaxSize(SizeTarget),   // e.g., "under 500kb", downscaling with "and resize"
+    MinSsim(f64),          // e.g., "keeping ssim 0.95"
     Default,               // No specification
 }
 
@@ -59,7 +67,10 @@ impl QualitySpec {
         match self {
             Self::Percentage(p) => transmute_compress::QualitySettings::Custom(*p),
             Self::Preset(preset) => preset.to_settings(),
-            Self::Default => transmute_compress::QualitySettings::High,
+            // The quality is searched for, this is only where the search would start
+            Self::MaxSize(_) | Self::MinSsim(_) | Self::Default => {
+                transmute_compress::QualitySettings::High
+            }
         }
     }
 }
@@ -87,6 +98,63 @@ pub struct CombineToPdfIntent {
     pub output: PathBuf,
 }
 
+/// Resize/crop/rotate/flip an image
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformIntent {
+    pub input: PathBuf,
+    pub ops: Vec<TransformSpec>,
+    pub target_format: Option<MediaFormat>, // None keeps the input format
+    pub output: Option<PathBuf>,
+}
+
+/// Write every frame of an animated GIF/WebP/PNG as a still image
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractFramesIntent {
+    pub input: PathBuf,
+    pub target_format: MediaFormat, // PNG unless specified
+    pub output: Option<PathBuf>,    // output directory
+}
+
+/// Combine still images into an animated GIF/WebP/PNG
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssembleAnimationIntent {
+    pub inputs: Vec<PathBuf>,
+    pub target_format: MediaFormat,
+    pub delay_ms: Option<u32>, // e.g., "with 80ms delay"
+    pub output: Option<PathBuf>,
+}
+
+/// Save the images embedded in a PDF without re-rendering them
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractImagesIntent {
+    pub input: PathBuf,
+    pub output: Option<PathBuf>, // output directory
+}
+
+/// Render selected PDF pages to images
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractPagesIntent {
+    pub input: PathBuf,
+    pub target_format: MediaFormat, // PNG unless specified
+    pub pages: PageSelection,       // e.g., "pages 1-3,7,last"
+    pub output: Option<PathBuf>,    // output directory
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransformSpec {
+    Resize {
+        width: Option<u32>,
+        height: Option<u32>,
+    }, // e.g., "800px wide", "800x600"
+    MaxEdge(u32), // e.g., "1200px"
+    Crop {
+        aspect: (u32, u32),
+    }, // center crop, e.g., "to 16:9"
+    Rotate(i32),  // degrees clockwise
+    FlipHorizontal, // e.g., "flip horizontally"
+    FlipVertical,
+}
+
 impl Intent {
     /// Get input path from any intent
     pub fn input_path(&self) -> Option<&PathBuf> {
@@ -96,6 +164,11 @@ impl Intent {
             Self::Enhance(i) => Some(&i.input),
             Self::Batch(_) => None, // Batch uses pattern
             Self::CombineToPdf(i) => i.inputs.first(), // Return first input
+            Self::Transform(i) => Some(&i.input),
+            Self::ExtractFrames(i) => Some(&i.input),
+            Self::AssembleAnimation(i) => i.inputs.first(),
+            Self::ExtractImages(i) => Some(&i.input),
+            Self::ExtractPages(i) => Some(&i.input),
         }
     }
 
@@ -107,6 +180,11 @@ impl Intent {
             Self::Enhance(i) => i.output.as_ref(),
             Self::Batch(i) => i.output.as_ref(),
             Self::CombineToPdf(i) => Some(&i.output),
+            Self::Transform(i) => i.output.as_ref(),
+            Self::ExtractFrames(i) => i.output.as_ref(),
+            Self::AssembleAnimation(i) => i.output.as_ref(),
+            Self::ExtractImages(i) => i.output.as_ref(),
+            Self::ExtractPages(i) => i.output.as_ref(),
         }
     }
 }
@@ -3,6 +3,10 @@ pub mod intent;
 pub mod parser;
 pub mod path_resolver;
 
-pub use intent::{BatchIntent, CompressIntent, ConvertIntent, EnhanceIntent, Intent};
+pub use intent::{
+    AssembleAnimationIntent, BatchIntent, CompressIntent, ConvertIntent, EnhanceIntent,
+    ExtractFramesIntent, ExtractImagesIntent, ExtractPagesIntent, Intent, QualitySpec, TransformIntent,
+    TransformSpec,
+};
 pub use parser::CommandParser;
-pub use path_resolver::PathResolver;
+pub use path_resolver::{PathResolver, PatternMatches};
@@ -1,7 +1,9 @@
 use crate::intent::*;
 use crate::path_resolver::PathResolver;
 use pest_derive::Parser;
-use transmute_common::{Error, MediaFormat, Result};
+use std::path::PathBuf;
+use transmute_common::{Error, FileSize, MediaFormat, PageSelection, Result};
+use transmute_compress::SizeTarget;
 
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
@@ -43,13 +45,21 @@ impl CommandParser {
         let pairs = CommandGrammar::parse(Rule::command, command)
             .map_err(|e| Error::ConversionError(format!("Parse error: {}", e)))?;
 
-        for pair in pairs {
+        // `command` wraps the matched sub-command
+        for pair in pairs.flat_map(|p| p.into_inner()) {
             match pair.as_rule() {
                 Rule::convert_cmd => return self.parse_convert(pair),
                 Rule::compress_cmd => return self.parse_compress(pair),
                 Rule::enhance_cmd => return self.parse_enhance(pair),
                 Rule::batch_cmd => return self.parse_batch(pair),
                 Rule::combine_pdf_cmd => return self.parse_combine_pdf(pair),
+                Rule::resize_cmd | Rule::crop_cmd | Rule::rotate_cmd | Rule::flip_cmd => {
+                    return self.parse_transform(pair)
+                }
+                Rule::extract_frames_cmd => return self.parse_extract_frames(pair),
+                Rule::extract_images_cmd => return self.parse_extract_images(pair),
+                Rule::extract_pages_cmd => return self.parse_extract_pages(pair),
+                Rule::assemble_cmd => return self.parse_assemble(pair),
                 _ => {}
             }
         }
@@ -227,9 +237,317 @@ impl CommandParser {
         Ok(Intent::CombineToPdf(CombineToPdfIntent { inputs, output }))
     }
 
+    fn parse_transform(&self, pair: pest::iterators::Pair<Rule>) -> Result<Intent> {
+        let mut input = None;
+        let mut ops = Vec::new();
+        let mut output = None;
+
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::path => {
+                    let path_str = inner.as_str().trim_matches(|c| c == '"' || c == '\'');
+                    input = Some(self.path_resolver.resolve(path_str)?);
+                }
+                Rule::resize_spec => {
+                    ops.push(self.parse_resize(inner)?);
+                }
+                Rule::aspect_ratio => {
+                    let ratio = inner.as_str().trim();
+                    let sides = inner
+                        .into_inner()
+                        .map(|p| p.as_str().trim().parse::<u32>().ok())
+                        .collect::<Option<Vec<u32>>>();
+                    match sides.as_deref() {
+                        Some(&[width, height]) if width > 0 && height > 0 => {
+                            ops.push(TransformSpec::Crop {
+                                aspect: (width, height),
+                            });
+                        }
+                        _ => {
+                            return Err(Error::ConversionError(format!(
+                                "Invalid aspect ratio: {}",
+                                ratio
+                            )));
+                        }
+                    }
+                }
+                Rule::degrees => {
+                    let degrees = inner
+                        .as_str()
+                        .split_whitespace()
+                        .collect::<String>()
+                        .parse::<i32>()
+                        .map_err(|_| Error::ConversionError("Invalid rotation".into()))?;
+                    ops.push(TransformSpec::Rotate(degrees));
+                }
+                Rule::flip_axis => {
+                    let axis = inner.as_str().to_lowercase();
+                    if axis.starts_with("horizontal") {
+                        ops.push(TransformSpec::FlipHorizontal);
+                    } else {
+                        ops.push(TransformSpec::FlipVertical);
+                    }
+                }
+                Rule::output_path => {
+                    let path_str = inner.as_str().trim_matches(|c| c == '"' || c == '\'');
+                    output = Some(self.path_resolver.resolve(path_str)?);
+                }
+                _ => {}
+            }
+        }
+
+        let input = input.ok_or_else(|| Error::ConversionError("Missing input path".into()))?;
+
+        Ok(Intent::Transform(TransformIntent {
+            input,
+            ops,
+            target_format: None,
+            output,
+        }))
+    }
+
+    fn parse_extract_frames(&self, pair: pest::iterators::Pair<Rule>) -> Result<Intent> {
+        let mut input = None;
+        let mut target_format = MediaFormat::Png;
+        let mut output = None;
+
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::path => {
+                    let path_str = inner.as_str().trim_matches(|c| c == '"' || c == '\'');
+                    input = Some(self.path_resolver.resolve(path_str)?);
+                }
+                Rule::format => {
+                    target_format = MediaFormat::from_extension(inner.as_str())
+                        .ok_or_else(|| Error::UnsupportedFormat(inner.as_str().into()))?;
+                }
+                Rule::output_path => {
+                    let path_str = inner.as_str().trim_matches(|c| c == '"' || c == '\'');
+                    output = Some(self.path_resolver.resolve(path_str)?);
+                }
+                _ => {}
+            }
+        }
+
+        let input = input.ok_or_else(|| Error::ConversionError("Missing input path".into()))?;
+
+        Ok(Intent::ExtractFrames(ExtractFramesIntent {
+            input,
+            target_format,
+            output,
+        }))
+    }
+
+    fn parse_extract_images(&self, pair: pest::iterators::Pair<Rule>) -> Result<Intent> {
+        let mut input = None;
+        let mut output = None;
+
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::path => {
+                    let path_str = inner.as_str().trim_matches(|c| c == '"' || c == '\'');
+                    input = Some(self.path_resolver.resolve(path_str)?);
+                }
+                Rule::output_path => {
+                    let path_str = inner.as_str().trim_matches(|c| c == '"' || c == '\'');
+                    output = Some(self.path_resolver.resolve(path_str)?);
+                }
+                _ => {}
+            }
+        }
+
+        let input = input.ok_or_else(|| Error::ConversionError("Missing input path".into()))?;
+
+        Ok(Intent::ExtractImages(ExtractImagesIntent { input, output }))
+    }
+
+    fn parse_extract_pages(&self, pair: pest::iterators::Pair<Rule>) -> Result<Intent> {
+        let mut input = None;
+        let mut target_format = MediaFormat::Png;
+        let mut pages = PageSelection::all();
+        let mut output = None;
+
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::path => {
+                    let path_str = inner.as_str().trim_matches(|c| c == '"' || c == '\'');
+                    input = Some(self.path_resolver.resolve(path_str)?);
+                }
+                Rule::page_ranges => pages = PageSelection::parse(inner.as_str())?,
+                Rule::format => {
+                    target_format = MediaFormat::from_extension(inner.as_str())
+                        .ok_or_else(|| Error::UnsupportedFormat(inner.as_str().into()))?;
+                }
+                Rule::output_path => {
+                    let path_str = inner.as_str().trim_matches(|c| c == '"' || c == '\'');
+                    output = Some(self.path_resolver.resolve(path_str)?);
+                }
+                _ => {}
+            }
+        }
+
+        let input = input.ok_or_else(|| Error::ConversionError("Missing input path".into()))?;
+
+        Ok(Intent::ExtractPages(ExtractPagesIntent {
+            input,
+            target_format,
+            pages,
+            output,
+        }))
+    }
+
+    fn parse_assemble(&self, pair: pest::iterators::Pair<Rule>) -> Result<Intent> {
+        let mut inputs = Vec::new();
+        let mut target_format = None;
+        let mut delay_ms = None;
+        let mut output = None;
+
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::path => {
+                    let path_str = inner.as_str().trim_matches(|c| c == '"' || c == '\'');
+                    inputs.extend(self.resolve_inputs(path_str)?);
+                }
+                Rule::anim_format => {
+                    target_format = MediaFormat::from_extension(inner.as_str());
+                }
+                Rule::delay_spec => {
+                    for delay_inner in inner.into_inner() {
+                        if delay_inner.as_rule() == Rule::delay_ms {
+                            delay_ms = Some(delay_inner.as_str().parse().map_err(|_| {
+                                Error::ConversionError("Invalid frame delay".into())
+                            })?);
+                        }
+                    }
+                }
+                Rule::output_path => {
+                    let path_str = inner.as_str().trim_matches(|c| c == '"' || c == '\'');
+                    output = Some(self.path_resolver.resolve(path_str)?);
+                }
+                _ => {}
+            }
+        }
+
+        let target_format =
+            target_format.ok_or_else(|| Error::ConversionError("Missing target format".into()))?;
+
+        Self::assemble_intent(inputs, target_format, delay_ms, output)
+    }
+
+    fn assemble_intent(
+        inputs: Vec<PathBuf>,
+        target_format: MediaFormat,
+        delay_ms: Option<u32>,
+        output: Option<PathBuf>,
+    ) -> Result<Intent> {
+        if inputs.is_empty() {
+            return Err(Error::ConversionError(
+                "Assembling an animation requires at least 1 input file".into(),
+            ));
+        }
+
+        Ok(Intent::AssembleAnimation(AssembleAnimationIntent {
+            inputs,
+            target_format,
+            delay_ms,
+            output,
+        }))
+    }
+
+    /// Resolve a path, expanding glob patterns such as `frames/*.png` in sorted order
+    fn resolve_inputs(&self, path_str: &str) -> Result<Vec<PathBuf>> {
+        if path_str.contains('*') {
+            self.path_resolver.resolve_pattern(path_str)
+        } else {
+            Ok(vec![self.path_resolver.resolve(path_str)?])
+        }
+    }
+
+    /// Split whitespace-separated paths, keeping quoted paths together
+    fn split_paths(paths_str: &str) -> Vec<String> {
+        let mut paths = Vec::new();
+        let mut current_path = String::new();
+        let mut in_quotes = false;
+        let mut quote_char = '\0';
+
+        for ch in paths_str.chars() {
+            if (ch == '"' || ch == '\'') && !in_quotes {
+                in_quotes = true;
+                quote_char = ch;
+            } else if ch == quote_char && in_quotes {
+                in_quotes = false;
+                if !current_path.is_empty() {
+                    paths.push(current_path.trim().to_string());
+                    current_path.clear();
+                }
+            } else if ch.is_whitespace() && !in_quotes {
+                if !current_path.is_empty() {
+                    paths.push(current_path.trim().to_string());
+                    current_path.clear();
+                }
+            } else {
+                current_path.push(ch);
+            }
+        }
+
+        // Don't forget the last path
+        if !current_path.is_empty() {
+            paths.push(current_path.trim().to_string());
+        }
+
+        paths
+    }
+
+    fn parse_resize(&self, pair: pest::iterators::Pair<Rule>) -> Result<TransformSpec> {
+        let spec = pair
+            .into_inner()
+            .next()
+            .ok_or_else(|| Error::ConversionError("Missing resize size".into()))?;
+        let rule = spec.as_rule();
+
+        let pixels = spec
+            .into_inner()
+            .filter(|p| p.as_rule() == Rule::pixels)
+            .map(|p| {
+                p.as_str()
+                    .parse::<u32>()
+                    .map_err(|_| Error::ConversionError("Invalid pixel size".into()))
+            })
+            .collect::<Result<Vec<u32>>>()?;
+
+        match (rule, pixels.as_slice()) {
+            (Rule::dimensions, [w, h]) => Ok(TransformSpec::Resize {
+                width: Some(*w),
+                height: Some(*h),
+            }),
+            (Rule::width_spec, [w]) => Ok(TransformSpec::Resize {
+                width: Some(*w),
+                height: None,
+            }),
+            (Rule::height_spec, [h]) => Ok(TransformSpec::Resize {
+                width: None,
+                height: Some(*h),
+            }),
+            (Rule::edge_spec, [max]) => Ok(TransformSpec::MaxEdge(*max)),
+            _ => Err(Error::ConversionError("Invalid resize size".into())),
+        }
+    }
+
     fn parse_quality(&self, pair: pest::iterators::Pair<Rule>) -> Result<QualitySpec> {
+        // Only "and resize" after a size limit lets it shrink the dimensions
+        let allow_downscale = pair
+            .clone()
+            .into_inner()
+            .any(|inner| inner.as_rule() == Rule::downscale_spec);
+
         for inner in pair.into_inner() {
             match inner.as_rule() {
+                Rule::file_size => {
+                    return Ok(QualitySpec::MaxSize(SizeTarget {
+                        max_size: FileSize::parse(inner.as_str())?,
+                        allow_downscale,
+                    }));
+                }
                 Rule::percentage => {
                     let percent_str = inner.as_str().trim_end_matches('%');
                     let value = percent_str
@@ -264,9 +582,45 @@ impl CommandParser {
     fn parse_with_regex(&self, command: &str) -> Result<Intent> {
         use regex::Regex;
 
+        // PDF pages pattern: "extract pages <ranges> from <path> [as <format>] [in <dir>]"
+        let extract_pages_re = Regex::new(
+            r"(?i)(?:extract|export|split|convert|render)\s+(?:all\s+)?(?:the\s+)?pages?\s+((?:\d+|last)(?:-(?:\d+|last)?)?(?:\s*,\s*(?:\d+|last)(?:-(?:\d+|last)?)?)*\s+)?(?:from|of)\s+(.+?)(?:\s+(?:as|to)\s+(png|jpg|jpeg|webp|avif|jxl|tiff|bmp|gif))?(?:\s+(?:at|in|to)\s+(.+))?$",
+        )
+        .unwrap();
+
+        if let Some(caps) = extract_pages_re.captures(command) {
+            let pages = caps
+                .get(1)
+                .map(|m| PageSelection::parse(m.as_str()))
+                .transpose()?
+                .unwrap_or_default();
+            let path_str = caps.get(2).unwrap().as_str().trim();
+            let path_str = path_str.trim_matches(|c| c == '"' || c == '\'');
+            let input = self.path_resolver.resolve(path_str)?;
+            let target_format = caps
+                .get(3)
+                .and_then(|m| MediaFormat::from_extension(m.as_str()))
+                .unwrap_or(MediaFormat::Png);
+            let output = caps
+                .get(4)
+                .map(|m| {
+                    let out_path = m.as_str().trim();
+                    let out_path = out_path.trim_matches(|c| c == '"' || c == '\'');
+                    self.path_resolver.resolve(out_path)
+                })
+                .transpose()?;
+
+            return Ok(Intent::ExtractPages(ExtractPagesIntent {
+                input,
+                target_format,
+                pages,
+                output,
+            }));
+        }
+
         // Convert pattern: "convert <path> to <format>"
         let convert_re = Regex::new(
-            r"(?i)convert\s+(.+?)\s+to\s+(png|jpg|jpeg|webp|pdf|tiff|bmp|gif)(?:\s+(?:at|in)\s+(.+))?",
+            r"(?i)convert\s+(.+?)\s+to\s+(png|jpg|jpeg|webp|avif|jxl|pdf|tiff|bmp|gif)(?:\s+(?:at|in)\s+(.+))?",
         )
         .unwrap();
 
@@ -292,6 +646,59 @@ impl CommandParser {
             }));
         }
 
+        // Compress pattern with a size limit: "compress <path> to under 500kb [and resize]"
+        let compress_size_re = Regex::new(
+            r"(?i)(compress|optimize|reduce|shrink)\s+(.+?)\s+(?:to\s+)?(?:under|below|less\s+than|at\s+most)\s+(\d+(?:\.\d+)?\s?(?:[kmg]i?b|[kmg]))(\s+and\s+(?:resize|downscale|scale\s+down|shrink)(?:\s+it)?(?:\s+if\s+needed)?)?(?:\s+(?:at|in)\s+(.+))?$",
+        )
+        .unwrap();
+
+        if let Some(caps) = compress_size_re.captures(command) {
+            let path_str = caps.get(2).unwrap().as_str().trim();
+            let path_str = path_str.trim_matches(|c| c == '"' || c == '\'');
+            let input = self.path_resolver.resolve(path_str)?;
+            let target = SizeTarget {
+                max_size: FileSize::parse(caps.get(3).unwrap().as_str())?,
+                allow_downscale: caps.get(4).is_some(),
+            };
+            let output = caps
+                .get(5)
+                .map(|m| self.path_resolver.resolve(m.as_str().trim()))
+                .transpose()?;
+
+            return Ok(Intent::Compress(CompressIntent {
+                input,
+                target_format: None,
+                quality: QualitySpec::MaxSize(target),
+                output,
+            }));
+        }
+
+        // Compress pattern with an SSIM floor: "compress <path> keeping ssim 0.95"
+        let compress_ssim_re = Regex::new(
+            r"(?i)(compress|optimize|reduce|shrink)\s+(.+?)\s+(?:to|at|with|keeping)\s+(?:an?\s+)?ssim\s+(?:of\s+)?(?:at\s+least\s+|above\s+)?(0?\.\d+|1(?:\.0+)?)(?:\s+(?:at|in)\s+(.+))?$",
+        )
+        .unwrap();
+
+        if let Some(caps) = compress_ssim_re.captures(command) {
+            let path_str = caps.get(2).unwrap().as_str().trim();
+            let path_str = path_str.trim_matches(|c| c == '"' || c == '\'');
+            let input = self.path_resolver.resolve(path_str)?;
+            let min_ssim = caps.get(3).unwrap().as_str().parse::<f64>().map_err(|_| {
+                Error::ConversionError(format!("Invalid SSIM: {}", caps.get(3).unwrap().as_str()))
+            })?;
+            let output = caps
+                .get(4)
+                .map(|m| self.path_resolver.resolve(m.as_str().trim()))
+                .transpose()?;
+
+            return Ok(Intent::Compress(CompressIntent {
+                input,
+                target_format: None,
+                quality: QualitySpec::MinSsim(min_ssim),
+                output,
+            }));
+        }
+
         // Compress pattern: "compress <path> to <quality>" (percentage or preset)
         let compress_percent_re = Regex::new(
             r"(?i)(compress|optimize|reduce)\s+(.+?)\s+to\s+(\d+)%(?:\s+(?:at|in)\s+(.+))?$",
@@ -400,7 +807,7 @@ impl CommandParser {
 
         // Batch pattern: "batch <pattern> convert to <format> at <output>"
         let batch_re = Regex::new(
-            r"(?i)(batch|bulk)\s+(.+?)\s+convert\s+to\s+(png|jpg|jpeg|webp|pdf|tiff|bmp|gif)(?:\s+(?:at|in)\s+(.+))?$",
+            r"(?i)(batch|bulk)\s+(.+?)\s+convert\s+to\s+(png|jpg|jpeg|webp|avif|jxl|pdf|tiff|bmp|gif)(?:\s+(?:at|in)\s+(.+))?$",
         )
         .unwrap();
 
@@ -432,35 +839,10 @@ impl CommandParser {
             let paths_str = caps.get(2).unwrap().as_str();
 
             // Split by spaces but respect quoted paths
-            let mut inputs = Vec::new();
-            let mut current_path = String::new();
-            let mut in_quotes = false;
-            let mut quote_char = '\0';
-
-            for ch in paths_str.chars() {
-                if (ch == '"' || ch == '\'') && !in_quotes {
-                    in_quotes = true;
-                    quote_char = ch;
-                } else if ch == quote_char && in_quotes {
-                    in_quotes = false;
-                    if !current_path.is_empty() {
-                        inputs.push(self.path_resolver.resolve(current_path.trim())?);
-                        current_path.clear();
-                    }
-                } else if ch.is_whitespace() && !in_quotes {
-                    if !current_path.is_empty() {
-                        inputs.push(self.path_resolver.resolve(current_path.trim())?);
-                        current_path.clear();
-                    }
-                } else {
-                    current_path.push(ch);
-                }
-            }
-
-            // Don't forget the last path
-            if !current_path.is_empty() {
-                inputs.push(self.path_resolver.resolve(current_path.trim())?);
-            }
+            let inputs = Self::split_paths(paths_str)
+                .iter()
+                .map(|path| self.path_resolver.resolve(path))
+                .collect::<Result<Vec<_>>>()?;
 
             if inputs.len() < 2 {
                 return Err(Error::ConversionError(
@@ -481,6 +863,84 @@ impl CommandParser {
             return Ok(Intent::CombineToPdf(CombineToPdfIntent { inputs, output }));
         }
 
+        // Extract frames pattern: "extract frames from <path> [as <format>] [in <dir>]"
+        let extract_frames_re = Regex::new(
+            r"(?i)(?:extract|export|split)\s+(?:all\s+)?(?:the\s+)?frames?\s+(?:from|of)\s+(.+?)(?:\s+(?:as|to)\s+(png|jpg|jpeg|webp|avif|jxl|tiff|bmp|gif))?(?:\s+(?:at|in|to)\s+(.+))?$",
+        )
+        .unwrap();
+
+        if let Some(caps) = extract_frames_re.captures(command) {
+            let path_str = caps.get(1).unwrap().as_str().trim();
+            let path_str = path_str.trim_matches(|c| c == '"' || c == '\'');
+            let input = self.path_resolver.resolve(path_str)?;
+            let target_format = caps
+                .get(2)
+                .and_then(|m| MediaFormat::from_extension(m.as_str()))
+                .unwrap_or(MediaFormat::Png);
+            let output = caps
+                .get(3)
+                .map(|m| {
+                    let out_path = m.as_str().trim();
+                    let out_path = out_path.trim_matches(|c| c == '"' || c == '\'');
+                    self.path_resolver.resolve(out_path)
+                })
+                .transpose()?;
+
+            return Ok(Intent::ExtractFrames(ExtractFramesIntent {
+                input,
+                target_format,
+                output,
+            }));
+        }
+
+        // Extract images pattern: "extract images from <path> [in <dir>]"
+        let extract_images_re = Regex::new(
+            r"(?i)(?:extract|export|split)\s+(?:all\s+)?(?:the\s+)?(?:embedded\s+)?(?:images?|pictures|photos)\s+(?:from|in|of)\s+(.+?)(?:\s+(?:at|in|to)\s+(.+))?$",
+        )
+        .unwrap();
+
+        if let Some(caps) = extract_images_re.captures(command) {
+            let path_str = caps.get(1).unwrap().as_str().trim();
+            let path_str = path_str.trim_matches(|c| c == '"' || c == '\'');
+            let input = self.path_resolver.resolve(path_str)?;
+            let output = caps
+                .get(2)
+                .map(|m| {
+                    let out_path = m.as_str().trim();
+                    let out_path = out_path.trim_matches(|c| c == '"' || c == '\'');
+                    self.path_resolver.resolve(out_path)
+                })
+                .transpose()?;
+
+            return Ok(Intent::ExtractImages(ExtractImagesIntent { input, output }));
+        }
+
+        // Assemble pattern: "animate <path1> <path2> ... into gif [with <n>ms delay]"
+        let assemble_re = Regex::new(
+            r"(?i)(?:assemble|animate)\s+(.+?)\s+(?:into|to|as)\s+(?:an?\s+)?(?:animated\s+)?(gif|webp|apng|png)(?:\s+(?:with\s+|at\s+)?(\d+)\s*ms(?:\s+(?:delay|per frame))?)?(?:\s+(?:at|in|to|as)\s+(.+))?$",
+        )
+        .unwrap();
+
+        if let Some(caps) = assemble_re.captures(command) {
+            let mut inputs = Vec::new();
+            for path in Self::split_paths(caps.get(1).unwrap().as_str()) {
+                inputs.extend(self.resolve_inputs(&path)?);
+            }
+            let target_format = MediaFormat::from_extension(caps.get(2).unwrap().as_str())
+                .ok_or_else(|| Error::UnsupportedFormat(caps.get(2).unwrap().as_str().into()))?;
+            let delay_ms = caps.get(3).and_then(|m| m.as_str().parse().ok());
+            let output = caps
+                .get(4)
+                .map(|m| {
+                    let out_path = m.as_str().trim();
+                    let out_path = out_path.trim_matches(|c| c == '"' || c == '\'');
+                    self.path_resolver.resolve(out_path)
+                })
+                .transpose()?;
+
+            return Self::assemble_intent(inputs, target_format, delay_ms, output);
+        }
+
         Err(Error::ConversionError(format!(
             "Could not parse command: {}. Try 'convert <file> to <format>'",
             command
@@ -513,6 +973,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_convert_to_avif() {
+        let parser = CommandParser::new().unwrap();
+
+        match parser.parse("convert photo.png to avif").unwrap() {
+            Intent::Convert(conv) => assert_eq!(conv.target_format, MediaFormat::Avif),
+            _ => panic!("Wrong intent type"),
+        }
+    }
+
     #[test]
     fn test_parse_compress_with_quality() {
         let parser = CommandParser::new().unwrap();
@@ -527,6 +997,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_compress_to_ssim() {
+        let parser = CommandParser::new().unwrap();
+
+        for command in [
+            "compress photo.jpg keeping ssim 0.95",
+            "compress photo.jpg with an SSIM of at least .95",
+            "optimize photo.jpg to ssim 0.95",
+        ] {
+            match parser.parse(command).unwrap() {
+                Intent::Compress(comp) => {
+                    assert!(comp.input.to_string_lossy().contains("photo.jpg"));
+                    assert_eq!(comp.quality, QualitySpec::MinSsim(0.95), "{}", command);
+                }
+                _ => panic!("Wrong intent type for {}", command),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_compress_to_size() {
+        let parser = CommandParser::new().unwrap();
+
+        for command in [
+            "compress photo.jpg to under 500kb",
+            "shrink photo.jpg below 500 KB",
+            "compress photo.jpg to under 0.5mb",
+        ] {
+            match parser.parse(command).unwrap() {
+                Intent::Compress(comp) => {
+                    assert!(comp.input.to_string_lossy().contains("photo.jpg"));
+                    assert_eq!(
+                        comp.quality,
+                        QualitySpec::MaxSize(SizeTarget::new(FileSize::from_bytes(500_000))),
+                        "{}",
+                        command
+                    );
+                }
+                _ => panic!("Wrong intent type for {}", command),
+            }
+        }
+
+        // Dimensions only shrink when asked to
+        for command in [
+            "compress photo.jpg to under 500kb and resize",
+            "shrink photo.jpg below 500 KB and downscale if needed",
+        ] {
+            match parser.parse(command).unwrap() {
+                Intent::Compress(comp) => {
+                    assert!(comp.input.to_string_lossy().contains("photo.jpg"));
+                    assert_eq!(
+                        comp.quality,
+                        QualitySpec::MaxSize(SizeTarget {
+                            max_size: FileSize::from_bytes(500_000),
+                            allow_downscale: true,
+                        }),
+                        "{}",
+                        command
+                    );
+                }
+                _ => panic!("Wrong intent type for {}", command),
+            }
+        }
+    }
+
     #[test]
     fn test_parse_enhance() {
         let parser = CommandParser::new().unwrap();
@@ -541,6 +1076,183 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_resize() {
+        let parser = CommandParser::new().unwrap();
+
+        let intent = parser.parse("resize photo.png to 800px wide").unwrap();
+        match intent {
+            Intent::Transform(t) => {
+                assert_eq!(
+                    t.ops,
+                    vec![TransformSpec::Resize {
+                        width: Some(800),
+                        height: None
+                    }]
+                );
+            }
+            _ => panic!("Wrong intent type"),
+        }
+
+        let intent = parser.parse("resize photo.png to 640x480").unwrap();
+        match intent {
+            Intent::Transform(t) => {
+                assert_eq!(
+                    t.ops,
+                    vec![TransformSpec::Resize {
+                        width: Some(640),
+                        height: Some(480)
+                    }]
+                );
+            }
+            _ => panic!("Wrong intent type"),
+        }
+    }
+
+    #[test]
+    fn test_parse_crop() {
+        let parser = CommandParser::new().unwrap();
+
+        match parser.parse("crop photo.png to 16:9").unwrap() {
+            Intent::Transform(t) => {
+                assert_eq!(t.ops, vec![TransformSpec::Crop { aspect: (16, 9) }]);
+                assert!(t.input.ends_with("photo.png"));
+                assert_eq!(t.output, None);
+            }
+            _ => panic!("Wrong intent type"),
+        }
+
+        let intent = parser
+            .parse("crop photo.png to 1:1 aspect ratio in square.png")
+            .unwrap();
+        match intent {
+            Intent::Transform(t) => {
+                assert_eq!(t.ops, vec![TransformSpec::Crop { aspect: (1, 1) }]);
+                assert!(t.output.unwrap().ends_with("square.png"));
+            }
+            _ => panic!("Wrong intent type"),
+        }
+
+        assert!(parser.parse("crop photo.png to 0:9").is_err());
+    }
+
+    #[test]
+    fn test_parse_rotate_and_flip() {
+        let parser = CommandParser::new().unwrap();
+
+        match parser.parse("rotate photo.jpg by 90 degrees").unwrap() {
+            Intent::Transform(t) => assert_eq!(t.ops, vec![TransformSpec::Rotate(90)]),
+            _ => panic!("Wrong intent type"),
+        }
+
+        match parser.parse("flip photo.jpg horizontally").unwrap() {
+            Intent::Transform(t) => assert_eq!(t.ops, vec![TransformSpec::FlipHorizontal]),
+            _ => panic!("Wrong intent type"),
+        }
+    }
+
+    #[test]
+    fn test_parse_extract_frames() {
+        let parser = CommandParser::new().unwrap();
+
+        match parser
+            .parse("extract all frames from anim.gif in ./frames")
+            .unwrap()
+        {
+            Intent::ExtractFrames(e) => {
+                assert!(e.input.ends_with("anim.gif"));
+                assert_eq!(e.target_format, MediaFormat::Png);
+                assert!(e.output.unwrap().ends_with("frames"));
+            }
+            other => panic!("Wrong intent type: {:?}", other),
+        }
+
+        match parser.parse("extract frames from anim.webp as jpg").unwrap() {
+            Intent::ExtractFrames(e) => assert_eq!(e.target_format, MediaFormat::Jpeg),
+            other => panic!("Wrong intent type: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_extract_images() {
+        let parser = CommandParser::new().unwrap();
+
+        match parser.parse("extract images from report.pdf").unwrap() {
+            Intent::ExtractImages(e) => {
+                assert!(e.input.ends_with("report.pdf"));
+                assert!(e.output.is_none());
+            }
+            other => panic!("Wrong intent type: {:?}", other),
+        }
+
+        match parser
+            .parse("extract all embedded images from scan.pdf to ./scans")
+            .unwrap()
+        {
+            Intent::ExtractImages(e) => assert!(e.output.unwrap().ends_with("scans")),
+            other => panic!("Wrong intent type: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_extract_pages() {
+        let parser = CommandParser::new().unwrap();
+
+        match parser
+            .parse("extract pages 1-3,7,last from report.pdf as jpg in ./pages")
+            .unwrap()
+        {
+            Intent::ExtractPages(e) => {
+                assert!(e.input.ends_with("report.pdf"));
+                assert_eq!(e.target_format, MediaFormat::Jpeg);
+                assert_eq!(e.pages.resolve(10).unwrap(), vec![1, 2, 3, 7, 10]);
+                assert!(e.output.unwrap().ends_with("pages"));
+            }
+            other => panic!("Wrong intent type: {:?}", other),
+        }
+
+        match parser.parse("convert report.pdf pages 2-4 to webp").unwrap() {
+            Intent::ExtractPages(e) => {
+                assert_eq!(e.target_format, MediaFormat::Webp);
+                assert_eq!(e.pages.resolve(5).unwrap(), vec![2, 3, 4]);
+            }
+            other => panic!("Wrong intent type: {:?}", other),
+        }
+
+        match parser.parse("extract all pages from report.pdf").unwrap() {
+            Intent::ExtractPages(e) => assert!(e.pages.is_all()),
+            other => panic!("Wrong intent type: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_assemble_animation() {
+        let parser = CommandParser::new().unwrap();
+
+        match parser
+            .parse("animate a.png b.png c.png into an animated gif with 80ms delay to out.gif")
+            .unwrap()
+        {
+            Intent::AssembleAnimation(a) => {
+                assert_eq!(a.inputs.len(), 3);
+                assert!(a.inputs[0].ends_with("a.png"));
+                assert_eq!(a.target_format, MediaFormat::Gif);
+                assert_eq!(a.delay_ms, Some(80));
+                assert!(a.output.unwrap().ends_with("out.gif"));
+            }
+            other => panic!("Wrong intent type: {:?}", other),
+        }
+
+        match parser.parse("assemble tomato.png into apng").unwrap() {
+            Intent::AssembleAnimation(a) => {
+                assert!(a.inputs[0].ends_with("tomato.png"));
+                assert_eq!(a.target_format, MediaFormat::Png);
+                assert_eq!(a.delay_ms, None);
+            }
+            other => panic!("Wrong intent type: {:?}", other),
+        }
+    }
+
     #[test]
     fn test_case_insensitive() {
         let parser = CommandParser::new().unwrap();
@@ -1,6 +1,16 @@
 use std::env;
 use std::path::PathBuf;
-use transmute_common::{Error, Result};
+use transmute_common::{Error, MediaFormat, Result};
+
+/// Files a glob pattern matched, split by whether they can be read as media
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatternMatches {
+    /// Images, animations and PDFs that can be decoded
+    pub files: Vec<PathBuf>,
+
+    /// Other matched files, whose content is not a known format
+    pub skipped: Vec<PathBuf>,
+}
 
 /// Resolves natural language paths to absolute paths
 pub struct PathResolver {
@@ -97,8 +107,22 @@ impl PathResolver {
         std::borrow::Cow::Borrowed(path)
     }
 
-    /// Resolve glob pattern for batch operations
+    /// Resolve glob pattern for batch operations, warning about matched
+    /// files that are skipped (see [`Self::match_pattern`] to list them)
     pub fn resolve_pattern(&self, pattern: &str) -> Result<Vec<PathBuf>> {
+        let matches = self.match_pattern(pattern)?;
+        if !matches.skipped.is_empty() {
+            tracing::warn!(
+                "Skipping {} files matching {} that are not readable media",
+                matches.skipped.len(),
+                pattern
+            );
+        }
+        Ok(matches.files)
+    }
+
+    /// Resolve glob pattern, keeping the matched files that cannot be read
+    pub fn match_pattern(&self, pattern: &str) -> Result<PatternMatches> {
         use glob::glob;
 
         let resolved_pattern = self.resolve(pattern)?;
@@ -106,22 +130,30 @@ impl PathResolver {
 
         tracing::debug!("Glob pattern: {}", pattern_str);
 
-        let mut matches = Vec::new();
+        let mut matches = PatternMatches::default();
         for entry in glob(&pattern_str).map_err(|_e| Error::InvalidPath(resolved_pattern.clone()))? {
             match entry {
                 Ok(path) => {
-                    if path.is_file() {
-                        matches.push(path);
+                    if !path.is_file() {
+                        continue;
+                    }
+
+                    // Sniff contents so extensionless or mislabeled media is still picked up
+                    if MediaFormat::detect(&path).is_some() {
+                        matches.files.push(path);
+                    } else {
+                        tracing::debug!("Skipping unreadable file: {:?}", path);
+                        matches.skipped.push(path);
                     }
                 }
                 Err(e) => tracing::warn!("Glob error: {}", e),
             }
         }
 
-        if matches.is_empty() {
+        if matches.files.is_empty() {
             tracing::warn!("No files matched pattern: {}", pattern);
         } else {
-            tracing::debug!("Found {} files matching pattern", matches.len());
+            tracing::debug!("Found {} files matching pattern", matches.files.len());
         }
 
         Ok(matches)
@@ -168,4 +200,35 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_resolve_pattern_sniffs_content() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("scan"), b"\x89PNG\r\n\x1a\n").unwrap();
+        std::fs::write(dir.path().join("notes"), b"plain text").unwrap();
+
+        let resolver = PathResolver::new().unwrap();
+        let pattern = format!("{}/*", dir.path().display());
+        let matches = resolver.resolve_pattern(&pattern).unwrap();
+
+        assert_eq!(matches, vec![dir.path().join("scan")]);
+    }
+
+    #[test]
+    fn test_match_pattern_reports_skipped_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.png"), b"\x89PNG\r\n\x1a\n").unwrap();
+        std::fs::write(dir.path().join("b.avif"), b"\0\0\0\x1cftypavif").unwrap();
+        std::fs::write(dir.path().join("c.txt"), b"plain text").unwrap();
+
+        let resolver = PathResolver::new().unwrap();
+        let pattern = format!("{}/*", dir.path().display());
+        let matches = resolver.match_pattern(&pattern).unwrap();
+
+        assert_eq!(
+            matches.files,
+            vec![dir.path().join("a.png"), dir.path().join("b.avif")]
+        );
+        assert_eq!(matches.skipped, vec![dir.path().join("c.txt")]);
+    }
 }